@@ -33,6 +33,44 @@ pub fn normalize_path(path: &std::path::Path) -> std::path::PathBuf {
   normalized_path
 }
 
+/// Percent encodes everything outside of [a-z0-9_-], so every name maps to a unique file name
+/// that stays inside of its directory, even on case-insensitive file systems
+pub fn name_to_file_name(name: &str) -> String {
+  let mut file_name = String::with_capacity(name.len());
+
+  for byte in name.bytes() {
+    match byte {
+      b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' => file_name.push(byte as char),
+      _ => file_name.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+
+  file_name
+}
+
+pub fn file_name_to_name(file_name: &str) -> Option<String> {
+  let mut bytes = Vec::with_capacity(file_name.len());
+  let mut chars = file_name.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      'a'..='z' | '0'..='9' | '_' | '-' => bytes.push(c as u8),
+      '%' => {
+        let hex: String = chars.by_ref().take(2).collect();
+
+        if hex.len() != 2 {
+          return None;
+        }
+
+        bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+      }
+      _ => return None,
+    }
+  }
+
+  String::from_utf8(bytes).ok()
+}
+
 // use is_global when it's stabilized https://github.com/rust-lang/rust/issues/27709
 fn is_internal_ip(ip: IpAddr) -> bool {
   match ip {
@@ -59,3 +97,43 @@ pub fn use_public_ip(address: SocketAddr, public_ip: IpAddr) -> SocketAddr {
 }
 
 pub mod iterators;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn file_names() {
+    for name in [
+      "boss_rush",
+      "boss/rush",
+      "a+b",
+      "time trial 2",
+      "..hidden",
+      "%41",
+      "a\\b",
+      "Case",
+      "ランキング",
+    ] {
+      let file_name = name_to_file_name(name);
+
+      assert!(file_name
+        .chars()
+        .all(|c| c.is_ascii_digit() || c.is_ascii_lowercase() || "_-%ABCDEF".contains(c)));
+      assert_eq!(file_name_to_name(&file_name).as_deref(), Some(name));
+    }
+
+    assert_ne!(name_to_file_name("a_b"), name_to_file_name("a/b"));
+    assert_ne!(
+      name_to_file_name("Case").to_lowercase(),
+      name_to_file_name("case").to_lowercase()
+    );
+    assert_eq!(name_to_file_name("."), "%2E");
+    assert_eq!(name_to_file_name(".."), "%2E%2E");
+
+    assert_eq!(file_name_to_name("bad%4"), None);
+    assert_eq!(file_name_to_name("bad%zz"), None);
+    assert_eq!(file_name_to_name("has.dot"), None);
+    assert_eq!(file_name_to_name("Upper"), None);
+  }
+}
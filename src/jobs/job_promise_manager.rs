@@ -2,7 +2,7 @@ use super::job_promise::JobPromise;
 use std::collections::HashMap;
 
 pub struct JobPromiseManager {
-  promises: HashMap<usize, JobPromise>,
+  promises: HashMap<usize, (usize, JobPromise)>,
   next_id: usize,
 }
 
@@ -15,17 +15,17 @@ impl JobPromiseManager {
   }
 
   pub fn get_promise(&self, id: usize) -> Option<&JobPromise> {
-    self.promises.get(&id)
+    self.promises.get(&id).map(|(_, promise)| promise)
   }
 
   pub fn get_promise_mut(&mut self, id: usize) -> Option<&mut JobPromise> {
-    self.promises.get_mut(&id)
+    self.promises.get_mut(&id).map(|(_, promise)| promise)
   }
 
-  pub fn add_promise(&mut self, promise: JobPromise) -> usize {
+  pub fn add_promise(&mut self, owner: usize, promise: JobPromise) -> usize {
     let id = self.next_id;
 
-    self.promises.insert(id, promise);
+    self.promises.insert(id, (owner, promise));
 
     self.next_id += 1;
 
@@ -39,4 +39,14 @@ impl JobPromiseManager {
       self.next_id = 0;
     }
   }
+
+  pub fn remove_owned_promises(&mut self, owner: usize) {
+    self
+      .promises
+      .retain(|_, (promise_owner, _)| *promise_owner != owner);
+
+    if self.promises.is_empty() {
+      self.next_id = 0;
+    }
+  }
 }
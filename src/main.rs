@@ -7,7 +7,7 @@ mod plugins;
 mod threads;
 
 use helpers::unwrap_and_parse_or_default;
-use plugins::LuaPluginInterface;
+use plugins::{load_native_plugins, LuaPluginInterface};
 use std::net::IpAddr;
 
 fn main() {
@@ -139,6 +139,215 @@ fn main() {
           }
         }),
     )
+    .arg(
+      clap::Arg::new("packet_rate_limit")
+        .long("packet-rate-limit")
+        .help("Packets each address can send per second before packets are dropped")
+        .value_name("PACKETS")
+        .default_value("300")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("byte_rate_limit")
+        .long("byte-rate-limit")
+        .help("Bytes each address can send per second before packets are dropped")
+        .value_name("SIZE_IN_BYTES")
+        .default_value("262144")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("emote_rate_limit")
+        .long("emote-rate-limit")
+        .help("Emotes each address can send per second before packets are dropped")
+        .value_name("EMOTES")
+        .default_value("5")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("chat_rate_limit")
+        .long("chat-rate-limit")
+        .help("Chat messages each address can send per second before packets are dropped")
+        .value_name("MESSAGES")
+        .default_value("2")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("asset_stream_rate_limit")
+        .long("asset-stream-rate-limit")
+        .help("Asset stream packets each address can send per second before packets are dropped")
+        .value_name("PACKETS")
+        .default_value("200")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("position_rate_limit")
+        .long("position-rate-limit")
+        .help("Position updates each address can send per second before packets are dropped")
+        .value_name("PACKETS")
+        .default_value("120")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("rate_limit_violations")
+        .long("rate-limit-violations")
+        .help("Dropped packets within a second before the address is banned")
+        .value_name("PACKETS")
+        .default_value("200")
+        .takes_value(true)
+        .validator(|value| {
+          value
+            .parse::<u32>()
+            .map(|_| ())
+            .map_err(|_| String::from("Invalid count"))
+        }),
+    )
+    .arg(
+      clap::Arg::new("rate_limit_ban_duration")
+        .long("rate-limit-ban-duration")
+        .help("Seconds an address is banned for after exceeding --rate-limit-violations")
+        .value_name("SECONDS")
+        .default_value("60")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(duration) if (0.0..=net::MAX_RATE_LIMIT_BAN_DURATION).contains(&duration) => Ok(()),
+          _ => Err(String::from("Invalid duration, must be at most a year")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("max_movement_speed")
+        .long("max-movement-speed")
+        .help("Enables movement validation, rejecting movement faster than this limit or through walls, holes, and solid objects")
+        .value_name("TILES_PER_SECOND")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(speed) if speed > 0.0 => Ok(()),
+          _ => Err(String::from("Invalid speed")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("credentials_file")
+        .long("credentials-file")
+        .help("Only allows identities listed in this file to join, one identity per line optionally followed by a username")
+        .value_name("PATH")
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("chat_filter_file")
+        .long("chat-filter-file")
+        .help("Masks words listed in this file from chat messages, one word per line")
+        .value_name("PATH")
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("player_storage_path")
+        .long("player-storage-path")
+        .help("Folder used to save player data between sessions")
+        .value_name("PATH")
+        .default_value(net::DEFAULT_PLAYER_STORAGE_PATH)
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("player_save_rate")
+        .long("player-save-rate")
+        .help("Seconds between saving the data of every connected player, players are also saved as they disconnect")
+        .value_name("SECONDS")
+        .default_value("60")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(()),
+          _ => Err(String::from("Invalid rate")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("battle_history_path")
+        .long("battle-history-path")
+        .help("Folder used to save battle results")
+        .value_name("PATH")
+        .default_value("./storage/battles")
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("leaderboards_path")
+        .long("leaderboards-path")
+        .help("Folder used to save leaderboards")
+        .value_name("PATH")
+        .default_value("./storage/leaderboards")
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("coop_battle_timeout")
+        .long("coop-battle-timeout")
+        .help("Seconds to wait for every player in a co-op encounter to send results")
+        .value_name("SECONDS")
+        .default_value("600")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(timeout) if timeout > 0.0 && timeout.is_finite() => Ok(()),
+          _ => Err(String::from("Invalid timeout")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("pvp_battle_timeout")
+        .long("pvp-battle-timeout")
+        .help("Seconds to wait for both players in a PvP battle to send results")
+        .value_name("SECONDS")
+        .default_value("600")
+        .takes_value(true)
+        .validator(|value| match value.parse::<f32>() {
+          Ok(timeout) if timeout > 0.0 && timeout.is_finite() => Ok(()),
+          _ => Err(String::from("Invalid timeout")),
+        }),
+    )
+    .arg(
+      clap::Arg::new("quests_path")
+        .long("quests-path")
+        .help("Folder containing quest definitions, loaded when the server starts")
+        .value_name("PATH")
+        .default_value("./quests")
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("watch_assets")
+        .long("watch-assets")
+        .help("Reloads areas and assets when files in ./areas or ./assets change"),
+    )
+    .arg(
+      clap::Arg::new("trigger_class")
+        .long("trigger-class")
+        .help("Objects with this class send object_enter and object_exit events as players walk in and out")
+        .value_name("CLASS")
+        .default_value("Trigger")
+        .takes_value(true),
+    )
+    .arg(
+      clap::Arg::new("watch_scripts")
+        .long("watch-scripts")
+        .help("Reloads scripts when files in ./scripts change"),
+    )
     .get_matches();
 
   let config = net::ServerConfig {
@@ -163,14 +372,59 @@ fn main() {
     custom_emotes_path: matches
       .value_of("custom_emotes_path")
       .map(|path| path.to_string()),
+    max_movement_speed: matches
+      .value_of("max_movement_speed")
+      .map(|speed| speed.parse().unwrap()),
     max_idle_packet_duration: 1.0,
     max_silence_duration: 5.0,
     heartbeat_rate: 0.5,
+    player_save_rate: unwrap_and_parse_or_default(matches.value_of("player_save_rate")),
+    ban_list_path: String::from("./bans.txt"),
+    watch_assets: matches.is_present("watch_assets"),
+    trigger_class: matches.value_of("trigger_class").unwrap().to_string(),
+    battle_history_path: matches.value_of("battle_history_path").unwrap().to_string(),
+    leaderboards_path: matches.value_of("leaderboards_path").unwrap().to_string(),
+    coop_battle_timeout: unwrap_and_parse_or_default(matches.value_of("coop_battle_timeout")),
+    pvp_battle_timeout: unwrap_and_parse_or_default(matches.value_of("pvp_battle_timeout")),
+    quests_path: matches.value_of("quests_path").unwrap().to_string(),
+    rate_limits: net::RateLimits {
+      packets_per_second: unwrap_and_parse_or_default(matches.value_of("packet_rate_limit")),
+      bytes_per_second: unwrap_and_parse_or_default(matches.value_of("byte_rate_limit")),
+      emotes_per_second: unwrap_and_parse_or_default(matches.value_of("emote_rate_limit")),
+      chat_messages_per_second: unwrap_and_parse_or_default(matches.value_of("chat_rate_limit")),
+      asset_streams_per_second: unwrap_and_parse_or_default(
+        matches.value_of("asset_stream_rate_limit"),
+      ),
+      positions_per_second: unwrap_and_parse_or_default(matches.value_of("position_rate_limit")),
+      violations_before_ban: unwrap_and_parse_or_default(matches.value_of("rate_limit_violations")),
+      ban_duration: unwrap_and_parse_or_default(matches.value_of("rate_limit_ban_duration")),
+    },
   };
 
+  let player_store = net::FilePlayerStore::new(matches.value_of("player_storage_path").unwrap());
+
   let mut server = net::Server::new(config);
 
-  server.add_plugin_interface(Box::new(LuaPluginInterface::new()));
+  server.set_player_store(Box::new(player_store));
+
+  if let Some(path) = matches.value_of("credentials_file") {
+    let authenticator = net::CredentialsFileAuthenticator::new(path.to_string());
+    server.set_authenticator(Box::new(authenticator));
+  }
+
+  if let Some(path) = matches.value_of("chat_filter_file") {
+    let chat_filter = net::WordListChatFilter::load(path);
+    server.set_chat_filter(Box::new(chat_filter));
+  }
+
+  let mut lua_plugin_interface = LuaPluginInterface::new();
+  lua_plugin_interface.set_watch_scripts(matches.is_present("watch_scripts"));
+
+  server.add_plugin_interface(Box::new(lua_plugin_interface));
+
+  for native_plugin_interface in load_native_plugins("./plugins") {
+    server.add_plugin_interface(Box::new(native_plugin_interface));
+  }
 
   if let Err(err) = server.start() {
     panic!("{}", err);
@@ -1,4 +1,6 @@
 use super::map::Map;
+use super::spatial_index::SpatialIndex;
+use std::collections::HashSet;
 
 pub struct Area {
   id: String,
@@ -7,6 +9,7 @@ pub struct Area {
   // cache
   connected_players: Vec<String>,
   connected_bots: Vec<String>,
+  actor_index: SpatialIndex<String>,
 }
 
 impl Area {
@@ -17,6 +20,7 @@ impl Area {
       required_assets: Vec::new(),
       connected_players: Vec::new(),
       connected_bots: Vec::new(),
+      actor_index: SpatialIndex::new(),
     }
   }
 
@@ -60,6 +64,8 @@ impl Area {
       .iter()
       .position(|id| id == player_id)
       .map(|position| self.connected_players.swap_remove(position));
+
+    self.actor_index.remove(&player_id.to_string());
   }
 
   pub fn get_connected_bots(&self) -> &Vec<String> {
@@ -76,5 +82,23 @@ impl Area {
       .iter()
       .position(|id| id == bot_id)
       .map(|position| self.connected_bots.swap_remove(position));
+
+    self.actor_index.remove(&bot_id.to_string());
+  }
+
+  /// Tracks positions of connected actors for spatial queries, ignores actors outside of this area
+  pub(super) fn update_actor_position(&mut self, actor_id: &str, x: f32, y: f32, z: f32) {
+    let actor_id = actor_id.to_string();
+
+    if !self.connected_players.contains(&actor_id) && !self.connected_bots.contains(&actor_id) {
+      return;
+    }
+
+    self.actor_index.insert_point(actor_id, x, y, z);
+  }
+
+  /// Candidate actors near the bounds, bounds are (min_x, min_y, max_x, max_y)
+  pub(super) fn query_actors(&self, z: f32, bounds: (f32, f32, f32, f32)) -> HashSet<String> {
+    self.actor_index.query(z.floor() as i32, bounds)
   }
 }
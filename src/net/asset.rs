@@ -400,11 +400,11 @@ impl Asset {
         if let Ok(requires_scripts_func) =
           globals.get::<&str, mlua::Function>("package_requires_scripts")
         {
-          requires_scripts_func.call(())?;
+          requires_scripts_func.call::<_, ()>(())?;
         }
 
         let init_func: mlua::Function = globals.get("package_init")?;
-        init_func.call(package_table)?;
+        init_func.call::<_, ()>(package_table)?;
 
         // encounter detection
         let package_build_func: mlua::Value = globals.get("package_build")?;
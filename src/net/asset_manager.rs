@@ -24,12 +24,7 @@ impl AssetManager {
         if path.is_dir() {
           self.load_assets_from_dir(&path);
         } else {
-          let mut path_string = String::from("/server/") + path.to_str().unwrap_or_default();
-
-          // adjust windows paths
-          path_string = path_string.replace('\\', "/");
-
-          self.set_asset(path_string, Asset::load_from_file(&path));
+          self.set_asset(get_server_asset_path(&path), Asset::load_from_file(&path));
         }
       }
     }
@@ -131,3 +126,10 @@ impl AssetManager {
     }
   }
 }
+
+pub fn get_server_asset_path(path: &std::path::Path) -> String {
+  let path_string = String::from("/server/") + path.to_str().unwrap_or_default();
+
+  // adjust windows paths
+  path_string.replace('\\', "/")
+}
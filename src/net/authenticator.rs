@@ -0,0 +1,84 @@
+use crate::jobs::{JobPromise, PromiseValue};
+use std::net::SocketAddr;
+
+pub type AuthenticationResolver = Box<dyn FnOnce(Option<PromiseValue>) -> Authentication>;
+
+pub enum Authentication {
+  Accept,
+  Deny(String),
+  /// Resolved once the promise completes, the resolver may defer again
+  Defer(JobPromise, AuthenticationResolver),
+}
+
+pub trait Authenticator {
+  fn authenticate(
+    &mut self,
+    socket_address: SocketAddr,
+    username: &str,
+    identity: &str,
+    data: &str,
+  ) -> Authentication;
+}
+
+/// Accepts identities listed in a credentials file.
+///
+/// Each line is an identity optionally followed by a space and the only username allowed to use it.
+/// Lines starting with # are ignored. The file is read for every login, edits apply immediately.
+pub struct CredentialsFileAuthenticator {
+  path: String,
+}
+
+impl CredentialsFileAuthenticator {
+  pub fn new(path: String) -> CredentialsFileAuthenticator {
+    CredentialsFileAuthenticator { path }
+  }
+}
+
+impl Authenticator for CredentialsFileAuthenticator {
+  fn authenticate(
+    &mut self,
+    _socket_address: SocketAddr,
+    username: &str,
+    identity: &str,
+    _data: &str,
+  ) -> Authentication {
+    use crate::jobs::read_file::read_file;
+
+    let username = username.to_string();
+    let identity = identity.to_string();
+
+    Authentication::Defer(
+      read_file(self.path.clone()),
+      Box::new(move |value| {
+        let contents = match value {
+          Some(PromiseValue::Bytes(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
+          _ => String::new(),
+        };
+
+        if has_credentials(&contents, &username, &identity) {
+          Authentication::Accept
+        } else {
+          Authentication::Deny(String::from("Unrecognized identity"))
+        }
+      }),
+    )
+  }
+}
+
+fn has_credentials(contents: &str, username: &str, identity: &str) -> bool {
+  contents
+    .lines()
+    .map(|line| line.trim())
+    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    .any(|line| {
+      let (line_identity, line_username) = match line.split_once(' ') {
+        Some((line_identity, line_username)) => (line_identity, Some(line_username.trim())),
+        None => (line, None),
+      };
+
+      line_identity == identity
+        && line_username
+          .map(|line_username| line_username == username)
+          .unwrap_or(true)
+    })
+}
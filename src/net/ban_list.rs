@@ -0,0 +1,357 @@
+use log::*;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum BanTarget {
+  AddressRange { address: IpAddr, prefix_length: u8 },
+  Identity(String),
+}
+
+impl BanTarget {
+  /// Parses an ip address with an optional CIDR prefix length, such as 192.168.0.0/16
+  pub fn parse_address_range(text: &str) -> Option<BanTarget> {
+    let (address_str, prefix_str) = match text.split_once('/') {
+      Some((address_str, prefix_str)) => (address_str, Some(prefix_str)),
+      None => (text, None),
+    };
+
+    let address: IpAddr = address_str.trim().parse().ok()?;
+
+    let max_prefix_length = if address.is_ipv4() { 32 } else { 128 };
+
+    let prefix_length = match prefix_str {
+      Some(prefix_str) => prefix_str.trim().parse().ok()?,
+      None => max_prefix_length,
+    };
+
+    if prefix_length > max_prefix_length {
+      return None;
+    }
+
+    Some(BanTarget::AddressRange {
+      address,
+      prefix_length,
+    })
+  }
+
+  pub fn matches_address(&self, ip: IpAddr) -> bool {
+    let (address, prefix_length) = match self {
+      BanTarget::AddressRange {
+        address,
+        prefix_length,
+      } => (*address, *prefix_length as u32),
+      BanTarget::Identity(_) => return false,
+    };
+
+    match (address, ip) {
+      (IpAddr::V4(address), IpAddr::V4(ip)) => {
+        let mask = u32::MAX.checked_shl(32 - prefix_length).unwrap_or(0);
+        u32::from(address) & mask == u32::from(ip) & mask
+      }
+      (IpAddr::V6(address), IpAddr::V6(ip)) => {
+        let mask = u128::MAX.checked_shl(128 - prefix_length).unwrap_or(0);
+        u128::from(address) & mask == u128::from(ip) & mask
+      }
+      _ => false,
+    }
+  }
+}
+
+impl std::fmt::Display for BanTarget {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match self {
+      BanTarget::AddressRange {
+        address,
+        prefix_length,
+      } => write!(f, "{}/{}", address, prefix_length),
+      BanTarget::Identity(identity) => write!(f, "{}", identity),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Ban {
+  pub target: BanTarget,
+  pub reason: String,
+  /// Unix timestamp in seconds, None for permanent bans
+  pub expiration: Option<u64>,
+}
+
+impl Ban {
+  pub fn is_expired(&self) -> bool {
+    match self.expiration {
+      Some(expiration) => expiration <= current_timestamp(),
+      None => false,
+    }
+  }
+}
+
+pub(super) struct BanList {
+  path: PathBuf,
+  bans: Vec<Ban>,
+}
+
+impl BanList {
+  pub fn load(path: impl Into<PathBuf>) -> BanList {
+    let path = path.into();
+    let text = std::fs::read_to_string(&path).unwrap_or_default();
+
+    let bans = text
+      .lines()
+      .filter_map(parse_ban)
+      .filter(|ban| !ban.is_expired())
+      .collect();
+
+    BanList { path, bans }
+  }
+
+  pub fn add(&mut self, ban: Ban) {
+    self
+      .bans
+      .retain(|existing_ban| existing_ban.target != ban.target);
+    self.bans.push(ban);
+    self.save();
+  }
+
+  /// Returns true if a ban was removed
+  pub fn remove(&mut self, target: &BanTarget) -> bool {
+    let original_len = self.bans.len();
+
+    self.bans.retain(|ban| ban.target != *target);
+
+    let removed = self.bans.len() != original_len;
+
+    if removed {
+      self.save();
+    }
+
+    removed
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &Ban> {
+    self.bans.iter().filter(|ban| !ban.is_expired())
+  }
+
+  pub fn find_address_ban(&self, ip: IpAddr) -> Option<&Ban> {
+    self.iter().find(|ban| ban.target.matches_address(ip))
+  }
+
+  pub fn find_identity_ban(&self, identity: &str) -> Option<&Ban> {
+    self
+      .iter()
+      .find(|ban| matches!(&ban.target, BanTarget::Identity(banned) if banned == identity))
+  }
+
+  fn save(&mut self) {
+    self.bans.retain(|ban| !ban.is_expired());
+
+    let text: String = self.bans.iter().map(stringify_ban).collect();
+
+    if let Err(err) = std::fs::write(&self.path, text) {
+      warn!("Failed to save bans to {:?}: {}", self.path, err);
+    }
+  }
+}
+
+pub(super) fn current_timestamp() -> u64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+// [address|identity]\t[target]\t[expiration or -]\t[reason]
+fn stringify_ban(ban: &Ban) -> String {
+  let kind = match &ban.target {
+    BanTarget::AddressRange { .. } => "address",
+    BanTarget::Identity(_) => "identity",
+  };
+
+  let expiration = ban
+    .expiration
+    .map(|expiration| expiration.to_string())
+    .unwrap_or_else(|| String::from("-"));
+
+  // keep the reason on a single line
+  let reason = ban.reason.replace(['\t', '\n', '\r'], " ");
+
+  format!("{}\t{}\t{}\t{}\n", kind, ban.target, expiration, reason)
+}
+
+fn parse_ban(line: &str) -> Option<Ban> {
+  let mut fields = line.splitn(4, '\t');
+
+  let kind = fields.next()?;
+  let target_str = fields.next()?;
+  let expiration = fields.next()?.parse().ok();
+  let reason = fields.next().unwrap_or_default().to_string();
+
+  let target = match kind {
+    "address" => BanTarget::parse_address_range(target_str)?,
+    "identity" => BanTarget::Identity(target_str.to_string()),
+    _ => return None,
+  };
+
+  Some(Ban {
+    target,
+    reason,
+    expiration,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn address_range(text: &str) -> BanTarget {
+    BanTarget::parse_address_range(text).unwrap()
+  }
+
+  #[test]
+  fn parse_address_ranges() {
+    assert!(matches!(
+      address_range("192.168.0.1"),
+      BanTarget::AddressRange {
+        prefix_length: 32,
+        ..
+      }
+    ));
+    assert!(matches!(
+      address_range("::1"),
+      BanTarget::AddressRange {
+        prefix_length: 128,
+        ..
+      }
+    ));
+    assert!(matches!(
+      address_range("10.0.0.0 / 8"),
+      BanTarget::AddressRange {
+        prefix_length: 8,
+        ..
+      }
+    ));
+
+    assert!(BanTarget::parse_address_range("192.168.0.1/33").is_none());
+    assert!(BanTarget::parse_address_range("::1/129").is_none());
+    assert!(BanTarget::parse_address_range("192.168.0.1/-1").is_none());
+    assert!(BanTarget::parse_address_range("192.168.0").is_none());
+    assert!(BanTarget::parse_address_range("not an address").is_none());
+  }
+
+  #[test]
+  fn ipv4_ranges() {
+    let ip = |text: &str| text.parse::<IpAddr>().unwrap();
+
+    let range = address_range("192.168.0.0/16");
+    assert!(range.matches_address(ip("192.168.4.20")));
+    assert!(!range.matches_address(ip("192.169.0.1")));
+
+    let single = address_range("192.168.0.1/32");
+    assert!(single.matches_address(ip("192.168.0.1")));
+    assert!(!single.matches_address(ip("192.168.0.2")));
+
+    let everything = address_range("0.0.0.0/0");
+    assert!(everything.matches_address(ip("8.8.8.8")));
+    assert!(
+      !everything.matches_address(ip("::1")),
+      "ipv4 ranges should not match ipv6 addresses"
+    );
+  }
+
+  #[test]
+  fn ipv6_ranges() {
+    let ip = |text: &str| text.parse::<IpAddr>().unwrap();
+
+    let range = address_range("2001:db8::/32");
+    assert!(range.matches_address(ip("2001:db8:1234::1")));
+    assert!(!range.matches_address(ip("2001:db9::1")));
+
+    let single = address_range("2001:db8::1/128");
+    assert!(single.matches_address(ip("2001:db8::1")));
+    assert!(!single.matches_address(ip("2001:db8::2")));
+
+    let everything = address_range("::/0");
+    assert!(everything.matches_address(ip("fe80::1")));
+    assert!(!everything.matches_address(ip("127.0.0.1")));
+
+    assert!(!BanTarget::Identity(String::from("::1")).matches_address(ip("::1")));
+  }
+
+  #[test]
+  fn expiration() {
+    let mut ban = Ban {
+      target: BanTarget::Identity(String::from("identity")),
+      reason: String::new(),
+      expiration: None,
+    };
+
+    assert!(!ban.is_expired());
+
+    ban.expiration = Some(current_timestamp() - 1);
+    assert!(ban.is_expired());
+
+    ban.expiration = Some(current_timestamp() + 60);
+    assert!(!ban.is_expired());
+  }
+
+  #[test]
+  fn parse_lines() {
+    let ban = parse_ban("address\t10.0.0.0/8\t-\treason\twith tab").unwrap();
+    assert!(ban.target == address_range("10.0.0.0/8"));
+    assert_eq!(ban.expiration, None);
+    assert_eq!(ban.reason, "reason\twith tab");
+
+    let ban = parse_ban("identity\tabc+/=\t100").unwrap();
+    assert!(ban.target == BanTarget::Identity(String::from("abc+/=")));
+    assert_eq!(ban.expiration, Some(100));
+    assert_eq!(ban.reason, "");
+
+    assert!(parse_ban("").is_none());
+    assert!(parse_ban("address").is_none());
+    assert!(parse_ban("address\t10.0.0.0/8").is_none());
+    assert!(parse_ban("address\t10.0.0.0/40\t-\treason").is_none());
+    assert!(parse_ban("player\tabc\t-\treason").is_none());
+  }
+
+  #[test]
+  fn file_round_trip() {
+    let path = std::env::temp_dir().join(format!("ban_list_test_{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut ban_list = BanList::load(&path);
+
+    ban_list.add(Ban {
+      target: address_range("192.168.0.0/16"),
+      reason: String::from("multi\nline"),
+      expiration: None,
+    });
+    ban_list.add(Ban {
+      target: BanTarget::Identity(String::from("identity")),
+      reason: String::from("temporary"),
+      expiration: Some(current_timestamp() + 60),
+    });
+    ban_list.add(Ban {
+      target: BanTarget::Identity(String::from("expired")),
+      reason: String::new(),
+      expiration: Some(current_timestamp() - 1),
+    });
+
+    let loaded = BanList::load(&path);
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(loaded.iter().count(), 2, "expired bans should not be saved");
+
+    let address_ban = loaded
+      .find_address_ban("192.168.1.1".parse().unwrap())
+      .unwrap();
+    assert_eq!(address_ban.reason, "multi line");
+    assert_eq!(address_ban.expiration, None);
+
+    let identity_ban = loaded.find_identity_ban("identity").unwrap();
+    assert_eq!(identity_ban.reason, "temporary");
+    assert!(identity_ban.expiration.is_some());
+
+    assert!(loaded.find_identity_ban("expired").is_none());
+  }
+}
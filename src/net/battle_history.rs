@@ -0,0 +1,734 @@
+use super::player_store::{escape, unescape, FLUSH_TIMEOUT};
+use super::{BattleStats, EnemyBattleStats};
+use crate::helpers::{file_name_to_name, name_to_file_name};
+use log::*;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// older records are dropped once a player has more than this many, best times are kept in the index
+const MAX_RECORDS_PER_PLAYER: usize = 200;
+// percent encoded identities never contain dots, so the index can't collide with a player's records
+const BEST_BATTLES_FILE_NAME: &str = "best_times.tsv";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BattleOutcome {
+  Won,
+  Lost,
+  Ran,
+}
+
+impl BattleOutcome {
+  pub fn from_stats(battle_stats: &BattleStats) -> BattleOutcome {
+    if battle_stats.ran {
+      BattleOutcome::Ran
+    } else if battle_stats.health == 0 {
+      BattleOutcome::Lost
+    } else {
+      BattleOutcome::Won
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      BattleOutcome::Won => "won",
+      BattleOutcome::Lost => "lost",
+      BattleOutcome::Ran => "ran",
+    }
+  }
+
+  fn parse(text: &str) -> Option<BattleOutcome> {
+    match text {
+      "won" => Some(BattleOutcome::Won),
+      "lost" => Some(BattleOutcome::Lost),
+      "ran" => Some(BattleOutcome::Ran),
+      _ => None,
+    }
+  }
+}
+
+/// A finished battle from the perspective of a single player
+#[derive(Clone, Debug)]
+pub struct BattleRecord {
+  pub identity: String,
+  /// The player's name at the time of the battle
+  pub name: String,
+  /// None for PvP
+  pub package_path: Option<String>,
+  /// Identity of the other player in PvP
+  pub opponent: Option<String>,
+  /// Unix timestamps in seconds
+  pub started_at: u64,
+  pub ended_at: u64,
+  pub outcome: BattleOutcome,
+  pub stats: BattleStats,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct BattleSummary {
+  pub wins: usize,
+  pub losses: usize,
+  pub escapes: usize,
+}
+
+/// Battle records stored on disk with a file per identity, loaded off of the server thread while players are connected.
+/// Best times are kept in an index so leaderboards don't need to load every player's records.
+pub(super) struct BattleHistory {
+  dir: PathBuf,
+  records: RefCell<HashMap<String, Vec<BattleRecord>>>,
+  // players with records still being read, along with battles recorded while waiting
+  loading: RefCell<HashMap<String, PendingLoad>>,
+  loaded: Arc<Mutex<Vec<LoadedRecords>>>,
+  next_load_id: u64,
+  // fastest won battle for each player, keyed by package path with None for PvP
+  best_battles: HashMap<String, HashMap<Option<String>, BattleRecord>>,
+  // file access runs off of the server thread, one task at a time in the order they were queued
+  pending_tasks: Arc<Mutex<VecDeque<FileTask>>>,
+  // queued tasks that haven't completed yet
+  queued_tasks: Arc<AtomicUsize>,
+  task_lock: Arc<async_std::sync::Mutex<()>>,
+}
+
+struct PendingLoad {
+  id: u64,
+  records: Vec<BattleRecord>,
+}
+
+struct LoadedRecords {
+  identity: String,
+  id: u64,
+  records: Vec<BattleRecord>,
+}
+
+enum FileTask {
+  Write {
+    path: PathBuf,
+    text: String,
+    append: bool,
+  },
+  Load {
+    identity: String,
+    id: u64,
+    path: PathBuf,
+  },
+}
+
+impl BattleHistory {
+  pub fn new(dir: impl Into<PathBuf>) -> BattleHistory {
+    let dir = dir.into();
+    let best_battles = load_best_battles(&dir);
+
+    BattleHistory {
+      dir,
+      records: RefCell::new(HashMap::new()),
+      loading: RefCell::new(HashMap::new()),
+      loaded: Arc::new(Mutex::new(Vec::new())),
+      next_load_id: 0,
+      best_battles,
+      pending_tasks: Arc::new(Mutex::new(VecDeque::new())),
+      queued_tasks: Arc::new(AtomicUsize::new(0)),
+      task_lock: Arc::new(async_std::sync::Mutex::new(())),
+    }
+  }
+
+  /// Starts reading the identity's records, queries return nothing until the records are read
+  pub fn load(&mut self, identity: &str) {
+    if self.records.borrow().contains_key(identity) || self.loading.borrow().contains_key(identity)
+    {
+      return;
+    }
+
+    let id = self.next_load_id;
+    self.next_load_id += 1;
+
+    self.loading.borrow_mut().insert(
+      identity.to_string(),
+      PendingLoad {
+        id,
+        records: Vec::new(),
+      },
+    );
+
+    self.queue_task(FileTask::Load {
+      identity: identity.to_string(),
+      id,
+      path: self.dir.join(name_to_file_name(identity)),
+    });
+  }
+
+  /// Drops the identity's records from memory, new records are still saved
+  pub fn unload(&mut self, identity: &str) {
+    self.records.borrow_mut().remove(identity);
+    self.loading.borrow_mut().remove(identity);
+  }
+
+  pub fn record(&mut self, record: BattleRecord) {
+    self.receive_loaded_records();
+
+    let identity = record.identity.clone();
+    let line = stringify_record(&record);
+    let path = self.dir.join(name_to_file_name(&identity));
+
+    if record.outcome == BattleOutcome::Won {
+      self.update_best_battle(&record);
+    }
+
+    let mut records = self.records.borrow_mut();
+
+    let player_records = match records.get_mut(&identity) {
+      Some(player_records) => player_records,
+      None => {
+        // kept until the load completes, excess records are dropped after that
+        if let Some(pending_load) = self.loading.borrow_mut().get_mut(&identity) {
+          pending_load.records.push(record);
+        }
+
+        self.queue_write(path, line, true);
+        return;
+      }
+    };
+
+    player_records.push(record);
+
+    if player_records.len() > MAX_RECORDS_PER_PLAYER {
+      let text = drop_excess_records(player_records);
+      self.queue_write(path, text, false);
+    } else {
+      self.queue_write(path, line, true);
+    }
+  }
+
+  /// Newest records first
+  pub fn recent_battles(&self, identity: &str, count: usize) -> Vec<BattleRecord> {
+    self.with_records(identity, |records| {
+      records.iter().rev().take(count).cloned().collect()
+    })
+  }
+
+  pub fn summary(&self, identity: &str) -> BattleSummary {
+    self.with_records(identity, |records| {
+      let mut summary = BattleSummary::default();
+
+      for record in records {
+        match record.outcome {
+          BattleOutcome::Won => summary.wins += 1,
+          BattleOutcome::Lost => summary.losses += 1,
+          BattleOutcome::Ran => summary.escapes += 1,
+        }
+      }
+
+      summary
+    })
+  }
+
+  /// Fastest won battle, optionally limited to a package
+  pub fn best_battle(&self, identity: &str, package_path: Option<&str>) -> Option<BattleRecord> {
+    find_indexed_best_battle(self.best_battles.get(identity)?, package_path).cloned()
+  }
+
+  /// Fastest won battle for each player, fastest first
+  pub fn best_battles(&self, package_path: Option<&str>, count: usize) -> Vec<BattleRecord> {
+    let mut best_battles: Vec<BattleRecord> = self
+      .best_battles
+      .values()
+      .filter_map(|best_battles| find_indexed_best_battle(best_battles, package_path))
+      .cloned()
+      .collect();
+
+    best_battles.sort_by(|a, b| a.stats.time.total_cmp(&b.stats.time));
+    best_battles.truncate(count);
+
+    best_battles
+  }
+
+  fn update_best_battle(&mut self, record: &BattleRecord) {
+    let best_battles = self
+      .best_battles
+      .entry(record.identity.clone())
+      .or_default();
+
+    let is_faster = match best_battles.get(&record.package_path) {
+      Some(best_battle) => record.stats.time < best_battle.stats.time,
+      None => true,
+    };
+
+    if !is_faster {
+      return;
+    }
+
+    best_battles.insert(record.package_path.clone(), record.clone());
+
+    let text = stringify_best_battles(&self.best_battles);
+    self.queue_write(self.dir.join(BEST_BATTLES_FILE_NAME), text, false);
+  }
+
+  fn with_records<F, R>(&self, identity: &str, callback: F) -> R
+  where
+    F: FnOnce(&[BattleRecord]) -> R,
+  {
+    self.receive_loaded_records();
+
+    match self.records.borrow().get(identity) {
+      Some(player_records) => callback(player_records),
+      None => callback(&[]),
+    }
+  }
+
+  // moves finished loads into the cache, loads for players who were unloaded are discarded
+  fn receive_loaded_records(&self) {
+    let loaded = std::mem::take(&mut *self.loaded.lock().unwrap());
+
+    for loaded_records in loaded {
+      let mut loading = self.loading.borrow_mut();
+
+      match loading.get(&loaded_records.identity) {
+        Some(pending_load) if pending_load.id == loaded_records.id => {}
+        _ => continue,
+      }
+
+      let pending_load = loading.remove(&loaded_records.identity).unwrap();
+      drop(loading);
+
+      let mut player_records = loaded_records.records;
+      player_records.extend(pending_load.records);
+
+      if player_records.len() > MAX_RECORDS_PER_PLAYER {
+        let text = drop_excess_records(&mut player_records);
+        let path = self.dir.join(name_to_file_name(&loaded_records.identity));
+        self.queue_write(path, text, false);
+      }
+
+      self
+        .records
+        .borrow_mut()
+        .insert(loaded_records.identity, player_records);
+    }
+  }
+
+  fn queue_write(&self, path: PathBuf, text: String, append: bool) {
+    self.queue_task(FileTask::Write { path, text, append });
+  }
+
+  /// Blocks until queued writes are complete, used as the server stops
+  pub fn flush(&self) {
+    let timeout = std::time::Duration::from_secs_f32(FLUSH_TIMEOUT);
+    let start = std::time::Instant::now();
+
+    while self.queued_tasks.load(Ordering::Acquire) > 0 {
+      if start.elapsed() > timeout {
+        warn!("Timed out waiting for battle history to save");
+        break;
+      }
+
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+  }
+
+  fn queue_task(&self, task: FileTask) {
+    self.pending_tasks.lock().unwrap().push_back(task);
+    self.queued_tasks.fetch_add(1, Ordering::AcqRel);
+
+    let dir = self.dir.clone();
+    let pending_tasks = self.pending_tasks.clone();
+    let queued_tasks = self.queued_tasks.clone();
+    let task_lock = self.task_lock.clone();
+    let loaded = self.loaded.clone();
+
+    async_std::task::spawn(async move {
+      let _task_guard = task_lock.lock().await;
+
+      // tasks may start out of order, taking the oldest task keeps appends and loads in order
+      let task = pending_tasks.lock().unwrap().pop_front();
+
+      if let Some(task) = task {
+        run_file_task(&dir, task, &loaded).await;
+      }
+
+      queued_tasks.fetch_sub(1, Ordering::AcqRel);
+    });
+  }
+}
+
+async fn run_file_task(dir: &Path, task: FileTask, loaded: &Mutex<Vec<LoadedRecords>>) {
+  let (path, mut text, append) = match task {
+    FileTask::Write { path, text, append } => (path, text, append),
+    FileTask::Load { identity, id, path } => {
+      // missing files are new players
+      let text = async_std::fs::read_to_string(&path)
+        .await
+        .unwrap_or_default();
+      let records = parse_records(&identity, &text);
+
+      loaded.lock().unwrap().push(LoadedRecords {
+        identity,
+        id,
+        records,
+      });
+      return;
+    }
+  };
+
+  if let Err(err) = async_std::fs::create_dir_all(dir).await {
+    warn!("Failed to create {:?}: {}", dir, err);
+    return;
+  }
+
+  // record files are small, appends rewrite the file to avoid writing through async_std's File
+  if append {
+    if let Ok(existing_text) = async_std::fs::read_to_string(&path).await {
+      text.insert_str(0, &existing_text);
+    }
+  }
+
+  if let Err(err) = async_std::fs::write(&path, text).await {
+    warn!("Failed to save battle history to {:?}: {}", path, err);
+  }
+}
+
+// returns the text for the records that are kept
+fn drop_excess_records(player_records: &mut Vec<BattleRecord>) -> String {
+  let excess = player_records.len().saturating_sub(MAX_RECORDS_PER_PLAYER);
+  player_records.drain(..excess);
+
+  player_records.iter().map(stringify_record).collect()
+}
+
+fn find_indexed_best_battle<'a>(
+  best_battles: &'a HashMap<Option<String>, BattleRecord>,
+  package_path: Option<&str>,
+) -> Option<&'a BattleRecord> {
+  match package_path {
+    Some(package_path) => best_battles.get(&Some(package_path.to_string())),
+    None => best_battles
+      .values()
+      .min_by(|a, b| a.stats.time.total_cmp(&b.stats.time)),
+  }
+}
+
+// the index is rebuilt from every record file when it's missing, such as when upgrading from older servers
+fn load_best_battles(dir: &Path) -> HashMap<String, HashMap<Option<String>, BattleRecord>> {
+  let mut best_battles: HashMap<String, HashMap<Option<String>, BattleRecord>> = HashMap::new();
+
+  if let Ok(text) = std::fs::read_to_string(dir.join(BEST_BATTLES_FILE_NAME)) {
+    for line in text.lines() {
+      let (identity, record_line) = match line.split_once('\t') {
+        Some(fields) => fields,
+        None => continue,
+      };
+
+      if let Some(record) = parse_record(&unescape(identity), record_line) {
+        best_battles
+          .entry(record.identity.clone())
+          .or_default()
+          .insert(record.package_path.clone(), record);
+      }
+    }
+
+    return best_battles;
+  }
+
+  let dir_entries = match std::fs::read_dir(dir) {
+    Ok(dir_entries) => dir_entries,
+    Err(_) => return best_battles,
+  };
+
+  for dir_entry in dir_entries.flatten() {
+    let file_name = dir_entry.file_name().to_string_lossy().into_owned();
+
+    let identity = match file_name_to_name(&file_name) {
+      Some(identity) => identity,
+      // the index and unexpected files
+      None => continue,
+    };
+
+    let records = load_records(dir, &identity);
+    let player_best_battles = best_battles.entry(identity).or_default();
+
+    for record in records {
+      if record.outcome != BattleOutcome::Won {
+        continue;
+      }
+
+      let is_faster = match player_best_battles.get(&record.package_path) {
+        Some(best_battle) => record.stats.time < best_battle.stats.time,
+        None => true,
+      };
+
+      if is_faster {
+        player_best_battles.insert(record.package_path.clone(), record);
+      }
+    }
+  }
+
+  best_battles.retain(|_, player_best_battles| !player_best_battles.is_empty());
+
+  let text = stringify_best_battles(&best_battles);
+
+  if let Err(err) = std::fs::write(dir.join(BEST_BATTLES_FILE_NAME), text) {
+    warn!("Failed to save battle history index to {:?}: {}", dir, err);
+  }
+
+  best_battles
+}
+
+// [identity]\t followed by the record
+fn stringify_best_battles(
+  best_battles: &HashMap<String, HashMap<Option<String>, BattleRecord>>,
+) -> String {
+  let mut identities: Vec<&String> = best_battles.keys().collect();
+  identities.sort();
+
+  let mut text = String::new();
+
+  for identity in identities {
+    for record in best_battles[identity].values() {
+      text.push_str(&escape(identity));
+      text.push('\t');
+      text.push_str(&stringify_record(record));
+    }
+  }
+
+  text
+}
+
+// only used while the server starts, records are loaded off of the server thread after that
+fn load_records(dir: &Path, identity: &str) -> Vec<BattleRecord> {
+  let path = dir.join(name_to_file_name(identity));
+  let text = std::fs::read_to_string(path).unwrap_or_default();
+
+  parse_records(identity, &text)
+}
+
+fn parse_records(identity: &str, text: &str) -> Vec<BattleRecord> {
+  text
+    .lines()
+    .filter_map(|line| parse_record(identity, line))
+    .collect()
+}
+
+// [started_at]\t[ended_at]\t[outcome]\t[package_path or empty]\t[opponent or empty]\t[name]
+// \t[health]\t[score]\t[time]\t[ran]\t[emotion]\t[turns] followed by [enemy_id]\t[enemy_health] pairs
+fn stringify_record(record: &BattleRecord) -> String {
+  let stats = &record.stats;
+
+  let mut text = format!(
+    "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+    record.started_at,
+    record.ended_at,
+    record.outcome.as_str(),
+    escape(record.package_path.as_deref().unwrap_or_default()),
+    escape(record.opponent.as_deref().unwrap_or_default()),
+    escape(&record.name),
+    stats.health,
+    stats.score,
+    stats.time,
+    stats.ran,
+    stats.emotion,
+    stats.turns,
+  );
+
+  for enemy in &stats.enemies {
+    text.push_str(&format!("\t{}\t{}", escape(&enemy.id), enemy.health));
+  }
+
+  text.push('\n');
+  text
+}
+
+fn parse_record(identity: &str, line: &str) -> Option<BattleRecord> {
+  let mut fields = line.split('\t');
+
+  let started_at = fields.next()?.parse().ok()?;
+  let ended_at = fields.next()?.parse().ok()?;
+  let outcome = BattleOutcome::parse(fields.next()?)?;
+  let package_path = Some(unescape(fields.next()?)).filter(|path| !path.is_empty());
+  let opponent = Some(unescape(fields.next()?)).filter(|opponent| !opponent.is_empty());
+  let name = unescape(fields.next()?);
+
+  let mut stats = BattleStats {
+    health: fields.next()?.parse().ok()?,
+    score: fields.next()?.parse().ok()?,
+    time: fields.next()?.parse().ok()?,
+    ran: fields.next()?.parse().ok()?,
+    emotion: fields.next()?.parse().ok()?,
+    turns: fields.next()?.parse().ok()?,
+    enemies: Vec::new(),
+  };
+
+  while let (Some(id), Some(health)) = (fields.next(), fields.next()) {
+    stats.enemies.push(EnemyBattleStats {
+      id: unescape(id),
+      health: health.parse().ok()?,
+    });
+  }
+
+  Some(BattleRecord {
+    identity: identity.to_string(),
+    name,
+    package_path,
+    opponent,
+    started_at,
+    ended_at,
+    outcome,
+    stats,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_record(
+    identity: &str,
+    package_path: Option<&str>,
+    health: u32,
+    time: f32,
+  ) -> BattleRecord {
+    BattleRecord {
+      identity: identity.to_string(),
+      name: String::from("name"),
+      package_path: package_path.map(|path| path.to_string()),
+      opponent: None,
+      started_at: 10,
+      ended_at: 20,
+      outcome: if health == 0 {
+        BattleOutcome::Lost
+      } else {
+        BattleOutcome::Won
+      },
+      stats: BattleStats {
+        health,
+        score: 5,
+        time,
+        ran: false,
+        emotion: 0,
+        turns: 3,
+        enemies: vec![EnemyBattleStats {
+          id: String::from("enemy\tid"),
+          health: 0,
+        }],
+      },
+    }
+  }
+
+  #[test]
+  fn record_round_trip() {
+    let record = create_record("identity", Some("/server/mods/boss"), 40, 12.5);
+    let text = stringify_record(&record);
+    let parsed = parse_record("identity", text.trim_end()).unwrap();
+
+    assert_eq!(parsed.package_path, record.package_path);
+    assert_eq!(parsed.outcome, BattleOutcome::Won);
+    assert_eq!(parsed.stats.time, 12.5);
+    assert_eq!(parsed.stats.enemies[0].id, "enemy\tid");
+  }
+
+  #[test]
+  fn load_and_unload() {
+    let dir = std::env::temp_dir().join(format!("battle_history_load_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let text: String = [
+      create_record("a", Some("boss"), 10, 30.0),
+      create_record("a", Some("boss"), 0, 10.0),
+    ]
+    .iter()
+    .map(stringify_record)
+    .collect();
+
+    std::fs::write(dir.join(name_to_file_name("a")), text).unwrap();
+
+    let mut history = BattleHistory::new(&dir);
+    history.load("a");
+
+    // recorded while the file is read, kept after the older records
+    history.record(create_record("a", Some("other"), 10, 20.0));
+
+    let mut attempts = 0;
+
+    while history.recent_battles("a", 10).is_empty() && attempts < 500 {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+      attempts += 1;
+    }
+
+    let times: Vec<f32> = history
+      .recent_battles("a", 10)
+      .iter()
+      .map(|record| record.stats.time)
+      .collect();
+
+    assert_eq!(times, [20.0, 10.0, 30.0]);
+    assert_eq!(history.summary("a").losses, 1);
+
+    history.unload("a");
+    assert!(history.recent_battles("a", 10).is_empty());
+
+    // the new record is appended to the file after the load
+    let mut attempts = 0;
+
+    while load_records(&dir, "a").len() < 3 && attempts < 500 {
+      std::thread::sleep(std::time::Duration::from_millis(10));
+      attempts += 1;
+    }
+
+    assert_eq!(load_records(&dir, "a").len(), 3);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn best_battle_index() {
+    let dir = std::env::temp_dir().join(format!("battle_history_test_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let records = [
+      create_record("a/+", Some("boss"), 10, 30.0),
+      create_record("a/+", Some("boss"), 10, 20.0),
+      // losses don't count
+      create_record("a/+", Some("boss"), 0, 5.0),
+      create_record("a/+", Some("other"), 10, 25.0),
+      create_record("b", Some("boss"), 10, 15.0),
+    ];
+
+    for identity in ["a/+", "b"] {
+      let text: String = records
+        .iter()
+        .filter(|record| record.identity == identity)
+        .map(stringify_record)
+        .collect();
+
+      std::fs::write(dir.join(name_to_file_name(identity)), text).unwrap();
+    }
+
+    // rebuilt from the record files, then loaded from the saved index
+    let rebuilt = BattleHistory::new(&dir);
+    assert!(dir.join(BEST_BATTLES_FILE_NAME).exists());
+    let loaded = BattleHistory::new(&dir);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for history in [rebuilt, loaded] {
+      let best_time = |identity, package_path| {
+        history
+          .best_battle(identity, package_path)
+          .map(|record| record.stats.time)
+      };
+
+      assert_eq!(best_time("a/+", Some("boss")), Some(20.0));
+      assert_eq!(best_time("a/+", Some("other")), Some(25.0));
+      assert_eq!(best_time("a/+", None), Some(20.0));
+      assert_eq!(best_time("a/+", Some("missing")), None);
+
+      let best_battles = history.best_battles(Some("boss"), 10);
+      let identities: Vec<&str> = best_battles
+        .iter()
+        .map(|record| record.identity.as_str())
+        .collect();
+
+      assert_eq!(identities, ["b", "a/+"]);
+      assert_eq!(history.best_battles(Some("boss"), 1).len(), 1);
+    }
+  }
+}
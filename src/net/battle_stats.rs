@@ -1,10 +1,10 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EnemyBattleStats {
   pub id: String,
   pub health: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BattleStats {
   pub health: u32,
   pub score: u32,
@@ -0,0 +1,119 @@
+/// Messages longer than this are truncated
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChatChannel {
+  /// Players in the same area
+  Area,
+  Global,
+  /// Players sharing the sender's party
+  Party,
+  /// A single player
+  Whisper,
+  /// Messages from the server or plugins, players can't send these
+  System,
+}
+
+impl ChatChannel {
+  pub fn from_byte(byte: u8) -> Option<ChatChannel> {
+    match byte {
+      0 => Some(ChatChannel::Area),
+      1 => Some(ChatChannel::Global),
+      2 => Some(ChatChannel::Party),
+      3 => Some(ChatChannel::Whisper),
+      4 => Some(ChatChannel::System),
+      _ => None,
+    }
+  }
+
+  pub fn as_byte(self) -> u8 {
+    match self {
+      ChatChannel::Area => 0,
+      ChatChannel::Global => 1,
+      ChatChannel::Party => 2,
+      ChatChannel::Whisper => 3,
+      ChatChannel::System => 4,
+    }
+  }
+
+  pub fn as_str(self) -> &'static str {
+    match self {
+      ChatChannel::Area => "area",
+      ChatChannel::Global => "global",
+      ChatChannel::Party => "party",
+      ChatChannel::Whisper => "whisper",
+      ChatChannel::System => "system",
+    }
+  }
+
+  pub fn parse(name: &str) -> Option<ChatChannel> {
+    match name {
+      "area" => Some(ChatChannel::Area),
+      "global" => Some(ChatChannel::Global),
+      "party" => Some(ChatChannel::Party),
+      "whisper" => Some(ChatChannel::Whisper),
+      "system" => Some(ChatChannel::System),
+      _ => None,
+    }
+  }
+}
+
+/// Screens chat messages before plugins see them.
+pub trait ChatFilter {
+  /// Returns the message to send, or None to block it
+  fn filter(&mut self, player_id: &str, message: &str) -> Option<String>;
+}
+
+/// Masks words listed in a file.
+///
+/// Each line is a word to mask, matching is case insensitive and ignores partial words.
+/// Lines starting with # are ignored. The file is read once at startup.
+pub struct WordListChatFilter {
+  words: Vec<String>,
+}
+
+impl WordListChatFilter {
+  pub fn load(path: &str) -> WordListChatFilter {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|err| {
+      log::warn!("Failed to read chat filter {:?}: {}", path, err);
+      String::new()
+    });
+
+    let words = contents
+      .lines()
+      .map(|line| line.trim())
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .map(|line| line.to_lowercase())
+      .collect();
+
+    WordListChatFilter { words }
+  }
+}
+
+impl ChatFilter for WordListChatFilter {
+  fn filter(&mut self, _player_id: &str, message: &str) -> Option<String> {
+    let mut filtered = String::with_capacity(message.len());
+    let mut word = String::new();
+
+    for c in message.chars().chain(std::iter::once(' ')) {
+      if c.is_alphanumeric() {
+        word.push(c);
+        continue;
+      }
+
+      if self.words.contains(&word.to_lowercase()) {
+        filtered.extend(word.chars().map(|_| '*'));
+      } else {
+        filtered.push_str(&word);
+      }
+
+      word.clear();
+      filtered.push(c);
+    }
+
+    // remove the space added to flush the last word
+    filtered.pop();
+
+    Some(filtered)
+  }
+}
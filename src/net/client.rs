@@ -1,8 +1,19 @@
+use super::leaderboards::LeaderboardView;
 use super::{Actor, Direction, PlayerData, WidgetTracker};
-use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet};
 use std::net::SocketAddr;
 
+/// Details for the battle history, tracked alongside the battle_tracker
+pub(super) struct PendingBattle {
+  /// None for PvP
+  pub package_path: Option<String>,
+  pub opponent: Option<String>,
+  pub started_at: u64,
+  /// Set for co-op encounters
+  pub group_id: Option<String>,
+}
+
 pub(super) struct Client {
   pub socket_address: SocketAddr,
   pub actor: Actor,
@@ -20,8 +31,20 @@ pub(super) struct Client {
   pub animation_buffer: Vec<u8>,
   pub mugshot_texture_buffer: Vec<u8>,
   pub mugshot_animation_buffer: Vec<u8>,
-  pub widget_tracker: WidgetTracker<usize>,
-  pub battle_tracker: VecDeque<usize>,
+  /// Plugins that opened each widget, None for widgets opened by the server
+  pub widget_tracker: WidgetTracker<Option<usize>>,
+  /// Plugins that started each battle, None for battles started by the server
+  pub battle_tracker: VecDeque<Option<usize>>,
+  pub pending_battles: VecDeque<PendingBattle>,
+  /// An entry for each board in the widget tracker, in the same order. None for boards that aren't leaderboards
+  pub leaderboard_views: Vec<Option<LeaderboardView>>,
+  /// Id of the managed shop the player is viewing
+  pub managed_shop: Option<String>,
+  /// Item ids keyed by the names shown in the managed shop, purchases arrive as names
+  pub managed_shop_item_ids: HashMap<String, String>,
+  pub party_id: Option<String>,
+  /// Objects in the current area excluded for this player, by scripts or by gating
+  pub hidden_objects: HashSet<u32>,
   pub player_data: PlayerData,
   pub is_input_locked: bool,
 }
@@ -82,6 +105,12 @@ impl Client {
       mugshot_animation_buffer: Vec::new(),
       widget_tracker: WidgetTracker::new(),
       battle_tracker: VecDeque::new(),
+      pending_battles: VecDeque::new(),
+      leaderboard_views: Vec::new(),
+      managed_shop: None,
+      managed_shop_item_ids: HashMap::new(),
+      party_id: None,
+      hidden_objects: HashSet::new(),
       player_data: PlayerData::new(identity),
       is_input_locked: false,
     }
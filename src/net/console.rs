@@ -0,0 +1,183 @@
+use super::plugin_wrapper::PluginWrapper;
+use super::Net;
+use crate::plugins::PluginInterface;
+use log::*;
+
+pub struct ConsoleCommand {
+  pub name: String,
+  pub help: String,
+  /// The plugin that registered the command, the only plugin to receive it
+  pub(super) owner: Option<usize>,
+}
+
+const BUILT_IN_COMMANDS: [(&str, &str); 9] = [
+  ("help", "lists commands"),
+  ("players", "lists connected players"),
+  ("kick", "kick <player_id> <reason> - kicks a player"),
+  ("areas", "lists areas"),
+  (
+    "reload-area",
+    "reload-area <area_id> - reloads an area from ./areas",
+  ),
+  (
+    "reload-scripts",
+    "reload-scripts [script_name] - reloads every script, or a single script from ./scripts",
+  ),
+  ("broadcast", "broadcast <message> - messages every player"),
+  (
+    "transfer",
+    "transfer <player_id> <area_id> - transfers a player to an area's spawn",
+  ),
+  // handled by the console thread
+  ("stop", "saves player data and stops the server"),
+];
+
+pub(super) fn handle_console_command(
+  net: &mut Net,
+  plugin_wrapper: &mut PluginWrapper,
+  line: &str,
+) {
+  let mut words = line.split_whitespace();
+
+  let command = match words.next() {
+    Some(command) => command,
+    None => return,
+  };
+
+  let args: Vec<&str> = words.collect();
+
+  match command {
+    "help" => print_help(net),
+    "players" => list_players(net),
+    "kick" => {
+      let player_id = match args.first() {
+        Some(player_id) => *player_id,
+        None => {
+          warn!("Usage: kick <player_id> <reason>");
+          return;
+        }
+      };
+
+      if net.get_player(player_id).is_none() {
+        warn!("No player matching \"{}\" found", player_id);
+        return;
+      }
+
+      let reason = args[1..].join(" ");
+
+      net.kick_player(player_id, &reason, true);
+    }
+    "areas" => {
+      for area in net.get_areas() {
+        info!(
+          "{} \"{}\" ({} players)",
+          area.get_id(),
+          area.get_map().get_name(),
+          area.get_connected_players().len()
+        );
+      }
+    }
+    "reload-area" => match args.first() {
+      Some(area_id) => reload_area(net, area_id),
+      None => warn!("Usage: reload-area <area_id>"),
+    },
+    "reload-scripts" => plugin_wrapper.reload(net, args.first().copied()),
+    "broadcast" => {
+      let message = args.join(" ");
+
+      let player_ids: Vec<String> = net
+        .get_areas()
+        .flat_map(|area| area.get_connected_players().clone())
+        .collect();
+
+      // responses are tracked without an owner, so plugins won't receive them
+      net.set_active_plugin(None);
+
+      for player_id in player_ids {
+        net.message_player(&player_id, &message, "", "");
+      }
+    }
+    "transfer" => match (args.first(), args.get(1)) {
+      (Some(player_id), Some(area_id)) => transfer_player(net, player_id, area_id),
+      _ => warn!("Usage: transfer <player_id> <area_id>"),
+    },
+    _ => {
+      let registered = net
+        .get_console_commands()
+        .iter()
+        .any(|console_command| console_command.name == command);
+
+      if registered {
+        plugin_wrapper.handle_console_command(net, command, &args);
+      } else {
+        warn!(
+          "Unknown command \"{}\", use \"help\" to list commands",
+          command
+        );
+      }
+    }
+  }
+}
+
+fn print_help(net: &Net) {
+  for (name, help) in BUILT_IN_COMMANDS {
+    info!("{}: {}", name, help);
+  }
+
+  for console_command in net.get_console_commands() {
+    info!("{}: {}", console_command.name, console_command.help);
+  }
+}
+
+fn list_players(net: &Net) {
+  for area in net.get_areas() {
+    for player_id in area.get_connected_players() {
+      let player = match net.get_player(player_id) {
+        Some(player) => player,
+        None => continue,
+      };
+
+      let ip = net
+        .get_player_addr(player_id)
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default();
+
+      info!(
+        "{} \"{}\" in {} ({})",
+        player_id,
+        player.name,
+        area.get_id(),
+        ip
+      );
+    }
+  }
+}
+
+fn reload_area(net: &mut Net, area_id: &str) {
+  match net.load_area_file(area_id) {
+    Ok(()) => info!("Reloaded {}", area_id),
+    Err(err) => warn!("{}", err),
+  }
+}
+
+fn transfer_player(net: &mut Net, player_id: &str, area_id: &str) {
+  if net.get_player(player_id).is_none() {
+    warn!("No player matching \"{}\" found", player_id);
+    return;
+  }
+
+  let (x, y, z, direction) = match net.get_area(area_id) {
+    Some(area) => {
+      let map = area.get_map();
+      let (x, y, z) = map.get_spawn();
+
+      (x, y, z, map.get_spawn_direction())
+    }
+    None => {
+      warn!("No area matching \"{}\" found", area_id);
+      return;
+    }
+  };
+
+  net.transfer_player(player_id, area_id, true, x, y, z, direction);
+}
@@ -0,0 +1,146 @@
+use super::BattleStats;
+use std::time::Instant;
+
+/// A participant's results, None if the player disconnected or the battle timed out
+#[derive(Clone, Debug)]
+pub struct CoopBattleResult {
+  pub player_id: String,
+  pub battle_stats: Option<BattleStats>,
+}
+
+pub(super) struct CoopBattle {
+  pub group_id: String,
+  pub package_path: String,
+  /// In the order players were given
+  pub results: Vec<CoopBattleResult>,
+  /// Players who haven't reported results
+  pub waiting: Vec<String>,
+  pub started_at: Instant,
+}
+
+impl CoopBattle {
+  pub fn new(group_id: String, package_path: String, player_ids: Vec<String>) -> CoopBattle {
+    let results = player_ids
+      .iter()
+      .map(|player_id| CoopBattleResult {
+        player_id: player_id.clone(),
+        battle_stats: None,
+      })
+      .collect();
+
+    CoopBattle {
+      group_id,
+      package_path,
+      results,
+      waiting: player_ids,
+      started_at: Instant::now(),
+    }
+  }
+
+  pub fn report(&mut self, player_id: &str, battle_stats: Option<BattleStats>) {
+    if !self.waiting.iter().any(|id| id == player_id) {
+      return;
+    }
+
+    self.waiting.retain(|id| id != player_id);
+
+    if let Some(result) = self
+      .results
+      .iter_mut()
+      .find(|result| result.player_id == player_id)
+    {
+      result.battle_stats = battle_stats;
+    }
+  }
+
+  /// Stops waiting for results once the timeout passes, players who haven't reported are given no results
+  pub fn check_timeout(&mut self, timeout: f32) {
+    if self.started_at.elapsed().as_secs_f32() >= timeout {
+      self.waiting.clear();
+    }
+  }
+
+  pub fn is_complete(&self) -> bool {
+    self.waiting.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  fn create_battle() -> CoopBattle {
+    CoopBattle::new(
+      String::from("group"),
+      String::from("/server/mods/battle.zip"),
+      vec![String::from("a"), String::from("b"), String::from("c")],
+    )
+  }
+
+  fn stats(score: u32) -> BattleStats {
+    BattleStats {
+      health: 100,
+      score,
+      time: 60.0,
+      ran: false,
+      emotion: 0,
+      turns: 3,
+      enemies: Vec::new(),
+    }
+  }
+
+  fn scores(coop_battle: &CoopBattle) -> Vec<Option<u32>> {
+    coop_battle
+      .results
+      .iter()
+      .map(|result| result.battle_stats.as_ref().map(|stats| stats.score))
+      .collect()
+  }
+
+  #[test]
+  fn combined_results() {
+    let mut coop_battle = create_battle();
+
+    coop_battle.report("c", Some(stats(30)));
+    coop_battle.report("a", Some(stats(10)));
+    assert!(!coop_battle.is_complete());
+
+    // only the first report counts, outsiders are ignored
+    coop_battle.report("a", Some(stats(99)));
+    coop_battle.report("d", Some(stats(99)));
+
+    // disconnecting reports no results
+    coop_battle.report("b", None);
+
+    assert!(coop_battle.is_complete());
+    assert_eq!(scores(&coop_battle), vec![Some(10), None, Some(30)]);
+
+    let player_ids: Vec<&str> = coop_battle
+      .results
+      .iter()
+      .map(|result| result.player_id.as_str())
+      .collect();
+
+    assert_eq!(player_ids, vec!["a", "b", "c"]);
+  }
+
+  #[test]
+  fn timeout() {
+    let mut coop_battle = create_battle();
+    coop_battle.report("a", Some(stats(10)));
+
+    coop_battle.check_timeout(60.0);
+    assert!(!coop_battle.is_complete());
+
+    coop_battle.started_at = Instant::now() - Duration::from_secs(61);
+    coop_battle.check_timeout(60.0);
+
+    assert!(coop_battle.is_complete());
+    assert_eq!(scores(&coop_battle), vec![Some(10), None, None]);
+
+    // late results are ignored
+    coop_battle.report("b", Some(stats(20)));
+    assert_eq!(scores(&coop_battle), vec![Some(10), None, None]);
+  }
+}
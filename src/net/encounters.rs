@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+const DEFAULT_MIN_STEPS: f32 = 10.0;
+const DEFAULT_MAX_STEPS: f32 = 20.0;
+// movement further than this in a single update is treated as a teleport
+const MAX_STEP_DISTANCE: f32 = 1.0;
+
+/// Battle chosen from an encounter table, plugins may modify it before it starts
+#[derive(Clone)]
+pub struct Encounter {
+  pub package_path: String,
+  /// A Lua table such as `{ rank = 2 }`, read as the second param in package_build
+  pub data: Option<String>,
+}
+
+/// Read from the custom properties of an area or object:
+///
+/// - Encounters: one encounter per line, formatted as `package_path, weight, data?`
+/// - Encounter Min Steps: number
+/// - Encounter Max Steps: number
+pub(super) struct EncounterTable {
+  encounters: Vec<(Encounter, f32)>,
+  min_steps: f32,
+  max_steps: f32,
+}
+
+impl EncounterTable {
+  pub fn has_table(custom_properties: &HashMap<String, String>) -> bool {
+    custom_properties.contains_key("Encounters")
+  }
+
+  pub fn from(custom_properties: &HashMap<String, String>) -> Option<EncounterTable> {
+    let encounters_str = custom_properties.get("Encounters")?;
+
+    let encounters = encounters_str
+      .lines()
+      .filter(|line| !line.trim().is_empty())
+      .filter_map(|line| {
+        let mut fields = line.splitn(3, ',').map(|field| field.trim());
+
+        let package_path = fields.next()?.to_string();
+        let weight: f32 = fields.next().and_then(|weight| weight.parse().ok())?;
+        let data = fields
+          .next()
+          .filter(|data| !data.is_empty())
+          .map(|data| data.to_string());
+
+        Some((Encounter { package_path, data }, weight))
+      })
+      .filter(|(_, weight)| *weight > 0.0)
+      .collect();
+
+    let read_steps = |name: &str, default: f32| {
+      custom_properties
+        .get(name)
+        .and_then(|value| value.parse::<f32>().ok())
+        .filter(|steps| *steps >= 0.0)
+        .unwrap_or(default)
+    };
+
+    let min_steps = read_steps("Encounter Min Steps", DEFAULT_MIN_STEPS);
+    let max_steps = read_steps("Encounter Max Steps", DEFAULT_MAX_STEPS).max(min_steps);
+
+    Some(EncounterTable {
+      encounters,
+      min_steps,
+      max_steps,
+    })
+  }
+
+  pub fn roll_required_steps(&self) -> f32 {
+    use rand::Rng;
+
+    if self.min_steps == self.max_steps {
+      return self.min_steps;
+    }
+
+    rand::thread_rng().gen_range(self.min_steps..=self.max_steps)
+  }
+
+  /// Picks an encounter using the weights
+  pub fn choose(&self) -> Option<Encounter> {
+    let total_weight: f32 = self.encounters.iter().map(|(_, weight)| weight).sum();
+    let mut roll = rand::random::<f32>() * total_weight;
+
+    for (encounter, weight) in &self.encounters {
+      roll -= weight;
+
+      if roll <= 0.0 {
+        return Some(encounter.clone());
+      }
+    }
+
+    self
+      .encounters
+      .last()
+      .map(|(encounter, _)| encounter.clone())
+  }
+}
+
+pub(super) struct EncounterTracker {
+  pub area_id: String,
+  pub distance: f32,
+  /// Rolled when the player first walks somewhere with an encounter table
+  pub required_distance: Option<f32>,
+  pub enabled: bool,
+}
+
+impl EncounterTracker {
+  pub fn new(area_id: String) -> EncounterTracker {
+    EncounterTracker {
+      area_id,
+      distance: 0.0,
+      required_distance: None,
+      enabled: true,
+    }
+  }
+
+  pub fn reset(&mut self) {
+    self.distance = 0.0;
+    self.required_distance = None;
+  }
+}
+
+pub(super) fn step_distance(previous: (f32, f32, f32), current: (f32, f32, f32)) -> f32 {
+  let distance = ((current.0 - previous.0).powi(2) + (current.1 - previous.1).powi(2)).sqrt();
+
+  if distance > MAX_STEP_DISTANCE {
+    return 0.0;
+  }
+
+  distance
+}
@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+const POLL_RATE: f32 = 1.0;
+
+/// Polls folders for created, modified, and deleted files
+pub(super) struct FileWatcher {
+  dirs: Vec<PathBuf>,
+  modified_times: HashMap<PathBuf, SystemTime>,
+  last_poll: Instant,
+}
+
+impl FileWatcher {
+  pub fn new(dirs: Vec<PathBuf>) -> FileWatcher {
+    let mut modified_times = HashMap::new();
+
+    for dir in &dirs {
+      collect_modified_times(dir, &mut modified_times);
+    }
+
+    FileWatcher {
+      dirs,
+      modified_times,
+      last_poll: Instant::now(),
+    }
+  }
+
+  /// Returns paths of files that changed since the last poll, deleted files are included
+  pub fn poll(&mut self) -> Vec<PathBuf> {
+    if self.last_poll.elapsed().as_secs_f32() < POLL_RATE {
+      return Vec::new();
+    }
+
+    self.last_poll = Instant::now();
+
+    let mut modified_times = HashMap::new();
+
+    for dir in &self.dirs {
+      collect_modified_times(dir, &mut modified_times);
+    }
+
+    let mut changed_paths: Vec<PathBuf> = modified_times
+      .iter()
+      .filter(|(path, modified_time)| self.modified_times.get(*path) != Some(modified_time))
+      .map(|(path, _)| path.clone())
+      .collect();
+
+    let deleted_paths = self
+      .modified_times
+      .keys()
+      .filter(|path| !modified_times.contains_key(*path))
+      .cloned();
+
+    changed_paths.extend(deleted_paths);
+
+    self.modified_times = modified_times;
+
+    changed_paths
+  }
+}
+
+fn collect_modified_times(dir: &Path, modified_times: &mut HashMap<PathBuf, SystemTime>) {
+  let entries = match std::fs::read_dir(dir) {
+    Ok(entries) => entries,
+    Err(_) => return,
+  };
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+
+    if path.is_dir() {
+      collect_modified_times(&path, modified_times);
+    } else if let Ok(modified_time) = entry.metadata().and_then(|metadata| metadata.modified()) {
+      modified_times.insert(path, modified_time);
+    }
+  }
+}
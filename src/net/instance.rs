@@ -0,0 +1,65 @@
+use std::time::Instant;
+
+/// Seconds an instance can wait for its first member before it's destroyed
+const EMPTY_INSTANCE_GRACE_PERIOD: f32 = 60.0;
+
+/// A copy of a template area owned by a party, destroyed once every member leaves
+pub struct Instance {
+  area_id: String,
+  template_id: String,
+  party_id: String,
+  members: Vec<String>,
+  had_members: bool,
+  created_at: Instant,
+}
+
+impl Instance {
+  pub(super) fn new(area_id: String, template_id: String, party_id: String) -> Instance {
+    Instance {
+      area_id,
+      template_id,
+      party_id,
+      members: Vec::new(),
+      had_members: false,
+      created_at: Instant::now(),
+    }
+  }
+
+  pub fn get_area_id(&self) -> &str {
+    &self.area_id
+  }
+
+  pub fn get_template_id(&self) -> &str {
+    &self.template_id
+  }
+
+  pub fn get_party_id(&self) -> &str {
+    &self.party_id
+  }
+
+  pub fn get_members(&self) -> &Vec<String> {
+    &self.members
+  }
+
+  pub(super) fn add_member(&mut self, player_id: String) {
+    if !self.members.contains(&player_id) {
+      self.members.push(player_id);
+    }
+
+    self.had_members = true;
+  }
+
+  pub(super) fn remove_member(&mut self, player_id: &str) {
+    self
+      .members
+      .iter()
+      .position(|id| id == player_id)
+      .map(|position| self.members.swap_remove(position));
+  }
+
+  /// Instances are kept until every member leaves, new instances get time for scripts to populate them
+  pub(super) fn is_abandoned(&self) -> bool {
+    self.members.is_empty()
+      && (self.had_members || self.created_at.elapsed().as_secs_f32() > EMPTY_INSTANCE_GRACE_PERIOD)
+  }
+}
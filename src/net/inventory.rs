@@ -0,0 +1,253 @@
+use super::Item;
+
+/// Per-instance data, stacks only merge when their metadata matches
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ItemMetadata {
+  pub durability: Option<u32>,
+  /// Custom data for plugins
+  pub data: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ItemStack {
+  pub item_id: String,
+  pub quantity: u32,
+  pub metadata: ItemMetadata,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InventoryError {
+  PlayerNotFound,
+  UnknownItem,
+  InvalidQuantity,
+  NotEnoughItems,
+  QuantityLimit,
+  NotTradable,
+}
+
+impl std::fmt::Display for InventoryError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let message = match self {
+      InventoryError::PlayerNotFound => "player not found",
+      InventoryError::UnknownItem => "unknown item",
+      InventoryError::InvalidQuantity => "invalid quantity",
+      InventoryError::NotEnoughItems => "not enough items",
+      InventoryError::QuantityLimit => "quantity limit reached",
+      InventoryError::NotTradable => "item is not tradable",
+    };
+
+    write!(f, "{}", message)
+  }
+}
+
+pub(super) fn count_items(stacks: &[ItemStack], item_id: &str) -> u32 {
+  stacks
+    .iter()
+    .filter(|stack| stack.item_id == item_id)
+    .map(|stack| stack.quantity)
+    .sum()
+}
+
+/// Fills existing stacks with matching metadata before creating new stacks
+pub(super) fn add_items(
+  stacks: &mut Vec<ItemStack>,
+  item: &Item,
+  item_id: &str,
+  mut quantity: u32,
+  metadata: ItemMetadata,
+) {
+  let max_stack = item.max_stack.max(1);
+
+  for stack in stacks.iter_mut() {
+    if quantity == 0 {
+      return;
+    }
+
+    if stack.item_id != item_id || stack.metadata != metadata || stack.quantity >= max_stack {
+      continue;
+    }
+
+    let added = quantity.min(max_stack - stack.quantity);
+    stack.quantity += added;
+    quantity -= added;
+  }
+
+  while quantity > 0 {
+    let added = quantity.min(max_stack);
+
+    stacks.push(ItemStack {
+      item_id: item_id.to_string(),
+      quantity: added,
+      metadata: metadata.clone(),
+    });
+
+    quantity -= added;
+  }
+}
+
+/// Combines stacks of the item with matching metadata, keeping the order of the first stacks
+pub(super) fn merge_stacks(stacks: &mut Vec<ItemStack>, item: &Item, item_id: &str) {
+  let max_stack = item.max_stack.max(1);
+
+  for i in 0..stacks.len() {
+    if stacks[i].item_id != item_id || stacks[i].quantity == 0 {
+      continue;
+    }
+
+    for j in i + 1..stacks.len() {
+      if stacks[i].quantity >= max_stack {
+        break;
+      }
+
+      if stacks[j].item_id != item_id || stacks[j].metadata != stacks[i].metadata {
+        continue;
+      }
+
+      let moved = stacks[j].quantity.min(max_stack - stacks[i].quantity);
+      stacks[i].quantity += moved;
+      stacks[j].quantity -= moved;
+    }
+  }
+
+  stacks.retain(|stack| stack.quantity > 0);
+}
+
+/// Takes from the newest stacks first, returns the removed items with their metadata.
+/// Callers should check the count first, nothing is removed if there's not enough
+pub(super) fn remove_items(
+  stacks: &mut Vec<ItemStack>,
+  item_id: &str,
+  mut quantity: u32,
+) -> Vec<ItemStack> {
+  let mut removed = Vec::new();
+
+  if count_items(stacks, item_id) < quantity {
+    return removed;
+  }
+
+  for stack in stacks.iter_mut().rev() {
+    if quantity == 0 {
+      break;
+    }
+
+    if stack.item_id != item_id {
+      continue;
+    }
+
+    let taken = quantity.min(stack.quantity);
+    stack.quantity -= taken;
+    quantity -= taken;
+
+    removed.push(ItemStack {
+      item_id: item_id.to_string(),
+      quantity: taken,
+      metadata: stack.metadata.clone(),
+    });
+  }
+
+  stacks.retain(|stack| stack.quantity > 0);
+
+  removed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_item(max_stack: u32, max_quantity: Option<u32>) -> Item {
+    let mut item = Item::new(String::from("Potion"), String::new());
+    item.max_stack = max_stack;
+    item.max_quantity = max_quantity;
+    item
+  }
+
+  fn durability(durability: u32) -> ItemMetadata {
+    ItemMetadata {
+      durability: Some(durability),
+      data: None,
+    }
+  }
+
+  fn quantities(stacks: &[ItemStack]) -> Vec<u32> {
+    stacks.iter().map(|stack| stack.quantity).collect()
+  }
+
+  #[test]
+  fn stack_merging() {
+    let item = create_item(5, None);
+    let mut stacks = Vec::new();
+
+    add_items(&mut stacks, &item, "potion", 3, ItemMetadata::default());
+    add_items(&mut stacks, &item, "potion", 4, ItemMetadata::default());
+    assert_eq!(quantities(&stacks), vec![5, 2]);
+
+    // different metadata never shares a stack
+    add_items(&mut stacks, &item, "potion", 1, durability(10));
+    add_items(&mut stacks, &item, "potion", 2, ItemMetadata::default());
+    assert_eq!(quantities(&stacks), vec![5, 4, 1]);
+    assert_eq!(stacks[2].metadata, durability(10));
+
+    // unlimited stacks by default
+    let item = Item::new(String::from("Chip"), String::new());
+    let mut stacks = Vec::new();
+
+    add_items(&mut stacks, &item, "chip", 5, ItemMetadata::default());
+    add_items(&mut stacks, &item, "chip", 5, ItemMetadata::default());
+    assert_eq!(quantities(&stacks), vec![10]);
+  }
+
+  #[test]
+  fn merging_after_metadata_changes() {
+    let item = create_item(5, None);
+    let mut stacks = Vec::new();
+
+    add_items(&mut stacks, &item, "potion", 3, ItemMetadata::default());
+    add_items(&mut stacks, &item, "potion", 4, durability(10));
+    add_items(&mut stacks, &item, "other", 1, ItemMetadata::default());
+
+    stacks[1].metadata = ItemMetadata::default();
+    merge_stacks(&mut stacks, &item, "potion");
+
+    assert_eq!(quantities(&stacks), vec![5, 2, 1]);
+    assert_eq!(stacks[2].item_id, "other");
+  }
+
+  #[test]
+  fn max_quantity() {
+    let item = create_item(5, Some(10));
+
+    assert_eq!(item.validate_add(0, 10), Ok(()));
+    assert_eq!(item.validate_add(8, 3), Err(InventoryError::QuantityLimit));
+    assert_eq!(
+      item.validate_add(0, 0),
+      Err(InventoryError::InvalidQuantity)
+    );
+    assert_eq!(
+      item.validate_add(u32::MAX, 1),
+      Err(InventoryError::QuantityLimit)
+    );
+  }
+
+  #[test]
+  fn removal_across_stacks() {
+    let item = create_item(5, None);
+    let mut stacks = Vec::new();
+
+    add_items(&mut stacks, &item, "potion", 5, ItemMetadata::default());
+    add_items(&mut stacks, &item, "other", 1, ItemMetadata::default());
+    add_items(&mut stacks, &item, "potion", 3, durability(10));
+
+    // not enough items, nothing is removed
+    assert!(remove_items(&mut stacks, "potion", 9).is_empty());
+    assert_eq!(count_items(&stacks, "potion"), 8);
+
+    // newest stacks are taken first, keeping their metadata
+    let removed = remove_items(&mut stacks, "potion", 4);
+
+    assert_eq!(quantities(&removed), vec![3, 1]);
+    assert_eq!(removed[0].metadata, durability(10));
+    assert_eq!(removed[1].metadata, ItemMetadata::default());
+    assert_eq!(quantities(&stacks), vec![4, 1]);
+    assert_eq!(count_items(&stacks, "potion"), 4);
+  }
+}
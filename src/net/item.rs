@@ -1,4 +1,59 @@
+use super::InventoryError;
+
 pub struct Item {
   pub name: String,
   pub description: String,
+  /// Used to group items, such as "key" or "consumable"
+  pub category: String,
+  /// Quantity a single stack can hold, extra items create new stacks. Unlimited by default
+  pub max_stack: u32,
+  /// Quantity a single player can hold across stacks
+  pub max_quantity: Option<u32>,
+  /// Whether the item can be transferred between players
+  pub tradable: bool,
+}
+
+impl Item {
+  pub fn new(name: String, description: String) -> Item {
+    Item {
+      name,
+      description,
+      category: String::new(),
+      max_stack: u32::MAX,
+      max_quantity: None,
+      tradable: true,
+    }
+  }
+
+  /// Checked before items are given to a player holding `held` of this item
+  pub fn validate_add(&self, held: u32, quantity: u32) -> Result<(), InventoryError> {
+    if quantity == 0 {
+      return Err(InventoryError::InvalidQuantity);
+    }
+
+    match self.max_quantity {
+      Some(max_quantity) if held.saturating_add(quantity) > max_quantity => {
+        Err(InventoryError::QuantityLimit)
+      }
+      _ => Ok(()),
+    }
+  }
+
+  /// Checked before items move between players, in addition to validate_add
+  pub fn validate_transfer(&self) -> Result<(), InventoryError> {
+    if !self.tradable {
+      return Err(InventoryError::NotTradable);
+    }
+
+    Ok(())
+  }
+
+  /// Name sent to the client, stacks display their quantity
+  pub fn display_name(&self, quantity: u32) -> String {
+    if quantity > 1 {
+      format!("{} x{}", self.name, quantity)
+    } else {
+      self.name.clone()
+    }
+  }
 }
@@ -0,0 +1,518 @@
+use super::ban_list::current_timestamp;
+use super::player_store::{escape, unescape, FLUSH_TIMEOUT};
+use super::BbsPost;
+use crate::helpers::{file_name_to_name, name_to_file_name};
+use log::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortOrder {
+  /// Higher scores rank first
+  Descending,
+  /// Lower scores rank first, such as times
+  Ascending,
+}
+
+impl SortOrder {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SortOrder::Descending => "descending",
+      SortOrder::Ascending => "ascending",
+    }
+  }
+
+  pub fn parse(text: &str) -> Option<SortOrder> {
+    match text {
+      "descending" => Some(SortOrder::Descending),
+      "ascending" => Some(SortOrder::Ascending),
+      _ => None,
+    }
+  }
+
+  fn is_better(&self, score: f64, other: f64) -> bool {
+    match self {
+      SortOrder::Descending => score > other,
+      SortOrder::Ascending => score < other,
+    }
+  }
+}
+
+/// Controls how scores are displayed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScoreType {
+  Integer,
+  Number,
+  /// Seconds, displayed as minutes:seconds
+  Time,
+}
+
+impl ScoreType {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ScoreType::Integer => "integer",
+      ScoreType::Number => "number",
+      ScoreType::Time => "time",
+    }
+  }
+
+  pub fn parse(text: &str) -> Option<ScoreType> {
+    match text {
+      "integer" => Some(ScoreType::Integer),
+      "number" => Some(ScoreType::Number),
+      "time" => Some(ScoreType::Time),
+      _ => None,
+    }
+  }
+
+  pub fn format(&self, score: f64) -> String {
+    match self {
+      ScoreType::Integer => format!("{}", score.round() as i64),
+      ScoreType::Number => format!("{}", score),
+      ScoreType::Time => {
+        let centiseconds = (score.max(0.0) * 100.0).round() as u64;
+        let minutes = centiseconds / 6000;
+        let seconds = (centiseconds / 100) % 60;
+
+        format!("{}:{:02}.{:02}", minutes, seconds, centiseconds % 100)
+      }
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct LeaderboardEntry {
+  pub identity: String,
+  /// The player's name when the score was submitted
+  pub name: String,
+  pub score: f64,
+  /// Unix timestamp in seconds, earlier submissions win ties
+  pub updated_at: u64,
+}
+
+pub(super) struct Leaderboard {
+  pub sort_order: SortOrder,
+  pub score_type: ScoreType,
+  /// Sorted, best first
+  entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+  fn sort(&mut self) {
+    let sort_order = self.sort_order;
+
+    self.entries.sort_by(|a, b| {
+      let ordering = match sort_order {
+        SortOrder::Descending => b.score.total_cmp(&a.score),
+        SortOrder::Ascending => a.score.total_cmp(&b.score),
+      };
+
+      ordering.then(a.updated_at.cmp(&b.updated_at))
+    });
+  }
+
+  fn position(&self, identity: &str) -> Option<usize> {
+    self
+      .entries
+      .iter()
+      .position(|entry| entry.identity == identity)
+  }
+}
+
+/// Open leaderboard boards, the next page is sent when the client requests more posts
+pub(super) struct LeaderboardView {
+  pub board_name: String,
+  pub viewer_identity: String,
+  pub next_offset: usize,
+  pub page_size: usize,
+}
+
+/// Leaderboards stored on disk with a file per board, loaded when the server starts
+pub(super) struct Leaderboards {
+  dir: PathBuf,
+  boards: HashMap<String, Leaderboard>,
+  // board files that haven't been written yet, None for files waiting to be deleted
+  unwritten: Arc<Mutex<HashMap<String, Option<String>>>>,
+  // writes happen one at a time so an older save can't finish after a newer one
+  write_lock: Arc<async_std::sync::Mutex<()>>,
+}
+
+impl Leaderboards {
+  pub fn load(dir: impl Into<PathBuf>) -> Leaderboards {
+    let dir = dir.into();
+    let mut boards = HashMap::new();
+
+    if let Ok(dir_entries) = std::fs::read_dir(&dir) {
+      for dir_entry in dir_entries.flatten() {
+        let name = match file_name_to_name(&dir_entry.file_name().to_string_lossy()) {
+          Some(name) if is_valid_board_name(&name) => name,
+          _ => {
+            warn!(
+              "Skipping unexpected leaderboard file {:?}",
+              dir_entry.path()
+            );
+            continue;
+          }
+        };
+
+        let text = std::fs::read_to_string(dir_entry.path()).unwrap_or_default();
+
+        match parse_board(&text) {
+          Some(board) => {
+            boards.insert(name, board);
+          }
+          None => warn!("Failed to parse leaderboard {:?}", dir_entry.path()),
+        }
+      }
+    }
+
+    Leaderboards {
+      dir,
+      boards,
+      unwritten: Arc::new(Mutex::new(HashMap::new())),
+      write_lock: Arc::new(async_std::sync::Mutex::new(())),
+    }
+  }
+
+  /// Creates the board, or updates the sort order and score type of an existing board.
+  /// Returns false if the name can't be used
+  pub fn create(&mut self, name: &str, sort_order: SortOrder, score_type: ScoreType) -> bool {
+    if !is_valid_board_name(name) {
+      return false;
+    }
+
+    let board = self
+      .boards
+      .entry(name.to_string())
+      .or_insert_with(|| Leaderboard {
+        sort_order,
+        score_type,
+        entries: Vec::new(),
+      });
+
+    board.sort_order = sort_order;
+    board.score_type = score_type;
+    board.sort();
+
+    self.save(name);
+
+    true
+  }
+
+  pub fn remove(&mut self, name: &str) -> bool {
+    if self.boards.remove(name).is_none() {
+      return false;
+    }
+
+    self.queue_write(name, None);
+
+    true
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Leaderboard> {
+    self.boards.get(name)
+  }
+
+  /// Replaces the identity's score unless keep_best is set and the existing score is better.
+  /// Returns the rank for the identity, None if the board doesn't exist
+  pub fn submit(
+    &mut self,
+    board_name: &str,
+    identity: &str,
+    name: &str,
+    score: f64,
+    keep_best: bool,
+  ) -> Option<usize> {
+    let board = self.boards.get_mut(board_name)?;
+
+    match board.position(identity) {
+      Some(index) => {
+        let entry = &mut board.entries[index];
+
+        if keep_best && !board.sort_order.is_better(score, entry.score) {
+          return Some(index + 1);
+        }
+
+        entry.name = name.to_string();
+        entry.score = score;
+        entry.updated_at = current_timestamp();
+      }
+      None => board.entries.push(LeaderboardEntry {
+        identity: identity.to_string(),
+        name: name.to_string(),
+        score,
+        updated_at: current_timestamp(),
+      }),
+    }
+
+    board.sort();
+    let rank = board.position(identity).map(|index| index + 1);
+
+    self.save(board_name);
+
+    rank
+  }
+
+  pub fn remove_score(&mut self, board_name: &str, identity: &str) {
+    let board = match self.boards.get_mut(board_name) {
+      Some(board) => board,
+      None => return,
+    };
+
+    if let Some(index) = board.position(identity) {
+      board.entries.remove(index);
+      self.save(board_name);
+    }
+  }
+
+  /// Ranks start at 1
+  pub fn rank(&self, board_name: &str, identity: &str) -> Option<usize> {
+    let board = self.boards.get(board_name)?;
+
+    board.position(identity).map(|index| index + 1)
+  }
+
+  pub fn entries(&self, board_name: &str, offset: usize, count: usize) -> &[LeaderboardEntry] {
+    let entries = match self.boards.get(board_name) {
+      Some(board) => &board.entries,
+      None => return &[],
+    };
+
+    let start = offset.min(entries.len());
+    let end = offset.saturating_add(count).min(entries.len());
+
+    &entries[start..end]
+  }
+
+  pub fn len(&self, board_name: &str) -> usize {
+    self
+      .boards
+      .get(board_name)
+      .map(|board| board.entries.len())
+      .unwrap_or_default()
+  }
+
+  /// Post ids are ranks, the viewer's entry is marked as unread to stand out
+  pub fn posts(
+    &self,
+    board_name: &str,
+    viewer_identity: Option<&str>,
+    offset: usize,
+    count: usize,
+  ) -> Vec<BbsPost> {
+    let score_type = match self.boards.get(board_name) {
+      Some(board) => board.score_type,
+      None => return Vec::new(),
+    };
+
+    self
+      .entries(board_name, offset, count)
+      .iter()
+      .enumerate()
+      .map(|(i, entry)| {
+        let rank = offset + i + 1;
+
+        BbsPost {
+          id: rank.to_string(),
+          read: viewer_identity != Some(entry.identity.as_str()),
+          title: format!("#{} {}", rank, entry.name),
+          author: score_type.format(entry.score),
+        }
+      })
+      .collect()
+  }
+
+  /// Blocks until queued writes are complete, used as the server stops
+  pub fn flush(&self) {
+    let timeout = std::time::Duration::from_secs_f32(FLUSH_TIMEOUT);
+    let start = std::time::Instant::now();
+
+    // failed writes stay in the map, the timeout keeps them from blocking forever
+    while !self.unwritten.lock().unwrap().is_empty() {
+      if start.elapsed() > timeout {
+        warn!("Timed out waiting for leaderboards to save");
+        break;
+      }
+
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+  }
+
+  fn save(&self, board_name: &str) {
+    if let Some(board) = self.boards.get(board_name) {
+      self.queue_write(board_name, Some(stringify_board(board)));
+    }
+  }
+
+  // writes run off of the server thread, None deletes the board's file
+  fn queue_write(&self, board_name: &str, text: Option<String>) {
+    let board_name = board_name.to_string();
+
+    self
+      .unwritten
+      .lock()
+      .unwrap()
+      .insert(board_name.clone(), text);
+
+    let dir = self.dir.clone();
+    let path = self.dir.join(name_to_file_name(&board_name));
+    let unwritten = self.unwritten.clone();
+    let write_lock = self.write_lock.clone();
+
+    async_std::task::spawn(async move {
+      let _write_guard = write_lock.lock().await;
+
+      // a later save may have replaced the text while waiting, only the latest is written
+      let text = match unwritten.lock().unwrap().get(&board_name) {
+        Some(text) => text.clone(),
+        None => return,
+      };
+
+      let result = match &text {
+        Some(text) => match async_std::fs::create_dir_all(&dir).await {
+          Ok(()) => async_std::fs::write(&path, text).await,
+          Err(err) => Err(err),
+        },
+        None => match async_std::fs::remove_file(&path).await {
+          Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+          result => result,
+        },
+      };
+
+      if let Err(err) = result {
+        warn!("Failed to save leaderboard to {:?}: {}", path, err);
+        return;
+      }
+
+      let mut unwritten = unwritten.lock().unwrap();
+
+      if unwritten.get(&board_name) == Some(&text) {
+        unwritten.remove(&board_name);
+      }
+    });
+  }
+}
+
+/// Empty names and names made of dots are rejected, other names are encoded into file names
+fn is_valid_board_name(name: &str) -> bool {
+  !name.is_empty() && name != "." && name != ".."
+}
+
+// [sort_order]\t[score_type] followed by [identity]\t[name]\t[score]\t[updated_at] lines
+fn stringify_board(board: &Leaderboard) -> String {
+  let mut text = format!(
+    "{}\t{}\n",
+    board.sort_order.as_str(),
+    board.score_type.as_str()
+  );
+
+  for entry in &board.entries {
+    text.push_str(&format!(
+      "{}\t{}\t{}\t{}\n",
+      escape(&entry.identity),
+      escape(&entry.name),
+      entry.score,
+      entry.updated_at
+    ));
+  }
+
+  text
+}
+
+fn parse_board(text: &str) -> Option<Leaderboard> {
+  let mut lines = text.lines();
+  let mut header = lines.next()?.split('\t');
+
+  let sort_order = SortOrder::parse(header.next()?)?;
+  let score_type = ScoreType::parse(header.next()?)?;
+
+  let entries = lines.filter_map(parse_entry).collect();
+
+  let mut board = Leaderboard {
+    sort_order,
+    score_type,
+    entries,
+  };
+
+  board.sort();
+
+  Some(board)
+}
+
+fn parse_entry(line: &str) -> Option<LeaderboardEntry> {
+  let mut fields = line.split('\t');
+
+  Some(LeaderboardEntry {
+    identity: unescape(fields.next()?),
+    name: unescape(fields.next()?),
+    score: fields.next()?.parse().ok()?,
+    updated_at: fields.next()?.parse().ok()?,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn invalid_names() {
+    let dir = std::env::temp_dir().join(format!("leaderboards_test_{}", std::process::id()));
+    let mut leaderboards = Leaderboards::load(&dir);
+
+    for name in ["", ".", ".."] {
+      assert!(!leaderboards.create(name, SortOrder::Descending, ScoreType::Integer));
+      assert!(leaderboards.get(name).is_none());
+    }
+
+    assert!(!dir.exists());
+  }
+
+  #[test]
+  fn reload() {
+    let dir = std::env::temp_dir().join(format!("leaderboards_reload_test_{}", std::process::id()));
+    let mut leaderboards = Leaderboards::load(&dir);
+
+    assert!(leaderboards.create("boss_rush", SortOrder::Ascending, ScoreType::Time));
+    assert!(!leaderboards.create("..", SortOrder::Descending, ScoreType::Integer));
+    assert!(leaderboards.create("a/b", SortOrder::Descending, ScoreType::Integer));
+
+    assert_eq!(
+      leaderboards.submit("boss_rush", "a", "A", 30.0, true),
+      Some(1)
+    );
+    assert_eq!(
+      leaderboards.submit("boss_rush", "b", "B", 20.0, true),
+      Some(1)
+    );
+    assert_eq!(
+      leaderboards.submit("boss_rush", "a", "A", 40.0, true),
+      Some(2)
+    );
+    assert_eq!(leaderboards.submit("a/b", "a", "A", 5.0, true), Some(1));
+
+    assert!(leaderboards.create("removed", SortOrder::Descending, ScoreType::Integer));
+    assert!(leaderboards.remove("removed"));
+
+    leaderboards.flush();
+
+    let reloaded = Leaderboards::load(&dir);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let board = reloaded.get("boss_rush").unwrap();
+    assert_eq!(board.sort_order, SortOrder::Ascending);
+    assert_eq!(board.score_type, ScoreType::Time);
+
+    let identities: Vec<&str> = reloaded
+      .entries("boss_rush", 0, 10)
+      .iter()
+      .map(|entry| entry.identity.as_str())
+      .collect();
+
+    assert_eq!(identities, ["b", "a"]);
+    assert_eq!(reloaded.entries("boss_rush", 1, 10)[0].score, 30.0);
+    assert_eq!(reloaded.rank("a/b", "a"), Some(1));
+    assert!(reloaded.get("removed").is_none());
+  }
+}
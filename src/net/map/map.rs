@@ -1,3 +1,4 @@
+use super::super::spatial_index::SpatialIndex;
 use super::super::{Asset, Direction};
 use super::map_layer::MapLayer;
 use super::map_object::{MapObject, MapObjectData, MapObjectSpecification};
@@ -39,6 +40,7 @@ pub struct Map {
   layers: Vec<MapLayer>,
   next_layer_id: u32,
   objects: Vec<MapObject>,
+  object_index: SpatialIndex<u32>,
   next_object_id: u32,
   asset_stale: bool,
   cached: bool,
@@ -73,6 +75,7 @@ impl Map {
       layers: Vec::new(),
       next_layer_id: 0,
       objects: Vec::new(),
+      object_index: SpatialIndex::new(),
       next_object_id: 0,
       asset_stale: true,
       cached: false,
@@ -186,6 +189,7 @@ impl Map {
               }
             }
 
+            map.index_object(&map_object);
             map.objects.push(map_object);
           }
 
@@ -501,6 +505,57 @@ impl Map {
     self.objects.iter().find(|&o| o.name == name)
   }
 
+  /// Visible objects on layer z with a shape containing the point
+  pub fn get_objects_at(&self, x: f32, y: f32, z: f32) -> impl Iterator<Item = &MapObject> {
+    let (scale_x, scale_y) = self.object_scale();
+    let layer = z.floor() as usize;
+    let candidates = self.object_index.query(z.floor() as i32, (x, y, x, y));
+
+    self.objects.iter().filter(move |object| {
+      object.visible
+        && object.layer == layer
+        && candidates.contains(&object.id)
+        && object.contains_point(x, y, scale_x, scale_y)
+    })
+  }
+
+  /// Tests if a point in tile space is within the object's shape, using the map's object scale
+  pub fn object_contains_point(&self, object: &MapObject, x: f32, y: f32) -> bool {
+    let (scale_x, scale_y) = self.object_scale();
+
+    object.contains_point(x, y, scale_x, scale_y)
+  }
+
+  /// Visible objects on layer z with bounds overlapping the rect
+  pub fn get_objects_in_rect(
+    &self,
+    x: f32,
+    y: f32,
+    z: f32,
+    width: f32,
+    height: f32,
+  ) -> impl Iterator<Item = &MapObject> {
+    let (scale_x, scale_y) = self.object_scale();
+    let layer = z.floor() as usize;
+    let rect = (
+      x.min(x + width),
+      y.min(y + height),
+      x.max(x + width),
+      y.max(y + height),
+    );
+    let candidates = self.object_index.query(z.floor() as i32, rect);
+
+    self.objects.iter().filter(move |object| {
+      if !object.visible || object.layer != layer || !candidates.contains(&object.id) {
+        return false;
+      }
+
+      let (min_x, min_y, max_x, max_y) = object.bounds(scale_x, scale_y);
+
+      min_x <= rect.2 && max_x >= rect.0 && min_y <= rect.3 && max_y >= rect.1
+    })
+  }
+
   pub fn create_object(&mut self, specification: MapObjectSpecification) -> u32 {
     let id = self.next_object_id;
 
@@ -519,6 +574,7 @@ impl Map {
       custom_properties: specification.custom_properties,
     };
 
+    self.index_object(&map_object);
     self.objects.push(map_object);
 
     self.next_object_id += 1;
@@ -530,6 +586,7 @@ impl Map {
   pub fn remove_object(&mut self, id: u32) {
     if let Some(index) = self.objects.iter().position(|object| object.id == id) {
       self.objects.remove(index);
+      self.object_index.remove(&id);
 
       self.mark_dirty();
     }
@@ -560,6 +617,8 @@ impl Map {
   }
 
   pub fn resize_object(&mut self, id: u32, width: f32, height: f32) {
+    let (scale_x, scale_y) = self.object_scale();
+
     if let Some(object) = self.objects.iter_mut().find(|object| object.id == id) {
       if matches!(object.data, MapObjectData::Point) {
         // cant resize a point
@@ -569,14 +628,28 @@ impl Map {
       object.width = width;
       object.height = height;
 
+      self.object_index.insert(
+        object.id,
+        object.layer as i32,
+        object.bounds(scale_x, scale_y),
+      );
+
       self.mark_dirty();
     }
   }
 
   pub fn set_object_rotation(&mut self, id: u32, rotation: f32) {
+    let (scale_x, scale_y) = self.object_scale();
+
     if let Some(object) = self.objects.iter_mut().find(|object| object.id == id) {
       object.rotation = rotation;
 
+      self.object_index.insert(
+        object.id,
+        object.layer as i32,
+        object.bounds(scale_x, scale_y),
+      );
+
       self.mark_dirty();
     }
   }
@@ -590,19 +663,35 @@ impl Map {
   }
 
   pub fn move_object(&mut self, id: u32, x: f32, y: f32, layer: usize) {
+    let (scale_x, scale_y) = self.object_scale();
+
     if let Some(object) = self.objects.iter_mut().find(|object| object.id == id) {
       object.x = x;
       object.y = y;
       object.layer = layer;
 
+      self.object_index.insert(
+        object.id,
+        object.layer as i32,
+        object.bounds(scale_x, scale_y),
+      );
+
       self.mark_dirty();
     }
   }
 
   pub fn set_object_data(&mut self, id: u32, data: MapObjectData) {
+    let (scale_x, scale_y) = self.object_scale();
+
     if let Some(object) = self.objects.iter_mut().find(|object| object.id == id) {
       object.data = data;
 
+      self.object_index.insert(
+        object.id,
+        object.layer as i32,
+        object.bounds(scale_x, scale_y),
+      );
+
       self.mark_dirty();
     }
   }
@@ -660,6 +749,24 @@ impl Map {
     self.cached_string.clone()
   }
 
+  // objects are stored unscaled, tile space is half a tile wide
+  fn object_scale(&self) -> (f32, f32) {
+    (
+      1.0 / (self.tile_width as f32 / 2.0),
+      1.0 / self.tile_height as f32,
+    )
+  }
+
+  fn index_object(&mut self, object: &MapObject) {
+    let (scale_x, scale_y) = self.object_scale();
+
+    self.object_index.insert(
+      object.id,
+      object.layer as i32,
+      object.bounds(scale_x, scale_y),
+    );
+  }
+
   fn mark_dirty(&mut self) {
     self.asset_stale = true;
     self.cached = false;
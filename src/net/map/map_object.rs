@@ -2,6 +2,9 @@ use super::Tile;
 use crate::helpers::unwrap_and_parse_or_default;
 use std::collections::HashMap;
 
+// polylines have no area, points this close to a segment (in tiles) count as hits
+const POLYLINE_TOLERANCE: f32 = 0.1;
+
 pub struct MapObjectSpecification {
   pub name: String,
   pub class: String,
@@ -125,6 +128,115 @@ impl MapObject {
     }
   }
 
+  /// Solid objects block movement, set through the custom property "Solid"
+  pub fn is_solid(&self) -> bool {
+    self
+      .custom_properties
+      .get("Solid")
+      .map(|value| value == "true")
+      .unwrap_or_default()
+  }
+
+  /// Tests if a point in tile space is within this object's shape, rotation included.
+  /// Scale is the same scale used for rendering, points are stored unscaled.
+  pub fn contains_point(&self, x: f32, y: f32, scale_x: f32, scale_y: f32) -> bool {
+    // move the point into the object's local space, tiled rotates clockwise around the object's position
+    let (local_x, local_y) = rotate_point(x - self.x, y - self.y, -self.rotation);
+
+    match &self.data {
+      MapObjectData::Point => false,
+      MapObjectData::Polyline { points } => {
+        let points: Vec<(f32, f32)> = points
+          .iter()
+          .map(|(point_x, point_y)| (point_x * scale_x, point_y * scale_y))
+          .collect();
+
+        points.windows(2).any(|segment| {
+          distance_to_segment(local_x, local_y, segment[0], segment[1]) <= POLYLINE_TOLERANCE
+        })
+      }
+      MapObjectData::Rect => {
+        (0.0..=self.width).contains(&local_x) && (0.0..=self.height).contains(&local_y)
+      }
+      MapObjectData::TileObject { tile: _ } => {
+        // tile objects cover a single tile, matching spawn calculations for Home Warps
+        (0.0..=self.height).contains(&local_x) && (0.0..=self.height).contains(&local_y)
+      }
+      MapObjectData::Ellipse => {
+        if self.width <= 0.0 || self.height <= 0.0 {
+          return false;
+        }
+
+        let radius_x = self.width / 2.0;
+        let radius_y = self.height / 2.0;
+        let normalized_x = (local_x - radius_x) / radius_x;
+        let normalized_y = (local_y - radius_y) / radius_y;
+
+        normalized_x * normalized_x + normalized_y * normalized_y <= 1.0
+      }
+      MapObjectData::Polygon { points } => {
+        let points = points
+          .iter()
+          .map(|(point_x, point_y)| (point_x * scale_x, point_y * scale_y));
+
+        polygon_contains_point(points, local_x, local_y)
+      }
+    }
+  }
+
+  /// Axis aligned bounds in tile space as (min_x, min_y, max_x, max_y), rotation included.
+  pub fn bounds(&self, scale_x: f32, scale_y: f32) -> (f32, f32, f32, f32) {
+    let local_points = match &self.data {
+      MapObjectData::Point => vec![(0.0, 0.0)],
+      MapObjectData::Rect | MapObjectData::Ellipse => vec![
+        (0.0, 0.0),
+        (self.width, 0.0),
+        (0.0, self.height),
+        (self.width, self.height),
+      ],
+      MapObjectData::TileObject { tile: _ } => vec![
+        (0.0, 0.0),
+        (self.height, 0.0),
+        (0.0, self.height),
+        (self.height, self.height),
+      ],
+      MapObjectData::Polyline { points } | MapObjectData::Polygon { points } => points
+        .iter()
+        .map(|(point_x, point_y)| (point_x * scale_x, point_y * scale_y))
+        .collect(),
+    };
+
+    let padding = if matches!(self.data, MapObjectData::Polyline { points: _ }) {
+      POLYLINE_TOLERANCE
+    } else {
+      0.0
+    };
+
+    let mut bounds = (
+      f32::INFINITY,
+      f32::INFINITY,
+      f32::NEG_INFINITY,
+      f32::NEG_INFINITY,
+    );
+
+    for (local_x, local_y) in local_points {
+      let (x, y) = rotate_point(local_x, local_y, self.rotation);
+      let (x, y) = (x + self.x, y + self.y);
+
+      bounds.0 = bounds.0.min(x - padding);
+      bounds.1 = bounds.1.min(y - padding);
+      bounds.2 = bounds.2.max(x + padding);
+      bounds.3 = bounds.3.max(y + padding);
+    }
+
+    if bounds.0 > bounds.2 {
+      // polygons without points
+      return (self.x, self.y, self.x, self.y);
+    }
+
+    bounds
+  }
+
   pub fn render(&mut self, scale_x: f32, scale_y: f32) -> String {
     use super::render_helpers::render_custom_properties;
 
@@ -202,6 +314,56 @@ impl MapObject {
   }
 }
 
+fn distance_to_segment(x: f32, y: f32, (x1, y1): (f32, f32), (x2, y2): (f32, f32)) -> f32 {
+  let (segment_x, segment_y) = (x2 - x1, y2 - y1);
+  let length_squared = segment_x * segment_x + segment_y * segment_y;
+
+  let t = if length_squared == 0.0 {
+    0.0
+  } else {
+    (((x - x1) * segment_x + (y - y1) * segment_y) / length_squared).clamp(0.0, 1.0)
+  };
+
+  let (closest_x, closest_y) = (x1 + segment_x * t, y1 + segment_y * t);
+
+  ((x - closest_x).powi(2) + (y - closest_y).powi(2)).sqrt()
+}
+
+fn rotate_point(x: f32, y: f32, degrees: f32) -> (f32, f32) {
+  if degrees == 0.0 {
+    return (x, y);
+  }
+
+  let (sin, cos) = degrees.to_radians().sin_cos();
+
+  (x * cos - y * sin, x * sin + y * cos)
+}
+
+// even-odd rule
+fn polygon_contains_point<I>(points: I, x: f32, y: f32) -> bool
+where
+  I: Iterator<Item = (f32, f32)> + Clone,
+{
+  let mut inside = false;
+  let mut previous_point = match points.clone().last() {
+    Some(point) => point,
+    None => return false,
+  };
+
+  for point in points {
+    let (x1, y1) = previous_point;
+    let (x2, y2) = point;
+
+    if (y1 > y) != (y2 > y) && x < (x2 - x1) * (y - y1) / (y2 - y1) + x1 {
+      inside = !inside;
+    }
+
+    previous_point = point;
+  }
+
+  inside
+}
+
 fn read_points(points_str: &str) -> Vec<(f32, f32)> {
   points_str
     .split(' ')
@@ -224,3 +386,167 @@ fn render_points(points: &[(f32, f32)]) -> String {
     .collect::<Vec<String>>()
     .join(" ")
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_object(data: MapObjectData, x: f32, y: f32, width: f32, height: f32) -> MapObject {
+    MapObject {
+      id: 1,
+      name: String::new(),
+      class: String::new(),
+      visible: true,
+      x,
+      y,
+      layer: 0,
+      width,
+      height,
+      rotation: 0.0,
+      custom_properties: HashMap::new(),
+      data,
+    }
+  }
+
+  fn assert_bounds_eq(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) {
+    let close = |a: f32, b: f32| (a - b).abs() < 0.001;
+
+    assert!(
+      close(a.0, b.0) && close(a.1, b.1) && close(a.2, b.2) && close(a.3, b.3),
+      "{:?} != {:?}",
+      a,
+      b
+    );
+  }
+
+  #[test]
+  fn rect() {
+    let object = create_object(MapObjectData::Rect, 1.0, 1.0, 2.0, 1.0);
+
+    assert!(object.contains_point(1.5, 1.5, 1.0, 1.0));
+    assert!(object.contains_point(3.0, 2.0, 1.0, 1.0));
+    assert!(!object.contains_point(0.5, 1.5, 1.0, 1.0));
+    assert!(!object.contains_point(1.5, 2.5, 1.0, 1.0));
+
+    assert_bounds_eq(object.bounds(1.0, 1.0), (1.0, 1.0, 3.0, 2.0));
+  }
+
+  #[test]
+  fn rotated_rect() {
+    let mut object = create_object(MapObjectData::Rect, 0.0, 0.0, 2.0, 1.0);
+    object.rotation = 90.0;
+
+    // rotates clockwise around the object's position, swinging the rect into negative x
+    assert!(object.contains_point(-0.5, 1.5, 1.0, 1.0));
+    assert!(!object.contains_point(1.5, 0.5, 1.0, 1.0));
+
+    assert_bounds_eq(object.bounds(1.0, 1.0), (-1.0, 0.0, 0.0, 2.0));
+
+    object.rotation = 45.0;
+
+    let half_diagonal = std::f32::consts::FRAC_1_SQRT_2;
+
+    assert_bounds_eq(
+      object.bounds(1.0, 1.0),
+      (
+        -half_diagonal,
+        0.0,
+        half_diagonal * 2.0,
+        half_diagonal * 3.0,
+      ),
+    );
+  }
+
+  #[test]
+  fn ellipse() {
+    let object = create_object(MapObjectData::Ellipse, 0.0, 0.0, 4.0, 2.0);
+
+    assert!(object.contains_point(2.0, 1.0, 1.0, 1.0));
+    assert!(object.contains_point(3.9, 1.0, 1.0, 1.0));
+    // inside the bounding rect, outside of the curve
+    assert!(!object.contains_point(0.1, 0.1, 1.0, 1.0));
+    assert!(!object.contains_point(4.1, 1.0, 1.0, 1.0));
+
+    assert_bounds_eq(object.bounds(1.0, 1.0), (0.0, 0.0, 4.0, 2.0));
+
+    let flat_object = create_object(MapObjectData::Ellipse, 0.0, 0.0, 4.0, 0.0);
+
+    assert!(!flat_object.contains_point(2.0, 0.0, 1.0, 1.0));
+  }
+
+  #[test]
+  fn even_odd_polygon() {
+    // pentagram drawn in a single stroke, the center overlaps twice so it's outside
+    let points = vec![
+      (2.0, 0.0),
+      (3.176, 3.618),
+      (0.098, 1.382),
+      (3.902, 1.382),
+      (0.824, 3.618),
+    ];
+    let object = create_object(MapObjectData::Polygon { points }, 0.0, 0.0, 0.0, 0.0);
+
+    assert!(object.contains_point(2.0, 0.3, 1.0, 1.0));
+    assert!(object.contains_point(0.5, 1.5, 1.0, 1.0));
+    assert!(!object.contains_point(2.0, 2.0, 1.0, 1.0));
+    assert!(!object.contains_point(0.2, 0.2, 1.0, 1.0));
+
+    assert_bounds_eq(object.bounds(1.0, 1.0), (0.098, 0.0, 3.902, 3.618));
+  }
+
+  #[test]
+  fn scaled_polygon() {
+    // points are stored in pixels
+    let points = vec![(0.0, 0.0), (64.0, 0.0), (0.0, 64.0)];
+    let object = create_object(MapObjectData::Polygon { points }, 1.0, 1.0, 0.0, 0.0);
+    let scale = 1.0 / 32.0;
+
+    assert!(object.contains_point(1.5, 1.5, scale, scale));
+    assert!(!object.contains_point(2.5, 2.5, scale, scale));
+
+    assert_bounds_eq(object.bounds(scale, scale), (1.0, 1.0, 3.0, 3.0));
+
+    let empty_object = create_object(
+      MapObjectData::Polygon { points: Vec::new() },
+      1.0,
+      1.0,
+      0.0,
+      0.0,
+    );
+
+    assert!(!empty_object.contains_point(1.0, 1.0, 1.0, 1.0));
+    assert_bounds_eq(empty_object.bounds(1.0, 1.0), (1.0, 1.0, 1.0, 1.0));
+  }
+
+  #[test]
+  fn polyline_tolerance() {
+    let points = vec![(0.0, 0.0), (4.0, 0.0), (4.0, 2.0)];
+    let object = create_object(MapObjectData::Polyline { points }, 0.0, 0.0, 0.0, 0.0);
+
+    assert!(object.contains_point(2.0, 0.05, 1.0, 1.0));
+    assert!(object.contains_point(4.05, 1.0, 1.0, 1.0));
+    assert!(object.contains_point(4.05, 2.05, 1.0, 1.0));
+    assert!(!object.contains_point(2.0, 0.2, 1.0, 1.0));
+    assert!(!object.contains_point(4.0, 2.2, 1.0, 1.0));
+    // polylines aren't closed
+    assert!(!object.contains_point(2.0, 1.0, 1.0, 1.0));
+
+    assert_bounds_eq(
+      object.bounds(1.0, 1.0),
+      (
+        -POLYLINE_TOLERANCE,
+        -POLYLINE_TOLERANCE,
+        4.0 + POLYLINE_TOLERANCE,
+        2.0 + POLYLINE_TOLERANCE,
+      ),
+    );
+  }
+
+  #[test]
+  fn point() {
+    let object = create_object(MapObjectData::Point, 1.0, 2.0, 0.0, 0.0);
+
+    assert!(!object.contains_point(1.0, 2.0, 1.0, 1.0));
+    assert_bounds_eq(object.bounds(1.0, 1.0), (1.0, 2.0, 1.0, 2.0));
+  }
+}
@@ -0,0 +1,272 @@
+use std::time::Instant;
+
+/// Rating for players who haven't played in a queue
+pub const DEFAULT_RATING: f32 = 1500.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchmakingError {
+  UnknownQueue,
+  AlreadyQueued,
+  PlayerBusy,
+  InvalidRating,
+}
+
+impl std::fmt::Display for MatchmakingError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let message = match self {
+      MatchmakingError::UnknownQueue => "no matching queue",
+      MatchmakingError::AlreadyQueued => "player is already queued",
+      MatchmakingError::PlayerBusy => "player is busy",
+      MatchmakingError::InvalidRating => "invalid rating",
+    };
+
+    write!(f, "{}", message)
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchmakingRules {
+  /// Largest rating difference allowed when a player joins the queue
+  pub rating_window: f32,
+  /// Added to the rating window for every second a player waits
+  pub rating_window_growth: f32,
+  /// The rating window stops growing here
+  pub max_rating_window: Option<f32>,
+  /// Only match players in the same area
+  pub same_area: bool,
+  /// How far ratings move after a battle, the Elo K-factor
+  pub k_factor: f32,
+}
+
+impl Default for MatchmakingRules {
+  fn default() -> MatchmakingRules {
+    MatchmakingRules {
+      rating_window: 100.0,
+      rating_window_growth: 10.0,
+      max_rating_window: None,
+      same_area: false,
+      k_factor: 32.0,
+    }
+  }
+}
+
+pub(super) struct QueueEntry {
+  pub player_id: String,
+  pub rating: f32,
+  pub joined_at: Instant,
+}
+
+pub(super) struct MatchmakingQueue {
+  /// Plugin receiving results for matched battles
+  pub owner: Option<usize>,
+  pub rules: MatchmakingRules,
+  /// In join order
+  pub entries: Vec<QueueEntry>,
+}
+
+impl MatchmakingQueue {
+  pub fn new(owner: Option<usize>, rules: MatchmakingRules) -> MatchmakingQueue {
+    MatchmakingQueue {
+      owner,
+      rules,
+      entries: Vec::new(),
+    }
+  }
+
+  /// Indices of the two players who waited the longest and are allowed to play each other.
+  /// `get_area_id` returns None for players who can't battle right now
+  pub fn find_match<'a>(
+    &self,
+    get_area_id: impl Fn(&str) -> Option<&'a str>,
+  ) -> Option<(usize, usize)> {
+    let now = Instant::now();
+
+    let area_ids: Vec<Option<&str>> = self
+      .entries
+      .iter()
+      .map(|entry| get_area_id(&entry.player_id))
+      .collect();
+
+    for (i, entry) in self.entries.iter().enumerate() {
+      let area_id = match area_ids[i] {
+        Some(area_id) => area_id,
+        None => continue,
+      };
+
+      // the older entry has the widest window
+      let rating_window = self.rating_window(entry, now);
+
+      for (j, other_entry) in self.entries.iter().enumerate().skip(i + 1) {
+        let other_area_id = match area_ids[j] {
+          Some(other_area_id) => other_area_id,
+          None => continue,
+        };
+
+        if self.rules.same_area && area_id != other_area_id {
+          continue;
+        }
+
+        if (entry.rating - other_entry.rating).abs() <= rating_window {
+          return Some((i, j));
+        }
+      }
+    }
+
+    None
+  }
+
+  fn rating_window(&self, entry: &QueueEntry, now: Instant) -> f32 {
+    let wait_time = (now - entry.joined_at).as_secs_f32();
+    let rating_window = self.rules.rating_window + self.rules.rating_window_growth * wait_time;
+
+    match self.rules.max_rating_window {
+      Some(max_rating_window) => rating_window.min(max_rating_window),
+      None => rating_window,
+    }
+  }
+}
+
+/// Elo, the score is 1.0 for a win and 0.0 for a loss
+pub(super) fn update_rating(rating: f32, opponent_rating: f32, score: f32, k_factor: f32) -> f32 {
+  let expected_score = 1.0 / (1.0 + 10.0_f32.powf((opponent_rating - rating) / 400.0));
+
+  rating + k_factor * (score - expected_score)
+}
+
+/// Two queued players were matched and their battle started
+pub(super) struct MatchmakingEvent {
+  pub queue_id: String,
+  pub player_ids: [String; 2],
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::Duration;
+
+  fn create_queue(rules: MatchmakingRules, entries: &[(&str, f32, u64)]) -> MatchmakingQueue {
+    let now = Instant::now();
+    let mut queue = MatchmakingQueue::new(None, rules);
+
+    for (player_id, rating, waited_seconds) in entries {
+      queue.entries.push(QueueEntry {
+        player_id: player_id.to_string(),
+        rating: *rating,
+        joined_at: now - Duration::from_secs(*waited_seconds),
+      });
+    }
+
+    queue
+  }
+
+  fn fixed_window_rules(rating_window: f32) -> MatchmakingRules {
+    MatchmakingRules {
+      rating_window,
+      rating_window_growth: 0.0,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn update_rating() {
+    // evenly matched players move by half of the k-factor
+    assert_eq!(super::update_rating(1500.0, 1500.0, 1.0, 32.0), 1516.0);
+    assert_eq!(super::update_rating(1500.0, 1500.0, 0.0, 32.0), 1484.0);
+
+    // upsets move ratings further than expected results
+    let upset = super::update_rating(1300.0, 1700.0, 1.0, 32.0) - 1300.0;
+    let expected = super::update_rating(1700.0, 1300.0, 1.0, 32.0) - 1700.0;
+
+    assert!((upset - 32.0 * 10.0 / 11.0).abs() < 0.01);
+    assert!((expected - 32.0 / 11.0).abs() < 0.01);
+
+    // rating is conserved between the two players
+    let winner = super::update_rating(1620.0, 1480.0, 1.0, 24.0);
+    let loser = super::update_rating(1480.0, 1620.0, 0.0, 24.0);
+
+    assert!((winner + loser - 3100.0).abs() < 0.01);
+  }
+
+  #[test]
+  fn find_match_in_join_order() {
+    let queue = create_queue(
+      fixed_window_rules(100.0),
+      &[
+        ("a", 1500.0, 0),
+        ("b", 1800.0, 0),
+        ("c", 1750.0, 0),
+        ("d", 1550.0, 0),
+      ],
+    );
+
+    // a waited the longest and matches d before b matches c
+    assert_eq!(queue.find_match(|_| Some("area")), Some((0, 3)));
+
+    let queue = create_queue(
+      fixed_window_rules(100.0),
+      &[("a", 1500.0, 0), ("b", 1800.0, 0)],
+    );
+
+    assert_eq!(queue.find_match(|_| Some("area")), None);
+  }
+
+  #[test]
+  fn find_match_skips_busy_players() {
+    let queue = create_queue(
+      fixed_window_rules(100.0),
+      &[("a", 1500.0, 0), ("b", 1500.0, 0), ("c", 1500.0, 0)],
+    );
+
+    let find_area = |player_id: &str| Some("area").filter(|_| player_id != "a");
+
+    assert_eq!(queue.find_match(find_area), Some((1, 2)));
+  }
+
+  #[test]
+  fn find_match_window_growth() {
+    let rules = MatchmakingRules {
+      rating_window: 100.0,
+      rating_window_growth: 10.0,
+      max_rating_window: Some(200.0),
+      ..Default::default()
+    };
+
+    // 100 + 10 * 5 covers the 150 difference
+    let queue = create_queue(rules.clone(), &[("a", 1500.0, 5), ("b", 1650.0, 0)]);
+    assert_eq!(queue.find_match(|_| Some("area")), Some((0, 1)));
+
+    let queue = create_queue(rules.clone(), &[("a", 1500.0, 2), ("b", 1650.0, 0)]);
+    assert_eq!(queue.find_match(|_| Some("area")), None);
+
+    // the window stops growing at the max
+    let queue = create_queue(rules, &[("a", 1500.0, 60), ("b", 1750.0, 0)]);
+    assert_eq!(queue.find_match(|_| Some("area")), None);
+  }
+
+  #[test]
+  fn find_match_same_area() {
+    let rules = MatchmakingRules {
+      same_area: true,
+      ..fixed_window_rules(100.0)
+    };
+
+    let queue = create_queue(
+      rules,
+      &[("a", 1500.0, 0), ("b", 1500.0, 0), ("c", 1500.0, 0)],
+    );
+
+    let find_area = |player_id: &str| match player_id {
+      "a" => Some("lobby"),
+      _ => Some("arena"),
+    };
+
+    assert_eq!(queue.find_match(find_area), Some((1, 2)));
+
+    let queue = create_queue(
+      fixed_window_rules(100.0),
+      &[("a", 1500.0, 0), ("b", 1500.0, 0)],
+    );
+
+    assert_eq!(queue.find_match(find_area), Some((0, 1)));
+  }
+}
@@ -6,28 +6,71 @@ pub mod actor_property_animation;
 mod area;
 pub mod asset;
 mod asset_manager;
+mod authenticator;
+mod ban_list;
+mod battle_history;
 mod battle_stats;
 pub mod bbs_post;
 mod boot;
+mod chat;
 mod client;
+mod console;
+mod coop_battles;
 mod direction;
+mod encounters;
+mod file_watcher;
+mod instance;
+mod inventory;
 mod item;
+mod leaderboards;
 pub mod map;
+mod matchmaking;
+mod movement_validator;
+mod net_event;
+mod parties;
+mod pathfinding;
 mod player_data;
+mod player_store;
 mod plugin_wrapper;
+mod pvp_battles;
+mod quests;
+mod rate_limiter;
 mod server;
 mod shop_item;
+mod spatial_index;
+mod tile_classes;
+mod trades;
+mod triggers;
 mod widget_tracker;
 
 pub use actor::Actor;
 pub use area::Area;
 pub use asset::*;
+pub use authenticator::*;
+pub use ban_list::{Ban, BanTarget};
+pub use battle_history::{BattleRecord, BattleSummary};
 pub use battle_stats::*;
 pub use bbs_post::BbsPost;
+pub use chat::{ChatChannel, ChatFilter, WordListChatFilter};
+pub use console::ConsoleCommand;
+pub use coop_battles::CoopBattleResult;
 pub use direction::Direction;
+pub use encounters::Encounter;
+pub use instance::Instance;
+pub use inventory::{InventoryError, ItemMetadata, ItemStack};
 pub use item::Item;
+pub use leaderboards::{LeaderboardEntry, ScoreType, SortOrder};
+pub use matchmaking::{MatchmakingError, MatchmakingRules};
 pub use net::Net;
+pub use parties::PartyError;
 pub use player_data::PlayerData;
+pub use player_store::{
+  FilePlayerStore, PlayerDataLoad, PlayerDataResolver, PlayerStore, DEFAULT_PLAYER_STORAGE_PATH,
+};
+pub use pvp_battles::SpectateError;
+pub use quests::{FlagValue, Quest, QuestCondition, QuestError, QuestProgress, QuestState};
+pub use rate_limiter::*;
 pub use server::*;
-pub use shop_item::ShopItem;
+pub use shop_item::{ShopItem, ShopPurchase};
+pub use trades::{TradeError, TradeOffer};
 pub use widget_tracker::WidgetTracker;
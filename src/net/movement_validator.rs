@@ -0,0 +1,225 @@
+use super::asset_manager::AssetManager;
+use super::map::Map;
+use super::tile_classes::TileClassCache;
+use super::Net;
+
+// extra distance allowed per update to account for jitter and packet bundling
+const DISTANCE_TOLERANCE: f32 = 0.5;
+// caps how much idle time can be spent on a single movement
+const MAX_ELAPSED_TIME: f32 = 1.0;
+// distance between points checked along a movement, small enough to catch thin objects
+const PATH_STEP: f32 = 0.125;
+
+pub(super) struct MovementValidator {
+  max_speed: f32,
+  tile_class_cache: TileClassCache,
+}
+
+impl MovementValidator {
+  pub fn new(max_speed: f32) -> MovementValidator {
+    MovementValidator {
+      max_speed,
+      tile_class_cache: TileClassCache::new(),
+    }
+  }
+
+  /// Returns the reason for rejection if the movement is invalid
+  pub fn validate(
+    &mut self,
+    net: &Net,
+    player_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+  ) -> Result<(), &'static str> {
+    let client = match net.get_client(player_id) {
+      Some(client) => client,
+      None => return Ok(()),
+    };
+
+    let actor = &client.actor;
+
+    // the server asked the client to be here
+    if distance(x, y, client.warp_x, client.warp_y) <= DISTANCE_TOLERANCE
+      && (z - client.warp_z).abs() <= DISTANCE_TOLERANCE
+    {
+      return Ok(());
+    }
+
+    let elapsed_time = actor
+      .last_movement_time
+      .elapsed()
+      .as_secs_f32()
+      .min(MAX_ELAPSED_TIME);
+
+    let max_distance = self.max_speed * elapsed_time + DISTANCE_TOLERANCE;
+
+    if distance(x, y, actor.x, actor.y) > max_distance {
+      return Err("speed");
+    }
+
+    let area = match net.get_area(&actor.area_id) {
+      Some(area) => area,
+      None => return Ok(()), // area deleted, should be getting kicked
+    };
+
+    self.validate_path(
+      net.get_asset_manager(),
+      area.get_map(),
+      (actor.x, actor.y, actor.z),
+      (x, y, z),
+    )
+  }
+
+  /// Checks every tile and object between the start and end positions
+  fn validate_path(
+    &mut self,
+    asset_manager: &AssetManager,
+    map: &Map,
+    start: (f32, f32, f32),
+    end: (f32, f32, f32),
+  ) -> Result<(), &'static str> {
+    let (start_x, start_y, start_z) = start;
+    let (x, y, z) = end;
+
+    if x < 0.0 || y < 0.0 || z < 0.0 {
+      return Err("out of bounds");
+    }
+
+    let changing_layers = (z - start_z).abs() > f32::EPSILON;
+
+    if changing_layers {
+      let stairs_layer = z.min(start_z).max(0.0);
+      let tile_class_cache = &mut self.tile_class_cache;
+
+      let on_stairs = tile_class_cache.is_stairs(asset_manager, map, x, y, stairs_layer)
+        || tile_class_cache.is_stairs(asset_manager, map, start_x, start_y, stairs_layer);
+
+      if !on_stairs {
+        return Err("layer change");
+      }
+    }
+
+    let longest_axis = (x - start_x)
+      .abs()
+      .max((y - start_y).abs())
+      .max((z - start_z).abs());
+    let steps = ((longest_axis / PATH_STEP).ceil() as usize).max(1);
+
+    for step in 1..=steps {
+      let t = step as f32 / steps as f32;
+      let point_x = start_x + (x - start_x) * t;
+      let point_y = start_y + (y - start_y) * t;
+      let point_z = start_z + (z - start_z) * t;
+
+      if point_x < 0.0 || point_y < 0.0 || point_z < 0.0 {
+        return Err("out of bounds");
+      }
+
+      let tile = map.get_tile(point_x as usize, point_y as usize, point_z as usize);
+
+      if tile.gid == 0 {
+        return Err("missing tile");
+      }
+
+      // allow players to walk out of objects that appeared on top of them
+      let blocked = map
+        .get_objects_at(point_x, point_y, point_z)
+        .filter(|object| object.is_solid())
+        .any(|object| !map.object_contains_point(object, start_x, start_y));
+
+      if blocked {
+        return Err("solid object");
+      }
+    }
+
+    Ok(())
+  }
+}
+
+fn distance(x1: f32, y1: f32, x2: f32, y2: f32) -> f32 {
+  (x1 - x2).hypot(y1 - y2)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::pathfinding::tests::{create_asset_manager, create_map};
+  use super::*;
+
+  fn validate(map: &Map, start: (f32, f32, f32), end: (f32, f32, f32)) -> Result<(), &'static str> {
+    let asset_manager = create_asset_manager();
+    let mut validator = MovementValidator::new(1.0);
+
+    validator.validate_path(&asset_manager, map, start, end)
+  }
+
+  #[test]
+  fn missing_tiles() {
+    let map = create_map(&[&["1,1,0,1,1"]], "");
+
+    assert_eq!(validate(&map, (0.5, 0.5, 0.0), (1.9, 0.5, 0.0)), Ok(()));
+    assert_eq!(
+      validate(&map, (0.5, 0.5, 0.0), (2.5, 0.5, 0.0)),
+      Err("missing tile")
+    );
+
+    // jumping over the gap in a single update
+    assert_eq!(
+      validate(&map, (1.5, 0.5, 0.0), (3.5, 0.5, 0.0)),
+      Err("missing tile")
+    );
+    assert_eq!(
+      validate(&map, (0.5, 0.5, 0.0), (9.5, 0.5, 0.0)),
+      Err("missing tile")
+    );
+    assert_eq!(
+      validate(&map, (0.5, 0.5, 0.0), (-0.5, 0.5, 0.0)),
+      Err("out of bounds")
+    );
+  }
+
+  #[test]
+  fn solid_objects() {
+    let map = create_map(
+      &[&["1,1,1,1"]],
+      // a thin wall across tile (1, 0), and a non solid object on tile (2, 0)
+      r#"<object id="1" x="38" y="0" width="4" height="32"><properties><property name="Solid" value="true"/></properties></object>
+<object id="2" x="64" y="0" width="32" height="32"/>"#,
+    );
+
+    assert_eq!(validate(&map, (0.5, 0.5, 0.0), (1.1, 0.5, 0.0)), Ok(()));
+    assert_eq!(validate(&map, (2.5, 0.5, 0.0), (3.5, 0.5, 0.0)), Ok(()));
+
+    // passing through the wall, even if the destination is clear
+    assert_eq!(
+      validate(&map, (0.5, 0.5, 0.0), (1.5, 0.5, 0.0)),
+      Err("solid object")
+    );
+    assert_eq!(
+      validate(&map, (0.5, 0.5, 0.0), (2.5, 0.5, 0.0)),
+      Err("solid object")
+    );
+
+    // walking out of a solid object is allowed
+    assert_eq!(validate(&map, (1.2, 0.5, 0.0), (1.5, 0.5, 0.0)), Ok(()));
+  }
+
+  #[test]
+  fn stairs() {
+    let map = create_map(&[&["1,2,1"], &["0,0,1"]], "");
+
+    assert_eq!(validate(&map, (1.5, 0.5, 0.5), (2.2, 0.5, 1.0)), Ok(()));
+    assert_eq!(validate(&map, (2.2, 0.5, 1.0), (1.5, 0.5, 0.5)), Ok(()));
+
+    // changing layers without stairs
+    assert_eq!(
+      validate(&map, (0.5, 0.5, 0.0), (0.5, 0.5, 1.0)),
+      Err("layer change")
+    );
+    // walking off the upper layer onto nothing
+    assert_eq!(
+      validate(&map, (2.5, 0.5, 1.0), (1.5, 0.5, 1.0)),
+      Err("missing tile")
+    );
+  }
+}
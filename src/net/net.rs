@@ -1,16 +1,40 @@
 use super::actor_property_animation::KeyFrame;
 use super::asset_manager::AssetManager;
+use super::ban_list::{current_timestamp, BanList};
+use super::battle_history::BattleHistory;
 use super::boot::Boot;
-use super::client::Client;
+use super::client::{Client, PendingBattle};
+use super::coop_battles::{CoopBattle, CoopBattleResult};
+use super::encounters::{step_distance, Encounter, EncounterTable, EncounterTracker};
+use super::file_watcher::FileWatcher;
+use super::leaderboards::{LeaderboardView, Leaderboards};
 use super::map::Map;
+use super::matchmaking::{MatchmakingEvent, MatchmakingQueue, QueueEntry};
+use super::net_event::NetEvent;
+use super::parties::{Party, PartyEvent};
+use super::pathfinding::{find_path, to_tile, BotPath};
+use super::pvp_battles::{PvpBattle, SpectatorEvent};
+use super::quests::{load_quests, GatedObject, QuestEvent};
+use super::rate_limiter::{PacketCounters, RateLimiter};
 use super::server::ServerConfig;
-use super::{Actor, Area, Asset, AssetData, BbsPost, Direction, Item, PlayerData, ShopItem};
+use super::tile_classes::TileClassCache;
+use super::trades::{deposit_offer, escrow_offer, validate_receipt, Trade, TradeEvent, TradeState};
+use super::triggers::{OccupiedTriggers, ProximityWatch, TriggerEvent};
+use super::{
+  Actor, Area, Asset, AssetData, Ban, BanTarget, BattleRecord, BattleStats, BattleSummary, BbsPost,
+  ChatChannel, ConsoleCommand, Direction, FlagValue, Instance, InventoryError, Item, ItemMetadata,
+  ItemStack, LeaderboardEntry, MatchmakingError, MatchmakingRules, PartyError, PlayerData,
+  PlayerDataLoad, PlayerStore, Quest, QuestCondition, QuestError, QuestProgress, QuestState,
+  ScoreType, ShopItem, ShopPurchase, SortOrder, SpectateError, TradeError, TradeOffer,
+};
 use crate::packets::{create_asset_stream, PacketOrchestrator, Reliability, ServerPacket};
 use log::*;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::net::UdpSocket;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 pub struct Net {
   socket: Rc<UdpSocket>,
@@ -20,9 +44,35 @@ pub struct Net {
   clients: HashMap<String, Client>,
   bots: HashMap<String, Actor>,
   asset_manager: AssetManager,
-  active_plugin: usize,
+  active_plugin: Option<usize>,
   kick_list: Vec<Boot>,
   items: HashMap<String, Item>,
+  player_store: Box<dyn PlayerStore>,
+  last_player_save: std::time::Instant,
+  rate_limiter: Arc<Mutex<RateLimiter>>,
+  ban_list: BanList,
+  console_commands: Vec<ConsoleCommand>,
+  file_watcher: Option<FileWatcher>,
+  instances: HashMap<String, Instance>,
+  tile_class_cache: TileClassCache,
+  bot_paths: HashMap<String, BotPath>,
+  completed_bot_paths: Vec<String>,
+  occupied_triggers: HashMap<String, OccupiedTriggers>,
+  proximity_watches: HashMap<String, ProximityWatch>,
+  events: Vec<NetEvent>,
+  encounter_trackers: HashMap<String, EncounterTracker>,
+  pending_encounters: Vec<(String, Encounter)>,
+  battle_history: BattleHistory,
+  leaderboards: Leaderboards,
+  trades: Vec<Trade>,
+  managed_shops: HashMap<String, Vec<ShopItem>>,
+  parties: HashMap<String, Party>,
+  coop_battles: Vec<CoopBattle>,
+  pvp_battles: Vec<PvpBattle>,
+  matchmaking_queues: HashMap<String, MatchmakingQueue>,
+  quests: HashMap<String, Quest>,
+  /// Keyed by area id and object id
+  gated_objects: HashMap<String, HashMap<u32, GatedObject>>,
 }
 
 impl Net {
@@ -30,6 +80,8 @@ impl Net {
     socket: Rc<UdpSocket>,
     packet_orchestrator: Rc<RefCell<PacketOrchestrator>>,
     config: Rc<ServerConfig>,
+    player_store: Box<dyn PlayerStore>,
+    rate_limiter: Arc<Mutex<RateLimiter>>,
   ) -> Net {
     use super::asset::get_map_path;
     use std::fs::{read_dir, read_to_string};
@@ -70,6 +122,18 @@ impl Net {
       panic!("No default (default.tmx) area data found");
     }
 
+    let ban_list = BanList::load(&config.ban_list_path);
+    let battle_history = BattleHistory::new(&config.battle_history_path);
+    let leaderboards = Leaderboards::load(&config.leaderboards_path);
+    let quests = load_quests(&config.quests_path);
+
+    let file_watcher = if config.watch_assets {
+      let dirs = vec![PathBuf::from("./areas"), PathBuf::from("assets")];
+      Some(FileWatcher::new(dirs))
+    } else {
+      None
+    };
+
     Net {
       socket,
       packet_orchestrator,
@@ -78,12 +142,41 @@ impl Net {
       clients: HashMap::new(),
       bots: HashMap::new(),
       asset_manager,
-      active_plugin: 0,
+      active_plugin: None,
       kick_list: Vec::new(),
       items: HashMap::new(),
+      player_store,
+      last_player_save: std::time::Instant::now(),
+      rate_limiter,
+      ban_list,
+      console_commands: Vec::new(),
+      file_watcher,
+      instances: HashMap::new(),
+      tile_class_cache: TileClassCache::new(),
+      bot_paths: HashMap::new(),
+      completed_bot_paths: Vec::new(),
+      occupied_triggers: HashMap::new(),
+      proximity_watches: HashMap::new(),
+      events: Vec::new(),
+      encounter_trackers: HashMap::new(),
+      pending_encounters: Vec::new(),
+      battle_history,
+      leaderboards,
+      trades: Vec::new(),
+      managed_shops: HashMap::new(),
+      parties: HashMap::new(),
+      coop_battles: Vec::new(),
+      pvp_battles: Vec::new(),
+      matchmaking_queues: HashMap::new(),
+      quests,
+      gated_objects: HashMap::new(),
     }
   }
 
+  pub(super) fn get_asset_manager(&self) -> &AssetManager {
+    &self.asset_manager
+  }
+
   pub fn get_asset(&self, path: &str) -> Option<&Asset> {
     self.asset_manager.get_asset(path)
   }
@@ -112,6 +205,80 @@ impl Net {
     self.areas.get_mut(area_id)
   }
 
+  /// Ids of visible objects on layer z with a shape containing the point
+  pub fn objects_at(&self, area_id: &str, x: f32, y: f32, z: f32) -> Vec<u32> {
+    let area = match self.areas.get(area_id) {
+      Some(area) => area,
+      None => return Vec::new(),
+    };
+
+    area
+      .get_map()
+      .get_objects_at(x, y, z)
+      .map(|object| object.id)
+      .collect()
+  }
+
+  /// Ids of visible objects on layer z with bounds overlapping the rect
+  pub fn objects_in_rect(
+    &self,
+    area_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    width: f32,
+    height: f32,
+  ) -> Vec<u32> {
+    let area = match self.areas.get(area_id) {
+      Some(area) => area,
+      None => return Vec::new(),
+    };
+
+    area
+      .get_map()
+      .get_objects_in_rect(x, y, z, width, height)
+      .map(|object| object.id)
+      .collect()
+  }
+
+  /// Ids of players and bots on layer z within the radius, closest first
+  pub fn actors_in_radius(
+    &self,
+    area_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    radius: f32,
+  ) -> Vec<String> {
+    let area = match self.areas.get(area_id) {
+      Some(area) => area,
+      None => return Vec::new(),
+    };
+
+    let bounds = (x - radius, y - radius, x + radius, y + radius);
+    let layer = z.floor();
+
+    let mut actors: Vec<(f32, String)> = area
+      .query_actors(z, bounds)
+      .into_iter()
+      .filter_map(|id| {
+        let actor = self.get_player(&id).or_else(|| self.get_bot(&id))?;
+
+        if actor.z.floor() != layer {
+          return None;
+        }
+
+        let distance = ((actor.x - x).powi(2) + (actor.y - y).powi(2)).sqrt();
+
+        (distance <= radius).then_some((distance, id))
+      })
+      .collect();
+
+    actors.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    actors.into_iter().map(|(_, id)| id).collect()
+  }
+
   pub fn add_area(&mut self, id: String, map: Map) {
     let mut map = map;
 
@@ -126,6 +293,24 @@ impl Net {
     }
   }
 
+  /// Loads ./areas/{area_id}.tmx, replacing the map of an existing area
+  pub(super) fn load_area_file(&mut self, area_id: &str) -> Result<(), String> {
+    let path = format!("./areas/{}.tmx", area_id);
+
+    let raw_map =
+      std::fs::read_to_string(&path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+
+    // Map::from panics on invalid xml
+    if raw_map.parse::<minidom::Element>().is_err() {
+      return Err(format!("{} is not a valid map", path));
+    }
+
+    // the map asset is regenerated and sent to players in the area on the next tick
+    self.add_area(area_id.to_string(), Map::from(&raw_map));
+
+    Ok(())
+  }
+
   pub fn remove_area(&mut self, id: &str) {
     use super::asset::get_map_path;
 
@@ -137,6 +322,9 @@ impl Net {
     let map_path = get_map_path(id);
     self.asset_manager.remove_asset(&map_path);
 
+    self.instances.remove(id);
+    self.gated_objects.remove(id);
+
     if let Some(area) = self.areas.remove(id) {
       let player_ids = area.get_connected_players();
 
@@ -146,6 +334,88 @@ impl Net {
     }
   }
 
+  /// Instances share ids with their area
+  pub fn get_instance(&self, area_id: &str) -> Option<&Instance> {
+    self.instances.get(area_id)
+  }
+
+  pub fn find_instance(&self, template_id: &str, party_id: &str) -> Option<&Instance> {
+    self.instances.values().find(|instance| {
+      instance.get_template_id() == template_id && instance.get_party_id() == party_id
+    })
+  }
+
+  /// Returns the area id of the party's instance, creating it if it doesn't exist.
+  /// Instances are destroyed once every member leaves, or after a minute if no one joins.
+  pub fn create_instance(&mut self, template_id: &str, party_id: &str) -> Option<String> {
+    use uuid::Uuid;
+
+    if let Some(instance) = self.find_instance(template_id, party_id) {
+      return Some(instance.get_area_id().to_string());
+    }
+
+    let map = self.areas.get(template_id)?.get_map().clone();
+    let area_id = format!("{}-{}", template_id, Uuid::new_v4());
+
+    self.add_area(area_id.clone(), map);
+
+    let instance = Instance::new(
+      area_id.clone(),
+      template_id.to_string(),
+      party_id.to_string(),
+    );
+
+    self.instances.insert(area_id.clone(), instance);
+
+    Some(area_id)
+  }
+
+  /// Transfers the player to the party's instance of the template, creating it if it doesn't exist
+  #[allow(clippy::too_many_arguments)]
+  pub fn transfer_player_to_instance(
+    &mut self,
+    id: &str,
+    template_id: &str,
+    party_id: &str,
+    warp_in: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+    direction: Direction,
+  ) -> Option<String> {
+    if !self.clients.contains_key(id) {
+      return None;
+    }
+
+    let area_id = self.create_instance(template_id, party_id)?;
+
+    self.transfer_player(id, &area_id, warp_in, x, y, z, direction);
+
+    Some(area_id)
+  }
+
+  fn remove_empty_instances(&mut self) {
+    let empty_instance_ids: Vec<String> = self
+      .instances
+      .values()
+      .filter(|instance| instance.is_abandoned())
+      .map(|instance| instance.get_area_id().to_string())
+      .collect();
+
+    for area_id in empty_instance_ids {
+      let bot_ids = match self.areas.get(&area_id) {
+        Some(area) => area.get_connected_bots().clone(),
+        None => Vec::new(),
+      };
+
+      for bot_id in bot_ids {
+        self.remove_bot(&bot_id, false);
+      }
+
+      self.remove_area(&area_id);
+    }
+  }
+
   pub fn remove_asset(&mut self, path: &str) {
     self.asset_manager.remove_asset(path);
   }
@@ -154,6 +424,19 @@ impl Net {
     self.clients.get(id).map(|client| &client.actor)
   }
 
+  pub fn get_player_packet_counters(&self, id: &str) -> Option<PacketCounters> {
+    let client = self.clients.get(id)?;
+
+    let counters = self
+      .rate_limiter
+      .lock()
+      .unwrap()
+      .get_counters(&client.socket_address)
+      .unwrap_or_default();
+
+    Some(counters)
+  }
+
   pub fn get_player_addr(&self, id: &str) -> Option<std::net::SocketAddr> {
     self.clients.get(id).map(|client| client.socket_address)
   }
@@ -315,6 +598,147 @@ impl Net {
       .send_by_id(target_id, Reliability::Reliable, packet);
   }
 
+  /// Matches player ids before names, names are case insensitive
+  pub fn find_player_by_name(&self, name: &str) -> Option<&str> {
+    if let Some((id, _)) = self.clients.get_key_value(name) {
+      return Some(id);
+    }
+
+    self
+      .clients
+      .iter()
+      .find(|(_, client)| client.actor.name.eq_ignore_ascii_case(name))
+      .map(|(id, _)| id.as_str())
+  }
+
+  /// Sends a message from a player to the channel, skipping players who muted the sender.
+  /// Whispers require a target_id, the sender also receives the message
+  pub fn send_chat_message(
+    &mut self,
+    sender_id: &str,
+    channel: ChatChannel,
+    target_id: Option<&str>,
+    message: &str,
+  ) {
+    use crate::packets::build_packet;
+
+    let sender = match self.clients.get(sender_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let packet = build_packet(ServerPacket::ChatMessage {
+      channel,
+      sender_id,
+      sender_name: &sender.actor.name,
+      message,
+    });
+
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    for recipient_id in self.get_chat_recipients(sender_id, channel, target_id) {
+      let recipient = match self.clients.get(&recipient_id) {
+        Some(client) => client,
+        None => continue,
+      };
+
+      if recipient
+        .player_data
+        .muted
+        .contains(&sender.player_data.identity)
+      {
+        continue;
+      }
+
+      packet_orchestrator.send_byte_packets(
+        recipient.socket_address,
+        Reliability::ReliableOrdered,
+        std::slice::from_ref(&packet),
+      );
+    }
+  }
+
+  /// Sends a message to a single player without a sender, used for command responses and announcements
+  pub fn send_system_chat_message(&mut self, player_id: &str, sender_name: &str, message: &str) {
+    let packet = ServerPacket::ChatMessage {
+      channel: ChatChannel::System,
+      sender_id: "",
+      sender_name,
+      message,
+    };
+
+    self.packet_orchestrator.borrow_mut().send_by_id(
+      player_id,
+      Reliability::ReliableOrdered,
+      packet,
+    );
+  }
+
+  /// Hides chat messages from the target for the player, saved with the player's data
+  pub fn mute_player(&mut self, player_id: &str, target_id: &str) {
+    let identity = match self.clients.get(target_id) {
+      Some(client) => client.player_data.identity.clone(),
+      None => return,
+    };
+
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client.player_data.muted.insert(identity);
+    }
+  }
+
+  pub fn unmute_player(&mut self, player_id: &str, target_id: &str) {
+    let identity = match self.clients.get(target_id) {
+      Some(client) => client.player_data.identity.clone(),
+      None => return,
+    };
+
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client.player_data.muted.remove(&identity);
+    }
+  }
+
+  pub fn is_player_muted(&self, player_id: &str, target_id: &str) -> bool {
+    match (self.clients.get(player_id), self.clients.get(target_id)) {
+      (Some(client), Some(target)) => client
+        .player_data
+        .muted
+        .contains(&target.player_data.identity),
+      _ => false,
+    }
+  }
+
+  fn get_chat_recipients(
+    &self,
+    sender_id: &str,
+    channel: ChatChannel,
+    target_id: Option<&str>,
+  ) -> Vec<String> {
+    let sender = match self.clients.get(sender_id) {
+      Some(client) => client,
+      None => return Vec::new(),
+    };
+
+    match channel {
+      ChatChannel::Area => self
+        .areas
+        .get(&sender.actor.area_id)
+        .map(|area| area.get_connected_players().clone())
+        .unwrap_or_default(),
+      ChatChannel::Global => self.clients.keys().cloned().collect(),
+      ChatChannel::Party => match &sender.party_id {
+        Some(party_id) => self.parties[party_id].members.clone(),
+        None => vec![sender_id.to_string()],
+      },
+      ChatChannel::Whisper => match target_id {
+        Some(target_id) if target_id != sender_id => {
+          vec![sender_id.to_string(), target_id.to_string()]
+        }
+        _ => vec![sender_id.to_string()],
+      },
+      ChatChannel::System => vec![sender_id.to_string()],
+    }
+  }
+
   pub fn set_player_minimap_color(&mut self, id: &str, color: (u8, u8, u8, u8)) {
     let client = match self.clients.get_mut(id) {
       Some(client) => client,
@@ -462,7 +886,12 @@ impl Net {
     );
   }
 
+  /// Shares hidden objects with quest gating, gated objects are updated again when the player's flags or quests change
   pub fn exclude_object_for_player(&mut self, id: &str, object_id: u32) {
+    if let Some(client) = self.clients.get_mut(id) {
+      client.hidden_objects.insert(object_id);
+    }
+
     self.packet_orchestrator.borrow_mut().send_by_id(
       id,
       Reliability::ReliableOrdered,
@@ -471,6 +900,10 @@ impl Net {
   }
 
   pub fn include_object_for_player(&mut self, id: &str, object_id: u32) {
+    if let Some(client) = self.clients.get_mut(id) {
+      client.hidden_objects.remove(&object_id);
+    }
+
     self.packet_orchestrator.borrow_mut().send_by_id(
       id,
       Reliability::ReliableOrdered,
@@ -616,10 +1049,19 @@ impl Net {
     direction: Direction,
   ) {
     let client = self.clients.get_mut(id).unwrap();
+    let previous_position = (client.actor.x, client.actor.y, client.actor.z);
 
     client.actor.set_position(x, y, z);
     client.actor.set_direction(direction);
 
+    if let Some(area) = self.areas.get_mut(&client.actor.area_id) {
+      area.update_actor_position(id, x, y, z);
+    }
+
+    self.update_encounter_steps(id, previous_position, (x, y, z));
+
+    let client = self.clients.get_mut(id).unwrap();
+
     // skip if client has not even been sent to anyone yet
     if !client.ready {
       return;
@@ -790,6 +1232,7 @@ impl Net {
 
     let start_depth = client.widget_tracker.get_board_count() as u8;
     client.widget_tracker.track_board(self.active_plugin);
+    client.leaderboard_views.push(None);
 
     if posts.is_empty() {
       // logic below will send nothing if there's no posts,
@@ -1044,8 +1487,7 @@ impl Net {
     mug_texture_path: &str,
     mug_animation_path: &str,
   ) {
-    use super::shop_item::calc_size;
-    use crate::helpers::iterators::IteratorHelper;
+    use crate::packets::build_packet;
 
     let client = if let Some(client) = self.clients.get_mut(player_id) {
       client
@@ -1054,27 +1496,10 @@ impl Net {
     };
 
     client.widget_tracker.track_shop(self.active_plugin);
+    client.managed_shop = None;
+    client.managed_shop_item_ids.clear();
 
-    let max_payload_size = self.config.max_payload_size;
-
-    let calc_chunk_limit = |_| {
-      // reliability + id + type
-      let header_size = 1 + 8 + 2;
-
-      max_payload_size - header_size
-    };
-
-    use crate::packets::build_packet;
-
-    let mut packets: Vec<Vec<u8>> = items
-      .into_iter()
-      .pack_chunks_lossy(calc_chunk_limit, calc_size)
-      .map(|chunk| {
-        build_packet(ServerPacket::ShopInventory {
-          items: chunk.as_slice(),
-        })
-      })
-      .collect();
+    let mut packets = build_shop_inventory_packets(self.config.max_payload_size, items);
 
     packets.push(build_packet(ServerPacket::OpenShop {
       mug_texture_path,
@@ -1088,334 +1513,2639 @@ impl Net {
     );
   }
 
-  pub fn is_player_battling(&self, id: &str) -> bool {
-    if let Some(client) = self.clients.get(id) {
-      return client.is_battling();
-    }
+  /// Creates or replaces a shop with stock shared between players, open viewers see the new items.
+  /// Returns false if two items share an item_id or a name, purchases are matched by both
+  pub fn create_managed_shop(&mut self, shop_id: &str, items: Vec<ShopItem>) -> bool {
+    let mut item_ids = HashSet::new();
+    let mut names = HashSet::new();
 
-    false
-  }
+    let unique = items
+      .iter()
+      .all(|item| item_ids.insert(&item.item_id) && names.insert(&item.name));
 
-  pub fn initiate_pvp(&mut self, player_1_id: &str, player_2_id: &str) {
-    use crate::helpers::use_public_ip;
-    use multi_mut::HashMapMultiMut;
+    if !unique {
+      return false;
+    }
 
-    let (client_1, client_2) =
-      if let Some((client_1, client_2)) = self.clients.get_pair_mut(player_1_id, player_2_id) {
-        (client_1, client_2)
-      } else {
-        return;
-      };
+    self.managed_shops.insert(shop_id.to_string(), items);
+    self.refresh_managed_shop(shop_id);
 
-    client_1.battle_tracker.push_back(self.active_plugin);
-    client_2.battle_tracker.push_back(self.active_plugin);
+    true
+  }
 
-    // todo: put these clients in slow mode
+  pub fn remove_managed_shop(&mut self, shop_id: &str) {
+    self.managed_shops.remove(shop_id);
+  }
 
-    let client_1_addr = use_public_ip(client_1.socket_address, self.config.public_ip);
-    let client_2_addr = use_public_ip(client_2.socket_address, self.config.public_ip);
+  pub fn get_managed_shop_items(&self, shop_id: &str) -> Option<&[ShopItem]> {
+    self
+      .managed_shops
+      .get(shop_id)
+      .map(|items| items.as_slice())
+  }
 
-    let mut orchestrator = self.packet_orchestrator.borrow_mut();
+  /// Sets the stock for the shop item granting the item, None for unlimited
+  pub fn set_managed_shop_stock(&mut self, shop_id: &str, item_id: &str, stock: Option<u32>) {
+    let items = match self.managed_shops.get_mut(shop_id) {
+      Some(items) => items,
+      None => return,
+    };
 
-    orchestrator.send(
-      client_1.socket_address,
-      Reliability::ReliableOrdered,
-      ServerPacket::InitiatePvp {
-        address: &client_2_addr.to_string(),
-      },
-    );
+    let shop_item = match items
+      .iter_mut()
+      .find(|shop_item| shop_item.item_id.as_deref() == Some(item_id))
+    {
+      Some(shop_item) => shop_item,
+      None => return,
+    };
 
-    orchestrator.send(
-      client_2.socket_address,
-      Reliability::ReliableOrdered,
-      ServerPacket::InitiatePvp {
-        address: &client_1_addr.to_string(),
-      },
-    );
+    shop_item.stock = stock;
+
+    self.refresh_managed_shop(shop_id);
   }
 
-  fn send_encounter_data_asset(&mut self, player_id: &str, data: Option<&str>) {
-    use super::asset;
+  /// Opens a managed shop, purchases are validated and completed by the server.
+  /// Sold out items are hidden
+  pub fn open_managed_shop(
+    &mut self,
+    player_id: &str,
+    shop_id: &str,
+    mug_texture_path: &str,
+    mug_animation_path: &str,
+  ) {
+    let items = match self.list_available_shop_items(shop_id) {
+      Some(items) => items,
+      None => return,
+    };
 
-    // store data as an asset
-    let data_path = asset::get_encounter_data_path();
-    let data_path_buf = std::path::PathBuf::from(data_path.to_string());
-    let mut data_asset =
-      Asset::load_from_memory(&data_path_buf, data.unwrap_or_default().as_bytes());
-    data_asset.cachable = false;
-    data_asset.cache_to_disk = false;
+    let item_ids = map_shop_item_ids(&items);
 
-    self
-      .asset_manager
-      .set_asset(data_path.to_string(), data_asset);
+    self.open_shop(player_id, items, mug_texture_path, mug_animation_path);
 
-    ensure_asset(
-      &mut *self.packet_orchestrator.borrow_mut(),
-      self.config.max_payload_size,
-      &self.asset_manager,
-      &mut self.clients,
-      &[player_id.to_string()],
-      data_path,
-    );
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client.managed_shop = Some(shop_id.to_string());
+      client.managed_shop_item_ids = item_ids;
+    }
   }
 
-  pub fn set_mod_whitelist_for_player(&mut self, player_id: &str, whitelist_path: &str) {
-    ensure_asset(
-      &mut *self.packet_orchestrator.borrow_mut(),
-      self.config.max_payload_size,
-      &self.asset_manager,
-      &mut self.clients,
-      &[String::from(player_id)],
-      &whitelist_path.to_string(),
+  pub(super) fn is_in_managed_shop(&self, player_id: &str) -> bool {
+    self
+      .clients
+      .get(player_id)
+      .map(|client| client.managed_shop.is_some())
+      .unwrap_or_default()
+  }
+
+  pub(super) fn close_managed_shop(&mut self, player_id: &str) {
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client.managed_shop = None;
+      client.managed_shop_item_ids.clear();
+    }
+  }
+
+  /// Takes money, grants the item, and updates stock. Returns None if the purchase was rejected
+  pub(super) fn purchase_managed_shop_item(
+    &mut self,
+    player_id: &str,
+    item_name: &str,
+  ) -> Option<ShopPurchase> {
+    let client = self.clients.get(player_id)?;
+    let shop_id = client.managed_shop.clone()?;
+    let item_id = client.managed_shop_item_ids.get(item_name)?.clone();
+    let money = client.player_data.money;
+
+    // the shop may have changed since the player saw it, the item is looked up by id
+    let shop_items = self.managed_shops.get(&shop_id)?;
+    let index = shop_items.iter().position(|shop_item| {
+      shop_item.item_id.as_deref() == Some(item_id.as_str()) && !shop_item.is_sold_out()
+    })?;
+    let shop_item = &shop_items[index];
+
+    let item_name = shop_item.name.clone();
+    let price = shop_item.price;
+
+    // stock left after the purchase, None for unlimited
+    let stock = match shop_item.stock {
+      Some(stock) => Some(stock.checked_sub(1)?),
+      None => None,
+    };
+
+    if money < price {
+      return None;
+    }
+
+    // the inventory validates the item before anything is taken
+    self
+      .give_player_item(player_id, &item_id, 1, ItemMetadata::default())
+      .ok()?;
+
+    self.set_player_money(player_id, money - price);
+
+    // the index is still valid, nothing can change the shop while the item is given
+    self.managed_shops.get_mut(&shop_id)?[index].stock = stock;
+
+    if stock.is_some() {
+      self.refresh_managed_shop(&shop_id);
+    }
+
+    Some(ShopPurchase {
+      shop_id,
+      item_id,
+      item_name,
+      price,
+      stock,
+    })
+  }
+
+  fn list_available_shop_items(&self, shop_id: &str) -> Option<Vec<ShopItem>> {
+    let items = self
+      .managed_shops
+      .get(shop_id)?
+      .iter()
+      .filter(|item| !item.is_sold_out())
+      .cloned()
+      .collect();
+
+    Some(items)
+  }
+
+  // resends the inventory to every player viewing the shop
+  fn refresh_managed_shop(&mut self, shop_id: &str) {
+    let items = match self.list_available_shop_items(shop_id) {
+      Some(items) => items,
+      None => return,
+    };
+
+    let item_ids = map_shop_item_ids(&items);
+    let packets = build_shop_inventory_packets(self.config.max_payload_size, items);
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    for client in self.clients.values_mut() {
+      if client.managed_shop.as_deref() != Some(shop_id) {
+        continue;
+      }
+
+      client.managed_shop_item_ids = item_ids.clone();
+
+      packet_orchestrator.send_byte_packets(
+        client.socket_address,
+        Reliability::ReliableOrdered,
+        &packets,
+      );
+    }
+  }
+
+  pub fn is_player_battling(&self, id: &str) -> bool {
+    if let Some(client) = self.clients.get(id) {
+      return client.is_battling();
+    }
+
+    false
+  }
+
+  pub fn initiate_pvp(&mut self, player_1_id: &str, player_2_id: &str) {
+    self.start_pvp(player_1_id, player_2_id, self.active_plugin, None);
+  }
+
+  fn start_pvp(
+    &mut self,
+    player_1_id: &str,
+    player_2_id: &str,
+    battle_owner: Option<usize>,
+    queue_id: Option<&str>,
+  ) {
+    use crate::helpers::use_public_ip;
+    use multi_mut::HashMapMultiMut;
+
+    let (client_1, client_2) =
+      if let Some((client_1, client_2)) = self.clients.get_pair_mut(player_1_id, player_2_id) {
+        (client_1, client_2)
+      } else {
+        return;
+      };
+
+    client_1.battle_tracker.push_back(battle_owner);
+    client_2.battle_tracker.push_back(battle_owner);
+
+    let started_at = current_timestamp();
+
+    client_1.pending_battles.push_back(PendingBattle {
+      package_path: None,
+      opponent: Some(client_2.player_data.identity.clone()),
+      started_at,
+      group_id: None,
+    });
+
+    client_2.pending_battles.push_back(PendingBattle {
+      package_path: None,
+      opponent: Some(client_1.player_data.identity.clone()),
+      started_at,
+      group_id: None,
+    });
+
+    // todo: put these clients in slow mode
+
+    let client_1_addr = use_public_ip(client_1.socket_address, self.config.public_ip);
+    let client_2_addr = use_public_ip(client_2.socket_address, self.config.public_ip);
+
+    let mut orchestrator = self.packet_orchestrator.borrow_mut();
+
+    orchestrator.send(
+      client_1.socket_address,
+      Reliability::ReliableOrdered,
+      ServerPacket::InitiatePvp {
+        address: &client_2_addr.to_string(),
+      },
     );
 
-    self.packet_orchestrator.borrow_mut().send_by_id(
-      player_id,
+    orchestrator.send(
+      client_2.socket_address,
       Reliability::ReliableOrdered,
-      ServerPacket::ModWhitelist { whitelist_path },
+      ServerPacket::InitiatePvp {
+        address: &client_1_addr.to_string(),
+      },
     );
+
+    self.pvp_battles.push(PvpBattle::new(
+      player_1_id.to_string(),
+      player_2_id.to_string(),
+      queue_id.map(String::from),
+    ));
   }
 
-  pub fn offer_package(&mut self, player_id: &str, package_path: &str) {
-    ensure_asset(
-      &mut *self.packet_orchestrator.borrow_mut(),
-      self.config.max_payload_size,
-      &self.asset_manager,
-      &mut self.clients,
-      &[String::from(player_id)],
-      &package_path.to_string(),
+  /// Creates or updates a queue, the calling plugin receives results for matched battles
+  pub fn create_matchmaking_queue(&mut self, queue_id: &str, rules: MatchmakingRules) {
+    match self.matchmaking_queues.get_mut(queue_id) {
+      Some(queue) => queue.rules = rules,
+      None => {
+        let queue = MatchmakingQueue::new(self.active_plugin, rules);
+        self.matchmaking_queues.insert(queue_id.to_string(), queue);
+      }
+    }
+  }
+
+  /// Players in the queue are removed from it
+  pub fn remove_matchmaking_queue(&mut self, queue_id: &str) {
+    self.matchmaking_queues.remove(queue_id);
+  }
+
+  pub fn is_matchmaking_queue(&self, queue_id: &str) -> bool {
+    self.matchmaking_queues.contains_key(queue_id)
+  }
+
+  /// Players are matched with the player's saved rating for the queue unless a rating is provided
+  pub fn join_matchmaking_queue(
+    &mut self,
+    queue_id: &str,
+    player_id: &str,
+    rating: Option<f32>,
+  ) -> Result<(), MatchmakingError> {
+    if !self.matchmaking_queues.contains_key(queue_id) {
+      return Err(MatchmakingError::UnknownQueue);
+    }
+
+    if self.get_matchmaking_queue(player_id).is_some() {
+      return Err(MatchmakingError::AlreadyQueued);
+    }
+
+    match self.clients.get(player_id) {
+      Some(client) if !client.is_busy() => {}
+      _ => return Err(MatchmakingError::PlayerBusy),
+    }
+
+    let rating = rating.unwrap_or_else(|| self.get_player_rating(player_id, queue_id));
+
+    if !rating.is_finite() {
+      return Err(MatchmakingError::InvalidRating);
+    }
+
+    let queue = self.matchmaking_queues.get_mut(queue_id).unwrap();
+
+    queue.entries.push(QueueEntry {
+      player_id: player_id.to_string(),
+      rating,
+      joined_at: std::time::Instant::now(),
+    });
+
+    Ok(())
+  }
+
+  pub fn leave_matchmaking_queue(&mut self, player_id: &str) {
+    for queue in self.matchmaking_queues.values_mut() {
+      queue.entries.retain(|entry| entry.player_id != player_id);
+    }
+  }
+
+  /// The id of the queue the player is waiting in
+  pub fn get_matchmaking_queue(&self, player_id: &str) -> Option<&str> {
+    self
+      .matchmaking_queues
+      .iter()
+      .find(|(_, queue)| {
+        queue
+          .entries
+          .iter()
+          .any(|entry| entry.player_id == player_id)
+      })
+      .map(|(queue_id, _)| queue_id.as_str())
+  }
+
+  /// Players waiting in the queue, longest wait first
+  pub fn list_matchmaking_queue(&self, queue_id: &str) -> Vec<&str> {
+    match self.matchmaking_queues.get(queue_id) {
+      Some(queue) => queue
+        .entries
+        .iter()
+        .map(|entry| entry.player_id.as_str())
+        .collect(),
+      None => Vec::new(),
+    }
+  }
+
+  pub fn get_player_rating(&self, player_id: &str, queue_id: &str) -> f32 {
+    use super::matchmaking::DEFAULT_RATING;
+
+    self
+      .clients
+      .get(player_id)
+      .and_then(|client| client.player_data.ratings.get(queue_id))
+      .copied()
+      .unwrap_or(DEFAULT_RATING)
+  }
+
+  /// Non finite ratings are ignored, they would spread to every opponent
+  pub fn set_player_rating(&mut self, player_id: &str, queue_id: &str, rating: f32) {
+    if !rating.is_finite() {
+      return;
+    }
+
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client
+        .player_data
+        .ratings
+        .insert(queue_id.to_string(), rating);
+    }
+  }
+
+  fn update_matchmaking(&mut self) {
+    let queue_ids: Vec<String> = self.matchmaking_queues.keys().cloned().collect();
+
+    for queue_id in queue_ids {
+      loop {
+        let queue = &self.matchmaking_queues[&queue_id];
+        let clients = &self.clients;
+
+        let found_match = queue.find_match(|player_id| {
+          clients
+            .get(player_id)
+            .filter(|client| !client.is_busy())
+            .map(|client| client.actor.area_id.as_str())
+        });
+
+        let (i, j) = match found_match {
+          Some(found_match) => found_match,
+          None => break,
+        };
+
+        let queue = self.matchmaking_queues.get_mut(&queue_id).unwrap();
+        let battle_owner = queue.owner;
+        // j is always after i
+        let player_2_id = queue.entries.remove(j).player_id;
+        let player_1_id = queue.entries.remove(i).player_id;
+
+        self.start_pvp(&player_1_id, &player_2_id, battle_owner, Some(&queue_id));
+
+        self.events.push(NetEvent::Matchmaking(MatchmakingEvent {
+          queue_id: queue_id.clone(),
+          player_ids: [player_1_id, player_2_id],
+        }));
+      }
+    }
+  }
+
+  // ratings change once both players report results that agree on the winner
+  fn report_pvp_score(&mut self, player_id: &str, score: f32) {
+    let index = match self.get_pvp_battle_index(player_id) {
+      Some(index) => index,
+      None => return,
+    };
+
+    let pvp_battle = &mut self.pvp_battles[index];
+    let side = pvp_battle.side(player_id).unwrap();
+    pvp_battle.scores[side] = Some(score);
+
+    if pvp_battle.scores.iter().any(|score| score.is_none()) {
+      return;
+    }
+
+    if let Some(score) = pvp_battle.agreed_score() {
+      self.update_match_ratings(index, score);
+    }
+
+    self.end_pvp_battle(index);
+  }
+
+  // disconnecting before sending results is a loss, the opponent is assumed to have won unless they reported otherwise
+  fn forfeit_pvp_battle(&mut self, player_id: &str) {
+    let index = match self
+      .pvp_battles
+      .iter()
+      .position(|pvp_battle| pvp_battle.side(player_id).is_some())
+    {
+      Some(index) => index,
+      None => return,
+    };
+
+    let pvp_battle = &mut self.pvp_battles[index];
+    let side = pvp_battle.side(player_id).unwrap();
+    let score = *pvp_battle.scores[side].get_or_insert(0.0);
+    pvp_battle.scores[1 - side].get_or_insert(1.0 - score);
+
+    if let Some(score) = pvp_battle.agreed_score() {
+      self.update_match_ratings(index, score);
+    }
+
+    self.end_pvp_battle(index);
+  }
+
+  // players who never report results are treated like they disconnected
+  fn resolve_timed_out_pvp_battles(&mut self) {
+    let timeout = self.config.pvp_battle_timeout;
+
+    for index in (0..self.pvp_battles.len()).rev() {
+      let pvp_battle = &mut self.pvp_battles[index];
+
+      if !pvp_battle.is_timed_out(timeout) {
+        continue;
+      }
+
+      pvp_battle.settle_missing_scores();
+
+      if let Some(score) = pvp_battle.agreed_score() {
+        self.update_match_ratings(index, score);
+      }
+
+      self.end_pvp_battle(index);
+    }
+  }
+
+  /// Elo for battles started by matchmaking, the score is from the first player's perspective
+  fn update_match_ratings(&mut self, index: usize, score: f32) {
+    use super::matchmaking::update_rating;
+
+    let pvp_battle = &self.pvp_battles[index];
+
+    let queue_id = match &pvp_battle.queue_id {
+      Some(queue_id) => queue_id.clone(),
+      None => return,
+    };
+
+    let [player_1_id, player_2_id] = pvp_battle.player_ids.clone();
+
+    let k_factor = self
+      .matchmaking_queues
+      .get(&queue_id)
+      .map(|queue| queue.rules.k_factor)
+      .unwrap_or_else(|| MatchmakingRules::default().k_factor);
+
+    let rating_1 = self.get_player_rating(&player_1_id, &queue_id);
+    let rating_2 = self.get_player_rating(&player_2_id, &queue_id);
+
+    self.set_player_rating(
+      &player_1_id,
+      &queue_id,
+      update_rating(rating_1, rating_2, score, k_factor),
+    );
+
+    self.set_player_rating(
+      &player_2_id,
+      &queue_id,
+      update_rating(rating_2, rating_1, 1.0 - score, k_factor),
+    );
+  }
+
+  pub fn get_quest(&self, quest_id: &str) -> Option<&Quest> {
+    self.quests.get(quest_id)
+  }
+
+  pub fn quest_ids(&self) -> impl Iterator<Item = &str> {
+    self.quests.keys().map(|quest_id| quest_id.as_str())
+  }
+
+  /// Quests without progress are not started, None if the player doesn't exist
+  pub fn get_quest_progress(&self, player_id: &str, quest_id: &str) -> Option<QuestProgress> {
+    use super::quests::get_quest_progress;
+
+    let client = self.clients.get(player_id)?;
+
+    Some(get_quest_progress(&client.player_data, quest_id))
+  }
+
+  /// Starts on the first step, failed quests can be started again
+  pub fn start_quest(&mut self, player_id: &str, quest_id: &str) -> Result<(), QuestError> {
+    if !self.quests.contains_key(quest_id) {
+      return Err(QuestError::UnknownQuest);
+    }
+
+    let progress = self
+      .get_quest_progress(player_id, quest_id)
+      .ok_or(QuestError::NotActive)?;
+
+    if matches!(progress.state, QuestState::Active | QuestState::Completed) {
+      return Err(QuestError::AlreadyStarted);
+    }
+
+    self.set_quest_progress(
+      player_id,
+      quest_id,
+      QuestProgress {
+        state: QuestState::Active,
+        step: 0,
+      },
+    );
+
+    Ok(())
+  }
+
+  /// Moves to the next step, completing the quest after the last step
+  pub fn advance_quest(&mut self, player_id: &str, quest_id: &str) -> Result<(), QuestError> {
+    let progress = self.get_active_quest_progress(player_id, quest_id)?;
+    let step_count = self.quests[quest_id].steps.len();
+    let step = progress.step + 1;
+
+    let state = if step >= step_count {
+      QuestState::Completed
+    } else {
+      QuestState::Active
+    };
+
+    self.set_quest_progress(player_id, quest_id, QuestProgress { state, step });
+
+    Ok(())
+  }
+
+  pub fn set_quest_step(
+    &mut self,
+    player_id: &str,
+    quest_id: &str,
+    step_id: &str,
+  ) -> Result<(), QuestError> {
+    self.get_active_quest_progress(player_id, quest_id)?;
+
+    let step = self.quests[quest_id]
+      .steps
+      .iter()
+      .position(|step| step.id == step_id)
+      .ok_or(QuestError::UnknownStep)?;
+
+    self.set_quest_progress(
+      player_id,
+      quest_id,
+      QuestProgress {
+        state: QuestState::Active,
+        step,
+      },
+    );
+
+    Ok(())
+  }
+
+  pub fn complete_quest(&mut self, player_id: &str, quest_id: &str) -> Result<(), QuestError> {
+    let progress = self.get_active_quest_progress(player_id, quest_id)?;
+
+    self.set_quest_progress(
+      player_id,
+      quest_id,
+      QuestProgress {
+        state: QuestState::Completed,
+        ..progress
+      },
     );
 
-    let client = if let Some(client) = self.clients.get_mut(player_id) {
-      client
-    } else {
-      return;
+    Ok(())
+  }
+
+  pub fn fail_quest(&mut self, player_id: &str, quest_id: &str) -> Result<(), QuestError> {
+    let progress = self.get_active_quest_progress(player_id, quest_id)?;
+
+    self.set_quest_progress(
+      player_id,
+      quest_id,
+      QuestProgress {
+        state: QuestState::Failed,
+        ..progress
+      },
+    );
+
+    Ok(())
+  }
+
+  /// Forgets the player's progress, the quest becomes not started
+  pub fn reset_quest(&mut self, player_id: &str, quest_id: &str) {
+    let had_progress = self
+      .clients
+      .get_mut(player_id)
+      .and_then(|client| client.player_data.quests.remove(quest_id))
+      .is_some();
+
+    if !had_progress {
+      return;
+    }
+
+    self.events.push(NetEvent::Quest(QuestEvent::QuestChange {
+      player_id: player_id.to_string(),
+      quest_id: quest_id.to_string(),
+      progress: QuestProgress {
+        state: QuestState::NotStarted,
+        step: 0,
+      },
+    }));
+
+    self.update_gated_objects(player_id);
+  }
+
+  pub fn get_player_flag(&self, player_id: &str, name: &str) -> Option<FlagValue> {
+    let client = self.clients.get(player_id)?;
+
+    client.player_data.flags.get(name).copied()
+  }
+
+  pub fn set_player_flag(&mut self, player_id: &str, name: &str, value: FlagValue) {
+    self.update_player_flag(player_id, name, Some(value));
+  }
+
+  pub fn remove_player_flag(&mut self, player_id: &str, name: &str) {
+    self.update_player_flag(player_id, name, None);
+  }
+
+  /// Hides the object from players in the area who don't meet every condition,
+  /// visibility is updated as flags and quests change. An empty list shows the object to everyone
+  pub fn set_object_visibility_conditions(
+    &mut self,
+    area_id: &str,
+    object_id: u32,
+    conditions: Vec<QuestCondition>,
+  ) {
+    let gated_object = self
+      .gated_objects
+      .entry(area_id.to_string())
+      .or_default()
+      .entry(object_id)
+      .or_default();
+
+    gated_object.visible_if = conditions;
+
+    let player_ids = match self.areas.get(area_id) {
+      Some(area) => area.get_connected_players().clone(),
+      None => return,
+    };
+
+    for player_id in player_ids {
+      self.update_gated_objects(&player_id);
+    }
+  }
+
+  /// Interactions from players who don't meet every condition are ignored.
+  /// An empty list allows everyone to interact
+  pub fn set_object_interaction_conditions(
+    &mut self,
+    area_id: &str,
+    object_id: u32,
+    conditions: Vec<QuestCondition>,
+  ) {
+    let gated_object = self
+      .gated_objects
+      .entry(area_id.to_string())
+      .or_default()
+      .entry(object_id)
+      .or_default();
+
+    gated_object.interactable_if = conditions;
+  }
+
+  /// Checks conditions for an object in the player's area
+  pub fn is_object_visible_to_player(&self, player_id: &str, object_id: u32) -> bool {
+    let client = match self.clients.get(player_id) {
+      Some(client) => client,
+      None => return false,
+    };
+
+    match self.get_gated_object(&client.actor.area_id, object_id) {
+      Some(gated_object) => gated_object
+        .visible_if
+        .iter()
+        .all(|condition| condition.is_met(&client.player_data)),
+      None => true,
+    }
+  }
+
+  /// Hidden objects can't be interacted with
+  pub fn can_player_interact_with_object(&self, player_id: &str, object_id: u32) -> bool {
+    if !self.is_object_visible_to_player(player_id, object_id) {
+      return false;
+    }
+
+    let client = self.clients.get(player_id).unwrap();
+
+    match self.get_gated_object(&client.actor.area_id, object_id) {
+      Some(gated_object) => gated_object
+        .interactable_if
+        .iter()
+        .all(|condition| condition.is_met(&client.player_data)),
+      None => true,
+    }
+  }
+
+  fn get_active_quest_progress(
+    &self,
+    player_id: &str,
+    quest_id: &str,
+  ) -> Result<QuestProgress, QuestError> {
+    if !self.quests.contains_key(quest_id) {
+      return Err(QuestError::UnknownQuest);
+    }
+
+    match self.get_quest_progress(player_id, quest_id) {
+      Some(progress) if progress.state == QuestState::Active => Ok(progress),
+      _ => Err(QuestError::NotActive),
+    }
+  }
+
+  fn set_quest_progress(&mut self, player_id: &str, quest_id: &str, progress: QuestProgress) {
+    let client = match self.clients.get_mut(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    client
+      .player_data
+      .quests
+      .insert(quest_id.to_string(), progress);
+
+    self.events.push(NetEvent::Quest(QuestEvent::QuestChange {
+      player_id: player_id.to_string(),
+      quest_id: quest_id.to_string(),
+      progress,
+    }));
+
+    self.update_gated_objects(player_id);
+  }
+
+  fn update_player_flag(&mut self, player_id: &str, name: &str, value: Option<FlagValue>) {
+    let client = match self.clients.get_mut(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let flags = &mut client.player_data.flags;
+
+    let previous_value = match value {
+      Some(value) => flags.insert(name.to_string(), value),
+      None => flags.remove(name),
+    };
+
+    if previous_value == value {
+      return;
+    }
+
+    self.events.push(NetEvent::Quest(QuestEvent::FlagChange {
+      player_id: player_id.to_string(),
+      name: name.to_string(),
+      value,
+    }));
+
+    self.update_gated_objects(player_id);
+  }
+
+  fn get_gated_object(&self, area_id: &str, object_id: u32) -> Option<&GatedObject> {
+    self.gated_objects.get(area_id)?.get(&object_id)
+  }
+
+  // includes or excludes gated objects in the player's area when their visibility changes
+  fn update_gated_objects(&mut self, player_id: &str) {
+    let client = match self.clients.get_mut(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let gated_objects = match self.gated_objects.get(&client.actor.area_id) {
+      Some(gated_objects) => gated_objects,
+      None => return,
+    };
+
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    for (object_id, gated_object) in gated_objects {
+      let visible = gated_object
+        .visible_if
+        .iter()
+        .all(|condition| condition.is_met(&client.player_data));
+
+      let packet = if visible {
+        if !client.hidden_objects.remove(object_id) {
+          continue;
+        }
+
+        ServerPacket::IncludeObject { id: *object_id }
+      } else {
+        if !client.hidden_objects.insert(*object_id) {
+          continue;
+        }
+
+        ServerPacket::ExcludeObject { id: *object_id }
+      };
+
+      packet_orchestrator.send(client.socket_address, Reliability::ReliableOrdered, packet);
+    }
+  }
+
+  /// Attaches a player to the PvP battle the other player is in.
+  /// Spectators are busy until they stop spectating or the battle ends
+  pub fn add_battle_spectator(
+    &mut self,
+    player_id: &str,
+    spectator_id: &str,
+  ) -> Result<(), SpectateError> {
+    let index = self
+      .get_pvp_battle_index(player_id)
+      .ok_or(SpectateError::NotInPvp)?;
+
+    let spectator = self
+      .clients
+      .get_mut(spectator_id)
+      .ok_or(SpectateError::NotInPvp)?;
+
+    if spectator.is_busy() {
+      return Err(SpectateError::PlayerBusy);
+    }
+
+    // the spectator's entry is always the oldest, as the tracker was empty
+    spectator.battle_tracker.push_back(self.active_plugin);
+
+    let pvp_battle = &mut self.pvp_battles[index];
+    pvp_battle.spectators.push(spectator_id.to_string());
+
+    // addresses are never shared outside of the battle, battle data is relayed through the server instead
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    for id in &pvp_battle.player_ids {
+      packet_orchestrator.send_by_id(
+        id,
+        Reliability::ReliableOrdered,
+        ServerPacket::PvpSpectatorJoin,
+      );
+    }
+
+    packet_orchestrator.send_by_id(
+      spectator_id,
+      Reliability::ReliableOrdered,
+      ServerPacket::SpectatePvp,
+    );
+
+    self.events.push(NetEvent::Spectator(SpectatorEvent::Join {
+      spectator_id: spectator_id.to_string(),
+      player_ids: pvp_battle.player_ids.clone(),
+    }));
+
+    Ok(())
+  }
+
+  pub fn remove_battle_spectator(&mut self, spectator_id: &str) {
+    self.remove_battle_spectator_with_reason(spectator_id, "removed");
+  }
+
+  /// Spectators of the PvP battle the player is in, the player can be a spectator
+  pub fn list_battle_spectators(&self, player_id: &str) -> &[String] {
+    let pvp_battle = self.pvp_battles.iter().find(|pvp_battle| {
+      pvp_battle.has_player(player_id) || pvp_battle.spectators.iter().any(|id| id == player_id)
+    });
+
+    match pvp_battle {
+      Some(pvp_battle) => &pvp_battle.spectators,
+      None => &[],
+    }
+  }
+
+  /// Forwards battle data from a player in a PvP battle to the battle's spectators
+  pub(super) fn relay_pvp_spectator_data(&mut self, player_id: &str, data: &[u8]) {
+    let pvp_battle = match self.get_pvp_battle_index(player_id) {
+      Some(index) => &self.pvp_battles[index],
+      None => return,
+    };
+
+    let side = pvp_battle.side(player_id).unwrap() as u8;
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    for spectator_id in &pvp_battle.spectators {
+      packet_orchestrator.send_by_id(
+        spectator_id,
+        Reliability::ReliableOrdered,
+        ServerPacket::PvpSpectatorData { side, data },
+      );
+    }
+  }
+
+  pub fn is_battle_spectator(&self, player_id: &str) -> bool {
+    self
+      .pvp_battles
+      .iter()
+      .any(|pvp_battle| pvp_battle.spectators.iter().any(|id| id == player_id))
+  }
+
+  /// Called when the spectator leaves through the client
+  pub(super) fn stop_spectating(&mut self, spectator_id: &str) {
+    self.remove_battle_spectator_with_reason(spectator_id, "left");
+  }
+
+  fn get_pvp_battle_index(&self, player_id: &str) -> Option<usize> {
+    self
+      .pvp_battles
+      .iter()
+      .position(|pvp_battle| pvp_battle.has_player(player_id))
+  }
+
+  fn remove_battle_spectator_with_reason(&mut self, spectator_id: &str, reason: &'static str) {
+    let pvp_battle = self
+      .pvp_battles
+      .iter_mut()
+      .find(|pvp_battle| pvp_battle.spectators.iter().any(|id| id == spectator_id));
+
+    let pvp_battle = match pvp_battle {
+      Some(pvp_battle) => pvp_battle,
+      None => return,
+    };
+
+    pvp_battle.spectators.retain(|id| id != spectator_id);
+
+    let spectator = match self.clients.get_mut(spectator_id) {
+      Some(spectator) => spectator,
+      None => return,
+    };
+
+    spectator.battle_tracker.pop_front();
+
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    if reason != "left" {
+      packet_orchestrator.send(
+        spectator.socket_address,
+        Reliability::ReliableOrdered,
+        ServerPacket::EndSpectating,
+      );
+    }
+
+    for id in &pvp_battle.player_ids {
+      packet_orchestrator.send_by_id(
+        id,
+        Reliability::ReliableOrdered,
+        ServerPacket::PvpSpectatorLeave,
+      );
+    }
+
+    self.events.push(NetEvent::Spectator(SpectatorEvent::Leave {
+      spectator_id: spectator_id.to_string(),
+      player_ids: pvp_battle.player_ids.clone(),
+      reason,
+    }));
+  }
+
+  // removes spectators once both players are done or either disconnects
+  fn end_pvp_battle(&mut self, index: usize) {
+    for spectator_id in self.pvp_battles[index].spectators.clone() {
+      self.remove_battle_spectator_with_reason(&spectator_id, "battle_ended");
+    }
+
+    self.pvp_battles.remove(index);
+  }
+
+  fn send_encounter_data_asset(&mut self, player_id: &str, data: Option<&str>) {
+    use super::asset;
+
+    // store data as an asset
+    let data_path = asset::get_encounter_data_path();
+    let data_path_buf = std::path::PathBuf::from(data_path.to_string());
+    let mut data_asset =
+      Asset::load_from_memory(&data_path_buf, data.unwrap_or_default().as_bytes());
+    data_asset.cachable = false;
+    data_asset.cache_to_disk = false;
+
+    self
+      .asset_manager
+      .set_asset(data_path.to_string(), data_asset);
+
+    ensure_asset(
+      &mut *self.packet_orchestrator.borrow_mut(),
+      self.config.max_payload_size,
+      &self.asset_manager,
+      &mut self.clients,
+      &[player_id.to_string()],
+      data_path,
+    );
+  }
+
+  pub fn set_mod_whitelist_for_player(&mut self, player_id: &str, whitelist_path: &str) {
+    ensure_asset(
+      &mut *self.packet_orchestrator.borrow_mut(),
+      self.config.max_payload_size,
+      &self.asset_manager,
+      &mut self.clients,
+      &[String::from(player_id)],
+      &whitelist_path.to_string(),
+    );
+
+    self.packet_orchestrator.borrow_mut().send_by_id(
+      player_id,
+      Reliability::ReliableOrdered,
+      ServerPacket::ModWhitelist { whitelist_path },
+    );
+  }
+
+  pub fn offer_package(&mut self, player_id: &str, package_path: &str) {
+    ensure_asset(
+      &mut *self.packet_orchestrator.borrow_mut(),
+      self.config.max_payload_size,
+      &self.asset_manager,
+      &mut self.clients,
+      &[String::from(player_id)],
+      &package_path.to_string(),
+    );
+
+    let client = if let Some(client) = self.clients.get_mut(player_id) {
+      client
+    } else {
+      return;
+    };
+
+    // send dependencies
+    let dependency_chain = self
+      .asset_manager
+      .get_flattened_dependency_chain(package_path);
+
+    let mut packets = Vec::new();
+    packets.reserve_exact(dependency_chain.len());
+
+    for asset_path in dependency_chain {
+      let asset = if let Some(asset) = self.asset_manager.get_asset(asset_path) {
+        asset
+      } else {
+        warn!("No asset found with path \"{}\"", asset_path);
+        continue;
+      };
+
+      let package_info = if let Some(package_info) = asset.resolve_package_info() {
+        package_info
+      } else {
+        warn!("\"{}\" is not a package", asset_path);
+        continue;
+      };
+
+      packets.push(ServerPacket::OfferPackage {
+        package_info,
+        package_path: asset_path,
+      });
+    }
+
+    self.packet_orchestrator.borrow_mut().send_packets(
+      client.socket_address,
+      Reliability::ReliableOrdered,
+      packets,
+    );
+  }
+
+  pub fn initiate_encounter(&mut self, player_id: &str, package_path: &str, data: Option<&str>) {
+    self.start_encounter(player_id, package_path, data, self.active_plugin);
+  }
+
+  /// Starts the same encounter for every player, each player's results are sent as usual and
+  /// the group's results are sent once every player reports or the group times out.
+  /// Returns the group id, None if none of the players are connected or any player is busy
+  pub fn initiate_coop_encounter(
+    &mut self,
+    player_ids: &[String],
+    package_path: &str,
+    data: Option<&str>,
+  ) -> Option<String> {
+    use uuid::Uuid;
+
+    let mut participants: Vec<String> = Vec::new();
+
+    for player_id in player_ids {
+      let client = match self.clients.get(player_id) {
+        Some(client) => client,
+        None => continue,
+      };
+
+      // like matchmaking, players already in a battle or a widget can't be pulled into a group
+      if client.is_busy() {
+        return None;
+      }
+
+      if !participants.contains(player_id) {
+        participants.push(player_id.clone());
+      }
+    }
+
+    if participants.is_empty() {
+      return None;
+    }
+
+    let group_id = Uuid::new_v4().to_string();
+
+    for player_id in &participants {
+      self.start_encounter(player_id, package_path, data, self.active_plugin);
+
+      let pending_battle = self
+        .clients
+        .get_mut(player_id)
+        .and_then(|client| client.pending_battles.back_mut());
+
+      if let Some(pending_battle) = pending_battle {
+        pending_battle.group_id = Some(group_id.clone());
+      }
+    }
+
+    self.coop_battles.push(CoopBattle::new(
+      group_id.clone(),
+      package_path.to_string(),
+      participants,
+    ));
+
+    Some(group_id)
+  }
+
+  /// Groups with results from every player, players who timed out are given no results
+  pub(super) fn take_completed_coop_battles(
+    &mut self,
+  ) -> Vec<(String, String, Vec<CoopBattleResult>)> {
+    let timeout = self.config.coop_battle_timeout;
+
+    for coop_battle in &mut self.coop_battles {
+      coop_battle.check_timeout(timeout);
+    }
+
+    let (completed, pending) = std::mem::take(&mut self.coop_battles)
+      .into_iter()
+      .partition(|coop_battle| coop_battle.is_complete());
+
+    self.coop_battles = pending;
+
+    completed
+      .into_iter()
+      .map(|coop_battle: CoopBattle| {
+        (
+          coop_battle.group_id,
+          coop_battle.package_path,
+          coop_battle.results,
+        )
+      })
+      .collect()
+  }
+
+  fn report_coop_battle_results(
+    &mut self,
+    group_id: &str,
+    player_id: &str,
+    battle_stats: Option<BattleStats>,
+  ) {
+    let coop_battle = self
+      .coop_battles
+      .iter_mut()
+      .find(|coop_battle| coop_battle.group_id == group_id);
+
+    if let Some(coop_battle) = coop_battle {
+      coop_battle.report(player_id, battle_stats);
+    }
+  }
+
+  /// Starts an encounter chosen by the server, results are sent to every plugin
+  pub(super) fn initiate_server_encounter(&mut self, player_id: &str, encounter: &Encounter) {
+    let data = encounter
+      .data
+      .as_ref()
+      .map(|data| format!("return {}", data));
+
+    self.start_encounter(player_id, &encounter.package_path, data.as_deref(), None);
+  }
+
+  /// Random encounters from encounter tables, enabled by default
+  pub fn set_encounters_enabled(&mut self, player_id: &str, enabled: bool) {
+    let client = match self.clients.get(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let tracker = self
+      .encounter_trackers
+      .entry(player_id.to_string())
+      .or_insert_with(|| EncounterTracker::new(client.actor.area_id.clone()));
+
+    tracker.enabled = enabled;
+  }
+
+  pub fn is_encounters_enabled(&self, player_id: &str) -> bool {
+    match self.encounter_trackers.get(player_id) {
+      Some(tracker) => tracker.enabled,
+      None => self.clients.contains_key(player_id),
+    }
+  }
+
+  /// Restarts the step count before the next random encounter
+  pub fn reset_encounter_steps(&mut self, player_id: &str) {
+    if let Some(tracker) = self.encounter_trackers.get_mut(player_id) {
+      tracker.reset();
+    }
+  }
+
+  pub(super) fn take_pending_encounters(&mut self) -> Vec<(String, Encounter)> {
+    std::mem::take(&mut self.pending_encounters)
+  }
+
+  fn update_encounter_steps(
+    &mut self,
+    player_id: &str,
+    previous_position: (f32, f32, f32),
+    position: (f32, f32, f32),
+  ) {
+    let client = match self.clients.get(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let area_id = &client.actor.area_id;
+
+    let tracker = self
+      .encounter_trackers
+      .entry(player_id.to_string())
+      .or_insert_with(|| EncounterTracker::new(area_id.clone()));
+
+    if tracker.area_id != *area_id {
+      tracker.area_id = area_id.clone();
+      tracker.reset();
+    }
+
+    if !tracker.enabled || client.is_busy() {
+      return;
+    }
+
+    let area = match self.areas.get(area_id) {
+      Some(area) => area,
+      None => return,
+    };
+
+    // encounter tables on objects take priority over the area's table
+    let map = area.get_map();
+    let (x, y, z) = position;
+
+    let custom_properties = map
+      .get_objects_at(x, y, z)
+      .map(|object| &object.custom_properties)
+      .find(|custom_properties| EncounterTable::has_table(custom_properties))
+      .unwrap_or_else(|| map.get_custom_properties());
+
+    if !EncounterTable::has_table(custom_properties) {
+      return;
+    }
+
+    tracker.distance += step_distance(previous_position, position);
+
+    let table = match EncounterTable::from(custom_properties) {
+      Some(table) => table,
+      None => return,
+    };
+
+    let required_distance = *tracker
+      .required_distance
+      .get_or_insert_with(|| table.roll_required_steps());
+
+    if tracker.distance < required_distance {
+      return;
+    }
+
+    tracker.reset();
+
+    if let Some(encounter) = table.choose() {
+      self
+        .pending_encounters
+        .push((player_id.to_string(), encounter));
+    }
+  }
+
+  fn start_encounter(
+    &mut self,
+    player_id: &str,
+    package_path: &str,
+    data: Option<&str>,
+    battle_owner: Option<usize>,
+  ) {
+    use super::asset;
+
+    ensure_asset(
+      &mut *self.packet_orchestrator.borrow_mut(),
+      self.config.max_payload_size,
+      &self.asset_manager,
+      &mut self.clients,
+      &[String::from(player_id)],
+      &package_path.to_string(),
+    );
+
+    self.send_encounter_data_asset(player_id, data);
+
+    let client = if let Some(client) = self.clients.get_mut(player_id) {
+      client
+    } else {
+      return;
+    };
+
+    // update tracking
+    client.battle_tracker.push_back(battle_owner);
+    client.pending_battles.push_back(PendingBattle {
+      package_path: Some(package_path.to_string()),
+      opponent: None,
+      started_at: current_timestamp(),
+      group_id: None,
+    });
+
+    // send dependencies
+    let dependency_chain = self
+      .asset_manager
+      .get_flattened_dependency_chain(package_path);
+
+    let mut packets = Vec::new();
+    packets.reserve_exact(dependency_chain.len());
+
+    for asset_path in dependency_chain {
+      let asset = if let Some(asset) = self.asset_manager.get_asset(asset_path) {
+        asset
+      } else {
+        warn!("No asset found with path \"{}\"", asset_path);
+        continue;
+      };
+
+      let package_category = if let Some(package_info) = asset.resolve_package_info() {
+        package_info.category
+      } else {
+        warn!("\"{}\" is not a package", asset_path);
+        continue;
+      };
+
+      packets.push(ServerPacket::LoadPackage {
+        package_path: asset_path,
+        package_category,
+      });
+    }
+
+    packets.push(ServerPacket::InitiateEncounter {
+      package_path,
+      data_path: asset::get_encounter_data_path(),
+    });
+
+    self.packet_orchestrator.borrow_mut().send_packets(
+      client.socket_address,
+      Reliability::ReliableOrdered,
+      packets,
+    );
+  }
+
+  /// Saves the results of the player's oldest battle, called before plugins receive the results
+  pub(super) fn record_battle_results(&mut self, player_id: &str, battle_stats: &BattleStats) {
+    use super::battle_history::{BattleOutcome, BattleRecord};
+
+    let client = match self.clients.get_mut(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let pending_battle = match client.pending_battles.pop_front() {
+      Some(pending_battle) => pending_battle,
+      // results without a battle
+      None => return,
+    };
+
+    let is_pvp = pending_battle.opponent.is_some();
+
+    self.battle_history.record(BattleRecord {
+      identity: client.player_data.identity.clone(),
+      name: client.actor.name.clone(),
+      package_path: pending_battle.package_path,
+      opponent: pending_battle.opponent,
+      started_at: pending_battle.started_at,
+      ended_at: current_timestamp(),
+      outcome: BattleOutcome::from_stats(battle_stats),
+      stats: battle_stats.clone(),
+    });
+
+    if let Some(group_id) = pending_battle.group_id {
+      self.report_coop_battle_results(&group_id, player_id, Some(battle_stats.clone()));
+    }
+
+    if is_pvp {
+      // running counts as a loss
+      let score = match BattleOutcome::from_stats(battle_stats) {
+        BattleOutcome::Won => 1.0,
+        BattleOutcome::Lost | BattleOutcome::Ran => 0.0,
+      };
+
+      self.report_pvp_score(player_id, score);
+    }
+  }
+
+  /// Newest battles first
+  pub fn get_recent_battles(&self, identity: &str, count: usize) -> Vec<BattleRecord> {
+    self.battle_history.recent_battles(identity, count)
+  }
+
+  pub fn get_battle_summary(&self, identity: &str) -> BattleSummary {
+    self.battle_history.summary(identity)
+  }
+
+  /// Fastest won battle, optionally limited to battles against a package
+  pub fn get_best_battle(
+    &self,
+    identity: &str,
+    package_path: Option<&str>,
+  ) -> Option<BattleRecord> {
+    self.battle_history.best_battle(identity, package_path)
+  }
+
+  /// Fastest won battle for each player, fastest first
+  pub fn get_best_battles(&self, package_path: Option<&str>, count: usize) -> Vec<BattleRecord> {
+    self.battle_history.best_battles(package_path, count)
+  }
+
+  /// Creates the leaderboard, or updates the sort order and score type of an existing leaderboard.
+  /// Returns false for empty names and names made of dots
+  pub fn create_leaderboard(
+    &mut self,
+    name: &str,
+    sort_order: SortOrder,
+    score_type: ScoreType,
+  ) -> bool {
+    self.leaderboards.create(name, sort_order, score_type)
+  }
+
+  pub fn remove_leaderboard(&mut self, name: &str) -> bool {
+    self.leaderboards.remove(name)
+  }
+
+  pub fn has_leaderboard(&self, name: &str) -> bool {
+    self.leaderboards.get(name).is_some()
+  }
+
+  pub fn get_leaderboard_settings(&self, name: &str) -> Option<(SortOrder, ScoreType)> {
+    let board = self.leaderboards.get(name)?;
+
+    Some((board.sort_order, board.score_type))
+  }
+
+  /// Saves the score if it's better than the identity's current score.
+  /// Returns the identity's rank, None if the leaderboard doesn't exist
+  pub fn submit_leaderboard_score(
+    &mut self,
+    board_name: &str,
+    identity: &str,
+    name: &str,
+    score: f64,
+  ) -> Option<usize> {
+    self
+      .leaderboards
+      .submit(board_name, identity, name, score, true)
+  }
+
+  /// Saves the score even if it's worse than the identity's current score.
+  /// Returns the identity's rank, None if the leaderboard doesn't exist
+  pub fn set_leaderboard_score(
+    &mut self,
+    board_name: &str,
+    identity: &str,
+    name: &str,
+    score: f64,
+  ) -> Option<usize> {
+    self
+      .leaderboards
+      .submit(board_name, identity, name, score, false)
+  }
+
+  pub fn remove_leaderboard_score(&mut self, board_name: &str, identity: &str) {
+    self.leaderboards.remove_score(board_name, identity);
+  }
+
+  /// Ranks start at 1
+  pub fn get_leaderboard_rank(&self, board_name: &str, identity: &str) -> Option<usize> {
+    self.leaderboards.rank(board_name, identity)
+  }
+
+  pub fn get_leaderboard_size(&self, board_name: &str) -> usize {
+    self.leaderboards.len(board_name)
+  }
+
+  /// Best entries first, offset 0 is rank 1
+  pub fn get_leaderboard_entries(
+    &self,
+    board_name: &str,
+    offset: usize,
+    count: usize,
+  ) -> &[LeaderboardEntry] {
+    self.leaderboards.entries(board_name, offset, count)
+  }
+
+  /// Entries as posts for open_board, prepend_posts, and append_posts. Post ids are ranks,
+  /// and the entry matching viewer_identity is marked as unread
+  pub fn get_leaderboard_posts(
+    &self,
+    board_name: &str,
+    viewer_identity: Option<&str>,
+    offset: usize,
+    count: usize,
+  ) -> Vec<BbsPost> {
+    self
+      .leaderboards
+      .posts(board_name, viewer_identity, offset, count)
+  }
+
+  /// Opens a board listing the leaderboard, more posts are appended as the player scrolls
+  pub fn open_leaderboard(
+    &mut self,
+    player_id: &str,
+    board_name: &str,
+    title: &str,
+    color: (u8, u8, u8),
+    page_size: usize,
+  ) {
+    let client = if let Some(client) = self.clients.get(player_id) {
+      client
+    } else {
+      return;
+    };
+
+    let viewer_identity = client.player_data.identity.clone();
+    let page_size = page_size.max(1);
+
+    let posts = self
+      .leaderboards
+      .posts(board_name, Some(&viewer_identity), 0, page_size);
+    let next_offset = posts.len();
+
+    self.open_board(player_id, title, color, posts);
+
+    // open_board added an entry for the new board
+    let view = self
+      .clients
+      .get_mut(player_id)
+      .and_then(|client| client.leaderboard_views.last_mut());
+
+    if let Some(view) = view {
+      *view = Some(LeaderboardView {
+        board_name: board_name.to_string(),
+        viewer_identity,
+        next_offset,
+        page_size,
+      });
+    }
+  }
+
+  /// Appends the next page if the player's current board is a leaderboard,
+  /// returns false if the request should be handled by plugins instead
+  pub(super) fn load_leaderboard_page(&mut self, player_id: &str) -> bool {
+    let client = if let Some(client) = self.clients.get_mut(player_id) {
+      client
+    } else {
+      return false;
+    };
+
+    let depth = match client.widget_tracker.get_open_board_count().checked_sub(1) {
+      Some(depth) => depth,
+      None => return false,
+    };
+
+    let view = match client.leaderboard_views.get_mut(depth) {
+      Some(Some(view)) => view,
+      _ => return false,
+    };
+
+    let posts = self.leaderboards.posts(
+      &view.board_name,
+      Some(&view.viewer_identity),
+      view.next_offset,
+      view.page_size,
+    );
+
+    if posts.is_empty() {
+      return true;
+    }
+
+    // post ids are ranks, so the last post sent is the rank at next_offset
+    let reference = Some(view.next_offset)
+      .filter(|offset| *offset > 0)
+      .map(|offset| offset.to_string());
+    view.next_offset += posts.len();
+
+    self.append_posts(player_id, reference.as_deref(), posts);
+
+    true
+  }
+
+  /// Drops the view for the player's current board, call before the widget tracker closes the board
+  pub(super) fn close_leaderboard_view(&mut self, player_id: &str) {
+    let client = if let Some(client) = self.clients.get_mut(player_id) {
+      client
+    } else {
+      return;
+    };
+
+    let depth = match client.widget_tracker.get_open_board_count().checked_sub(1) {
+      Some(depth) => depth,
+      None => return,
+    };
+
+    if depth < client.leaderboard_views.len() {
+      client.leaderboard_views.remove(depth);
+    }
+  }
+
+  pub fn is_player_busy(&self, id: &str) -> bool {
+    if let Some(client) = self.clients.get(id) {
+      return client.is_busy();
+    }
+
+    true
+  }
+
+  pub fn get_player_data(&self, player_id: &str) -> Option<&PlayerData> {
+    self
+      .clients
+      .get(player_id)
+      .map(|client| &client.player_data)
+  }
+
+  pub fn get_player_storage(&self, player_id: &str, key: &str) -> Option<&str> {
+    let client = self.clients.get(player_id)?;
+
+    client
+      .player_data
+      .storage
+      .get(key)
+      .map(|value| value.as_str())
+  }
+
+  pub fn set_player_storage(
+    &mut self,
+    player_id: &str,
+    key: String,
+    value: Option<String>,
+  ) -> Option<()> {
+    let client = self.clients.get_mut(player_id)?;
+
+    let storage = &mut client.player_data.storage;
+
+    if let Some(value) = value {
+      storage.insert(key, value);
+    } else {
+      storage.remove(&key);
+    }
+
+    Some(())
+  }
+
+  /// Money and items held for a trade are saved with the player, in case the server stops before the trade ends
+  pub fn save_player_data(&mut self, player_id: &str) {
+    let client = match self.clients.get(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let offer = match self.get_trade_offer(player_id) {
+      Some(offer) if offer.money > 0 || !offer.items.is_empty() => offer,
+      _ => {
+        self.player_store.save(&client.player_data);
+        return;
+      }
+    };
+
+    let mut player_data = client.player_data.clone();
+    deposit_offer(&self.items, &mut player_data, offer);
+
+    self.player_store.save(&player_data);
+  }
+
+  /// Data for players who haven't joined yet, passed to add_client
+  pub(super) fn load_player_data(&mut self, identity: &str) -> PlayerDataLoad {
+    self.player_store.load(identity)
+  }
+
+  fn save_all_player_data(&mut self) {
+    let player_ids: Vec<String> = self.clients.keys().cloned().collect();
+
+    for player_id in player_ids {
+      self.save_player_data(&player_id);
+    }
+  }
+
+  /// Blocks until saved player data, battle history, and leaderboards reach the disk, used as the server stops
+  pub(super) fn flush_saves(&mut self) {
+    self.player_store.flush();
+    self.battle_history.flush();
+    self.leaderboards.flush();
+  }
+
+  pub(crate) fn update_player_data(&mut self, player_id: &str, element: String, max_health: u32) {
+    let client = self.clients.get_mut(player_id).unwrap();
+
+    client.player_data.element = element;
+    client.player_data.health = max_health;
+    client.player_data.max_health = max_health;
+  }
+
+  pub fn set_player_health(&mut self, player_id: &str, health: u32) {
+    if let Some(client) = self.clients.get_mut(player_id) {
+      let max_health = client.player_data.max_health;
+
+      client.player_data.health = health;
+
+      self.packet_orchestrator.borrow_mut().send(
+        client.socket_address,
+        Reliability::ReliableOrdered,
+        ServerPacket::Health { health, max_health },
+      );
+    }
+  }
+
+  pub fn set_player_max_health(&mut self, player_id: &str, max_health: u32) {
+    if let Some(client) = self.clients.get_mut(player_id) {
+      let health = client.player_data.health;
+
+      client.player_data.max_health = max_health;
+
+      self.packet_orchestrator.borrow_mut().send(
+        client.socket_address,
+        Reliability::ReliableOrdered,
+        ServerPacket::Health { health, max_health },
+      );
+    }
+  }
+
+  pub fn set_player_emotion(&mut self, player_id: &str, emotion: u8) {
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client.player_data.emotion = emotion;
+
+      self.packet_orchestrator.borrow_mut().send(
+        client.socket_address,
+        Reliability::ReliableOrdered,
+        ServerPacket::Emotion { emotion },
+      );
+    }
+  }
+
+  pub fn set_player_money(&mut self, player_id: &str, money: u32) {
+    if let Some(client) = self.clients.get_mut(player_id) {
+      client.player_data.money = money;
+
+      self.packet_orchestrator.borrow_mut().send(
+        client.socket_address,
+        Reliability::ReliableOrdered,
+        ServerPacket::Money { money },
+      );
+    }
+  }
+
+  pub fn get_item(&mut self, item_id: &str) -> Option<&Item> {
+    self.items.get(item_id)
+  }
+
+  pub fn set_item(&mut self, item_id: String, item: Item) {
+    self.items.insert(item_id, item);
+  }
+
+  pub fn get_player_item_count(&self, player_id: &str, item_id: &str) -> u32 {
+    use super::inventory::count_items;
+
+    match self.clients.get(player_id) {
+      Some(client) => count_items(&client.player_data.items, item_id),
+      None => 0,
+    }
+  }
+
+  pub fn give_player_item(
+    &mut self,
+    player_id: &str,
+    item_id: &str,
+    quantity: u32,
+    metadata: ItemMetadata,
+  ) -> Result<(), InventoryError> {
+    use super::inventory::{add_items, count_items};
+
+    let client = self
+      .clients
+      .get_mut(player_id)
+      .ok_or(InventoryError::PlayerNotFound)?;
+
+    let item = self.items.get(item_id).ok_or(InventoryError::UnknownItem)?;
+
+    let stacks = &mut client.player_data.items;
+    item.validate_add(count_items(stacks, item_id), quantity)?;
+
+    let previous_stack_count = count_stacks(stacks, item_id);
+    add_items(stacks, item, item_id, quantity, metadata);
+
+    update_client_items(
+      &mut self.packet_orchestrator.borrow_mut(),
+      &self.items,
+      client,
+      item_id,
+      previous_stack_count,
+    );
+
+    Ok(())
+  }
+
+  /// Returns the removed stacks, nothing is removed if the player doesn't have enough
+  pub fn remove_player_item(
+    &mut self,
+    player_id: &str,
+    item_id: &str,
+    quantity: u32,
+  ) -> Result<Vec<ItemStack>, InventoryError> {
+    use super::inventory::{count_items, remove_items};
+
+    let client = self
+      .clients
+      .get_mut(player_id)
+      .ok_or(InventoryError::PlayerNotFound)?;
+
+    let stacks = &mut client.player_data.items;
+
+    if quantity == 0 {
+      return Err(InventoryError::InvalidQuantity);
+    }
+
+    if count_items(stacks, item_id) < quantity {
+      return Err(InventoryError::NotEnoughItems);
+    }
+
+    let previous_stack_count = count_stacks(stacks, item_id);
+    let removed = remove_items(stacks, item_id, quantity);
+
+    update_client_items(
+      &mut self.packet_orchestrator.borrow_mut(),
+      &self.items,
+      client,
+      item_id,
+      previous_stack_count,
+    );
+
+    Ok(removed)
+  }
+
+  /// Moves items between players, keeping metadata. Nothing changes if the transfer fails
+  pub fn transfer_player_item(
+    &mut self,
+    sender_id: &str,
+    recipient_id: &str,
+    item_id: &str,
+    quantity: u32,
+  ) -> Result<(), InventoryError> {
+    use super::inventory::{add_items, count_items, remove_items};
+
+    let (sender, recipient) = match (self.clients.get(sender_id), self.clients.get(recipient_id)) {
+      (Some(sender), Some(recipient)) => (sender, recipient),
+      _ => return Err(InventoryError::PlayerNotFound),
+    };
+
+    let item = self.items.get(item_id).ok_or(InventoryError::UnknownItem)?;
+
+    item.validate_transfer()?;
+
+    if count_items(&sender.player_data.items, item_id) < quantity {
+      return Err(InventoryError::NotEnoughItems);
+    }
+
+    item.validate_add(count_items(&recipient.player_data.items, item_id), quantity)?;
+
+    if sender_id == recipient_id {
+      return Ok(());
+    }
+
+    // everything is validated, moving the stacks directly can't fail halfway through
+    let sender = self.clients.get_mut(sender_id).unwrap();
+    let previous_sender_stack_count = count_stacks(&sender.player_data.items, item_id);
+    let removed = remove_items(&mut sender.player_data.items, item_id, quantity);
+
+    update_client_items(
+      &mut self.packet_orchestrator.borrow_mut(),
+      &self.items,
+      sender,
+      item_id,
+      previous_sender_stack_count,
+    );
+
+    let recipient = self.clients.get_mut(recipient_id).unwrap();
+    let previous_recipient_stack_count = count_stacks(&recipient.player_data.items, item_id);
+
+    for stack in removed {
+      add_items(
+        &mut recipient.player_data.items,
+        item,
+        item_id,
+        stack.quantity,
+        stack.metadata,
+      );
+    }
+
+    update_client_items(
+      &mut self.packet_orchestrator.borrow_mut(),
+      &self.items,
+      recipient,
+      item_id,
+      previous_recipient_stack_count,
+    );
+
+    Ok(())
+  }
+
+  /// Updates the metadata of every item in the stack, returns false if there's no stack at the index.
+  /// Stacks with matching metadata are merged, which can shift the index of later stacks
+  pub fn set_player_item_metadata(
+    &mut self,
+    player_id: &str,
+    stack_index: usize,
+    metadata: ItemMetadata,
+  ) -> bool {
+    use super::inventory::merge_stacks;
+
+    let client = match self.clients.get_mut(player_id) {
+      Some(client) => client,
+      None => return false,
+    };
+
+    let stacks = &mut client.player_data.items;
+
+    let stack = match stacks.get_mut(stack_index) {
+      Some(stack) => stack,
+      None => return false,
+    };
+
+    if stack.metadata == metadata {
+      return true;
+    }
+
+    stack.metadata = metadata;
+
+    let item_id = stack.item_id.clone();
+    let previous_stack_count = count_stacks(stacks, &item_id);
+
+    if let Some(item) = self.items.get(&item_id) {
+      merge_stacks(stacks, item, &item_id);
+    }
+
+    update_client_items(
+      &mut self.packet_orchestrator.borrow_mut(),
+      &self.items,
+      client,
+      &item_id,
+      previous_stack_count,
+    );
+
+    true
+  }
+
+  /// Invites the recipient to the player's party, creating a party led by the player if needed.
+  /// Returns the party id
+  pub fn invite_to_party(
+    &mut self,
+    player_id: &str,
+    recipient_id: &str,
+  ) -> Result<String, PartyError> {
+    use uuid::Uuid;
+
+    if player_id == recipient_id {
+      return Err(PartyError::SamePlayer);
+    }
+
+    let (client, recipient) = match (self.clients.get(player_id), self.clients.get(recipient_id)) {
+      (Some(client), Some(recipient)) => (client, recipient),
+      _ => return Err(PartyError::NotInParty),
+    };
+
+    if recipient.party_id.is_some() {
+      return Err(PartyError::AlreadyInParty);
+    }
+
+    let party_id = match &client.party_id {
+      Some(party_id) if self.parties[party_id].leader_id != player_id => {
+        return Err(PartyError::NotLeader);
+      }
+      Some(party_id) => party_id.clone(),
+      None => {
+        let party_id = Uuid::new_v4().to_string();
+
+        self
+          .parties
+          .insert(party_id.clone(), Party::new(player_id.to_string()));
+
+        self.add_party_member(&party_id, player_id);
+
+        party_id
+      }
+    };
+
+    let party = self.parties.get_mut(&party_id).unwrap();
+
+    if !party.invites.iter().any(|id| id == recipient_id) {
+      party.invites.push(recipient_id.to_string());
+    }
+
+    self.events.push(NetEvent::Party(PartyEvent::Invite {
+      party_id: party_id.clone(),
+      player_id: player_id.to_string(),
+      recipient_id: recipient_id.to_string(),
+    }));
+
+    Ok(party_id)
+  }
+
+  /// Joins the party, other invites for the player are declined
+  pub fn accept_party_invite(&mut self, player_id: &str, party_id: &str) -> Result<(), PartyError> {
+    match self.clients.get(player_id) {
+      Some(client) if client.party_id.is_some() => return Err(PartyError::AlreadyInParty),
+      Some(_) => {}
+      None => return Err(PartyError::NotInvited),
+    }
+
+    let party = self.parties.get(party_id).ok_or(PartyError::NotInvited)?;
+
+    if !party.invites.iter().any(|id| id == player_id) {
+      return Err(PartyError::NotInvited);
+    }
+
+    let party_ids: Vec<String> = self
+      .get_party_invites(player_id)
+      .map(String::from)
+      .collect();
+
+    for invite_party_id in party_ids {
+      if invite_party_id != party_id {
+        self.decline_party_invite(player_id, &invite_party_id);
+      }
+    }
+
+    let party = self.parties.get_mut(party_id).unwrap();
+    party.invites.retain(|id| id != player_id);
+
+    self.add_party_member(party_id, player_id);
+
+    Ok(())
+  }
+
+  pub fn decline_party_invite(&mut self, player_id: &str, party_id: &str) {
+    if let Some(party) = self.parties.get_mut(party_id) {
+      party.invites.retain(|id| id != player_id);
+    }
+
+    self.disband_party_if_alone(party_id);
+  }
+
+  pub fn leave_party(&mut self, player_id: &str) {
+    self.remove_party_member(player_id, "left");
+  }
+
+  pub fn kick_from_party(&mut self, leader_id: &str, player_id: &str) -> Result<(), PartyError> {
+    let party_id = self
+      .get_player_party(leader_id)
+      .ok_or(PartyError::NotInParty)?;
+
+    if self.parties[party_id].leader_id != leader_id {
+      return Err(PartyError::NotLeader);
+    }
+
+    if leader_id == player_id || self.get_player_party(player_id) != Some(party_id) {
+      return Err(PartyError::NotInParty);
+    }
+
+    self.remove_party_member(player_id, "kicked");
+
+    Ok(())
+  }
+
+  pub fn set_party_leader(&mut self, leader_id: &str, player_id: &str) -> Result<(), PartyError> {
+    let party_id = self
+      .get_player_party(leader_id)
+      .ok_or(PartyError::NotInParty)?
+      .to_string();
+
+    if self.parties[&party_id].leader_id != leader_id {
+      return Err(PartyError::NotLeader);
+    }
+
+    if self.get_player_party(player_id) != Some(party_id.as_str()) {
+      return Err(PartyError::NotInParty);
+    }
+
+    self.parties.get_mut(&party_id).unwrap().leader_id = player_id.to_string();
+
+    self.events.push(NetEvent::Party(PartyEvent::LeaderChange {
+      party_id,
+      player_id: player_id.to_string(),
+    }));
+
+    Ok(())
+  }
+
+  pub fn get_player_party(&self, player_id: &str) -> Option<&str> {
+    self.clients.get(player_id)?.party_id.as_deref()
+  }
+
+  pub fn get_party_leader(&self, party_id: &str) -> Option<&str> {
+    self
+      .parties
+      .get(party_id)
+      .map(|party| party.leader_id.as_str())
+  }
+
+  pub fn get_party_members(&self, party_id: &str) -> Option<&[String]> {
+    self
+      .parties
+      .get(party_id)
+      .map(|party| party.members.as_slice())
+  }
+
+  /// Ids of parties the player has been invited to
+  pub fn get_party_invites<'a>(&'a self, player_id: &'a str) -> impl Iterator<Item = &'a str> {
+    self
+      .parties
+      .iter()
+      .filter(move |(_, party)| party.invites.iter().any(|id| id == player_id))
+      .map(|(party_id, _)| party_id.as_str())
+  }
+
+  /// Transfers every member of the party
+  #[allow(clippy::too_many_arguments)]
+  pub fn transfer_party(
+    &mut self,
+    party_id: &str,
+    area_id: &str,
+    warp_in: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+    direction: Direction,
+  ) {
+    let members = match self.parties.get(party_id) {
+      Some(party) => party.members.clone(),
+      None => return,
+    };
+
+    for member_id in members {
+      self.transfer_player(&member_id, area_id, warp_in, x, y, z, direction);
+    }
+  }
+
+  /// Transfers every member of the party to the party's instance of the template
+  #[allow(clippy::too_many_arguments)]
+  pub fn transfer_party_to_instance(
+    &mut self,
+    party_id: &str,
+    template_id: &str,
+    warp_in: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+    direction: Direction,
+  ) -> Option<String> {
+    if !self.parties.contains_key(party_id) {
+      return None;
+    }
+
+    let area_id = self.create_instance(template_id, party_id)?;
+
+    self.transfer_party(party_id, &area_id, warp_in, x, y, z, direction);
+
+    Some(area_id)
+  }
+
+  pub fn send_party_system_chat_message(
+    &mut self,
+    party_id: &str,
+    sender_name: &str,
+    message: &str,
+  ) {
+    let packet = ServerPacket::ChatMessage {
+      channel: ChatChannel::System,
+      sender_id: "",
+      sender_name,
+      message,
+    };
+
+    self.broadcast_to_party(party_id, Reliability::ReliableOrdered, packet);
+  }
+
+  pub(super) fn broadcast_to_party(
+    &mut self,
+    party_id: &str,
+    reliability: Reliability,
+    packet: ServerPacket,
+  ) {
+    use super::parties::party_room_id;
+
+    self.packet_orchestrator.borrow_mut().broadcast_to_room(
+      &party_room_id(party_id),
+      reliability,
+      packet,
+    );
+  }
+
+  fn add_party_member(&mut self, party_id: &str, player_id: &str) {
+    use super::parties::party_room_id;
+
+    let client = self.clients.get_mut(player_id).unwrap();
+    client.party_id = Some(party_id.to_string());
+
+    self
+      .packet_orchestrator
+      .borrow_mut()
+      .join_room(client.socket_address, party_room_id(party_id));
+
+    let party = self.parties.get_mut(party_id).unwrap();
+
+    if !party.members.iter().any(|id| id == player_id) {
+      party.members.push(player_id.to_string());
+    }
+
+    self.events.push(NetEvent::Party(PartyEvent::Join {
+      party_id: party_id.to_string(),
+      player_id: player_id.to_string(),
+    }));
+  }
+
+  fn remove_party_member(&mut self, player_id: &str, reason: &'static str) {
+    use super::parties::party_room_id;
+
+    let client = match self.clients.get_mut(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let party_id = match client.party_id.take() {
+      Some(party_id) => party_id,
+      None => return,
+    };
+
+    self
+      .packet_orchestrator
+      .borrow_mut()
+      .leave_room(client.socket_address, &party_room_id(&party_id));
+
+    let party = self.parties.get_mut(&party_id).unwrap();
+    party.members.retain(|id| id != player_id);
+
+    self.events.push(NetEvent::Party(PartyEvent::Leave {
+      party_id: party_id.clone(),
+      player_id: player_id.to_string(),
+      reason,
+    }));
+
+    if party.members.is_empty() {
+      self.parties.remove(&party_id);
+      return;
+    }
+
+    if party.leader_id == player_id {
+      party.leader_id = party.members[0].clone();
+
+      self.events.push(NetEvent::Party(PartyEvent::LeaderChange {
+        party_id: party_id.clone(),
+        player_id: party.leader_id.clone(),
+      }));
+    }
+
+    self.disband_party_if_alone(&party_id);
+  }
+
+  // a party needs another member or a pending invite
+  fn disband_party_if_alone(&mut self, party_id: &str) {
+    let party = match self.parties.get(party_id) {
+      Some(party) => party,
+      None => return,
+    };
+
+    if party.members.len() == 1 && party.invites.is_empty() {
+      let player_id = party.members[0].clone();
+      self.remove_party_member(&player_id, "disbanded");
+    }
+  }
+
+  /// Starts a trade request, plugins can prevent it before the recipient is able to accept
+  pub fn request_trade(&mut self, player_id: &str, recipient_id: &str) -> Result<(), TradeError> {
+    if player_id == recipient_id {
+      return Err(TradeError::SamePlayer);
+    }
+
+    for id in [player_id, recipient_id] {
+      if self.get_trade_index(id).is_some() {
+        return Err(TradeError::AlreadyTrading);
+      }
+
+      match self.clients.get(id) {
+        Some(client) if client.is_busy() => return Err(TradeError::PlayerBusy),
+        Some(_) => {}
+        None => return Err(TradeError::PlayerNotFound),
+      }
+    }
+
+    for id in [player_id, recipient_id] {
+      let client = self.clients.get_mut(id).unwrap();
+      client.widget_tracker.track_trade(self.active_plugin);
+    }
+
+    self
+      .trades
+      .push(Trade::new(player_id.to_string(), recipient_id.to_string()));
+
+    self.events.push(NetEvent::Trade(TradeEvent::Request {
+      player_id: player_id.to_string(),
+      recipient_id: recipient_id.to_string(),
+    }));
+
+    Ok(())
+  }
+
+  pub fn accept_trade(&mut self, recipient_id: &str) -> Result<(), TradeError> {
+    let index = self
+      .get_trade_index(recipient_id)
+      .ok_or(TradeError::NotTrading)?;
+    let trade = &mut self.trades[index];
+
+    if trade.recipient_id != recipient_id || trade.state != TradeState::Requested {
+      return Err(TradeError::NotAccepted);
+    }
+
+    trade.state = TradeState::Active;
+
+    Ok(())
+  }
+
+  /// Cancels or declines the player's trade, offered money and items are returned
+  pub fn cancel_trade(&mut self, player_id: &str) {
+    let reason = match self.get_trade_index(player_id) {
+      Some(index)
+        if self.trades[index].state == TradeState::Requested
+          && self.trades[index].recipient_id == player_id =>
+      {
+        "declined"
+      }
+      _ => "cancelled",
     };
 
-    // send dependencies
-    let dependency_chain = self
-      .asset_manager
-      .get_flattened_dependency_chain(package_path);
+    self.cancel_trade_with_reason(player_id, reason);
+  }
 
-    let mut packets = Vec::new();
-    packets.reserve_exact(dependency_chain.len());
+  pub fn get_trade_partner(&self, player_id: &str) -> Option<&str> {
+    let trade = &self.trades[self.get_trade_index(player_id)?];
+    let side = trade.side(player_id)?;
 
-    for asset_path in dependency_chain {
-      let asset = if let Some(asset) = self.asset_manager.get_asset(asset_path) {
-        asset
-      } else {
-        warn!("No asset found with path \"{}\"", asset_path);
-        continue;
-      };
+    Some(trade.participant(1 - side))
+  }
 
-      let package_info = if let Some(package_info) = asset.resolve_package_info() {
-        package_info
-      } else {
-        warn!("\"{}\" is not a package", asset_path);
-        continue;
-      };
+  pub fn is_trade_accepted(&self, player_id: &str) -> bool {
+    self
+      .get_trade_index(player_id)
+      .map(|index| self.trades[index].state == TradeState::Active)
+      .unwrap_or_default()
+  }
 
-      packets.push(ServerPacket::OfferPackage {
-        package_info,
-        package_path: asset_path,
-      });
-    }
+  pub fn get_trade_offer(&self, player_id: &str) -> Option<&TradeOffer> {
+    let trade = &self.trades[self.get_trade_index(player_id)?];
 
-    self.packet_orchestrator.borrow_mut().send_packets(
-      client.socket_address,
-      Reliability::ReliableOrdered,
-      packets,
-    );
+    Some(&trade.offers[trade.side(player_id)?])
   }
 
-  pub fn initiate_encounter(&mut self, player_id: &str, package_path: &str, data: Option<&str>) {
-    use super::asset;
+  pub fn is_trade_confirmed(&self, player_id: &str) -> bool {
+    let trade = match self.get_trade_index(player_id) {
+      Some(index) => &self.trades[index],
+      None => return false,
+    };
 
-    ensure_asset(
-      &mut *self.packet_orchestrator.borrow_mut(),
-      self.config.max_payload_size,
-      &self.asset_manager,
-      &mut self.clients,
-      &[String::from(player_id)],
-      &package_path.to_string(),
-    );
+    trade
+      .side(player_id)
+      .map(|side| trade.confirmed[side])
+      .unwrap_or_default()
+  }
 
-    self.send_encounter_data_asset(player_id, data);
+  /// Replaces the player's offer, the offered money and items are held until the trade ends.
+  /// Changing an offer clears confirmations from both players, a rejected offer leaves the player offering nothing
+  pub fn set_trade_offer(
+    &mut self,
+    player_id: &str,
+    money: u32,
+    items: &[(String, u32)],
+  ) -> Result<(), TradeError> {
+    let index = self
+      .get_trade_index(player_id)
+      .ok_or(TradeError::NotTrading)?;
+
+    if self.trades[index].state != TradeState::Active {
+      return Err(TradeError::NotAccepted);
+    }
 
-    let client = if let Some(client) = self.clients.get_mut(player_id) {
-      client
-    } else {
-      return;
-    };
+    let side = self.trades[index].side(player_id).unwrap();
+    let previous_offer = std::mem::take(&mut self.trades[index].offers[side]);
+    self.trades[index].confirmed = [false; 2];
+    self.return_trade_offer(player_id, &previous_offer);
 
-    // update tracking
-    client.battle_tracker.push_back(self.active_plugin);
+    let item_ids: Vec<&str> = items.iter().map(|(item_id, _)| item_id.as_str()).collect();
 
-    // send dependencies
-    let dependency_chain = self
-      .asset_manager
-      .get_flattened_dependency_chain(package_path);
+    let offer = self
+      .update_player_inventory(player_id, &item_ids, |item_map, player_data| {
+        escrow_offer(item_map, player_data, money, items)
+      })
+      .ok_or(TradeError::PlayerNotFound)??;
 
-    let mut packets = Vec::new();
-    packets.reserve_exact(dependency_chain.len());
+    self.trades[index].offers[side] = offer;
 
-    for asset_path in dependency_chain {
-      let asset = if let Some(asset) = self.asset_manager.get_asset(asset_path) {
-        asset
-      } else {
-        warn!("No asset found with path \"{}\"", asset_path);
-        continue;
-      };
+    Ok(())
+  }
 
-      let package_category = if let Some(package_info) = asset.resolve_package_info() {
-        package_info.category
-      } else {
-        warn!("\"{}\" is not a package", asset_path);
-        continue;
-      };
+  /// The trade completes once both players confirm.
+  /// The trade is cancelled if either player can no longer hold the other offer when it completes
+  pub fn confirm_trade(&mut self, player_id: &str) -> Result<(), TradeError> {
+    let index = self
+      .get_trade_index(player_id)
+      .ok_or(TradeError::NotTrading)?;
+    let trade = &self.trades[index];
 
-      packets.push(ServerPacket::LoadPackage {
-        package_path: asset_path,
-        package_category,
-      });
+    if trade.state != TradeState::Active {
+      return Err(TradeError::NotAccepted);
     }
 
-    packets.push(ServerPacket::InitiateEncounter {
-      package_path,
-      data_path: asset::get_encounter_data_path(),
-    });
+    let side = trade.side(player_id).unwrap();
 
-    self.packet_orchestrator.borrow_mut().send_packets(
-      client.socket_address,
-      Reliability::ReliableOrdered,
-      packets,
-    );
-  }
+    // the player must be able to hold the other offer before agreeing to it
+    self.validate_trade_receipt(player_id, &trade.offers[1 - side])?;
 
-  pub fn is_player_busy(&self, id: &str) -> bool {
-    if let Some(client) = self.clients.get(id) {
-      return client.is_busy();
+    self.trades[index].confirmed[side] = true;
+
+    if self.trades[index].confirmed == [true; 2] {
+      self.complete_trade(index)?;
     }
 
-    true
+    Ok(())
   }
 
-  pub fn get_player_data(&self, player_id: &str) -> Option<&PlayerData> {
+  /// Called after plugins see the request, allows the recipient to accept
+  pub(super) fn resolve_trade_request(&mut self, player_id: &str, prevented: bool) {
+    let index = match self.get_trade_index(player_id) {
+      Some(index) if self.trades[index].state == TradeState::Pending => index,
+      _ => return,
+    };
+
+    if prevented {
+      self.cancel_trade_with_reason(player_id, "prevented");
+    } else {
+      self.trades[index].state = TradeState::Requested;
+    }
+  }
+
+  fn get_trade_index(&self, player_id: &str) -> Option<usize> {
     self
-      .clients
-      .get(player_id)
-      .map(|client| &client.player_data)
+      .trades
+      .iter()
+      .position(|trade| trade.side(player_id).is_some())
   }
 
-  pub(crate) fn update_player_data(&mut self, player_id: &str, element: String, max_health: u32) {
-    let client = self.clients.get_mut(player_id).unwrap();
+  fn validate_trade_receipt(&self, player_id: &str, offer: &TradeOffer) -> Result<(), TradeError> {
+    let client = self
+      .clients
+      .get(player_id)
+      .ok_or(TradeError::PlayerNotFound)?;
 
-    client.player_data.element = element;
-    client.player_data.health = max_health;
-    client.player_data.max_health = max_health;
+    validate_receipt(&self.items, &client.player_data, offer)
   }
 
-  pub fn set_player_health(&mut self, player_id: &str, health: u32) {
-    if let Some(client) = self.clients.get_mut(player_id) {
-      let max_health = client.player_data.max_health;
+  // inventories may have changed since each player confirmed, both sides are validated again
+  // before anything moves so the swap can't fail partway through
+  fn complete_trade(&mut self, index: usize) -> Result<(), TradeError> {
+    let trade = &self.trades[index];
 
-      client.player_data.health = health;
+    let validation = self
+      .validate_trade_receipt(&trade.player_id, &trade.offers[1])
+      .and_then(|_| self.validate_trade_receipt(&trade.recipient_id, &trade.offers[0]));
 
-      self.packet_orchestrator.borrow_mut().send(
-        client.socket_address,
-        Reliability::ReliableOrdered,
-        ServerPacket::Health { health, max_health },
-      );
+    if let Err(err) = validation {
+      let player_id = trade.player_id.clone();
+      self.cancel_trade_with_reason(&player_id, "invalid");
+      return Err(err);
     }
-  }
 
-  pub fn set_player_max_health(&mut self, player_id: &str, max_health: u32) {
-    if let Some(client) = self.clients.get_mut(player_id) {
-      let health = client.player_data.health;
+    let trade = self.trades.remove(index);
+    self.close_trade_widgets(&trade);
 
-      client.player_data.max_health = max_health;
+    self.return_trade_offer(&trade.recipient_id, &trade.offers[0]);
+    self.return_trade_offer(&trade.player_id, &trade.offers[1]);
 
-      self.packet_orchestrator.borrow_mut().send(
-        client.socket_address,
-        Reliability::ReliableOrdered,
-        ServerPacket::Health { health, max_health },
-      );
-    }
-  }
+    let [player_offer, recipient_offer] = trade.offers;
 
-  pub fn set_player_emotion(&mut self, player_id: &str, emotion: u8) {
-    if let Some(client) = self.clients.get_mut(player_id) {
-      client.player_data.emotion = emotion;
+    self.events.push(NetEvent::Trade(TradeEvent::Complete {
+      player_id: trade.player_id,
+      recipient_id: trade.recipient_id,
+      player_offer,
+      recipient_offer,
+    }));
 
-      self.packet_orchestrator.borrow_mut().send(
-        client.socket_address,
-        Reliability::ReliableOrdered,
-        ServerPacket::Emotion { emotion },
-      );
-    }
+    Ok(())
   }
 
-  pub fn set_player_money(&mut self, player_id: &str, money: u32) {
-    if let Some(client) = self.clients.get_mut(player_id) {
-      client.player_data.money = money;
+  fn cancel_trade_with_reason(&mut self, player_id: &str, reason: &'static str) {
+    let index = match self.get_trade_index(player_id) {
+      Some(index) => index,
+      None => return,
+    };
 
-      self.packet_orchestrator.borrow_mut().send(
-        client.socket_address,
-        Reliability::ReliableOrdered,
-        ServerPacket::Money { money },
-      );
-    }
+    let trade = self.trades.remove(index);
+    self.close_trade_widgets(&trade);
+
+    self.return_trade_offer(&trade.player_id, &trade.offers[0]);
+    self.return_trade_offer(&trade.recipient_id, &trade.offers[1]);
+
+    self.events.push(NetEvent::Trade(TradeEvent::Cancelled {
+      player_id: trade.player_id,
+      recipient_id: trade.recipient_id,
+      reason,
+    }));
   }
 
-  pub fn get_item(&mut self, item_id: &str) -> Option<&Item> {
-    self.items.get(item_id)
+  fn close_trade_widgets(&mut self, trade: &Trade) {
+    for player_id in [&trade.player_id, &trade.recipient_id] {
+      if let Some(client) = self.clients.get_mut(player_id) {
+        client.widget_tracker.close_trade();
+      }
+    }
   }
 
-  pub fn set_item(&mut self, item_id: String, item: Item) {
-    self.items.insert(item_id, item);
+  // gives an offer to a player without validating, the items were already held by a player in the trade
+  fn return_trade_offer(&mut self, player_id: &str, offer: &TradeOffer) {
+    let item_ids: Vec<&str> = offer
+      .items
+      .iter()
+      .map(|stack| stack.item_id.as_str())
+      .collect();
+
+    self.update_player_inventory(player_id, &item_ids, |item_map, player_data| {
+      deposit_offer(item_map, player_data, offer)
+    });
   }
 
-  pub fn give_player_item(&mut self, player_id: &str, item_id: String) {
-    let client = if let Some(client) = self.clients.get_mut(player_id) {
-      client
-    } else {
-      return;
+  // changes money and items directly, then sends the client anything that changed for the listed items
+  fn update_player_inventory<R>(
+    &mut self,
+    player_id: &str,
+    item_ids: &[&str],
+    update: impl FnOnce(&HashMap<String, Item>, &mut PlayerData) -> R,
+  ) -> Option<R> {
+    let client = self.clients.get_mut(player_id)?;
+
+    let mut item_ids = item_ids.to_vec();
+    item_ids.sort_unstable();
+    item_ids.dedup();
+
+    let filter_stacks = |stacks: &[ItemStack], item_id: &str| -> Vec<ItemStack> {
+      stacks
+        .iter()
+        .filter(|stack| stack.item_id == item_id)
+        .cloned()
+        .collect()
     };
 
-    let item = if let Some(item) = self.items.get(&item_id) {
-      item
-    } else {
-      warn!("No item found with id \"{}\"", item_id);
-      return;
-    };
+    let previous_money = client.player_data.money;
+    let previous_stacks: Vec<Vec<ItemStack>> = item_ids
+      .iter()
+      .map(|item_id| filter_stacks(&client.player_data.items, item_id))
+      .collect();
 
-    self.packet_orchestrator.borrow_mut().send(
-      client.socket_address,
-      Reliability::ReliableOrdered,
-      ServerPacket::AddItem {
-        id: &item_id,
-        name: &item.name,
-        description: &item.description,
-      },
-    );
+    let result = update(&self.items, &mut client.player_data);
 
-    client.player_data.items.push(item_id);
-  }
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
 
-  pub fn remove_player_item(&mut self, player_id: &str, item_id: &str) {
-    if let Some(client) = self.clients.get_mut(player_id) {
-      self.packet_orchestrator.borrow_mut().send(
+    if client.player_data.money != previous_money {
+      packet_orchestrator.send(
         client.socket_address,
         Reliability::ReliableOrdered,
-        ServerPacket::RemoveItem { id: item_id },
+        ServerPacket::Money {
+          money: client.player_data.money,
+        },
       );
+    }
 
-      let items = &mut client.player_data.items;
-
-      if let Some(index) = items.iter().position(|item| *item == item_id) {
-        items.remove(index);
+    for (item_id, previous_stacks) in item_ids.into_iter().zip(previous_stacks) {
+      if filter_stacks(&client.player_data.items, item_id) != previous_stacks {
+        update_client_items(
+          &mut packet_orchestrator,
+          &self.items,
+          client,
+          item_id,
+          previous_stacks.len(),
+        );
       }
     }
+
+    Some(result)
   }
 
   #[allow(clippy::too_many_arguments)]
@@ -1444,6 +4174,16 @@ impl Net {
       None => return, // area deleted, should be getting kicked
     };
 
+    if previous_area.get_id() != area_id {
+      if let Some(instance) = self.instances.get_mut(previous_area.get_id()) {
+        instance.remove_member(id);
+      }
+    }
+
+    if let Some(instance) = self.instances.get_mut(area_id) {
+      instance.add_member(id.to_string());
+    }
+
     client.warp_in = warp_in;
     client.warp_x = x;
     client.warp_y = y;
@@ -1531,9 +4271,14 @@ impl Net {
     area.add_player(player_id.to_string());
     self.send_area(player_id, &area_id);
 
-    let mut client = self.clients.get_mut(player_id).unwrap();
-
+    let client = self.clients.get_mut(player_id).unwrap();
     client.actor.area_id = area_id.to_string();
+    client.hidden_objects.clear();
+
+    self.update_gated_objects(player_id);
+
+    let client = self.clients.get_mut(player_id).unwrap();
+
     client.transferring = true;
     client.ready = false;
 
@@ -1595,12 +4340,116 @@ impl Net {
 
   pub fn kick_player(&mut self, id: &str, reason: &str, warp_out: bool) {
     if let Some(client) = self.clients.get(id) {
-      self.kick_list.push(Boot {
-        socket_address: client.socket_address,
-        reason: reason.to_string(),
-        warp_out,
-      });
+      let socket_address = client.socket_address;
+      self.kick_address(socket_address, reason, warp_out);
+    }
+  }
+
+  pub(super) fn kick_address(
+    &mut self,
+    socket_address: std::net::SocketAddr,
+    reason: &str,
+    warp_out: bool,
+  ) {
+    self.kick_list.push(Boot {
+      socket_address,
+      reason: reason.to_string(),
+      warp_out,
+    });
+  }
+
+  pub fn ban_player(&mut self, id: &str, reason: &str, duration: Option<f32>, ban_address: bool) {
+    let client = match self.clients.get(id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let identity = client.player_data.identity.clone();
+    let ip = client.socket_address.ip();
+
+    self.add_ban(BanTarget::Identity(identity), reason, duration);
+
+    if ban_address {
+      let prefix_length = if ip.is_ipv4() { 32 } else { 128 };
+
+      let address_target = BanTarget::AddressRange {
+        address: ip,
+        prefix_length,
+      };
+
+      self.add_ban(address_target, reason, duration);
+    }
+  }
+
+  /// Bans an ip address or CIDR range, returns false if the range is invalid
+  pub fn ban_address(&mut self, address_range: &str, reason: &str, duration: Option<f32>) -> bool {
+    match BanTarget::parse_address_range(address_range) {
+      Some(target) => {
+        self.add_ban(target, reason, duration);
+        true
+      }
+      None => false,
+    }
+  }
+
+  fn add_ban(&mut self, target: BanTarget, reason: &str, duration: Option<f32>) {
+    let expiration = duration.map(|duration| current_timestamp() + duration.max(0.0) as u64);
+
+    let socket_addresses: Vec<std::net::SocketAddr> = self
+      .clients
+      .values()
+      .filter(|client| match &target {
+        BanTarget::Identity(identity) => client.player_data.identity == *identity,
+        BanTarget::AddressRange { .. } => target.matches_address(client.socket_address.ip()),
+      })
+      .map(|client| client.socket_address)
+      .collect();
+
+    for socket_address in socket_addresses {
+      self.kick_address(socket_address, reason, true);
+    }
+
+    self.ban_list.add(Ban {
+      target,
+      reason: reason.to_string(),
+      expiration,
+    });
+  }
+
+  /// Accepts an identity or an address range, returns true if a ban was removed
+  pub fn unban(&mut self, target: &str) -> bool {
+    if let Some(address_target) = BanTarget::parse_address_range(target) {
+      if self.ban_list.remove(&address_target) {
+        return true;
+      }
     }
+
+    self
+      .ban_list
+      .remove(&BanTarget::Identity(target.to_string()))
+  }
+
+  pub fn get_bans(&self) -> impl std::iter::Iterator<Item = &Ban> {
+    self.ban_list.iter()
+  }
+
+  pub(super) fn get_address_ban(&self, ip: std::net::IpAddr) -> Option<&Ban> {
+    self.ban_list.find_address_ban(ip)
+  }
+
+  pub(super) fn get_identity_ban(&self, identity: &str) -> Option<&Ban> {
+    self.ban_list.find_identity_ban(identity)
+  }
+
+  pub(super) fn get_client_address_by_identity(
+    &self,
+    identity: &str,
+  ) -> Option<std::net::SocketAddr> {
+    self
+      .clients
+      .values()
+      .find(|client| client.player_data.identity == identity)
+      .map(|client| client.socket_address)
   }
 
   pub(super) fn take_kick_list(&mut self) -> Vec<Boot> {
@@ -1616,14 +4465,17 @@ impl Net {
     socket_address: std::net::SocketAddr,
     name: String,
     identity: String,
+    player_data: Option<PlayerData>,
   ) -> String {
+    self.battle_history.load(&identity);
+
     let area_id = String::from("default");
     let area = self.get_area_mut(&area_id).unwrap();
     let map = area.get_map();
     let (spawn_x, spawn_y, spawn_z) = map.get_spawn();
     let spawn_direction = map.get_spawn_direction();
 
-    let client = Client::new(
+    let mut client = Client::new(
       socket_address,
       name,
       identity,
@@ -1634,6 +4486,10 @@ impl Net {
       spawn_direction,
     );
 
+    if let Some(player_data) = player_data {
+      client.player_data = player_data;
+    }
+
     let id = client.actor.id.clone();
 
     let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
@@ -1723,6 +4579,9 @@ impl Net {
 
     area.add_player(client.actor.id.clone());
 
+    let actor = &client.actor;
+    area.update_actor_position(&actor.id, actor.x, actor.y, actor.z);
+
     self
       .packet_orchestrator
       .borrow_mut()
@@ -1738,6 +4597,7 @@ impl Net {
     );
 
     self.send_area(player_id, &area_id);
+    self.update_gated_objects(player_id);
 
     let client = self.clients.get_mut(player_id).unwrap();
 
@@ -1758,6 +4618,8 @@ impl Net {
   }
 
   pub(super) fn connect_client(&mut self, player_id: &str) {
+    self.sync_player_data(player_id);
+
     self.packet_orchestrator.borrow_mut().send_by_id(
       player_id,
       Reliability::ReliableOrdered,
@@ -1765,6 +4627,50 @@ impl Net {
     );
   }
 
+  // sends stored player data to the client
+  fn sync_player_data(&mut self, player_id: &str) {
+    let client = match self.clients.get(player_id) {
+      Some(client) => client,
+      None => return,
+    };
+
+    let player_data = &client.player_data;
+    let mut packet_orchestrator = self.packet_orchestrator.borrow_mut();
+
+    packet_orchestrator.send(
+      client.socket_address,
+      Reliability::ReliableOrdered,
+      ServerPacket::Money {
+        money: player_data.money,
+      },
+    );
+
+    packet_orchestrator.send(
+      client.socket_address,
+      Reliability::ReliableOrdered,
+      ServerPacket::Emotion {
+        emotion: player_data.emotion,
+      },
+    );
+
+    for stack in &player_data.items {
+      let item = match self.items.get(&stack.item_id) {
+        Some(item) => item,
+        None => continue, // item was not recreated by a script
+      };
+
+      packet_orchestrator.send(
+        client.socket_address,
+        Reliability::ReliableOrdered,
+        ServerPacket::AddItem {
+          id: &stack.item_id,
+          name: &item.display_name(stack.quantity),
+          description: &item.description,
+        },
+      );
+    }
+  }
+
   fn send_area(&mut self, player_id: &str, area_id: &str) {
     use super::asset::get_map_path;
 
@@ -1845,11 +4751,14 @@ impl Net {
       None => return,
     };
 
-    let area = match self.areas.get(&client.actor.area_id) {
+    let area = match self.areas.get_mut(&client.actor.area_id) {
       Some(area) => area,
       None => return, // area deleted, should be getting kicked
     };
 
+    let actor = &client.actor;
+    area.update_actor_position(&actor.id, actor.x, actor.y, actor.z);
+
     client.ready = true;
     client.transferring = false;
 
@@ -1869,11 +4778,35 @@ impl Net {
   pub(super) fn remove_player(&mut self, id: &str, warp_out: bool) {
     use super::asset;
 
+    // return offered money and items before the player is saved
+    self.cancel_trade_with_reason(id, "disconnected");
+
+    let party_ids: Vec<String> = self.get_party_invites(id).map(String::from).collect();
+
+    for party_id in party_ids {
+      self.decline_party_invite(id, &party_id);
+    }
+
+    self.remove_party_member(id, "disconnected");
+
+    for coop_battle in &mut self.coop_battles {
+      coop_battle.report(id, None);
+    }
+
+    self.leave_matchmaking_queue(id);
+    self.remove_battle_spectator_with_reason(id, "disconnected");
+    self.forfeit_pvp_battle(id);
+
     let client = match self.clients.remove(id) {
       Some(client) => client,
       None => return,
     };
 
+    self.player_store.save(&client.player_data);
+    self.battle_history.unload(&client.player_data.identity);
+
+    self.encounter_trackers.remove(id);
+
     let remove_list = [
       asset::get_player_texture_path(id),
       asset::get_player_animation_path(id),
@@ -1885,6 +4818,10 @@ impl Net {
       self.asset_manager.remove_asset(asset_path);
     }
 
+    if let Some(instance) = self.instances.get_mut(&client.actor.area_id) {
+      instance.remove_member(id);
+    }
+
     let area = match self.areas.get_mut(&client.actor.area_id) {
       Some(area) => area,
       None => return,
@@ -1926,6 +4863,7 @@ impl Net {
 
     if let Some(area) = self.areas.get_mut(&bot.area_id) {
       area.add_bot(bot.id.clone());
+      area.update_actor_position(&bot.id, bot.x, bot.y, bot.z);
 
       let packet = bot.create_spawn_packet(bot.x, bot.y, bot.z, warp_in);
 
@@ -1955,6 +4893,8 @@ impl Net {
       None => return,
     };
 
+    self.bot_paths.remove(id);
+
     let area = match self.areas.get_mut(&bot.area_id) {
       Some(area) => area,
       None => return,
@@ -2007,6 +4947,10 @@ impl Net {
       }
 
       bot.set_position(x, y, z);
+
+      if let Some(area) = self.areas.get_mut(&bot.area_id) {
+        area.update_actor_position(id, x, y, z);
+      }
     }
   }
 
@@ -2134,7 +5078,7 @@ impl Net {
 
     if let Some(bot) = self.bots.get_mut(id) {
       // store final values for new players
-      let area = match self.areas.get(&bot.area_id) {
+      let area = match self.areas.get_mut(&bot.area_id) {
         Some(area) => area,
         None => return,
       };
@@ -2164,6 +5108,8 @@ impl Net {
       bot.y = final_y;
       bot.z = final_z;
 
+      area.update_actor_position(id, final_x, final_y, final_z);
+
       broadcast_actor_keyframes(
         &mut *self.packet_orchestrator.borrow_mut(),
         area,
@@ -2174,12 +5120,134 @@ impl Net {
     }
   }
 
+  /// Walks the bot to the destination at the speed in tiles per second, avoiding walls, solid objects, and solid actors.
+  /// Returns false if there's no path, or if the speed isn't a positive finite number.
+  pub fn path_bot_to(&mut self, id: &str, x: f32, y: f32, z: f32, speed: f32) -> bool {
+    if !(speed > 0.0 && speed.is_finite()) {
+      return false;
+    }
+
+    let bot = match self.bots.get(id) {
+      Some(bot) => bot,
+      None => return false,
+    };
+
+    let area = match self.areas.get(&bot.area_id) {
+      Some(area) => area,
+      None => return false,
+    };
+
+    let blocked_tiles = area
+      .get_connected_players()
+      .iter()
+      .filter_map(|player_id| self.clients.get(player_id).map(|client| &client.actor))
+      .chain(
+        area
+          .get_connected_bots()
+          .iter()
+          .filter_map(|bot_id| self.bots.get(bot_id)),
+      )
+      .filter(|actor| actor.solid && actor.id != id)
+      .map(|actor| to_tile((actor.x, actor.y, actor.z)))
+      .collect();
+
+    let waypoints = find_path(
+      area.get_map(),
+      &self.asset_manager,
+      &mut self.tile_class_cache,
+      &blocked_tiles,
+      (bot.x, bot.y, bot.z),
+      (x, y, z),
+    );
+
+    let waypoints = match waypoints {
+      Some(waypoints) => waypoints,
+      None => return false,
+    };
+
+    self.bot_paths.insert(
+      id.to_string(),
+      BotPath {
+        waypoints,
+        speed,
+        last_update: std::time::Instant::now(),
+      },
+    );
+
+    true
+  }
+
+  pub fn cancel_bot_path(&mut self, id: &str) {
+    self.bot_paths.remove(id);
+  }
+
+  pub fn is_bot_pathing(&self, id: &str) -> bool {
+    self.bot_paths.contains_key(id)
+  }
+
+  pub(super) fn take_completed_bot_paths(&mut self) -> Vec<String> {
+    std::mem::take(&mut self.completed_bot_paths)
+  }
+
+  fn update_bot_paths(&mut self) {
+    let mut movements = Vec::new();
+
+    for (bot_id, bot_path) in &mut self.bot_paths {
+      let bot = match self.bots.get(bot_id) {
+        Some(bot) => bot,
+        None => continue,
+      };
+
+      let mut remaining_distance = bot_path.speed * bot_path.last_update.elapsed().as_secs_f32();
+      bot_path.last_update = std::time::Instant::now();
+
+      let (mut x, mut y, mut z) = (bot.x, bot.y, bot.z);
+
+      while let Some(&(target_x, target_y, target_z)) = bot_path.waypoints.front() {
+        let distance = (target_x - x).hypot(target_y - y);
+
+        if distance > remaining_distance {
+          let progress = remaining_distance / distance;
+
+          x += (target_x - x) * progress;
+          y += (target_y - y) * progress;
+          z += (target_z - z) * progress;
+          break;
+        }
+
+        remaining_distance -= distance;
+        (x, y, z) = (target_x, target_y, target_z);
+        bot_path.waypoints.pop_front();
+      }
+
+      movements.push((bot_id.clone(), x, y, z));
+    }
+
+    for (bot_id, x, y, z) in movements {
+      self.move_bot(&bot_id, x, y, z);
+    }
+
+    let completed_bot_ids: Vec<String> = self
+      .bot_paths
+      .iter()
+      .filter(|(_, bot_path)| bot_path.waypoints.is_empty())
+      .map(|(bot_id, _)| bot_id.clone())
+      .collect();
+
+    for bot_id in completed_bot_ids {
+      self.bot_paths.remove(&bot_id);
+      self.completed_bot_paths.push(bot_id);
+    }
+  }
+
   pub fn transfer_bot(&mut self, id: &str, area_id: &str, warp_in: bool, x: f32, y: f32, z: f32) {
     if self.areas.get(area_id).is_none() {
       // non existent area
       return;
     }
 
+    self.bot_paths.remove(id);
+
     if let Some(bot) = self.bots.get_mut(id) {
       if let Some(previous_area) = self.areas.get_mut(&bot.area_id) {
         previous_area.remove_bot(id);
@@ -2202,6 +5270,7 @@ impl Net {
 
       let area = self.areas.get_mut(area_id).unwrap();
       area.add_bot(id.to_string());
+      area.update_actor_position(id, x, y, z);
 
       ensure_assets(
         &mut *self.packet_orchestrator.borrow_mut(),
@@ -2229,16 +5298,226 @@ impl Net {
     }
   }
 
+  /// Replaces a command with the same name, commands are only sent to the registering plugin
+  pub fn register_console_command(&mut self, name: String, help: String) {
+    self
+      .console_commands
+      .retain(|console_command| console_command.name != name);
+
+    self.console_commands.push(ConsoleCommand {
+      name,
+      help,
+      owner: self.active_plugin,
+    });
+  }
+
+  /// Plugins can only remove commands they registered
+  pub fn remove_console_command(&mut self, name: &str) {
+    let active_plugin = self.active_plugin;
+
+    self.console_commands.retain(|console_command| {
+      console_command.name != name || console_command.owner != active_plugin
+    });
+  }
+
+  pub fn get_console_commands(&self) -> &[ConsoleCommand] {
+    &self.console_commands
+  }
+
   // ugly opengl like context storing
   // needed to correctly track message owners send without adding extra parameters
   // luckily not visible to plugin authors
-  pub(super) fn set_active_plugin(&mut self, active_plugin: usize) {
+  // None while the server itself is acting, such as console commands
+  pub(super) fn set_active_plugin(&mut self, active_plugin: Option<usize>) {
     self.active_plugin = active_plugin;
   }
 
+  /// Reports actors coming within the radius of this actor, None stops watching
+  pub fn set_actor_proximity_radius(&mut self, actor_id: &str, radius: Option<f32>) {
+    let radius = match radius {
+      Some(radius) => radius,
+      None => {
+        self.proximity_watches.remove(actor_id);
+        return;
+      }
+    };
+
+    if let Some(watch) = self.proximity_watches.get_mut(actor_id) {
+      watch.radius = radius;
+      return;
+    }
+
+    self.proximity_watches.insert(
+      actor_id.to_string(),
+      ProximityWatch {
+        radius,
+        nearby: HashSet::new(),
+      },
+    );
+  }
+
+  pub fn get_actor_proximity_radius(&self, actor_id: &str) -> Option<f32> {
+    self
+      .proximity_watches
+      .get(actor_id)
+      .map(|watch| watch.radius)
+  }
+
+  pub(super) fn take_events(&mut self) -> Vec<NetEvent> {
+    std::mem::take(&mut self.events)
+  }
+
+  // tracks players standing in objects matching the trigger class
+  fn update_object_triggers(&mut self) {
+    let trigger_class = &self.config.trigger_class;
+    let mut previous_triggers = std::mem::take(&mut self.occupied_triggers);
+
+    for area in self.areas.values() {
+      let area_id = area.get_id();
+      let map = area.get_map();
+
+      for player_id in area.get_connected_players() {
+        let previous = previous_triggers.remove(player_id);
+
+        let client = match self.clients.get(player_id) {
+          Some(client) => client,
+          None => continue,
+        };
+
+        if !client.ready {
+          // position is stale until the client is ready
+          if let Some(previous) = previous {
+            self.occupied_triggers.insert(player_id.clone(), previous);
+          }
+          continue;
+        }
+
+        let actor = &client.actor;
+        let object_ids: Vec<u32> = map
+          .get_objects_at(actor.x, actor.y, actor.z)
+          .filter(|object| object.class == *trigger_class)
+          .map(|object| object.id)
+          .collect();
+
+        let previous_ids = match previous {
+          Some(previous) if previous.area_id == area_id => previous.object_ids,
+          Some(previous) => {
+            previous_triggers.insert(player_id.clone(), previous);
+            Vec::new()
+          }
+          None => Vec::new(),
+        };
+
+        for object_id in &previous_ids {
+          if !object_ids.contains(object_id) {
+            self
+              .events
+              .push(NetEvent::Trigger(TriggerEvent::ObjectExit {
+                player_id: player_id.clone(),
+                area_id: area_id.to_string(),
+                object_id: *object_id,
+              }));
+          }
+        }
+
+        for object_id in &object_ids {
+          if !previous_ids.contains(object_id) {
+            self
+              .events
+              .push(NetEvent::Trigger(TriggerEvent::ObjectEnter {
+                player_id: player_id.clone(),
+                area_id: area_id.to_string(),
+                object_id: *object_id,
+              }));
+          }
+        }
+
+        if !object_ids.is_empty() {
+          self.occupied_triggers.insert(
+            player_id.clone(),
+            OccupiedTriggers {
+              area_id: area_id.to_string(),
+              object_ids,
+            },
+          );
+        }
+      }
+    }
+
+    // players that left their area exit every trigger, disconnected players are skipped
+    for (player_id, previous) in previous_triggers {
+      if !self.clients.contains_key(&player_id) {
+        continue;
+      }
+
+      for object_id in previous.object_ids {
+        self
+          .events
+          .push(NetEvent::Trigger(TriggerEvent::ObjectExit {
+            player_id: player_id.clone(),
+            area_id: previous.area_id.clone(),
+            object_id,
+          }));
+      }
+    }
+  }
+
+  fn update_proximity_watches(&mut self) {
+    let actor_ids: Vec<String> = self.proximity_watches.keys().cloned().collect();
+
+    for actor_id in actor_ids {
+      let actor = match self
+        .get_player(&actor_id)
+        .or_else(|| self.get_bot(&actor_id))
+      {
+        Some(actor) => actor,
+        None => {
+          // actor was removed
+          self.proximity_watches.remove(&actor_id);
+          continue;
+        }
+      };
+
+      let radius = self.proximity_watches[&actor_id].radius;
+
+      let nearby: HashSet<String> = self
+        .actors_in_radius(&actor.area_id, actor.x, actor.y, actor.z, radius)
+        .into_iter()
+        .filter(|id| *id != actor_id)
+        .collect();
+
+      let watch = self.proximity_watches.get_mut(&actor_id).unwrap();
+
+      for nearby_actor_id in &nearby {
+        if !watch.nearby.contains(nearby_actor_id) {
+          self
+            .events
+            .push(NetEvent::Trigger(TriggerEvent::ActorNearby {
+              actor_id: actor_id.clone(),
+              nearby_actor_id: nearby_actor_id.clone(),
+            }));
+        }
+      }
+
+      watch.nearby = nearby;
+    }
+  }
+
   pub(super) fn tick(&mut self) {
+    self.reload_watched_files();
+    self.remove_empty_instances();
+    self.update_bot_paths();
+    self.update_object_triggers();
+    self.update_proximity_watches();
     self.broadcast_bot_positions();
     self.broadcast_map_changes();
+    self.update_matchmaking();
+    self.resolve_timed_out_pvp_battles();
+
+    if self.last_player_save.elapsed().as_secs_f32() >= self.config.player_save_rate {
+      self.save_all_player_data();
+      self.last_player_save = std::time::Instant::now();
+    }
   }
 
   fn broadcast_bot_positions(&mut self) {
@@ -2275,6 +5554,82 @@ impl Net {
     }
   }
 
+  fn reload_watched_files(&mut self) {
+    let changed_paths = match &mut self.file_watcher {
+      Some(file_watcher) => file_watcher.poll(),
+      None => return,
+    };
+
+    for path in changed_paths {
+      if path.starts_with("assets") {
+        self.reload_asset_file(&path);
+        continue;
+      }
+
+      let is_map = path.extension().unwrap_or_default() == "tmx";
+
+      // deleted areas are kept, as removing them would kick players
+      if !is_map || !path.exists() {
+        continue;
+      }
+
+      let area_id = path.file_stem().unwrap_or_default().to_string_lossy();
+
+      match self.load_area_file(&area_id) {
+        Ok(()) => info!("Reloaded area {}", area_id),
+        Err(err) => warn!("{}", err),
+      }
+    }
+  }
+
+  fn reload_asset_file(&mut self, path: &Path) {
+    use super::asset::get_map_path;
+    use super::asset_manager::get_server_asset_path;
+
+    let asset_path = get_server_asset_path(path);
+
+    if !path.exists() {
+      self.remove_asset(&asset_path);
+      return;
+    }
+
+    let mut asset = Asset::load_from_file(path);
+
+    // clients only redownload assets with a newer timestamp, saves within the same second still count
+    if let Some(previous_asset) = self.asset_manager.get_asset(&asset_path) {
+      asset.last_modified = asset.last_modified.max(previous_asset.last_modified + 1);
+    }
+
+    self.set_asset(asset_path.clone(), asset);
+
+    info!("Reloaded asset {}", asset_path);
+
+    // maps using this asset, such as through a tileset, need to be reloaded by players
+    for area in self.areas.values() {
+      let map_path = get_map_path(area.get_id());
+
+      let depends_on_asset = self
+        .asset_manager
+        .get_flattened_dependency_chain(&map_path)
+        .contains(&asset_path.as_str());
+
+      if !depends_on_asset {
+        continue;
+      }
+
+      let packet = ServerPacket::MapUpdate {
+        map_path: &map_path,
+      };
+
+      broadcast_to_area(
+        &mut *self.packet_orchestrator.borrow_mut(),
+        area,
+        Reliability::ReliableOrdered,
+        packet,
+      );
+    }
+  }
+
   fn broadcast_map_changes(&mut self) {
     use super::asset::get_map_path;
 
@@ -2524,3 +5879,78 @@ fn ensure_assets<'a, I>(
     );
   }
 }
+
+fn map_shop_item_ids(items: &[ShopItem]) -> HashMap<String, String> {
+  items
+    .iter()
+    .filter_map(|item| Some((item.name.clone(), item.item_id.clone()?)))
+    .collect()
+}
+
+fn build_shop_inventory_packets(max_payload_size: usize, items: Vec<ShopItem>) -> Vec<Vec<u8>> {
+  use super::shop_item::calc_size;
+  use crate::helpers::iterators::IteratorHelper;
+  use crate::packets::build_packet;
+
+  let calc_chunk_limit = |_| {
+    // reliability + id + type
+    let header_size = 1 + 8 + 2;
+
+    max_payload_size - header_size
+  };
+
+  items
+    .into_iter()
+    .pack_chunks_lossy(calc_chunk_limit, calc_size)
+    .map(|chunk| {
+      build_packet(ServerPacket::ShopInventory {
+        items: chunk.as_slice(),
+      })
+    })
+    .collect()
+}
+
+fn count_stacks(stacks: &[ItemStack], item_id: &str) -> usize {
+  stacks
+    .iter()
+    .filter(|stack| stack.item_id == item_id)
+    .count()
+}
+
+// the client tracks items by id, so every stack with the id is replaced to display new quantities
+fn update_client_items(
+  packet_orchestrator: &mut PacketOrchestrator,
+  items: &HashMap<String, Item>,
+  client: &Client,
+  item_id: &str,
+  previous_stack_count: usize,
+) {
+  let item = match items.get(item_id) {
+    Some(item) => item,
+    None => return,
+  };
+
+  let mut packets = Vec::new();
+
+  for _ in 0..previous_stack_count {
+    packets.push(ServerPacket::RemoveItem { id: item_id });
+  }
+
+  let display_names: Vec<String> = client
+    .player_data
+    .items
+    .iter()
+    .filter(|stack| stack.item_id == item_id)
+    .map(|stack| item.display_name(stack.quantity))
+    .collect();
+
+  for name in &display_names {
+    packets.push(ServerPacket::AddItem {
+      id: item_id,
+      name,
+      description: &item.description,
+    });
+  }
+
+  packet_orchestrator.send_packets(client.socket_address, Reliability::ReliableOrdered, packets);
+}
@@ -0,0 +1,53 @@
+use super::matchmaking::MatchmakingEvent;
+use super::parties::PartyEvent;
+use super::pvp_battles::SpectatorEvent;
+use super::quests::QuestEvent;
+use super::trades::TradeEvent;
+use super::triggers::TriggerEvent;
+
+/// Changes made by Net that plugins should hear about.
+/// Queued in the order they happen and drained by the server after each tick
+pub(super) enum NetEvent {
+  Trigger(TriggerEvent),
+  Trade(TradeEvent),
+  Party(PartyEvent),
+  Spectator(SpectatorEvent),
+  Matchmaking(MatchmakingEvent),
+  Quest(QuestEvent),
+}
+
+impl From<TriggerEvent> for NetEvent {
+  fn from(event: TriggerEvent) -> NetEvent {
+    NetEvent::Trigger(event)
+  }
+}
+
+impl From<TradeEvent> for NetEvent {
+  fn from(event: TradeEvent) -> NetEvent {
+    NetEvent::Trade(event)
+  }
+}
+
+impl From<PartyEvent> for NetEvent {
+  fn from(event: PartyEvent) -> NetEvent {
+    NetEvent::Party(event)
+  }
+}
+
+impl From<SpectatorEvent> for NetEvent {
+  fn from(event: SpectatorEvent) -> NetEvent {
+    NetEvent::Spectator(event)
+  }
+}
+
+impl From<MatchmakingEvent> for NetEvent {
+  fn from(event: MatchmakingEvent) -> NetEvent {
+    NetEvent::Matchmaking(event)
+  }
+}
+
+impl From<QuestEvent> for NetEvent {
+  fn from(event: QuestEvent) -> NetEvent {
+    NetEvent::Quest(event)
+  }
+}
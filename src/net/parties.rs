@@ -0,0 +1,67 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PartyError {
+  SamePlayer,
+  AlreadyInParty,
+  NotInParty,
+  NotLeader,
+  NotInvited,
+}
+
+impl std::fmt::Display for PartyError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let message = match self {
+      PartyError::SamePlayer => "can't invite self",
+      PartyError::AlreadyInParty => "player is already in a party",
+      PartyError::NotInParty => "player is not in the party",
+      PartyError::NotLeader => "player is not the party leader",
+      PartyError::NotInvited => "player was not invited",
+    };
+
+    write!(f, "{}", message)
+  }
+}
+
+pub(super) struct Party {
+  pub leader_id: String,
+  /// In join order, the oldest member becomes leader if the leader leaves
+  pub members: Vec<String>,
+  /// Players who can accept an invite
+  pub invites: Vec<String>,
+}
+
+impl Party {
+  pub fn new(leader_id: String) -> Party {
+    Party {
+      members: vec![leader_id.clone()],
+      leader_id,
+      invites: Vec::new(),
+    }
+  }
+}
+
+/// Parties share a packet orchestrator room for broadcasting to members
+pub(super) fn party_room_id(party_id: &str) -> String {
+  format!("party:{}", party_id)
+}
+
+/// Membership changes, reasons match the strings sent to scripts
+pub(super) enum PartyEvent {
+  Invite {
+    party_id: String,
+    player_id: String,
+    recipient_id: String,
+  },
+  Join {
+    party_id: String,
+    player_id: String,
+  },
+  Leave {
+    party_id: String,
+    player_id: String,
+    reason: &'static str,
+  },
+  LeaderChange {
+    party_id: String,
+    player_id: String,
+  },
+}
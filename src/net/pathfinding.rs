@@ -0,0 +1,358 @@
+use super::asset_manager::AssetManager;
+use super::map::Map;
+use super::tile_classes::TileClassCache;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::time::Instant;
+
+// gives up on unreachable destinations in large maps
+const MAX_SEARCH_NODES: usize = 20000;
+const ORTHOGONAL_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+type Tile = (i32, i32, i32);
+
+pub(super) struct BotPath {
+  pub waypoints: VecDeque<(f32, f32, f32)>,
+  /// Tiles per second
+  pub speed: f32,
+  pub last_update: Instant,
+}
+
+struct Walkability<'a> {
+  map: &'a Map,
+  asset_manager: &'a AssetManager,
+  tile_class_cache: &'a mut TileClassCache,
+  blocked_tiles: &'a HashSet<Tile>,
+}
+
+impl<'a> Walkability<'a> {
+  fn is_walkable(&self, (x, y, z): Tile) -> bool {
+    if x < 0 || y < 0 || z < 0 || self.blocked_tiles.contains(&(x, y, z)) {
+      return false;
+    }
+
+    let map = self.map;
+
+    if map.get_tile(x as usize, y as usize, z as usize).gid == 0 {
+      return false;
+    }
+
+    let (center_x, center_y) = (x as f32 + 0.5, y as f32 + 0.5);
+
+    !map
+      .get_objects_at(center_x, center_y, z as f32)
+      .any(|object| object.is_solid())
+  }
+
+  fn is_stairs(&mut self, (x, y, z): Tile) -> bool {
+    self
+      .tile_class_cache
+      .is_stairs(self.asset_manager, self.map, x as f32, y as f32, z as f32)
+  }
+
+  fn neighbors(&mut self, tile: Tile) -> Vec<(Tile, u32)> {
+    let (x, y, z) = tile;
+    let mut neighbors = Vec::new();
+
+    for dy in -1..=1 {
+      for dx in -1..=1 {
+        if dx == 0 && dy == 0 {
+          continue;
+        }
+
+        let next = (x + dx, y + dy, z);
+        let diagonal = dx != 0 && dy != 0;
+
+        if diagonal {
+          // avoid cutting corners
+          if self.is_walkable(next)
+            && self.is_walkable((x + dx, y, z))
+            && self.is_walkable((x, y + dy, z))
+          {
+            neighbors.push((next, DIAGONAL_COST));
+          }
+
+          continue;
+        }
+
+        if self.is_walkable(next) {
+          neighbors.push((next, ORTHOGONAL_COST));
+        }
+
+        // layers connect through stairs on the lower layer, matching movement validation
+        let above = (x + dx, y + dy, z + 1);
+
+        if self.is_walkable(above) && (self.is_stairs(tile) || self.is_stairs(next)) {
+          neighbors.push((above, ORTHOGONAL_COST));
+        }
+
+        let below = (x + dx, y + dy, z - 1);
+
+        if self.is_walkable(below) && (self.is_stairs((x, y, z - 1)) || self.is_stairs(below)) {
+          neighbors.push((below, ORTHOGONAL_COST));
+        }
+      }
+    }
+
+    neighbors
+  }
+}
+
+/// A* search across tiles, returns waypoints excluding the start position and ending at the destination.
+/// Blocked tiles are treated as unwalkable, such as tiles occupied by solid actors.
+pub(super) fn find_path(
+  map: &Map,
+  asset_manager: &AssetManager,
+  tile_class_cache: &mut TileClassCache,
+  blocked_tiles: &HashSet<Tile>,
+  start: (f32, f32, f32),
+  destination: (f32, f32, f32),
+) -> Option<VecDeque<(f32, f32, f32)>> {
+  let mut walkability = Walkability {
+    map,
+    asset_manager,
+    tile_class_cache,
+    blocked_tiles,
+  };
+
+  let start_tile = to_tile(start);
+  let destination_tile = to_tile(destination);
+
+  if !walkability.is_walkable(destination_tile) {
+    return None;
+  }
+
+  let mut open_set = BinaryHeap::new();
+  let mut came_from: HashMap<Tile, Tile> = HashMap::new();
+  let mut costs: HashMap<Tile, u32> = HashMap::new();
+
+  open_set.push(Reverse((
+    heuristic(start_tile, destination_tile),
+    start_tile,
+  )));
+  costs.insert(start_tile, 0);
+
+  while let Some(Reverse((_, tile))) = open_set.pop() {
+    if tile == destination_tile {
+      let mut waypoints = VecDeque::new();
+      waypoints.push_front(destination);
+
+      let mut current = tile;
+
+      while let Some(previous) = came_from.get(&current) {
+        if *previous != start_tile {
+          let (x, y, z) = *previous;
+          waypoints.push_front((x as f32 + 0.5, y as f32 + 0.5, z as f32));
+        }
+
+        current = *previous;
+      }
+
+      return Some(waypoints);
+    }
+
+    if costs.len() > MAX_SEARCH_NODES {
+      return None;
+    }
+
+    let cost = costs[&tile];
+
+    for (next, step_cost) in walkability.neighbors(tile) {
+      let next_cost = cost + step_cost;
+
+      if costs.get(&next).map(|c| next_cost < *c).unwrap_or(true) {
+        costs.insert(next, next_cost);
+        came_from.insert(next, tile);
+        open_set.push(Reverse((
+          next_cost + heuristic(next, destination_tile),
+          next,
+        )));
+      }
+    }
+  }
+
+  None
+}
+
+pub(super) fn to_tile((x, y, z): (f32, f32, f32)) -> Tile {
+  (x.floor() as i32, y.floor() as i32, z.floor() as i32)
+}
+
+// octile distance, layer changes happen alongside a step so they can't add to it
+fn heuristic((x1, y1, z1): Tile, (x2, y2, z2): Tile) -> u32 {
+  let dx = x1.abs_diff(x2);
+  let dy = y1.abs_diff(y2);
+  let dz = z1.abs_diff(z2);
+
+  let diagonal_steps = dx.min(dy);
+  let straight_steps = dx.max(dy) - diagonal_steps;
+
+  let octile_distance = diagonal_steps * DIAGONAL_COST + straight_steps * ORTHOGONAL_COST;
+
+  octile_distance.max(dz * ORTHOGONAL_COST)
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+  use super::*;
+  use crate::net::{Asset, AssetData};
+
+  const STAIRS_TILESET: &str = r#"<tileset name="stairs" tilewidth="64" tileheight="32" tilecount="2" columns="2">
+ <tile id="1" class="Stairs"/>
+</tileset>"#;
+
+  // layers are lists of rows, objects are placed in tile space
+  pub(in crate::net) fn create_map(layers: &[&[&str]], objects: &str) -> Map {
+    let height = layers[0].len();
+    let width = layers[0][0].split(',').count();
+
+    let layer_elements: String = layers
+      .iter()
+      .enumerate()
+      .map(|(i, rows)| {
+        format!(
+          r#"<layer id="{}" name="Layer {}" width="{}" height="{}"><data encoding="csv">{}</data></layer>"#,
+          i + 1,
+          i,
+          width,
+          height,
+          rows.join(",\n")
+        )
+      })
+      .collect();
+
+    Map::from(&format!(
+      r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.8" orientation="isometric" width="{}" height="{}" tilewidth="64" tileheight="32" nextlayerid="10" nextobjectid="10">
+ <tileset firstgid="1" source="../assets/tiles/stairs.tsx"/>
+ {}
+ <objectgroup id="9" name="Objects">{}</objectgroup>
+</map>"#,
+      width, height, layer_elements, objects
+    ))
+  }
+
+  pub(in crate::net) fn create_asset_manager() -> AssetManager {
+    let mut asset_manager = AssetManager::new();
+
+    asset_manager.set_asset(
+      String::from("/server/assets/tiles/stairs.tsx"),
+      Asset {
+        data: AssetData::Text(String::from(STAIRS_TILESET)),
+        alternate_names: Vec::new(),
+        dependencies: Vec::new(),
+        last_modified: 0,
+        cachable: true,
+        cache_to_disk: true,
+      },
+    );
+
+    asset_manager
+  }
+
+  fn path(
+    map: &Map,
+    blocked_tiles: &HashSet<Tile>,
+    start: (f32, f32, f32),
+    destination: (f32, f32, f32),
+  ) -> Option<VecDeque<(f32, f32, f32)>> {
+    let asset_manager = create_asset_manager();
+    let mut tile_class_cache = TileClassCache::new();
+
+    find_path(
+      map,
+      &asset_manager,
+      &mut tile_class_cache,
+      blocked_tiles,
+      start,
+      destination,
+    )
+  }
+
+  fn visited_tiles(waypoints: &VecDeque<(f32, f32, f32)>) -> Vec<Tile> {
+    waypoints
+      .iter()
+      .map(|waypoint| to_tile(*waypoint))
+      .collect()
+  }
+
+  #[test]
+  fn straight_path() {
+    let map = create_map(&[&["1,1,1,1,1"]], "");
+
+    let waypoints = path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (4.5, 0.5, 0.0)).unwrap();
+
+    assert_eq!(
+      visited_tiles(&waypoints),
+      vec![(1, 0, 0), (2, 0, 0), (3, 0, 0), (4, 0, 0)]
+    );
+    assert_eq!(waypoints.back(), Some(&(4.5, 0.5, 0.0)));
+  }
+
+  #[test]
+  fn blocked_tiles() {
+    let map = create_map(&[&["1,1,1", "1,1,1", "1,1,1"]], "");
+    let blocked_tiles = HashSet::from([(1, 0, 0), (1, 1, 0)]);
+
+    let waypoints = path(&map, &blocked_tiles, (0.5, 0.5, 0.0), (2.5, 0.5, 0.0)).unwrap();
+    let tiles = visited_tiles(&waypoints);
+
+    assert!(tiles.iter().all(|tile| !blocked_tiles.contains(tile)));
+    assert!(tiles.contains(&(1, 2, 0)));
+
+    // the destination itself is blocked
+    let blocked_tiles = HashSet::from([(2, 0, 0)]);
+
+    assert!(path(&map, &blocked_tiles, (0.5, 0.5, 0.0), (2.5, 0.5, 0.0)).is_none());
+  }
+
+  #[test]
+  fn solid_objects() {
+    let map = create_map(
+      &[&["1,1,1", "1,1,1"]],
+      // covers tile (1, 0), object positions are in pixels scaled by the tile height
+      r#"<object id="1" x="32" y="0" width="32" height="32"><properties><property name="Solid" value="true"/></properties></object>
+<object id="2" x="32" y="32" width="32" height="32"/>"#,
+    );
+
+    let waypoints = path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (2.5, 0.5, 0.0)).unwrap();
+    let tiles = visited_tiles(&waypoints);
+
+    // non solid objects don't block, and corners next to the solid object can't be cut
+    assert_eq!(tiles, vec![(0, 1, 0), (1, 1, 0), (2, 1, 0), (2, 0, 0)]);
+  }
+
+  #[test]
+  fn stairs() {
+    let lower_layer: &[&str] = &["1,2,1"];
+    let upper_layer: &[&str] = &["0,0,1"];
+
+    let map = create_map(&[lower_layer, upper_layer], "");
+
+    let waypoints = path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (2.5, 0.5, 1.0)).unwrap();
+
+    assert_eq!(visited_tiles(&waypoints), vec![(1, 0, 0), (2, 0, 1)]);
+
+    let waypoints = path(&map, &HashSet::new(), (2.5, 0.5, 1.0), (0.5, 0.5, 0.0)).unwrap();
+
+    assert_eq!(visited_tiles(&waypoints), vec![(1, 0, 0), (0, 0, 0)]);
+
+    // without stairs the layers are disconnected
+    let map = create_map(&[&["1,1,1"], upper_layer], "");
+
+    assert!(path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (2.5, 0.5, 1.0)).is_none());
+  }
+
+  #[test]
+  fn no_path() {
+    let map = create_map(&[&["1,0,1", "1,0,1"]], "");
+
+    assert!(path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (2.5, 0.5, 0.0)).is_none());
+
+    // destinations on empty tiles or outside of the map
+    assert!(path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (1.5, 0.5, 0.0)).is_none());
+    assert!(path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (-1.5, 0.5, 0.0)).is_none());
+    assert!(path(&map, &HashSet::new(), (0.5, 0.5, 0.0), (0.5, 9.5, 0.0)).is_none());
+  }
+}
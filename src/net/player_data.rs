@@ -1,3 +1,7 @@
+use super::{FlagValue, ItemStack, QuestProgress};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone)]
 pub struct PlayerData {
   pub identity: String,
   pub element: String,
@@ -5,7 +9,15 @@ pub struct PlayerData {
   pub max_health: u32,
   pub emotion: u8,
   pub money: u32,
-  pub items: Vec<String>,
+  pub items: Vec<ItemStack>,
+  pub storage: HashMap<String, String>,
+  /// Identities of players whose chat messages are hidden from this player
+  pub muted: HashSet<String>,
+  /// Matchmaking ratings keyed by queue id
+  pub ratings: HashMap<String, f32>,
+  /// Keyed by quest id, quests without progress are not started
+  pub quests: HashMap<String, QuestProgress>,
+  pub flags: HashMap<String, FlagValue>,
 }
 
 impl PlayerData {
@@ -18,6 +30,11 @@ impl PlayerData {
       emotion: 0,
       money: 0,
       items: Vec::new(),
+      storage: HashMap::new(),
+      muted: HashSet::new(),
+      ratings: HashMap::new(),
+      quests: HashMap::new(),
+      flags: HashMap::new(),
     }
   }
 }
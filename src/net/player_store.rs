@@ -0,0 +1,432 @@
+use super::{FlagValue, ItemMetadata, ItemStack, PlayerData, QuestProgress, QuestState};
+use crate::helpers::name_to_file_name;
+use crate::jobs::{JobPromise, PromiseValue};
+use log::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_PLAYER_STORAGE_PATH: &str = "./storage/players";
+// seconds to wait for saves to complete as the server stops
+pub(super) const FLUSH_TIMEOUT: f32 = 10.0;
+
+pub type PlayerDataResolver = Box<dyn FnOnce(Option<PromiseValue>) -> Option<PlayerData>>;
+
+#[allow(clippy::large_enum_variant)]
+pub enum PlayerDataLoad {
+  Ready(Option<PlayerData>),
+  /// Resolved once the promise completes, the player joins after the data is resolved
+  Defer(JobPromise, PlayerDataResolver),
+}
+
+/// Called from the server thread, stores should defer slow work to jobs
+pub trait PlayerStore {
+  fn load(&mut self, identity: &str) -> PlayerDataLoad;
+  fn save(&mut self, player_data: &PlayerData);
+  /// Called once as the server stops, blocks until earlier saves are complete
+  fn flush(&mut self) {}
+}
+
+// stores each player in a separate file named after their percent encoded identity
+pub struct FilePlayerStore {
+  dir: PathBuf,
+  // saves that haven't reached the disk yet, loads check here first to avoid reading an older file
+  unwritten: Arc<Mutex<HashMap<String, String>>>,
+  // writes happen one at a time so an older save can't finish after a newer one
+  write_lock: Arc<async_std::sync::Mutex<()>>,
+}
+
+impl FilePlayerStore {
+  pub fn new(dir: impl Into<PathBuf>) -> FilePlayerStore {
+    FilePlayerStore {
+      dir: dir.into(),
+      unwritten: Arc::new(Mutex::new(HashMap::new())),
+      write_lock: Arc::new(async_std::sync::Mutex::new(())),
+    }
+  }
+
+  fn resolve_path(&self, identity: &str) -> PathBuf {
+    self.dir.join(name_to_file_name(identity))
+  }
+}
+
+impl PlayerStore for FilePlayerStore {
+  fn load(&mut self, identity: &str) -> PlayerDataLoad {
+    use crate::jobs::read_file::read_file;
+
+    if let Some(text) = self.unwritten.lock().unwrap().get(identity) {
+      return PlayerDataLoad::Ready(Some(parse_player_data(identity, text)));
+    }
+
+    let path = self.resolve_path(identity);
+    let identity = identity.to_string();
+
+    PlayerDataLoad::Defer(
+      read_file(path.to_string_lossy().into_owned()),
+      Box::new(move |value| match value {
+        // missing files resolve as empty, new players have nothing saved
+        Some(PromiseValue::Bytes(bytes)) if !bytes.is_empty() => Some(parse_player_data(
+          &identity,
+          &String::from_utf8_lossy(&bytes),
+        )),
+        _ => None,
+      }),
+    )
+  }
+
+  fn save(&mut self, player_data: &PlayerData) {
+    let identity = player_data.identity.clone();
+    let text = stringify_player_data(player_data);
+
+    self
+      .unwritten
+      .lock()
+      .unwrap()
+      .insert(identity.clone(), text);
+
+    let dir = self.dir.clone();
+    let path = self.resolve_path(&identity);
+    let unwritten = self.unwritten.clone();
+    let write_lock = self.write_lock.clone();
+
+    async_std::task::spawn(async move {
+      let _write_guard = write_lock.lock().await;
+
+      // a later save may have replaced the text while waiting, only the latest is written
+      let text = match unwritten.lock().unwrap().get(&identity) {
+        Some(text) => text.clone(),
+        None => return,
+      };
+
+      if let Err(err) = async_std::fs::create_dir_all(&dir).await {
+        warn!("Failed to create {:?}: {}", dir, err);
+        return;
+      }
+
+      if let Err(err) = async_std::fs::write(&path, &text).await {
+        warn!("Failed to save player data to {:?}: {}", path, err);
+        return;
+      }
+
+      let mut unwritten = unwritten.lock().unwrap();
+
+      if unwritten.get(&identity) == Some(&text) {
+        unwritten.remove(&identity);
+      }
+    });
+  }
+
+  fn flush(&mut self) {
+    let timeout = std::time::Duration::from_secs_f32(FLUSH_TIMEOUT);
+    let start = std::time::Instant::now();
+
+    // failed writes stay in the map, the timeout keeps them from blocking forever
+    while !self.unwritten.lock().unwrap().is_empty() {
+      if start.elapsed() > timeout {
+        warn!("Timed out waiting for player data to save");
+        break;
+      }
+
+      std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+  }
+}
+
+// one entry per line, fields separated by tabs
+fn stringify_player_data(player_data: &PlayerData) -> String {
+  let mut text = format!(
+    "element\t{}\nhealth\t{}\nmax_health\t{}\nemotion\t{}\nmoney\t{}\n",
+    escape(&player_data.element),
+    player_data.health,
+    player_data.max_health,
+    player_data.emotion,
+    player_data.money
+  );
+
+  // item\t[item_id]\t[quantity]\t[durability or empty]\t[data or empty]
+  for stack in &player_data.items {
+    let durability = stack
+      .metadata
+      .durability
+      .map(|durability| durability.to_string())
+      .unwrap_or_default();
+
+    text.push_str(&format!(
+      "item\t{}\t{}\t{}\t{}\n",
+      escape(&stack.item_id),
+      stack.quantity,
+      durability,
+      escape(stack.metadata.data.as_deref().unwrap_or_default())
+    ));
+  }
+
+  let mut keys: Vec<&String> = player_data.storage.keys().collect();
+  keys.sort();
+
+  for key in keys {
+    let value = &player_data.storage[key];
+    text.push_str(&format!("storage\t{}\t{}\n", escape(key), escape(value)));
+  }
+
+  let mut muted: Vec<&String> = player_data.muted.iter().collect();
+  muted.sort();
+
+  for identity in muted {
+    text.push_str(&format!("mute\t{}\n", escape(identity)));
+  }
+
+  let mut queue_ids: Vec<&String> = player_data.ratings.keys().collect();
+  queue_ids.sort();
+
+  for queue_id in queue_ids {
+    let rating = player_data.ratings[queue_id];
+    text.push_str(&format!("rating\t{}\t{}\n", escape(queue_id), rating));
+  }
+
+  // quest\t[quest_id]\t[state]\t[step]
+  let mut quest_ids: Vec<&String> = player_data.quests.keys().collect();
+  quest_ids.sort();
+
+  for quest_id in quest_ids {
+    let progress = &player_data.quests[quest_id];
+
+    text.push_str(&format!(
+      "quest\t{}\t{}\t{}\n",
+      escape(quest_id),
+      progress.state.as_str(),
+      progress.step
+    ));
+  }
+
+  // flag\t[name]\t[bool or int]\t[value]
+  let mut flag_names: Vec<&String> = player_data.flags.keys().collect();
+  flag_names.sort();
+
+  for name in flag_names {
+    let (flag_type, value) = match player_data.flags[name] {
+      FlagValue::Bool(value) => ("bool", value.to_string()),
+      FlagValue::Int(value) => ("int", value.to_string()),
+    };
+
+    text.push_str(&format!(
+      "flag\t{}\t{}\t{}\n",
+      escape(name),
+      flag_type,
+      value
+    ));
+  }
+
+  text
+}
+
+fn parse_player_data(identity: &str, text: &str) -> PlayerData {
+  use crate::helpers::unwrap_and_parse_or_default;
+
+  let mut player_data = PlayerData::new(identity.to_string());
+
+  for line in text.lines() {
+    let mut fields = line.split('\t');
+
+    match fields.next() {
+      Some("element") => player_data.element = unescape(fields.next().unwrap_or_default()),
+      Some("health") => player_data.health = unwrap_and_parse_or_default(fields.next()),
+      Some("max_health") => player_data.max_health = unwrap_and_parse_or_default(fields.next()),
+      Some("emotion") => player_data.emotion = unwrap_and_parse_or_default(fields.next()),
+      Some("money") => player_data.money = unwrap_and_parse_or_default(fields.next()),
+      Some("item") => {
+        // older saves only contain the item_id
+        if let Some(item_id) = fields.next() {
+          let quantity = fields.next().and_then(|quantity| quantity.parse().ok());
+          let durability = fields.next().and_then(|durability| durability.parse().ok());
+          let data = fields.next().map(unescape).filter(|data| !data.is_empty());
+
+          player_data.items.push(ItemStack {
+            item_id: unescape(item_id),
+            quantity: quantity.unwrap_or(1),
+            metadata: ItemMetadata { durability, data },
+          });
+        }
+      }
+      Some("storage") => {
+        if let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+          player_data.storage.insert(unescape(key), unescape(value));
+        }
+      }
+      Some("mute") => {
+        if let Some(identity) = fields.next() {
+          player_data.muted.insert(unescape(identity));
+        }
+      }
+      Some("rating") => {
+        let queue_id = fields.next();
+        let rating = fields.next().and_then(|rating| rating.parse().ok());
+
+        if let (Some(queue_id), Some(rating)) = (queue_id, rating) {
+          player_data.ratings.insert(unescape(queue_id), rating);
+        }
+      }
+      Some("quest") => {
+        let quest_id = fields.next();
+        let state = fields.next().and_then(QuestState::parse);
+        let step = fields.next().and_then(|step| step.parse().ok());
+
+        if let (Some(quest_id), Some(state)) = (quest_id, state) {
+          let step = step.unwrap_or_default();
+
+          player_data
+            .quests
+            .insert(unescape(quest_id), QuestProgress { state, step });
+        }
+      }
+      Some("flag") => {
+        let name = fields.next();
+
+        let value = match (fields.next(), fields.next()) {
+          (Some("bool"), Some(value)) => value.parse().ok().map(FlagValue::Bool),
+          (Some("int"), Some(value)) => value.parse().ok().map(FlagValue::Int),
+          _ => None,
+        };
+
+        if let (Some(name), Some(value)) = (name, value) {
+          player_data.flags.insert(unescape(name), value);
+        }
+      }
+      _ => {}
+    }
+  }
+
+  player_data
+}
+
+pub(super) fn escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+
+  for c in text.chars() {
+    match c {
+      '\\' => escaped.push_str("\\\\"),
+      '\t' => escaped.push_str("\\t"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      c => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+pub(super) fn unescape(text: &str) -> String {
+  let mut unescaped = String::with_capacity(text.len());
+  let mut chars = text.chars();
+
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      unescaped.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some('t') => unescaped.push('\t'),
+      Some('n') => unescaped.push('\n'),
+      Some('r') => unescaped.push('\r'),
+      Some(c) => unescaped.push(c),
+      None => {}
+    }
+  }
+
+  unescaped
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn escape_round_trip() {
+    let text = "tab\there\nnew line\r\\ slash";
+
+    assert_eq!(unescape(&escape(text)), text);
+    assert!(!escape(text).contains(['\t', '\n', '\r']));
+  }
+
+  #[test]
+  fn player_data_round_trip() {
+    let mut player_data = PlayerData::new(String::from("identity/+"));
+    player_data.element = String::from("aqua\telement");
+    player_data.health = 50;
+    player_data.max_health = 100;
+    player_data.emotion = 2;
+    player_data.money = 300;
+    player_data.items.push(ItemStack {
+      item_id: String::from("sword"),
+      quantity: 1,
+      metadata: ItemMetadata {
+        durability: Some(20),
+        data: Some(String::from("line\nbreak")),
+      },
+    });
+    player_data.items.push(ItemStack {
+      item_id: String::from("potion"),
+      quantity: 5,
+      metadata: ItemMetadata::default(),
+    });
+    player_data
+      .storage
+      .insert(String::from("key\t"), String::from("value\n"));
+    player_data.muted.insert(String::from("muted identity"));
+    player_data
+      .muted
+      .insert(String::from("muted\nmoney\t999999"));
+    player_data.ratings.insert(String::from("ranked"), 1532.5);
+    player_data.quests.insert(
+      String::from("delivery"),
+      QuestProgress {
+        state: QuestState::Active,
+        step: 1,
+      },
+    );
+    player_data
+      .flags
+      .insert(String::from("met_boss"), FlagValue::Bool(true));
+    player_data
+      .flags
+      .insert(String::from("coins"), FlagValue::Int(-4));
+
+    let text = stringify_player_data(&player_data);
+    let parsed = parse_player_data("identity/+", &text);
+
+    assert_eq!(parsed.identity, player_data.identity);
+    assert_eq!(parsed.element, player_data.element);
+    assert_eq!(parsed.health, player_data.health);
+    assert_eq!(parsed.max_health, player_data.max_health);
+    assert_eq!(parsed.emotion, player_data.emotion);
+    assert_eq!(parsed.money, player_data.money);
+    assert_eq!(parsed.items, player_data.items);
+    assert_eq!(parsed.storage, player_data.storage);
+    assert_eq!(parsed.muted, player_data.muted);
+    assert_eq!(parsed.ratings, player_data.ratings);
+    assert_eq!(parsed.quests, player_data.quests);
+    assert_eq!(parsed.flags, player_data.flags);
+  }
+
+  #[test]
+  fn old_item_lines() {
+    let parsed = parse_player_data("identity", "money\t10\nitem\tsword\nitem\tshield\n");
+
+    assert_eq!(parsed.money, 10);
+    assert_eq!(
+      parsed.items,
+      vec![
+        ItemStack {
+          item_id: String::from("sword"),
+          quantity: 1,
+          metadata: ItemMetadata::default(),
+        },
+        ItemStack {
+          item_id: String::from("shield"),
+          quantity: 1,
+          metadata: ItemMetadata::default(),
+        },
+      ]
+    );
+  }
+}
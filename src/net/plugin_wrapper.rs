@@ -1,4 +1,7 @@
-use super::{BattleStats, Net};
+use super::{
+  BattleStats, ChatChannel, CoopBattleResult, Encounter, FlagValue, Net, QuestState, ShopPurchase,
+  TradeOffer,
+};
 use crate::plugins::PluginInterface;
 
 pub(super) struct PluginWrapper {
@@ -22,7 +25,7 @@ impl PluginWrapper {
   {
     let mut call = call;
 
-    net.set_active_plugin(i);
+    net.set_active_plugin(Some(i));
     call(&mut self.plugin_interfaces[i], net);
   }
 
@@ -33,7 +36,7 @@ impl PluginWrapper {
     let mut call = call;
 
     for (i, plugin_interface) in self.plugin_interfaces.iter_mut().enumerate() {
-      net.set_active_plugin(i);
+      net.set_active_plugin(Some(i));
       call(plugin_interface, net);
     }
   }
@@ -44,6 +47,12 @@ impl PluginInterface for PluginWrapper {
     self.wrap_calls(net, |plugin_interface, net| plugin_interface.init(net));
   }
 
+  fn reload(&mut self, net: &mut Net, name: Option<&str>) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.reload(net, name)
+    });
+  }
+
   fn tick(&mut self, net: &mut Net, delta_time: f32) {
     self.wrap_calls(net, |plugin_interface, net| {
       plugin_interface.tick(net, delta_time)
@@ -99,6 +108,25 @@ impl PluginInterface for PluginWrapper {
     });
   }
 
+  fn handle_player_move_rejected(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    reason: &str,
+  ) -> bool {
+    let mut prevent_default = false;
+
+    self.wrap_calls(net, |plugin_interface, net| {
+      prevent_default |=
+        plugin_interface.handle_player_move_rejected(net, player_id, x, y, z, reason)
+    });
+
+    prevent_default
+  }
+
   fn handle_player_avatar_change(
     &mut self,
     net: &mut Net,
@@ -188,7 +216,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.pop_textbox() {
+    if let Some(Some(i)) = client.widget_tracker.pop_textbox() {
       self.wrap_call(i, net, |plugin_interface, net| {
         plugin_interface.handle_textbox_response(net, player_id, response)
       });
@@ -203,7 +231,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.pop_textbox() {
+    if let Some(Some(i)) = client.widget_tracker.pop_textbox() {
       self.wrap_call(i, net, |plugin_interface, net| {
         plugin_interface.handle_prompt_response(net, player_id, response.clone())
       });
@@ -220,7 +248,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.current_board() {
+    if let Some(Some(i)) = client.widget_tracker.current_board() {
       self.wrap_call(*i, net, |plugin_interface, net| {
         plugin_interface.handle_board_open(net, player_id)
       });
@@ -235,7 +263,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.close_board() {
+    if let Some(Some(i)) = client.widget_tracker.close_board() {
       self.wrap_call(i, net, |plugin_interface, net| {
         plugin_interface.handle_board_close(net, player_id)
       });
@@ -250,7 +278,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.current_board() {
+    if let Some(Some(i)) = client.widget_tracker.current_board() {
       self.wrap_call(*i, net, |plugin_interface, net| {
         plugin_interface.handle_post_request(net, player_id)
       });
@@ -265,7 +293,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.current_board() {
+    if let Some(Some(i)) = client.widget_tracker.current_board() {
       self.wrap_call(*i, net, |plugin_interface, net| {
         plugin_interface.handle_post_selection(net, player_id, post_id)
       });
@@ -280,7 +308,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.close_shop() {
+    if let Some(Some(i)) = client.widget_tracker.close_shop() {
       self.wrap_call(i, net, |plugin_interface, net| {
         plugin_interface.handle_shop_close(net, player_id);
       });
@@ -295,7 +323,7 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.widget_tracker.current_shop() {
+    if let Some(Some(i)) = client.widget_tracker.current_shop() {
       self.wrap_call(*i, net, |plugin_interface, net| {
         plugin_interface.handle_shop_purchase(net, player_id, item_name)
       });
@@ -310,10 +338,19 @@ impl PluginInterface for PluginWrapper {
     // expect the above to be correct
     // don't expect the client to be correct
     // otherwise someone can read the source and force a crash :p
-    if let Some(i) = client.battle_tracker.pop_front() {
-      self.wrap_call(i, net, |plugin_interface, net| {
-        plugin_interface.handle_battle_results(net, player_id, battle_stats)
-      });
+    match client.battle_tracker.pop_front() {
+      // battles started by the server are reported to every plugin
+      Some(None) => {
+        self.wrap_calls(net, |plugin_interface, net| {
+          plugin_interface.handle_battle_results(net, player_id, battle_stats)
+        });
+      }
+      Some(Some(i)) => {
+        self.wrap_call(i, net, |plugin_interface, net| {
+          plugin_interface.handle_battle_results(net, player_id, battle_stats)
+        });
+      }
+      None => {}
     }
   }
 
@@ -327,4 +364,243 @@ impl PluginInterface for PluginWrapper {
       plugin_interface.handle_server_message(net, socket_address, data)
     });
   }
+
+  fn handle_console_command(&mut self, net: &mut Net, command: &str, args: &[&str]) {
+    let owner = net
+      .get_console_commands()
+      .iter()
+      .find(|console_command| console_command.name == command)
+      .and_then(|console_command| console_command.owner);
+
+    if let Some(i) = owner {
+      self.wrap_call(i, net, |plugin_interface, net| {
+        plugin_interface.handle_console_command(net, command, args)
+      });
+    }
+  }
+
+  fn handle_bot_path_complete(&mut self, net: &mut Net, bot_id: &str) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_bot_path_complete(net, bot_id)
+    });
+  }
+
+  fn handle_object_enter(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_object_enter(net, player_id, area_id, object_id)
+    });
+  }
+
+  fn handle_object_exit(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_object_exit(net, player_id, area_id, object_id)
+    });
+  }
+
+  fn handle_actor_nearby(&mut self, net: &mut Net, actor_id: &str, nearby_actor_id: &str) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_actor_nearby(net, actor_id, nearby_actor_id)
+    });
+  }
+
+  fn handle_encounter(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    encounter: &mut Encounter,
+  ) -> bool {
+    let mut prevent_default = false;
+
+    self.wrap_calls(net, |plugin_interface, net| {
+      prevent_default |= plugin_interface.handle_encounter(net, player_id, encounter)
+    });
+
+    prevent_default
+  }
+
+  fn handle_trade_request(&mut self, net: &mut Net, player_id: &str, recipient_id: &str) -> bool {
+    let mut prevent_default = false;
+
+    self.wrap_calls(net, |plugin_interface, net| {
+      prevent_default |= plugin_interface.handle_trade_request(net, player_id, recipient_id)
+    });
+
+    prevent_default
+  }
+
+  fn handle_trade_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    player_offer: &TradeOffer,
+    recipient_offer: &TradeOffer,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_trade_complete(
+        net,
+        player_id,
+        recipient_id,
+        player_offer,
+        recipient_offer,
+      )
+    });
+  }
+
+  fn handle_trade_cancelled(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    reason: &str,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_trade_cancelled(net, player_id, recipient_id, reason)
+    });
+  }
+
+  fn handle_shop_purchase_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    purchase: &ShopPurchase,
+  ) {
+    let client = net
+      .get_client_mut(player_id)
+      .expect("An internal author should understand how to handle this better");
+
+    if let Some(Some(i)) = client.widget_tracker.current_shop() {
+      self.wrap_call(*i, net, |plugin_interface, net| {
+        plugin_interface.handle_shop_purchase_complete(net, player_id, purchase)
+      });
+    }
+  }
+
+  fn handle_chat_message(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    channel: ChatChannel,
+    target_id: Option<&str>,
+    message: &str,
+  ) -> bool {
+    let mut prevent_default = false;
+
+    self.wrap_calls(net, |plugin_interface, net| {
+      prevent_default |=
+        plugin_interface.handle_chat_message(net, player_id, channel, target_id, message)
+    });
+
+    prevent_default
+  }
+
+  fn handle_party_invite(
+    &mut self,
+    net: &mut Net,
+    party_id: &str,
+    player_id: &str,
+    recipient_id: &str,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_party_invite(net, party_id, player_id, recipient_id)
+    });
+  }
+
+  fn handle_party_join(&mut self, net: &mut Net, party_id: &str, player_id: &str) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_party_join(net, party_id, player_id)
+    });
+  }
+
+  fn handle_party_leave(&mut self, net: &mut Net, party_id: &str, player_id: &str, reason: &str) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_party_leave(net, party_id, player_id, reason)
+    });
+  }
+
+  fn handle_party_leader_change(&mut self, net: &mut Net, party_id: &str, player_id: &str) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_party_leader_change(net, party_id, player_id)
+    });
+  }
+
+  fn handle_battle_results_group(
+    &mut self,
+    net: &mut Net,
+    group_id: &str,
+    package_path: &str,
+    results: &[CoopBattleResult],
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_battle_results_group(net, group_id, package_path, results)
+    });
+  }
+
+  fn handle_battle_spectator_join(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_battle_spectator_join(net, spectator_id, player_1_id, player_2_id)
+    });
+  }
+
+  fn handle_battle_spectator_leave(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+    reason: &str,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_battle_spectator_leave(
+        net,
+        spectator_id,
+        player_1_id,
+        player_2_id,
+        reason,
+      )
+    });
+  }
+
+  fn handle_matchmaking_match(
+    &mut self,
+    net: &mut Net,
+    queue_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_matchmaking_match(net, queue_id, player_1_id, player_2_id)
+    });
+  }
+
+  fn handle_quest_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    quest_id: &str,
+    state: QuestState,
+    step_id: Option<&str>,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_quest_change(net, player_id, quest_id, state, step_id)
+    });
+  }
+
+  fn handle_flag_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    name: &str,
+    value: Option<FlagValue>,
+  ) {
+    self.wrap_calls(net, |plugin_interface, net| {
+      plugin_interface.handle_flag_change(net, player_id, name, value)
+    });
+  }
 }
@@ -0,0 +1,153 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SpectateError {
+  NotInPvp,
+  PlayerBusy,
+}
+
+impl std::fmt::Display for SpectateError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let message = match self {
+      SpectateError::NotInPvp => "player is not in a pvp battle",
+      SpectateError::PlayerBusy => "spectator is busy",
+    };
+
+    write!(f, "{}", message)
+  }
+}
+
+use std::time::Instant;
+
+/// A running PvP session, ends once both players send results, either disconnects, or the battle times out
+pub(super) struct PvpBattle {
+  pub player_ids: [String; 2],
+  pub spectators: Vec<String>,
+  /// Set for battles started by matchmaking, ratings are updated from the results
+  pub queue_id: Option<String>,
+  /// Indexed like player_ids, 1.0 for a win and 0.0 for a loss
+  pub scores: [Option<f32>; 2],
+  pub started_at: Instant,
+}
+
+impl PvpBattle {
+  pub fn new(player_1_id: String, player_2_id: String, queue_id: Option<String>) -> PvpBattle {
+    PvpBattle {
+      player_ids: [player_1_id, player_2_id],
+      spectators: Vec::new(),
+      queue_id,
+      scores: [None; 2],
+      started_at: Instant::now(),
+    }
+  }
+
+  pub fn is_timed_out(&self, timeout: f32) -> bool {
+    self.started_at.elapsed().as_secs_f32() >= timeout
+  }
+
+  /// Players who haven't reported lose to a player who reported, like disconnecting.
+  /// If neither player reported there's nothing to agree on and the battle is voided
+  pub fn settle_missing_scores(&mut self) {
+    match self.scores {
+      [Some(score), None] => self.scores[1] = Some(1.0 - score),
+      [None, Some(score)] => self.scores[0] = Some(1.0 - score),
+      _ => {}
+    }
+  }
+
+  pub fn side(&self, player_id: &str) -> Option<usize> {
+    self.player_ids.iter().position(|id| id == player_id)
+  }
+
+  /// True while the player is still battling, players who sent results are done
+  pub fn has_player(&self, player_id: &str) -> bool {
+    self
+      .side(player_id)
+      .map(|side| self.scores[side].is_none())
+      .unwrap_or_default()
+  }
+
+  /// The first player's score once both players agree on who won, None while waiting or if the results conflict
+  pub fn agreed_score(&self) -> Option<f32> {
+    match self.scores {
+      [Some(score), Some(other_score)] if score + other_score == 1.0 => Some(score),
+      _ => None,
+    }
+  }
+}
+
+/// Players starting or stopping spectating a PvP battle
+pub(super) enum SpectatorEvent {
+  Join {
+    spectator_id: String,
+    player_ids: [String; 2],
+  },
+  Leave {
+    spectator_id: String,
+    player_ids: [String; 2],
+    reason: &'static str,
+  },
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn agreed_score() {
+    let mut pvp_battle = PvpBattle::new(String::from("a"), String::from("b"), None);
+
+    assert!(pvp_battle.has_player("a"));
+    assert!(!pvp_battle.has_player("c"));
+    assert_eq!(pvp_battle.agreed_score(), None);
+
+    pvp_battle.scores[0] = Some(1.0);
+
+    assert!(!pvp_battle.has_player("a"));
+    assert!(pvp_battle.has_player("b"));
+    assert_eq!(pvp_battle.agreed_score(), None);
+
+    pvp_battle.scores[1] = Some(0.0);
+    assert_eq!(pvp_battle.agreed_score(), Some(1.0));
+
+    pvp_battle.scores = [Some(0.0), Some(1.0)];
+    assert_eq!(pvp_battle.agreed_score(), Some(0.0));
+
+    // both claiming the win or the loss is disputed
+    pvp_battle.scores = [Some(1.0), Some(1.0)];
+    assert_eq!(pvp_battle.agreed_score(), None);
+
+    pvp_battle.scores = [Some(0.0), Some(0.0)];
+    assert_eq!(pvp_battle.agreed_score(), None);
+  }
+
+  #[test]
+  fn timeout() {
+    use std::time::Duration;
+
+    let mut pvp_battle = PvpBattle::new(String::from("a"), String::from("b"), None);
+
+    assert!(!pvp_battle.is_timed_out(60.0));
+
+    pvp_battle.started_at = Instant::now() - Duration::from_secs(61);
+    assert!(pvp_battle.is_timed_out(60.0));
+
+    // neither player reported, voided
+    pvp_battle.settle_missing_scores();
+    assert_eq!(pvp_battle.scores, [None, None]);
+    assert_eq!(pvp_battle.agreed_score(), None);
+
+    // the silent player loses to the reported win
+    pvp_battle.scores = [None, Some(1.0)];
+    pvp_battle.settle_missing_scores();
+    assert_eq!(pvp_battle.agreed_score(), Some(0.0));
+
+    // a reported loss is a win for the silent player
+    pvp_battle.scores = [Some(0.0), None];
+    pvp_battle.settle_missing_scores();
+    assert_eq!(pvp_battle.agreed_score(), Some(0.0));
+
+    // disputes stay disputed
+    pvp_battle.scores = [Some(1.0), Some(1.0)];
+    pvp_battle.settle_missing_scores();
+    assert_eq!(pvp_battle.agreed_score(), None);
+  }
+}
@@ -0,0 +1,355 @@
+use super::player_store::unescape;
+use super::PlayerData;
+use log::*;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuestError {
+  UnknownQuest,
+  AlreadyStarted,
+  NotActive,
+  UnknownStep,
+}
+
+impl std::fmt::Display for QuestError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let message = match self {
+      QuestError::UnknownQuest => "no matching quest",
+      QuestError::AlreadyStarted => "quest was already started",
+      QuestError::NotActive => "quest is not active",
+      QuestError::UnknownStep => "no matching step",
+    };
+
+    write!(f, "{}", message)
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuestState {
+  NotStarted,
+  Active,
+  Completed,
+  Failed,
+}
+
+impl QuestState {
+  pub fn as_str(self) -> &'static str {
+    match self {
+      QuestState::NotStarted => "not_started",
+      QuestState::Active => "active",
+      QuestState::Completed => "completed",
+      QuestState::Failed => "failed",
+    }
+  }
+
+  pub fn parse(text: &str) -> Option<QuestState> {
+    match text {
+      "not_started" => Some(QuestState::NotStarted),
+      "active" => Some(QuestState::Active),
+      "completed" => Some(QuestState::Completed),
+      "failed" => Some(QuestState::Failed),
+      _ => None,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct QuestStep {
+  pub id: String,
+  pub description: String,
+}
+
+/// Loaded from ./quests (--quests-path) when the server starts, the quest id is the file name without the extension
+#[derive(Clone, Debug)]
+pub struct Quest {
+  pub id: String,
+  pub name: String,
+  pub description: String,
+  /// In order, the quest completes after the last step
+  pub steps: Vec<QuestStep>,
+}
+
+/// A player's progress on a quest, saved with player data
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct QuestProgress {
+  pub state: QuestState,
+  /// Index into the quest's steps, only meaningful while active
+  pub step: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FlagValue {
+  Bool(bool),
+  Int(i64),
+}
+
+/// Compared against player flags and quests to decide if objects are visible or interactable
+#[derive(Clone, Debug)]
+pub enum QuestCondition {
+  /// Unset flags are false
+  Flag {
+    name: String,
+    value: bool,
+  },
+  /// Unset flags are 0
+  FlagAtLeast {
+    name: String,
+    value: i64,
+  },
+  Quest {
+    quest_id: String,
+    state: QuestState,
+  },
+  /// The quest is active and on this step
+  QuestStep {
+    quest_id: String,
+    step: usize,
+  },
+}
+
+impl QuestCondition {
+  pub fn is_met(&self, player_data: &PlayerData) -> bool {
+    match self {
+      QuestCondition::Flag { name, value } => {
+        let flag = match player_data.flags.get(name) {
+          Some(FlagValue::Bool(flag)) => *flag,
+          Some(FlagValue::Int(flag)) => *flag != 0,
+          None => false,
+        };
+
+        flag == *value
+      }
+      QuestCondition::FlagAtLeast { name, value } => {
+        let flag = match player_data.flags.get(name) {
+          Some(FlagValue::Bool(flag)) => *flag as i64,
+          Some(FlagValue::Int(flag)) => *flag,
+          None => 0,
+        };
+
+        flag >= *value
+      }
+      QuestCondition::Quest { quest_id, state } => {
+        get_quest_progress(player_data, quest_id).state == *state
+      }
+      QuestCondition::QuestStep { quest_id, step } => {
+        let progress = get_quest_progress(player_data, quest_id);
+
+        progress.state == QuestState::Active && progress.step == *step
+      }
+    }
+  }
+}
+
+/// Conditions attached to an object, every condition must be met
+#[derive(Default)]
+pub(super) struct GatedObject {
+  pub visible_if: Vec<QuestCondition>,
+  pub interactable_if: Vec<QuestCondition>,
+}
+
+pub(super) fn get_quest_progress(player_data: &PlayerData, quest_id: &str) -> QuestProgress {
+  player_data
+    .quests
+    .get(quest_id)
+    .copied()
+    .unwrap_or(QuestProgress {
+      state: QuestState::NotStarted,
+      step: 0,
+    })
+}
+
+pub(super) fn load_quests(dir: &str) -> HashMap<String, Quest> {
+  let mut quests = HashMap::new();
+
+  let dir_entries = match std::fs::read_dir(dir) {
+    Ok(dir_entries) => dir_entries,
+    // quests are optional
+    Err(_) => return quests,
+  };
+
+  for dir_entry in dir_entries.flatten() {
+    let path = dir_entry.path();
+
+    let id = path
+      .file_stem()
+      .unwrap_or_default()
+      .to_string_lossy()
+      .into_owned();
+
+    match std::fs::read_to_string(&path) {
+      Ok(text) => {
+        quests.insert(id.clone(), parse_quest(id, &text));
+      }
+      Err(err) => warn!("Failed to read quest {:?}: {}", path, err),
+    }
+  }
+
+  quests
+}
+
+// one entry per line, fields separated by tabs, lines starting with # are ignored
+// name\t[name]
+// description\t[description]
+// step\t[step_id]\t[description]
+fn parse_quest(id: String, text: &str) -> Quest {
+  let mut quest = Quest {
+    id,
+    name: String::new(),
+    description: String::new(),
+    steps: Vec::new(),
+  };
+
+  for line in text.lines() {
+    if line.starts_with('#') {
+      continue;
+    }
+
+    let mut fields = line.split('\t');
+
+    match fields.next() {
+      Some("name") => quest.name = unescape(fields.next().unwrap_or_default()),
+      Some("description") => quest.description = unescape(fields.next().unwrap_or_default()),
+      Some("step") => {
+        if let Some(step_id) = fields.next() {
+          quest.steps.push(QuestStep {
+            id: unescape(step_id),
+            description: unescape(fields.next().unwrap_or_default()),
+          });
+        }
+      }
+      _ => {}
+    }
+  }
+
+  quest
+}
+
+/// Progress and flag changes for a player
+pub(super) enum QuestEvent {
+  QuestChange {
+    player_id: String,
+    quest_id: String,
+    progress: QuestProgress,
+  },
+  FlagChange {
+    player_id: String,
+    name: String,
+    value: Option<FlagValue>,
+  },
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn flag(name: &str, value: bool) -> QuestCondition {
+    QuestCondition::Flag {
+      name: name.to_string(),
+      value,
+    }
+  }
+
+  fn flag_at_least(name: &str, value: i64) -> QuestCondition {
+    QuestCondition::FlagAtLeast {
+      name: name.to_string(),
+      value,
+    }
+  }
+
+  #[test]
+  fn unset_flags() {
+    let player_data = PlayerData::new(String::from("identity"));
+
+    assert!(flag("door", false).is_met(&player_data));
+    assert!(!flag("door", true).is_met(&player_data));
+    assert!(flag_at_least("coins", 0).is_met(&player_data));
+    assert!(!flag_at_least("coins", 1).is_met(&player_data));
+  }
+
+  #[test]
+  fn bool_and_int_flags() {
+    let mut player_data = PlayerData::new(String::from("identity"));
+    player_data
+      .flags
+      .insert(String::from("door"), FlagValue::Bool(true));
+    player_data
+      .flags
+      .insert(String::from("coins"), FlagValue::Int(3));
+    player_data
+      .flags
+      .insert(String::from("empty"), FlagValue::Int(0));
+
+    assert!(flag("door", true).is_met(&player_data));
+    assert!(flag_at_least("door", 1).is_met(&player_data));
+    assert!(!flag_at_least("door", 2).is_met(&player_data));
+
+    // non zero ints are true
+    assert!(flag("coins", true).is_met(&player_data));
+    assert!(flag("empty", false).is_met(&player_data));
+    assert!(flag_at_least("coins", 3).is_met(&player_data));
+    assert!(!flag_at_least("coins", 4).is_met(&player_data));
+    assert!(flag_at_least("coins", -1).is_met(&player_data));
+  }
+
+  #[test]
+  fn quest_conditions() {
+    let mut player_data = PlayerData::new(String::from("identity"));
+
+    let step = |step| QuestCondition::QuestStep {
+      quest_id: String::from("quest"),
+      step,
+    };
+    let state = |state| QuestCondition::Quest {
+      quest_id: String::from("quest"),
+      state,
+    };
+
+    assert!(state(QuestState::NotStarted).is_met(&player_data));
+    assert!(!step(0).is_met(&player_data));
+
+    player_data.quests.insert(
+      String::from("quest"),
+      QuestProgress {
+        state: QuestState::Active,
+        step: 1,
+      },
+    );
+
+    assert!(state(QuestState::Active).is_met(&player_data));
+    assert!(step(1).is_met(&player_data));
+    assert!(!step(0).is_met(&player_data));
+
+    // steps only count while the quest is active
+    player_data.quests.insert(
+      String::from("quest"),
+      QuestProgress {
+        state: QuestState::Completed,
+        step: 1,
+      },
+    );
+
+    assert!(state(QuestState::Completed).is_met(&player_data));
+    assert!(!step(1).is_met(&player_data));
+  }
+
+  #[test]
+  fn quest_files() {
+    let quest = parse_quest(
+      String::from("delivery"),
+      "# comment\tignored\nname\tThe \\tDelivery\ndescription\tBring the package\\nto town\nstep\tpickup\tGet the package\nstep\tdrop_off\nunknown\tfield\nstep\n",
+    );
+
+    assert_eq!(quest.id, "delivery");
+    assert_eq!(quest.name, "The \tDelivery");
+    assert_eq!(quest.description, "Bring the package\nto town");
+
+    let steps: Vec<(&str, &str)> = quest
+      .steps
+      .iter()
+      .map(|step| (step.id.as_str(), step.description.as_str()))
+      .collect();
+
+    // steps without an id are skipped, descriptions are optional
+    assert_eq!(steps, vec![("pickup", "Get the package"), ("drop_off", "")]);
+  }
+}
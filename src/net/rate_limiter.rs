@@ -0,0 +1,356 @@
+use crate::packets::ClientPacket;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+// entries for addresses that have gone silent for this long are dropped, bans are kept until expiry
+const STALE_DURATION: f32 = 60.0;
+/// Longest ban in seconds, a year keeps expirations representable on every platform
+pub const MAX_RATE_LIMIT_BAN_DURATION: f32 = 31_536_000.0;
+
+#[derive(Clone)]
+pub struct RateLimits {
+  pub packets_per_second: f32,
+  pub bytes_per_second: f32,
+  pub emotes_per_second: f32,
+  pub chat_messages_per_second: f32,
+  pub asset_streams_per_second: f32,
+  pub positions_per_second: f32,
+  /// Dropped packets within a second before the address is banned
+  pub violations_before_ban: u32,
+  /// Ban duration in seconds
+  pub ban_duration: f32,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct PacketCounters {
+  pub packets: u64,
+  pub bytes: u64,
+  pub dropped_packets: u64,
+}
+
+pub enum RateLimitResult {
+  Allow,
+  Drop,
+  Ban,
+}
+
+struct TokenBucket {
+  tokens: f32,
+  rate: f32,
+}
+
+impl TokenBucket {
+  fn new(rate: f32) -> TokenBucket {
+    TokenBucket { tokens: rate, rate }
+  }
+
+  fn refill(&mut self, elapsed: f32) {
+    // a second of budget is allowed to build up for bursts
+    self.tokens = (self.tokens + self.rate * elapsed).min(self.rate);
+  }
+
+  fn try_take(&mut self, amount: f32) -> bool {
+    if self.tokens < amount {
+      return false;
+    }
+
+    self.tokens -= amount;
+    true
+  }
+}
+
+struct AddressLimits {
+  packets: TokenBucket,
+  bytes: TokenBucket,
+  emotes: TokenBucket,
+  chat_messages: TokenBucket,
+  asset_streams: TokenBucket,
+  positions: TokenBucket,
+  counters: PacketCounters,
+  violations: u32,
+  violation_window_start: Instant,
+  last_update: Instant,
+}
+
+impl AddressLimits {
+  fn new(limits: &RateLimits) -> AddressLimits {
+    AddressLimits {
+      packets: TokenBucket::new(limits.packets_per_second),
+      bytes: TokenBucket::new(limits.bytes_per_second),
+      emotes: TokenBucket::new(limits.emotes_per_second),
+      chat_messages: TokenBucket::new(limits.chat_messages_per_second),
+      asset_streams: TokenBucket::new(limits.asset_streams_per_second),
+      positions: TokenBucket::new(limits.positions_per_second),
+      counters: PacketCounters::default(),
+      violations: 0,
+      violation_window_start: Instant::now(),
+      last_update: Instant::now(),
+    }
+  }
+
+  fn refill(&mut self) {
+    let elapsed = self.last_update.elapsed().as_secs_f32();
+    self.last_update = Instant::now();
+
+    self.packets.refill(elapsed);
+    self.bytes.refill(elapsed);
+    self.emotes.refill(elapsed);
+    self.chat_messages.refill(elapsed);
+    self.asset_streams.refill(elapsed);
+    self.positions.refill(elapsed);
+  }
+}
+
+pub struct RateLimiter {
+  limits: RateLimits,
+  addresses: HashMap<SocketAddr, AddressLimits>,
+  bans: HashMap<SocketAddr, Instant>,
+  last_cleanup: Instant,
+}
+
+impl RateLimiter {
+  pub fn new(limits: RateLimits) -> RateLimiter {
+    RateLimiter {
+      limits,
+      addresses: HashMap::new(),
+      bans: HashMap::new(),
+      last_cleanup: Instant::now(),
+    }
+  }
+
+  pub fn is_banned(&mut self, socket_address: &SocketAddr) -> bool {
+    match self.bans.get(socket_address) {
+      Some(expiration) if *expiration > Instant::now() => true,
+      Some(_) => {
+        self.bans.remove(socket_address);
+        false
+      }
+      None => false,
+    }
+  }
+
+  /// Tracks a received packet, packet is None if it failed to parse
+  pub fn check(
+    &mut self,
+    socket_address: SocketAddr,
+    size: usize,
+    packet: Option<&ClientPacket>,
+  ) -> RateLimitResult {
+    if self.last_cleanup.elapsed().as_secs_f32() > STALE_DURATION {
+      self.remove_stale();
+    }
+
+    let limits = &self.limits;
+
+    let address_limits = self
+      .addresses
+      .entry(socket_address)
+      .or_insert_with(|| AddressLimits::new(limits));
+
+    address_limits.refill();
+    address_limits.counters.packets += 1;
+    address_limits.counters.bytes += size as u64;
+
+    let packet_budget = match packet {
+      Some(ClientPacket::Emote { .. }) => Some(&mut address_limits.emotes),
+      Some(ClientPacket::ChatMessage { .. }) => Some(&mut address_limits.chat_messages),
+      Some(ClientPacket::AssetStream { .. }) => Some(&mut address_limits.asset_streams),
+      Some(ClientPacket::Position { .. }) => Some(&mut address_limits.positions),
+      _ => None,
+    };
+
+    let within_budget = packet_budget
+      .map(|bucket| bucket.try_take(1.0))
+      .unwrap_or(true)
+      && address_limits.packets.try_take(1.0)
+      && address_limits.bytes.try_take(size as f32);
+
+    if within_budget {
+      return RateLimitResult::Allow;
+    }
+
+    address_limits.counters.dropped_packets += 1;
+
+    if address_limits
+      .violation_window_start
+      .elapsed()
+      .as_secs_f32()
+      > 1.0
+    {
+      address_limits.violation_window_start = Instant::now();
+      address_limits.violations = 0;
+    }
+
+    address_limits.violations += 1;
+
+    if address_limits.violations <= limits.violations_before_ban {
+      return RateLimitResult::Drop;
+    }
+
+    let ban_duration = limits.ban_duration.min(MAX_RATE_LIMIT_BAN_DURATION);
+    let expiration =
+      Instant::now() + std::time::Duration::try_from_secs_f32(ban_duration).unwrap_or_default();
+
+    self.bans.insert(socket_address, expiration);
+    self.addresses.remove(&socket_address);
+
+    RateLimitResult::Ban
+  }
+
+  pub fn get_counters(&self, socket_address: &SocketAddr) -> Option<PacketCounters> {
+    self
+      .addresses
+      .get(socket_address)
+      .map(|address_limits| address_limits.counters)
+  }
+
+  fn remove_stale(&mut self) {
+    let now = Instant::now();
+
+    self.addresses.retain(|_, address_limits| {
+      (now - address_limits.last_update).as_secs_f32() < STALE_DURATION
+    });
+
+    self.bans.retain(|_, expiration| *expiration > now);
+
+    self.last_cleanup = now;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn create_limits() -> RateLimits {
+    RateLimits {
+      packets_per_second: 10.0,
+      bytes_per_second: 1000.0,
+      emotes_per_second: 2.0,
+      chat_messages_per_second: 2.0,
+      asset_streams_per_second: 10.0,
+      positions_per_second: 10.0,
+      violations_before_ban: 3,
+      ban_duration: 60.0,
+    }
+  }
+
+  #[test]
+  fn token_bucket() {
+    let mut bucket = TokenBucket::new(2.0);
+
+    assert!(bucket.try_take(1.0));
+    assert!(bucket.try_take(1.0));
+    assert!(!bucket.try_take(1.0), "bucket should be empty");
+
+    bucket.refill(0.5);
+    assert!(
+      bucket.try_take(1.0),
+      "half a second should refill one token"
+    );
+    assert!(!bucket.try_take(1.0));
+
+    bucket.refill(10.0);
+    assert!(bucket.try_take(2.0));
+    assert!(
+      !bucket.try_take(1.0),
+      "refills should be capped at a second"
+    );
+  }
+
+  #[test]
+  fn packet_kind_budgets() {
+    let mut rate_limiter = RateLimiter::new(create_limits());
+    let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+    let emote = ClientPacket::Emote { emote_id: 0 };
+
+    assert!(matches!(
+      rate_limiter.check(addr, 10, Some(&emote)),
+      RateLimitResult::Allow
+    ));
+    assert!(matches!(
+      rate_limiter.check(addr, 10, Some(&emote)),
+      RateLimitResult::Allow
+    ));
+    assert!(
+      matches!(
+        rate_limiter.check(addr, 10, Some(&emote)),
+        RateLimitResult::Drop
+      ),
+      "emotes should be limited separately"
+    );
+    assert!(
+      matches!(
+        rate_limiter.check(addr, 10, Some(&ClientPacket::Heartbeat)),
+        RateLimitResult::Allow
+      ),
+      "other packets should still be allowed"
+    );
+
+    let counters = rate_limiter.get_counters(&addr).unwrap();
+    assert_eq!(counters.packets, 4);
+    assert_eq!(counters.bytes, 40);
+    assert_eq!(counters.dropped_packets, 1);
+  }
+
+  #[test]
+  fn byte_budget() {
+    let mut rate_limiter = RateLimiter::new(create_limits());
+    let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+    assert!(matches!(
+      rate_limiter.check(addr, 1000, None),
+      RateLimitResult::Allow
+    ));
+    assert!(matches!(
+      rate_limiter.check(addr, 1, None),
+      RateLimitResult::Drop
+    ));
+  }
+
+  #[test]
+  fn bans_after_violations() {
+    let mut rate_limiter = RateLimiter::new(create_limits());
+    let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+    let other_addr: SocketAddr = "127.0.0.1:3001".parse().unwrap();
+
+    for _ in 0..10 {
+      rate_limiter.check(addr, 1, None);
+    }
+
+    for _ in 0..3 {
+      assert!(matches!(
+        rate_limiter.check(addr, 1, None),
+        RateLimitResult::Drop
+      ));
+    }
+
+    assert!(matches!(
+      rate_limiter.check(addr, 1, None),
+      RateLimitResult::Ban
+    ));
+    assert!(rate_limiter.is_banned(&addr));
+    assert!(!rate_limiter.is_banned(&other_addr));
+  }
+
+  #[test]
+  fn ban_duration_limits() {
+    let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
+
+    for ban_duration in [f32::INFINITY, f32::MAX, f32::NAN, -1.0] {
+      let mut rate_limiter = RateLimiter::new(RateLimits {
+        ban_duration,
+        ..create_limits()
+      });
+
+      for _ in 0..13 {
+        rate_limiter.check(addr, 1, None);
+      }
+
+      assert!(matches!(
+        rate_limiter.check(addr, 1, None),
+        RateLimitResult::Ban
+      ));
+    }
+  }
+}
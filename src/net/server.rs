@@ -1,17 +1,35 @@
+use super::authenticator::{Authentication, AuthenticationResolver, Authenticator};
 use super::boot::Boot;
+use super::chat::MAX_CHAT_MESSAGE_LENGTH;
+use super::matchmaking::MatchmakingEvent;
+use super::movement_validator::MovementValidator;
+use super::net_event::NetEvent;
+use super::parties::PartyEvent;
 use super::plugin_wrapper::PluginWrapper;
-use super::Net;
+use super::pvp_battles::SpectatorEvent;
+use super::quests::QuestEvent;
+use super::trades::TradeEvent;
+use super::triggers::TriggerEvent;
+use super::{
+  ChatChannel, ChatFilter, FilePlayerStore, Net, PlayerData, PlayerDataLoad, PlayerDataResolver,
+  PlayerStore, QuestState, RateLimiter, RateLimits, DEFAULT_PLAYER_STORAGE_PATH,
+};
+use crate::jobs::JobPromise;
 use crate::packets::{
   build_unreliable_packet, ClientPacket, PacketOrchestrator, PacketSorter, Reliability,
   ServerPacket,
 };
 use crate::plugins::PluginInterface;
-use crate::threads::{create_clock_thread, create_listening_thread, ThreadMessage};
+use crate::threads::{
+  create_clock_thread, create_console_thread, create_listening_thread, create_shutdown_thread,
+  ThreadMessage,
+};
 use log::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::net::UdpSocket;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct ServerConfig {
@@ -25,15 +43,51 @@ pub struct ServerConfig {
   pub player_asset_limit: usize,
   pub avatar_dimensions_limit: u32,
   pub custom_emotes_path: Option<String>,
+  pub max_movement_speed: Option<f32>,
   pub max_idle_packet_duration: f32,
   pub max_silence_duration: f32,
   pub heartbeat_rate: f32,
+  pub rate_limits: RateLimits,
+  pub player_save_rate: f32,
+  pub ban_list_path: String,
+  pub watch_assets: bool,
+  pub trigger_class: String,
+  pub battle_history_path: String,
+  pub leaderboards_path: String,
+  pub coop_battle_timeout: f32,
+  pub pvp_battle_timeout: f32,
+  pub quests_path: String,
+}
+
+struct PendingLogin {
+  socket_address: std::net::SocketAddr,
+  username: String,
+  identity: String,
+  data: String,
+  promise: JobPromise,
+  resolver: AuthenticationResolver,
+}
+
+// accepted logins waiting on saved player data
+struct PendingPlayerData {
+  socket_address: std::net::SocketAddr,
+  username: String,
+  identity: String,
+  data: String,
+  promise: JobPromise,
+  resolver: PlayerDataResolver,
 }
 
 pub struct Server {
   player_id_map: HashMap<std::net::SocketAddr, String>,
   packet_sorter_map: HashMap<std::net::SocketAddr, PacketSorter>,
   plugin_wrapper: PluginWrapper,
+  player_store: Option<Box<dyn PlayerStore>>,
+  movement_validator: Option<MovementValidator>,
+  authenticator: Option<Box<dyn Authenticator>>,
+  chat_filter: Option<Box<dyn ChatFilter>>,
+  pending_logins: Vec<PendingLogin>,
+  pending_player_data: Vec<PendingPlayerData>,
   config: Rc<ServerConfig>,
 }
 
@@ -43,6 +97,12 @@ impl Server {
       player_id_map: HashMap::new(),
       packet_sorter_map: HashMap::new(),
       plugin_wrapper: PluginWrapper::new(),
+      player_store: None,
+      movement_validator: config.max_movement_speed.map(MovementValidator::new),
+      authenticator: None,
+      chat_filter: None,
+      pending_logins: Vec::new(),
+      pending_player_data: Vec::new(),
       config: Rc::new(config),
     }
   }
@@ -51,6 +111,18 @@ impl Server {
     self.plugin_wrapper.add_plugin_interface(plugin_interface);
   }
 
+  pub fn set_authenticator(&mut self, authenticator: Box<dyn Authenticator>) {
+    self.authenticator = Some(authenticator);
+  }
+
+  pub fn set_chat_filter(&mut self, chat_filter: Box<dyn ChatFilter>) {
+    self.chat_filter = Some(chat_filter);
+  }
+
+  pub fn set_player_store(&mut self, player_store: Box<dyn PlayerStore>) {
+    self.player_store = Some(player_store);
+  }
+
   pub fn start(&mut self) -> Result<(), Box<dyn std::error::Error>> {
     use std::sync::mpsc;
     use std::time::Instant;
@@ -68,17 +140,35 @@ impl Server {
       self.config.resend_budget,
     )));
 
+    let player_store = self
+      .player_store
+      .take()
+      .unwrap_or_else(|| Box::new(FilePlayerStore::new(DEFAULT_PLAYER_STORAGE_PATH)));
+
+    let rate_limiter = Arc::new(Mutex::new(RateLimiter::new(
+      self.config.rate_limits.clone(),
+    )));
+
     let mut net = Net::new(
       socket.clone(),
       packet_orchestrator.clone(),
       self.config.clone(),
+      player_store,
+      rate_limiter.clone(),
     );
 
     self.plugin_wrapper.init(&mut net);
 
     let (tx, rx) = mpsc::channel();
     create_clock_thread(tx.clone());
-    create_listening_thread(tx, socket.try_clone()?, (*self.config).clone());
+    create_console_thread(tx.clone());
+    create_shutdown_thread(tx.clone());
+    create_listening_thread(
+      tx,
+      socket.try_clone()?,
+      (*self.config).clone(),
+      rate_limiter,
+    );
 
     info!("Server started");
 
@@ -114,6 +204,9 @@ impl Server {
 
           kick_list.extend(net.take_kick_list());
 
+          self.resolve_pending_logins(&mut net, &socket);
+          self.resolve_pending_player_data(&mut net);
+
           // actually kick clients
           for boot in kick_list {
             self.disconnect_client(&mut net, &boot.socket_address, &boot.reason, boot.warp_out);
@@ -130,6 +223,25 @@ impl Server {
 
           net.tick();
 
+          for bot_id in net.take_completed_bot_paths() {
+            self
+              .plugin_wrapper
+              .handle_bot_path_complete(&mut net, &bot_id);
+          }
+
+          for event in net.take_events() {
+            self.handle_net_event(&mut net, event);
+          }
+
+          for (group_id, package_path, results) in net.take_completed_coop_battles() {
+            self.plugin_wrapper.handle_battle_results_group(
+              &mut net,
+              &group_id,
+              &package_path,
+              &results,
+            );
+          }
+
           if last_heartbeat.elapsed().as_secs_f32() >= self.config.heartbeat_rate {
             packet_orchestrator
               .borrow_mut()
@@ -138,6 +250,13 @@ impl Server {
             last_heartbeat = time;
           }
         }
+        ThreadMessage::ConsoleCommand(line) => {
+          super::console::handle_console_command(&mut net, &mut self.plugin_wrapper, &line);
+        }
+        ThreadMessage::RateLimitBan { socket_address } => {
+          net.kick_address(socket_address, "Rate limit exceeded", true);
+        }
+        ThreadMessage::Shutdown => break,
         ThreadMessage::ClientPacket {
           socket_address,
           headers,
@@ -145,6 +264,20 @@ impl Server {
         } => {
           let is_reliable = headers.reliability.is_reliable();
 
+          if !self.packet_sorter_map.contains_key(&socket_address) {
+            if let Some(ban) = net.get_address_ban(socket_address.ip()) {
+              if headers.id == 0 && is_reliable {
+                let buf = build_unreliable_packet(ServerPacket::Kick {
+                  reason: &format!("Banned: {}", ban.reason),
+                });
+
+                let _ = socket.send_to(&buf, socket_address);
+              }
+
+              continue;
+            }
+          }
+
           if headers.id == 0 && is_reliable && !self.packet_sorter_map.contains_key(&socket_address)
           {
             // received the first reliable packet, store a new connection
@@ -181,6 +314,28 @@ impl Server {
         }
       }
     }
+
+    info!("Server stopping");
+
+    self.stop(&mut net, &socket);
+
+    Ok(())
+  }
+
+  // kicks every player so their data is saved, then waits for saves to reach the disk
+  fn stop(&mut self, net: &mut Net, socket: &std::net::UdpSocket) {
+    let reason = "Server stopped";
+    let socket_addresses: Vec<std::net::SocketAddr> =
+      self.packet_sorter_map.keys().copied().collect();
+
+    for socket_address in socket_addresses {
+      self.disconnect_client(net, &socket_address, reason, true);
+
+      let buf = build_unreliable_packet(ServerPacket::Kick { reason });
+      let _ = socket.send_to(&buf, socket_address);
+    }
+
+    net.flush_saves();
   }
 
   fn handle_packet(
@@ -334,6 +489,34 @@ impl Server {
               client.actor.x != x || client.actor.y != y || client.actor.z != z;
 
             if position_changed {
+              let rejection = match &mut self.movement_validator {
+                Some(movement_validator) => {
+                  movement_validator.validate(net, player_id, x, y, z).err()
+                }
+                None => None,
+              };
+
+              if let Some(reason) = rejection {
+                if self.config.log_packets {
+                  debug!("Rejected movement from {} ({})", player_id, reason);
+                }
+
+                let prevent_default = self
+                  .plugin_wrapper
+                  .handle_player_move_rejected(net, player_id, x, y, z, reason);
+
+                if !prevent_default {
+                  // snap back to the last accepted position
+                  let actor = net.get_player(player_id).unwrap();
+                  let (x, y, z, direction) = (actor.x, actor.y, actor.z, actor.direction);
+
+                  net.teleport_player(player_id, false, x, y, z, direction);
+                }
+
+                return;
+              }
+
+              let client = net.get_client_mut(player_id).unwrap();
               client.actor.current_animation = None;
 
               self
@@ -342,6 +525,17 @@ impl Server {
             }
 
             net.update_player_position(player_id, x, y, z, direction);
+
+            for (player_id, mut encounter) in net.take_pending_encounters() {
+              let prevent_default =
+                self
+                  .plugin_wrapper
+                  .handle_encounter(net, &player_id, &mut encounter);
+
+              if !prevent_default {
+                net.initiate_server_encounter(&player_id, &encounter);
+              }
+            }
           }
         }
         ClientPacket::Ready { time } => {
@@ -428,9 +622,12 @@ impl Server {
             debug!("Received ObjectInteraction packet from {}", socket_address);
           }
 
-          self
-            .plugin_wrapper
-            .handle_object_interaction(net, player_id, tile_object_id, button);
+          // objects gated on flags and quests ignore players who don't meet the conditions
+          if net.can_player_interact_with_object(player_id, tile_object_id) {
+            self
+              .plugin_wrapper
+              .handle_object_interaction(net, player_id, tile_object_id, button);
+          }
         }
         ClientPacket::ActorInteraction { actor_id, button } => {
           if self.config.log_packets {
@@ -480,6 +677,7 @@ impl Server {
             debug!("Received BoardClose packet from {}", socket_address);
           }
 
+          net.close_leaderboard_view(player_id);
           self.plugin_wrapper.handle_board_close(net, player_id);
         }
         ClientPacket::PostRequest => {
@@ -487,7 +685,9 @@ impl Server {
             debug!("Received PostRequest packet from {}", socket_address);
           }
 
-          self.plugin_wrapper.handle_post_request(net, player_id);
+          if !net.load_leaderboard_page(player_id) {
+            self.plugin_wrapper.handle_post_request(net, player_id);
+          }
         }
         ClientPacket::PostSelection { post_id } => {
           if self.config.log_packets {
@@ -509,6 +709,7 @@ impl Server {
             debug!("Received ShopClose packet from {}", socket_address);
           }
 
+          net.close_managed_shop(player_id);
           self.plugin_wrapper.handle_shop_close(net, player_id);
         }
         ClientPacket::ShopPurchase { item_name } => {
@@ -516,18 +717,105 @@ impl Server {
             debug!("Received ShopPurchase packet from {}", socket_address);
           }
 
-          self
-            .plugin_wrapper
-            .handle_shop_purchase(net, player_id, &item_name);
+          if net.is_in_managed_shop(player_id) {
+            // the server handles the transaction, plugins only hear about successful purchases
+            if let Some(purchase) = net.purchase_managed_shop_item(player_id, &item_name) {
+              self
+                .plugin_wrapper
+                .handle_shop_purchase_complete(net, player_id, &purchase);
+            }
+          } else {
+            self
+              .plugin_wrapper
+              .handle_shop_purchase(net, player_id, &item_name);
+          }
         }
         ClientPacket::BattleResults { battle_stats } => {
           if self.config.log_packets {
             debug!("Received BattleResults packet from {}", socket_address);
           }
 
-          self
-            .plugin_wrapper
-            .handle_battle_results(net, player_id, &battle_stats);
+          if net.is_battle_spectator(player_id) {
+            // spectators have nothing to report, the battle ended for them
+            net.stop_spectating(player_id);
+          } else {
+            net.record_battle_results(player_id, &battle_stats);
+
+            self
+              .plugin_wrapper
+              .handle_battle_results(net, player_id, &battle_stats);
+          }
+        }
+        ClientPacket::ChatMessage {
+          channel,
+          target,
+          message,
+        } => {
+          if self.config.log_packets {
+            debug!("Received ChatMessage packet from {}", socket_address);
+          }
+
+          if channel == ChatChannel::System {
+            // reserved for the server
+            return;
+          }
+
+          let target_id = if channel == ChatChannel::Whisper {
+            match net.find_player_by_name(&target) {
+              Some(target_id) => Some(target_id.to_string()),
+              None => {
+                let response = format!("No player named \"{}\" found.", target);
+                net.send_system_chat_message(player_id, "", &response);
+                return;
+              }
+            }
+          } else {
+            None
+          };
+
+          let message: String = message
+            .trim()
+            .chars()
+            .take(MAX_CHAT_MESSAGE_LENGTH)
+            .collect();
+
+          if message.is_empty() {
+            return;
+          }
+
+          let message = match &mut self.chat_filter {
+            Some(chat_filter) => match chat_filter.filter(player_id, &message) {
+              Some(message) => message,
+              None => return,
+            },
+            None => message,
+          };
+
+          let prevent_default = self.plugin_wrapper.handle_chat_message(
+            net,
+            player_id,
+            channel,
+            target_id.as_deref(),
+            &message,
+          );
+
+          if !prevent_default {
+            net.send_chat_message(player_id, channel, target_id.as_deref(), &message);
+          }
+        }
+        ClientPacket::StopSpectating => {
+          if self.config.log_packets {
+            debug!("Received StopSpectating packet from {}", socket_address);
+          }
+
+          net.stop_spectating(player_id);
+        }
+        ClientPacket::PvpSpectatorData { data } => {
+          if self.config.log_packets {
+            debug!("Received PvpSpectatorData packet from {}", socket_address);
+          }
+
+          net.relay_pvp_spectator_data(player_id, &data);
         }
         ClientPacket::ServerMessage { data } => {
           // this should never happen but 🤷‍♂️
@@ -571,13 +859,38 @@ impl Server {
             debug!("Received Login packet from {}", socket_address);
           }
 
-          let player_id = net.add_client(socket_address, username, identity);
+          let already_pending = self
+            .pending_logins
+            .iter()
+            .any(|pending_login| pending_login.socket_address == socket_address)
+            || self
+              .pending_player_data
+              .iter()
+              .any(|pending_load| pending_load.socket_address == socket_address);
+
+          if already_pending {
+            return;
+          }
 
-          self.player_id_map.insert(socket_address, player_id.clone());
+          let identity_ban = net.get_identity_ban(&identity);
 
-          self
-            .plugin_wrapper
-            .handle_player_request(net, &player_id, &data);
+          let authentication = match (identity_ban, &mut self.authenticator) {
+            (Some(ban), _) => Authentication::Deny(format!("Banned: {}", ban.reason)),
+            (None, Some(authenticator)) => {
+              authenticator.authenticate(socket_address, &username, &identity, &data)
+            }
+            (None, None) => Authentication::Accept,
+          };
+
+          self.handle_authentication(
+            net,
+            socket,
+            socket_address,
+            username,
+            identity,
+            data,
+            authentication,
+          );
         }
         ClientPacket::ServerMessage { data } => {
           self
@@ -595,6 +908,323 @@ impl Server {
     }
   }
 
+  fn handle_net_event(&mut self, net: &mut Net, event: NetEvent) {
+    match event {
+      NetEvent::Trigger(event) => self.handle_trigger_event(net, event),
+      NetEvent::Trade(event) => self.handle_trade_event(net, event),
+      NetEvent::Party(event) => self.handle_party_event(net, event),
+      NetEvent::Spectator(event) => self.handle_spectator_event(net, event),
+      NetEvent::Matchmaking(event) => self.handle_matchmaking_event(net, event),
+      NetEvent::Quest(event) => self.handle_quest_event(net, event),
+    }
+  }
+
+  fn handle_trigger_event(&mut self, net: &mut Net, event: TriggerEvent) {
+    match event {
+      TriggerEvent::ObjectEnter {
+        player_id,
+        area_id,
+        object_id,
+      } => self
+        .plugin_wrapper
+        .handle_object_enter(net, &player_id, &area_id, object_id),
+      TriggerEvent::ObjectExit {
+        player_id,
+        area_id,
+        object_id,
+      } => self
+        .plugin_wrapper
+        .handle_object_exit(net, &player_id, &area_id, object_id),
+      TriggerEvent::ActorNearby {
+        actor_id,
+        nearby_actor_id,
+      } => self
+        .plugin_wrapper
+        .handle_actor_nearby(net, &actor_id, &nearby_actor_id),
+    }
+  }
+
+  fn handle_trade_event(&mut self, net: &mut Net, event: TradeEvent) {
+    match event {
+      TradeEvent::Request {
+        player_id,
+        recipient_id,
+      } => {
+        let prevent_default =
+          self
+            .plugin_wrapper
+            .handle_trade_request(net, &player_id, &recipient_id);
+
+        net.resolve_trade_request(&player_id, prevent_default);
+      }
+      TradeEvent::Complete {
+        player_id,
+        recipient_id,
+        player_offer,
+        recipient_offer,
+      } => self.plugin_wrapper.handle_trade_complete(
+        net,
+        &player_id,
+        &recipient_id,
+        &player_offer,
+        &recipient_offer,
+      ),
+      TradeEvent::Cancelled {
+        player_id,
+        recipient_id,
+        reason,
+      } => self
+        .plugin_wrapper
+        .handle_trade_cancelled(net, &player_id, &recipient_id, reason),
+    }
+  }
+
+  fn handle_party_event(&mut self, net: &mut Net, event: PartyEvent) {
+    match event {
+      PartyEvent::Invite {
+        party_id,
+        player_id,
+        recipient_id,
+      } => self
+        .plugin_wrapper
+        .handle_party_invite(net, &party_id, &player_id, &recipient_id),
+      PartyEvent::Join {
+        party_id,
+        player_id,
+      } => self
+        .plugin_wrapper
+        .handle_party_join(net, &party_id, &player_id),
+      PartyEvent::Leave {
+        party_id,
+        player_id,
+        reason,
+      } => self
+        .plugin_wrapper
+        .handle_party_leave(net, &party_id, &player_id, reason),
+      PartyEvent::LeaderChange {
+        party_id,
+        player_id,
+      } => self
+        .plugin_wrapper
+        .handle_party_leader_change(net, &party_id, &player_id),
+    }
+  }
+
+  fn handle_spectator_event(&mut self, net: &mut Net, event: SpectatorEvent) {
+    match event {
+      SpectatorEvent::Join {
+        spectator_id,
+        player_ids: [player_1_id, player_2_id],
+      } => self.plugin_wrapper.handle_battle_spectator_join(
+        net,
+        &spectator_id,
+        &player_1_id,
+        &player_2_id,
+      ),
+      SpectatorEvent::Leave {
+        spectator_id,
+        player_ids: [player_1_id, player_2_id],
+        reason,
+      } => self.plugin_wrapper.handle_battle_spectator_leave(
+        net,
+        &spectator_id,
+        &player_1_id,
+        &player_2_id,
+        reason,
+      ),
+    }
+  }
+
+  fn handle_matchmaking_event(&mut self, net: &mut Net, event: MatchmakingEvent) {
+    let [player_1_id, player_2_id] = event.player_ids;
+
+    self
+      .plugin_wrapper
+      .handle_matchmaking_match(net, &event.queue_id, &player_1_id, &player_2_id);
+  }
+
+  fn handle_quest_event(&mut self, net: &mut Net, event: QuestEvent) {
+    match event {
+      QuestEvent::QuestChange {
+        player_id,
+        quest_id,
+        progress,
+      } => {
+        let step_id = net
+          .get_quest(&quest_id)
+          .filter(|_| progress.state == QuestState::Active)
+          .and_then(|quest| quest.steps.get(progress.step))
+          .map(|step| step.id.clone());
+
+        self.plugin_wrapper.handle_quest_change(
+          net,
+          &player_id,
+          &quest_id,
+          progress.state,
+          step_id.as_deref(),
+        )
+      }
+      QuestEvent::FlagChange {
+        player_id,
+        name,
+        value,
+      } => self
+        .plugin_wrapper
+        .handle_flag_change(net, &player_id, &name, value),
+    }
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn handle_authentication(
+    &mut self,
+    net: &mut Net,
+    socket: &std::net::UdpSocket,
+    socket_address: std::net::SocketAddr,
+    username: String,
+    identity: String,
+    data: String,
+    authentication: Authentication,
+  ) {
+    let authentication = match authentication {
+      Authentication::Accept => self.resolve_existing_session(net, socket, &identity),
+      authentication => authentication,
+    };
+
+    match authentication {
+      Authentication::Accept => match net.load_player_data(&identity) {
+        PlayerDataLoad::Ready(player_data) => {
+          self.add_client(net, socket_address, username, identity, data, player_data);
+        }
+        PlayerDataLoad::Defer(promise, resolver) => {
+          self.pending_player_data.push(PendingPlayerData {
+            socket_address,
+            username,
+            identity,
+            data,
+            promise,
+            resolver,
+          });
+        }
+      },
+      Authentication::Deny(reason) => {
+        let buf = build_unreliable_packet(ServerPacket::Kick { reason: &reason });
+        let _ = socket.send_to(&buf, socket_address);
+
+        self.disconnect_client(net, &socket_address, &reason, false);
+      }
+      Authentication::Defer(promise, resolver) => {
+        self.pending_logins.push(PendingLogin {
+          socket_address,
+          username,
+          identity,
+          data,
+          promise,
+          resolver,
+        });
+      }
+    }
+  }
+
+  // sessions sharing an identity would load and save the same data, so only one may be connected.
+  // the older session is kicked and saved before the new session loads the data
+  fn resolve_existing_session(
+    &mut self,
+    net: &mut Net,
+    socket: &std::net::UdpSocket,
+    identity: &str,
+  ) -> Authentication {
+    let loading = self
+      .pending_player_data
+      .iter()
+      .any(|pending_load| pending_load.identity == identity);
+
+    if loading {
+      return Authentication::Deny(String::from("Already logging in"));
+    }
+
+    if let Some(socket_address) = net.get_client_address_by_identity(identity) {
+      let reason = "Logged in from another location";
+
+      self.disconnect_client(net, &socket_address, reason, true);
+
+      let buf = build_unreliable_packet(ServerPacket::Kick { reason });
+      let _ = socket.send_to(&buf, socket_address);
+    }
+
+    Authentication::Accept
+  }
+
+  fn resolve_pending_logins(&mut self, net: &mut Net, socket: &std::net::UdpSocket) {
+    let (resolved_logins, pending_logins) = std::mem::take(&mut self.pending_logins)
+      .into_iter()
+      .partition(|pending_login| !pending_login.promise.is_pending());
+
+    self.pending_logins = pending_logins;
+
+    for mut login in resolved_logins {
+      if !self.packet_sorter_map.contains_key(&login.socket_address) {
+        // disconnected while waiting
+        continue;
+      }
+
+      let authentication = (login.resolver)(login.promise.get_value());
+
+      self.handle_authentication(
+        net,
+        socket,
+        login.socket_address,
+        login.username,
+        login.identity,
+        login.data,
+        authentication,
+      );
+    }
+  }
+
+  fn resolve_pending_player_data(&mut self, net: &mut Net) {
+    let (resolved_loads, pending_loads) = std::mem::take(&mut self.pending_player_data)
+      .into_iter()
+      .partition(|pending_load| !pending_load.promise.is_pending());
+
+    self.pending_player_data = pending_loads;
+
+    for mut load in resolved_loads {
+      if !self.packet_sorter_map.contains_key(&load.socket_address) {
+        // disconnected while waiting
+        continue;
+      }
+
+      let player_data = (load.resolver)(load.promise.get_value());
+
+      self.add_client(
+        net,
+        load.socket_address,
+        load.username,
+        load.identity,
+        load.data,
+        player_data,
+      );
+    }
+  }
+
+  fn add_client(
+    &mut self,
+    net: &mut Net,
+    socket_address: std::net::SocketAddr,
+    username: String,
+    identity: String,
+    data: String,
+    player_data: Option<PlayerData>,
+  ) {
+    let player_id = net.add_client(socket_address, username, identity, player_data);
+
+    self.player_id_map.insert(socket_address, player_id.clone());
+
+    self
+      .plugin_wrapper
+      .handle_player_request(net, &player_id, &data);
+  }
+
   fn disconnect_client(
     &mut self,
     net: &mut Net,
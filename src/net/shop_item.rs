@@ -1,8 +1,29 @@
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct ShopItem {
   pub name: String,
   pub description: String,
   pub price: u32,
+  /// Item granted by managed shops
+  pub item_id: Option<String>,
+  /// Remaining stock for managed shops, None for unlimited
+  pub stock: Option<u32>,
+}
+
+impl ShopItem {
+  pub fn is_sold_out(&self) -> bool {
+    self.stock == Some(0)
+  }
+}
+
+/// A completed purchase from a managed shop
+#[derive(Clone, Debug)]
+pub struct ShopPurchase {
+  pub shop_id: String,
+  pub item_id: String,
+  pub item_name: String,
+  pub price: u32,
+  /// Remaining stock after the purchase, None for unlimited
+  pub stock: Option<u32>,
 }
 
 pub fn calc_size(item: &ShopItem) -> usize {
@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+// in tiles, large enough to keep big objects from spanning too many cells
+const CELL_SIZE: f32 = 4.0;
+
+type Cell = (i32, i32, i32);
+
+/// Buckets keys into grid cells per layer for fast broad phase lookups.
+/// Results are candidates, callers still need to test exact shapes.
+#[derive(Clone)]
+pub(super) struct SpatialIndex<K> {
+  cells: HashMap<Cell, Vec<K>>,
+  entries: HashMap<K, Vec<Cell>>,
+}
+
+impl<K: Clone + Eq + Hash> SpatialIndex<K> {
+  pub fn new() -> SpatialIndex<K> {
+    SpatialIndex {
+      cells: HashMap::new(),
+      entries: HashMap::new(),
+    }
+  }
+
+  /// Inserts or updates the key to cover the bounds, bounds are (min_x, min_y, max_x, max_y)
+  pub fn insert(&mut self, key: K, layer: i32, bounds: (f32, f32, f32, f32)) {
+    let cells: Vec<Cell> = cells_in_bounds(layer, bounds).collect();
+
+    if self.entries.get(&key) == Some(&cells) {
+      return;
+    }
+
+    self.remove(&key);
+
+    for cell in &cells {
+      self.cells.entry(*cell).or_default().push(key.clone());
+    }
+
+    self.entries.insert(key, cells);
+  }
+
+  pub fn insert_point(&mut self, key: K, x: f32, y: f32, z: f32) {
+    self.insert(key, z.floor() as i32, (x, y, x, y));
+  }
+
+  pub fn remove(&mut self, key: &K) {
+    let cells = match self.entries.remove(key) {
+      Some(cells) => cells,
+      None => return,
+    };
+
+    for cell in cells {
+      if let Some(keys) = self.cells.get_mut(&cell) {
+        keys.retain(|k| k != key);
+
+        if keys.is_empty() {
+          self.cells.remove(&cell);
+        }
+      }
+    }
+  }
+
+  /// Keys in cells overlapping the bounds, bounds are (min_x, min_y, max_x, max_y)
+  pub fn query(&self, layer: i32, bounds: (f32, f32, f32, f32)) -> HashSet<K> {
+    let (start_x, start_y, end_x, end_y) = cell_range(bounds);
+    let cell_count = (end_x as i64 - start_x as i64 + 1) * (end_y as i64 - start_y as i64 + 1);
+
+    if cell_count > self.cells.len() as i64 {
+      // large queries are cheaper as a scan over occupied cells
+      return self
+        .cells
+        .iter()
+        .filter(|((x, y, z), _)| {
+          *z == layer && (start_x..=end_x).contains(x) && (start_y..=end_y).contains(y)
+        })
+        .flat_map(|(_, keys)| keys)
+        .cloned()
+        .collect();
+    }
+
+    cells_in_bounds(layer, bounds)
+      .filter_map(|cell| self.cells.get(&cell))
+      .flatten()
+      .cloned()
+      .collect()
+  }
+}
+
+fn cell_range((min_x, min_y, max_x, max_y): (f32, f32, f32, f32)) -> (i32, i32, i32, i32) {
+  let to_cell = |value: f32| (value / CELL_SIZE).floor() as i32;
+
+  (
+    to_cell(min_x),
+    to_cell(min_y),
+    to_cell(max_x),
+    to_cell(max_y),
+  )
+}
+
+fn cells_in_bounds(layer: i32, bounds: (f32, f32, f32, f32)) -> impl Iterator<Item = Cell> {
+  let (start_x, start_y, end_x, end_y) = cell_range(bounds);
+
+  (start_y..=end_y).flat_map(move |y| (start_x..=end_x).map(move |x| (x, y, layer)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keys(keys: &[u32]) -> HashSet<u32> {
+    keys.iter().copied().collect()
+  }
+
+  #[test]
+  fn insert() {
+    let mut index = SpatialIndex::new();
+
+    index.insert(1, 0, (1.0, 1.0, 2.0, 2.0));
+    index.insert(2, 0, (10.0, 10.0, 11.0, 11.0));
+    index.insert_point(3, 1.5, 1.5, 1.2);
+
+    assert_eq!(index.query(0, (0.0, 0.0, 3.0, 3.0)), keys(&[1]));
+    assert_eq!(index.query(0, (9.0, 9.0, 10.0, 10.0)), keys(&[2]));
+    assert_eq!(index.query(1, (1.0, 1.0, 2.0, 2.0)), keys(&[3]));
+    assert_eq!(index.query(2, (1.0, 1.0, 2.0, 2.0)), keys(&[]));
+    assert_eq!(
+      index.query(0, (-100.0, -100.0, 100.0, 100.0)),
+      keys(&[1, 2])
+    );
+
+    // negative coordinates fall into their own cells
+    index.insert(4, 0, (-2.0, -2.0, -1.0, -1.0));
+
+    assert_eq!(index.query(0, (-1.5, -1.5, -1.5, -1.5)), keys(&[4]));
+  }
+
+  #[test]
+  fn spanning_bounds() {
+    let mut index = SpatialIndex::new();
+
+    index.insert(1, 0, (0.0, 0.0, CELL_SIZE * 2.5, 1.0));
+
+    assert_eq!(index.entries[&1].len(), 3);
+    assert_eq!(
+      index.query(0, (CELL_SIZE * 2.2, 0.5, CELL_SIZE * 2.2, 0.5)),
+      keys(&[1])
+    );
+    assert_eq!(
+      index.query(0, (CELL_SIZE * 3.5, 0.5, CELL_SIZE * 3.5, 0.5)),
+      keys(&[])
+    );
+  }
+
+  #[test]
+  fn move_key() {
+    let mut index = SpatialIndex::new();
+
+    index.insert_point(1, 0.5, 0.5, 0.0);
+    index.insert_point(1, 20.5, 20.5, 1.0);
+
+    assert_eq!(index.query(0, (0.0, 0.0, 1.0, 1.0)), keys(&[]));
+    assert_eq!(index.query(1, (20.0, 20.0, 21.0, 21.0)), keys(&[1]));
+    assert_eq!(index.cells.len(), 1);
+
+    // moving within the same cell keeps a single entry
+    index.insert_point(1, 21.5, 21.5, 1.0);
+
+    assert_eq!(index.cells.values().flatten().count(), 1);
+  }
+
+  #[test]
+  fn remove() {
+    let mut index = SpatialIndex::new();
+
+    index.insert(1, 0, (0.0, 0.0, CELL_SIZE * 2.0, CELL_SIZE * 2.0));
+    index.insert(2, 0, (0.0, 0.0, 1.0, 1.0));
+
+    index.remove(&1);
+
+    assert_eq!(
+      index.query(0, (0.0, 0.0, CELL_SIZE * 2.0, CELL_SIZE * 2.0)),
+      keys(&[2])
+    );
+    // cells left empty are cleaned up
+    assert_eq!(index.cells.len(), 1);
+
+    index.remove(&2);
+    index.remove(&3);
+
+    assert!(index.cells.is_empty());
+    assert!(index.entries.is_empty());
+  }
+}
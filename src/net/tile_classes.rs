@@ -0,0 +1,109 @@
+use super::asset_manager::AssetManager;
+use super::map::Map;
+use super::AssetData;
+use std::collections::HashMap;
+
+struct TilesetClasses {
+  last_modified: u64,
+  classes: HashMap<u32, String>,
+}
+
+/// Caches tile classes parsed from tileset assets, reparsed when the asset changes
+pub(super) struct TileClassCache {
+  tilesets: HashMap<String, TilesetClasses>,
+}
+
+impl TileClassCache {
+  pub fn new() -> TileClassCache {
+    TileClassCache {
+      tilesets: HashMap::new(),
+    }
+  }
+
+  pub fn is_stairs(
+    &mut self,
+    asset_manager: &AssetManager,
+    map: &Map,
+    x: f32,
+    y: f32,
+    z: f32,
+  ) -> bool {
+    if x < 0.0 || y < 0.0 || z < 0.0 {
+      return false;
+    }
+
+    let gid = map.get_tile(x as usize, y as usize, z as usize).gid;
+
+    self.resolve_tile_class(asset_manager, map, gid) == Some("Stairs")
+  }
+
+  pub fn resolve_tile_class(
+    &mut self,
+    asset_manager: &AssetManager,
+    map: &Map,
+    gid: u32,
+  ) -> Option<&str> {
+    let tileset = map
+      .get_tilesets()
+      .iter()
+      .filter(|tileset| tileset.first_gid <= gid)
+      .max_by_key(|tileset| tileset.first_gid)?;
+
+    let asset = asset_manager.get_asset(&tileset.path)?;
+
+    let is_stale = match self.tilesets.get(&tileset.path) {
+      Some(cached) => cached.last_modified != asset.last_modified,
+      None => true,
+    };
+
+    if is_stale {
+      let classes = match &asset.data {
+        AssetData::Text(text) => parse_tile_classes(text),
+        _ => HashMap::new(),
+      };
+
+      self.tilesets.insert(
+        tileset.path.clone(),
+        TilesetClasses {
+          last_modified: asset.last_modified,
+          classes,
+        },
+      );
+    }
+
+    let cached = self.tilesets.get(&tileset.path)?;
+
+    cached
+      .classes
+      .get(&(gid - tileset.first_gid))
+      .map(|class| class.as_str())
+  }
+}
+
+fn parse_tile_classes(text: &str) -> HashMap<u32, String> {
+  use crate::helpers::unwrap_and_parse_or_default;
+
+  let mut classes = HashMap::new();
+
+  let tileset_element: minidom::Element = match text.parse() {
+    Ok(element) => element,
+    Err(_) => return classes,
+  };
+
+  for tile_element in tileset_element.children() {
+    if tile_element.name() != "tile" {
+      continue;
+    }
+
+    let class_option = tile_element
+      .attr("class")
+      .or_else(|| tile_element.attr("type"));
+
+    if let Some(class) = class_option {
+      let id: u32 = unwrap_and_parse_or_default(tile_element.attr("id"));
+      classes.insert(id, class.to_string());
+    }
+  }
+
+  classes
+}
@@ -0,0 +1,379 @@
+use super::{InventoryError, Item, ItemStack, PlayerData};
+use std::collections::HashMap;
+
+/// Money and items held by the server until the trade completes or is cancelled
+#[derive(Clone, Default, Debug)]
+pub struct TradeOffer {
+  pub money: u32,
+  pub items: Vec<ItemStack>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TradeError {
+  SamePlayer,
+  PlayerNotFound,
+  PlayerBusy,
+  AlreadyTrading,
+  NotTrading,
+  NotAccepted,
+  NotEnoughMoney,
+  MoneyLimit,
+  Inventory(InventoryError),
+}
+
+impl std::fmt::Display for TradeError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      TradeError::SamePlayer => write!(f, "can't trade with self"),
+      TradeError::PlayerNotFound => write!(f, "player not found"),
+      TradeError::PlayerBusy => write!(f, "player is busy"),
+      TradeError::AlreadyTrading => write!(f, "player is already trading"),
+      TradeError::NotTrading => write!(f, "player is not trading"),
+      TradeError::NotAccepted => write!(f, "trade has not been accepted"),
+      TradeError::NotEnoughMoney => write!(f, "not enough money"),
+      TradeError::MoneyLimit => write!(f, "money limit reached"),
+      TradeError::Inventory(err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl From<InventoryError> for TradeError {
+  fn from(err: InventoryError) -> TradeError {
+    TradeError::Inventory(err)
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum TradeState {
+  /// Waiting for plugins to see the request
+  Pending,
+  /// Waiting for the recipient to accept
+  Requested,
+  Active,
+}
+
+/// Tracked as a widget for both players until the trade ends, so busy players can't start trades or battles
+pub(super) struct Trade {
+  /// The player who requested the trade
+  pub player_id: String,
+  pub recipient_id: String,
+  pub state: TradeState,
+  /// Indexed by side, 0 for the player and 1 for the recipient
+  pub offers: [TradeOffer; 2],
+  pub confirmed: [bool; 2],
+}
+
+impl Trade {
+  pub fn new(player_id: String, recipient_id: String) -> Trade {
+    Trade {
+      player_id,
+      recipient_id,
+      state: TradeState::Pending,
+      offers: Default::default(),
+      confirmed: [false; 2],
+    }
+  }
+
+  pub fn side(&self, player_id: &str) -> Option<usize> {
+    if self.player_id == player_id {
+      Some(0)
+    } else if self.recipient_id == player_id {
+      Some(1)
+    } else {
+      None
+    }
+  }
+
+  pub fn participant(&self, side: usize) -> &str {
+    if side == 0 {
+      &self.player_id
+    } else {
+      &self.recipient_id
+    }
+  }
+}
+
+/// Trade steps scripts can watch, requests can be prevented before the recipient can accept
+pub(super) enum TradeEvent {
+  Request {
+    player_id: String,
+    recipient_id: String,
+  },
+  Complete {
+    player_id: String,
+    recipient_id: String,
+    player_offer: TradeOffer,
+    recipient_offer: TradeOffer,
+  },
+  Cancelled {
+    player_id: String,
+    recipient_id: String,
+    reason: &'static str,
+  },
+}
+
+/// Validates the offer against the player's money and items, then takes them from the player.
+/// Nothing is taken if the offer is invalid
+pub(super) fn escrow_offer(
+  items: &HashMap<String, Item>,
+  player_data: &mut PlayerData,
+  money: u32,
+  offered_items: &[(String, u32)],
+) -> Result<TradeOffer, TradeError> {
+  use super::inventory::{count_items, remove_items};
+
+  if player_data.money < money {
+    return Err(TradeError::NotEnoughMoney);
+  }
+
+  let mut totals: HashMap<&str, u32> = HashMap::new();
+
+  for (item_id, quantity) in offered_items {
+    let item = items.get(item_id).ok_or(InventoryError::UnknownItem)?;
+    item.validate_transfer()?;
+
+    if *quantity == 0 {
+      return Err(InventoryError::InvalidQuantity.into());
+    }
+
+    let total = totals.entry(item_id).or_default();
+    *total = total.saturating_add(*quantity);
+  }
+
+  for (item_id, total) in &totals {
+    if count_items(&player_data.items, item_id) < *total {
+      return Err(InventoryError::NotEnoughItems.into());
+    }
+  }
+
+  player_data.money -= money;
+
+  let mut offer = TradeOffer {
+    money,
+    items: Vec::new(),
+  };
+
+  for (item_id, quantity) in offered_items {
+    offer
+      .items
+      .extend(remove_items(&mut player_data.items, item_id, *quantity));
+  }
+
+  Ok(offer)
+}
+
+/// Checks if the player can hold the offer on top of what they already have
+pub(super) fn validate_receipt(
+  items: &HashMap<String, Item>,
+  player_data: &PlayerData,
+  offer: &TradeOffer,
+) -> Result<(), TradeError> {
+  use super::inventory::count_items;
+
+  if player_data.money.checked_add(offer.money).is_none() {
+    return Err(TradeError::MoneyLimit);
+  }
+
+  let mut totals: HashMap<&str, u32> = HashMap::new();
+
+  for stack in &offer.items {
+    let total = totals.entry(&stack.item_id).or_default();
+    *total = total.saturating_add(stack.quantity);
+  }
+
+  for (item_id, total) in totals {
+    let item = items.get(item_id).ok_or(InventoryError::UnknownItem)?;
+    item.validate_add(count_items(&player_data.items, item_id), total)?;
+  }
+
+  Ok(())
+}
+
+/// Gives the offer to the player without validating, the money and items were already held by a player in the trade
+pub(super) fn deposit_offer(
+  items: &HashMap<String, Item>,
+  player_data: &mut PlayerData,
+  offer: &TradeOffer,
+) {
+  use super::inventory::add_items;
+
+  player_data.money = player_data.money.saturating_add(offer.money);
+
+  for stack in &offer.items {
+    match items.get(&stack.item_id) {
+      Some(item) => add_items(
+        &mut player_data.items,
+        item,
+        &stack.item_id,
+        stack.quantity,
+        stack.metadata.clone(),
+      ),
+      None => player_data.items.push(stack.clone()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::net::ItemMetadata;
+
+  fn create_items() -> HashMap<String, Item> {
+    let mut potion = Item::new(String::from("Potion"), String::new());
+    potion.max_quantity = Some(10);
+
+    let mut key = Item::new(String::from("Key"), String::new());
+    key.tradable = false;
+
+    HashMap::from([(String::from("potion"), potion), (String::from("key"), key)])
+  }
+
+  fn create_player(items: &HashMap<String, Item>, money: u32, potions: u32) -> PlayerData {
+    use super::super::inventory::add_items;
+
+    let mut player_data = PlayerData::new(String::from("identity"));
+    player_data.money = money;
+
+    let metadata = ItemMetadata {
+      durability: Some(3),
+      data: None,
+    };
+
+    add_items(
+      &mut player_data.items,
+      &items["potion"],
+      "potion",
+      potions,
+      metadata,
+    );
+    add_items(
+      &mut player_data.items,
+      &items["key"],
+      "key",
+      1,
+      ItemMetadata::default(),
+    );
+
+    player_data
+  }
+
+  fn offer_potions(quantity: u32) -> Vec<(String, u32)> {
+    vec![(String::from("potion"), quantity)]
+  }
+
+  #[test]
+  fn escrowed_offers() {
+    let items = create_items();
+    let mut player_data = create_player(&items, 100, 5);
+
+    let offer = escrow_offer(&items, &mut player_data, 30, &offer_potions(3)).unwrap();
+
+    assert_eq!(offer.money, 30);
+    assert_eq!(offer.items.len(), 1);
+    assert_eq!(offer.items[0].quantity, 3);
+    assert_eq!(offer.items[0].metadata.durability, Some(3));
+    assert_eq!(player_data.money, 70);
+    assert_eq!(player_data.items[0].quantity, 2);
+
+    // invalid offers take nothing
+    let invalid_offers = [
+      (71, offer_potions(1), TradeError::NotEnoughMoney),
+      (
+        10,
+        vec![(String::from("potion"), 2), (String::from("potion"), 1)],
+        InventoryError::NotEnoughItems.into(),
+      ),
+      (10, offer_potions(0), InventoryError::InvalidQuantity.into()),
+      (
+        10,
+        vec![(String::from("key"), 1)],
+        InventoryError::NotTradable.into(),
+      ),
+      (
+        10,
+        vec![(String::from("missing"), 1)],
+        InventoryError::UnknownItem.into(),
+      ),
+    ];
+
+    for (money, offered_items, err) in invalid_offers {
+      let result = escrow_offer(&items, &mut player_data, money, &offered_items);
+
+      assert_eq!(result.unwrap_err(), err);
+      assert_eq!(player_data.money, 70);
+      assert_eq!(player_data.items[0].quantity, 2);
+    }
+  }
+
+  #[test]
+  fn revalidation() {
+    let items = create_items();
+    let mut sender = create_player(&items, 100, 5);
+    let mut recipient = create_player(&items, 0, 5);
+
+    let offer = escrow_offer(&items, &mut sender, 50, &offer_potions(5)).unwrap();
+
+    assert_eq!(validate_receipt(&items, &recipient, &offer), Ok(()));
+
+    // the recipient gained items after confirming
+    super::super::inventory::add_items(
+      &mut recipient.items,
+      &items["potion"],
+      "potion",
+      1,
+      ItemMetadata::default(),
+    );
+
+    assert_eq!(
+      validate_receipt(&items, &recipient, &offer),
+      Err(InventoryError::QuantityLimit.into())
+    );
+
+    recipient.money = u32::MAX - 10;
+    let offer = escrow_offer(&items, &mut sender, 50, &[]).unwrap();
+
+    assert_eq!(
+      validate_receipt(&items, &recipient, &offer),
+      Err(TradeError::MoneyLimit)
+    );
+  }
+
+  #[test]
+  fn refunds() {
+    let items = create_items();
+    let original_player = create_player(&items, 100, 5);
+    let original_recipient = create_player(&items, 40, 2);
+
+    let mut trade = Trade::new(String::from("player"), String::from("recipient"));
+    trade.state = TradeState::Active;
+
+    let mut player_data = original_player.clone();
+    let mut recipient_data = original_recipient.clone();
+
+    trade.offers[0] = escrow_offer(&items, &mut player_data, 60, &offer_potions(4)).unwrap();
+    trade.offers[1] = escrow_offer(&items, &mut recipient_data, 40, &offer_potions(1)).unwrap();
+
+    // cancelling returns each offer to the player who made it
+    let mut cancelled_player = player_data.clone();
+    let mut cancelled_recipient = recipient_data.clone();
+
+    deposit_offer(&items, &mut cancelled_player, &trade.offers[0]);
+    deposit_offer(&items, &mut cancelled_recipient, &trade.offers[1]);
+
+    assert_eq!(cancelled_player.money, original_player.money);
+    assert_eq!(cancelled_player.items, original_player.items);
+    assert_eq!(cancelled_recipient.money, original_recipient.money);
+    assert_eq!(cancelled_recipient.items, original_recipient.items);
+
+    // a disconnecting player is saved with their offer returned, even with items moving around
+    super::super::inventory::remove_items(&mut player_data.items, "key", 1);
+    deposit_offer(&items, &mut player_data, &trade.offers[0]);
+
+    assert_eq!(player_data.money, original_player.money);
+    assert_eq!(
+      super::super::inventory::count_items(&player_data.items, "potion"),
+      5
+    );
+    assert_eq!(player_data.items[0].metadata.durability, Some(3));
+  }
+}
@@ -0,0 +1,31 @@
+use std::collections::HashSet;
+
+/// Presence changes found while updating triggers
+pub(super) enum TriggerEvent {
+  ObjectEnter {
+    player_id: String,
+    area_id: String,
+    object_id: u32,
+  },
+  ObjectExit {
+    player_id: String,
+    area_id: String,
+    object_id: u32,
+  },
+  ActorNearby {
+    actor_id: String,
+    nearby_actor_id: String,
+  },
+}
+
+/// Trigger objects a player is standing in
+pub(super) struct OccupiedTriggers {
+  pub area_id: String,
+  pub object_ids: Vec<u32>,
+}
+
+/// Actors within the radius are reported once, and again after leaving and returning
+pub(super) struct ProximityWatch {
+  pub radius: f32,
+  pub nearby: HashSet<String>,
+}
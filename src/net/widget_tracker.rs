@@ -5,6 +5,7 @@ pub struct WidgetTracker<T> {
   bbs_queue: VecDeque<T>,
   active_bbs: Vec<T>,
   active_shop: Option<T>,
+  active_trade: Option<T>,
 }
 
 impl<T> WidgetTracker<T> {
@@ -14,6 +15,7 @@ impl<T> WidgetTracker<T> {
       bbs_queue: VecDeque::new(),
       active_bbs: Vec::new(),
       active_shop: None,
+      active_trade: None,
     }
   }
 
@@ -22,12 +24,17 @@ impl<T> WidgetTracker<T> {
       && self.active_bbs.is_empty()
       && self.bbs_queue.is_empty()
       && self.active_shop.is_none()
+      && self.active_trade.is_none()
   }
 
   pub(super) fn get_board_count(&self) -> usize {
     self.active_bbs.len() + self.bbs_queue.len()
   }
 
+  pub(super) fn get_open_board_count(&self) -> usize {
+    self.active_bbs.len()
+  }
+
   pub fn track_textbox(&mut self, owner: T) {
     self.textbox_queue.push_back(owner);
   }
@@ -66,4 +73,32 @@ impl<T> WidgetTracker<T> {
   pub fn close_shop(&mut self) -> Option<T> {
     self.active_shop.take()
   }
+
+  /// Trades are handled by the server, only one trade can be active at a time
+  pub fn track_trade(&mut self, owner: T) {
+    self.active_trade = Some(owner)
+  }
+
+  pub fn close_trade(&mut self) -> Option<T> {
+    self.active_trade.take()
+  }
+}
+
+impl<T: PartialEq + Clone> WidgetTracker<T> {
+  /// Swaps ownership of every tracked widget, keeping the order responses are expected in
+  pub fn replace_owner(&mut self, owner: &T, replacement: T) {
+    let owners = self
+      .textbox_queue
+      .iter_mut()
+      .chain(self.bbs_queue.iter_mut())
+      .chain(self.active_bbs.iter_mut())
+      .chain(self.active_shop.iter_mut())
+      .chain(self.active_trade.iter_mut());
+
+    for tracked_owner in owners {
+      if tracked_owner == owner {
+        *tracked_owner = replacement.clone();
+      }
+    }
+  }
 }
@@ -3,7 +3,7 @@
 use super::bytes::*;
 use super::management::{get_reliability, Reliability};
 use super::PacketHeaders;
-use crate::net::{BattleStats, Direction, EnemyBattleStats};
+use crate::net::{BattleStats, ChatChannel, Direction, EnemyBattleStats};
 
 #[derive(Debug)]
 pub enum ClientPacket {
@@ -92,6 +92,17 @@ pub enum ClientPacket {
   BattleResults {
     battle_stats: BattleStats,
   },
+  ChatMessage {
+    channel: ChatChannel,
+    /// Player name or id for whispers, empty otherwise
+    target: String,
+    message: String,
+  },
+  StopSpectating,
+  /// Battle data from a spectated PvP player, relayed to spectators
+  PvpSpectatorData {
+    data: Vec<u8>,
+  },
 }
 
 pub fn parse_client_packet(buf: &[u8]) -> Option<(PacketHeaders, ClientPacket)> {
@@ -245,6 +256,15 @@ fn parse_body(work_buf: &mut &[u8]) -> Option<ClientPacket> {
 
       ClientPacket::BattleResults { battle_stats }
     }),
+    28 => Some(ClientPacket::ChatMessage {
+      channel: ChatChannel::from_byte(read_byte(work_buf)?)?,
+      target: read_string_u8(work_buf)?,
+      message: read_string_u16(work_buf)?,
+    }),
+    29 => Some(ClientPacket::StopSpectating),
+    30 => Some(ClientPacket::PvpSpectatorData {
+      data: work_buf.to_vec(),
+    }),
     _ => None,
   }
 }
@@ -3,7 +3,9 @@
 use super::bytes::*;
 use super::{VERSION_ID, VERSION_ITERATION};
 use crate::net::actor_property_animation::{ActorProperty, Ease, KeyFrame};
-use crate::net::{Asset, AssetData, BbsPost, Direction, PackageCategory, PackageInfo, ShopItem};
+use crate::net::{
+  Asset, AssetData, BbsPost, ChatChannel, Direction, PackageCategory, PackageInfo, ShopItem,
+};
 
 #[repr(u16)]
 enum ServerPacketId {
@@ -69,6 +71,12 @@ enum ServerPacketId {
   ActorPropertyKeyFrames,
   ActorMinimapColor,
   OfferPackage,
+  ChatMessage,
+  SpectatePvp,
+  EndSpectating,
+  PvpSpectatorJoin,
+  PvpSpectatorLeave,
+  PvpSpectatorData,
 }
 
 #[derive(Debug)]
@@ -319,6 +327,24 @@ pub enum ServerPacket<'a> {
     ticket: &'a str,
     color: (u8, u8, u8, u8),
   },
+  ChatMessage {
+    channel: ChatChannel,
+    /// Empty for system messages
+    sender_id: &'a str,
+    sender_name: &'a str,
+    message: &'a str,
+  },
+  /// Sent to a spectator, battle data is relayed through the server so addresses stay private
+  SpectatePvp,
+  EndSpectating,
+  /// Sent to both players in a PvP battle, battle data should be sent to the server while spectated
+  PvpSpectatorJoin,
+  PvpSpectatorLeave,
+  /// Battle data relayed to spectators, side is 0 for the first player and 1 for the second
+  PvpSpectatorData {
+    side: u8,
+    data: &'a [u8],
+  },
 }
 
 pub fn build_unreliable_packet(packet: ServerPacket) -> Vec<u8> {
@@ -865,6 +891,35 @@ pub fn build_packet(packet: ServerPacket) -> Vec<u8> {
       buf.push(b);
       buf.push(a);
     }
+    ServerPacket::ChatMessage {
+      channel,
+      sender_id,
+      sender_name,
+      message,
+    } => {
+      write_u16(buf, ServerPacketId::ChatMessage as u16);
+      buf.push(channel.as_byte());
+      write_string_u16(buf, sender_id);
+      write_string_u8(buf, sender_name);
+      write_string_u16(buf, message);
+    }
+    ServerPacket::SpectatePvp => {
+      write_u16(buf, ServerPacketId::SpectatePvp as u16);
+    }
+    ServerPacket::EndSpectating => {
+      write_u16(buf, ServerPacketId::EndSpectating as u16);
+    }
+    ServerPacket::PvpSpectatorJoin => {
+      write_u16(buf, ServerPacketId::PvpSpectatorJoin as u16);
+    }
+    ServerPacket::PvpSpectatorLeave => {
+      write_u16(buf, ServerPacketId::PvpSpectatorLeave as u16);
+    }
+    ServerPacket::PvpSpectatorData { side, data } => {
+      write_u16(buf, ServerPacketId::PvpSpectatorData as u16);
+      buf.push(side);
+      write_data(buf, data);
+    }
   }
 
   vec
@@ -1,4 +1,4 @@
-use super::lua_errors::create_area_error;
+use super::lua_errors::{create_actor_error, create_area_error};
 use super::LuaApi;
 use crate::net::map::{Map, Tile};
 use crate::net::Direction;
@@ -663,6 +663,55 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
     }
   });
 
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_actors_in_radius",
+    |api_ctx, lua_ctx, params| {
+      let (area_id, x, y, z, radius): (mlua::String, f32, f32, f32, f32) =
+        lua_ctx.unpack_multi(params)?;
+      let area_id_str = area_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      if net.get_area(area_id_str).is_none() {
+        return Err(create_area_error(area_id_str));
+      }
+
+      lua_ctx.pack_multi(net.actors_in_radius(area_id_str, x, y, z, radius))
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_actor_proximity_radius",
+    |api_ctx, lua_ctx, params| {
+      let (actor_id, radius): (mlua::String, Option<f32>) = lua_ctx.unpack_multi(params)?;
+      let actor_id_str = actor_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      if net.get_player(actor_id_str).is_none() && net.get_bot(actor_id_str).is_none() {
+        return Err(create_actor_error(actor_id_str));
+      }
+
+      net.set_actor_proximity_radius(actor_id_str, radius);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_actor_proximity_radius",
+    |api_ctx, lua_ctx, params| {
+      let actor_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      lua_ctx.pack_multi(net.get_actor_proximity_radius(actor_id.to_str()?))
+    },
+  );
+
   lua_api.add_dynamic_function("Net", "provide_asset", |api_ctx, lua_ctx, params| {
     let (area_id, asset_path): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
 
@@ -283,6 +283,48 @@ Net:on("battle_results", function(event)
   battle_trackers[player_id]:resolve(event)
 end)
 
+local coop_battle_resolvers = {}
+
+-- each player's results are skipped by the battle trackers, the group's results resolve instead
+local function initiate_coop_encounter(player_ids, ...)
+  for _, player_id in ipairs(player_ids) do
+    local tracker = battle_trackers[player_id]
+
+    if tracker ~= nil then
+      tracker:increment_count()
+    end
+  end
+
+  return Net._delegate("Net._initiate_coop_encounter", player_ids, ...)
+end
+
+function Async.initiate_coop_encounter(player_ids, ...)
+  local group_id = initiate_coop_encounter(player_ids, ...)
+
+  return Async.create_promise(function(resolve)
+    if group_id == nil then
+      -- every player has disconnected or never existed
+      resolve(nil)
+      return
+    end
+
+    coop_battle_resolvers[group_id] = resolve
+  end)
+end
+
+function Net.initiate_coop_encounter(player_ids, ...)
+  return initiate_coop_encounter(player_ids, ...)
+end
+
+Net:on("battle_results_group", function(event)
+  local resolve = coop_battle_resolvers[event.group_id]
+
+  if resolve ~= nil then
+    coop_battle_resolvers[event.group_id] = nil
+    resolve(event)
+  end
+end)
+
 -- shops
 
 local shop_emitters = {}
@@ -315,10 +357,33 @@ function Net.open_shop(player_id, ...)
   return emitter
 end
 
+function Net.open_managed_shop(player_id, ...)
+  local emitters = shop_emitters[player_id]
+
+  if not emitters then
+    -- player must have disconnected
+    return
+  end
+
+  Net._delegate("Net._open_managed_shop", player_id, ...)
+
+  local emitter = Net.EventEmitter.new()
+  emitters[#emitters+1] = emitter
+  return emitter
+end
+
 Net:on("shop_purchase", function(event)
   shop_emitters[event.player_id][1]:emit("shop_purchase", event)
 end)
 
+Net:on("shop_purchase_complete", function(event)
+  local emitter = shop_emitters[event.player_id][1]
+
+  if emitter then
+    emitter:emit("shop_purchase_complete", event)
+  end
+end)
+
 Net:on("shop_close", function(event)
   local emitter = table.remove(shop_emitters[event.player_id], 1)
   emitter:emit("shop_close", event)
@@ -358,6 +423,23 @@ function Net.open_board(player_id, ...)
   return emitter
 end
 
+-- leaderboard boards request more posts internally, post_request is not emitted for them
+function Net.open_leaderboard(player_id, ...)
+  local emitters = bbs_emitters[player_id]
+
+  if not emitters then
+    -- player must have disconnected
+    return
+  end
+
+  Net.close_bbs(player_id)
+  Net._delegate("Net._open_leaderboard", player_id, ...)
+
+  local emitter = Net.EventEmitter.new()
+  emitters[#emitters+1] = emitter
+  return emitter
+end
+
 Net:on("post_request", function(event)
   bbs_emitters[event.player_id][1]:emit("post_request", event)
 end)
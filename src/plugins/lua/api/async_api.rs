@@ -1,6 +1,5 @@
-use super::LuaApi;
-use crate::jobs::{JobPromise, JobPromiseManager, PromiseValue};
-use std::cell::RefCell;
+use super::{ApiContext, LuaApi};
+use crate::jobs::{JobPromise, PromiseValue};
 
 pub fn inject_static(lua_api: &mut LuaApi) {
   lua_api.add_global_table("Async");
@@ -124,7 +123,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let promise = web_request(url, method, headers, body);
 
-    let lua_promise = create_lua_promise(&lua_ctx, api_ctx.promise_manager_ref, promise);
+    let lua_promise = create_lua_promise(&lua_ctx, api_ctx, promise);
     lua_ctx.pack_multi(lua_promise)
   });
 
@@ -170,7 +169,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let promise = web_download(path, url, method, headers, body);
 
-    let lua_promise = create_lua_promise(&lua_ctx, api_ctx.promise_manager_ref, promise);
+    let lua_promise = create_lua_promise(&lua_ctx, api_ctx, promise);
 
     lua_ctx.pack_multi(lua_promise)
   });
@@ -182,7 +181,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let promise = read_file(path);
 
-    let lua_promise = create_lua_promise(&lua_ctx, api_ctx.promise_manager_ref, promise);
+    let lua_promise = create_lua_promise(&lua_ctx, api_ctx, promise);
 
     lua_ctx.pack_multi(lua_promise)
   });
@@ -194,7 +193,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let promise = write_file(path, content.as_bytes());
 
-    let lua_promise = create_lua_promise(&lua_ctx, api_ctx.promise_manager_ref, promise);
+    let lua_promise = create_lua_promise(&lua_ctx, api_ctx, promise);
 
     lua_ctx.pack_multi(lua_promise)
   });
@@ -206,7 +205,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let promise = poll_server(address, port);
 
-    let lua_promise = create_lua_promise(&lua_ctx, api_ctx.promise_manager_ref, promise);
+    let lua_promise = create_lua_promise(&lua_ctx, api_ctx, promise);
 
     lua_ctx.pack_multi(lua_promise)
   });
@@ -224,11 +223,11 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
 fn create_lua_promise<'a>(
   lua_ctx: &'a mlua::Lua,
-  promise_manager_ref: &RefCell<&mut JobPromiseManager>,
+  api_ctx: &ApiContext,
   promise: JobPromise,
 ) -> mlua::Result<mlua::Table<'a>> {
-  let mut promise_manager = promise_manager_ref.borrow_mut();
-  let id = promise_manager.add_promise(promise);
+  let mut promise_manager = api_ctx.promise_manager_ref.borrow_mut();
+  let id = promise_manager.add_promise(api_ctx.script_index, promise);
 
   let async_api: mlua::Table = lua_ctx.globals().get("Async")?;
   let create_promise: mlua::Function = async_api.get("_promise_from_id")?;
@@ -0,0 +1,82 @@
+use super::lua_errors::create_player_error;
+use super::lua_helpers::*;
+use super::LuaApi;
+use crate::net::BanTarget;
+
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "ban_player", |api_ctx, lua_ctx, params| {
+    let (player_id, reason, duration, ban_ip): (
+      mlua::String,
+      Option<mlua::String>,
+      Option<f32>,
+      Option<bool>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+    let reason_str = optional_lua_string_to_str(&reason)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if net.get_player(player_id_str).is_none() {
+      return Err(create_player_error(player_id_str));
+    }
+
+    net.ban_player(
+      player_id_str,
+      reason_str,
+      duration,
+      ban_ip.unwrap_or_default(),
+    );
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "ban_address", |api_ctx, lua_ctx, params| {
+    let (address_range, reason, duration): (mlua::String, Option<mlua::String>, Option<f32>) =
+      lua_ctx.unpack_multi(params)?;
+    let address_range_str = address_range.to_str()?;
+    let reason_str = optional_lua_string_to_str(&reason)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if !net.ban_address(address_range_str, reason_str, duration) {
+      return Err(mlua::Error::RuntimeError(format!(
+        "Invalid address range \"{}\".",
+        address_range_str
+      )));
+    }
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "unban", |api_ctx, lua_ctx, params| {
+    let target: mlua::String = lua_ctx.unpack_multi(params)?;
+    let target_str = target.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    lua_ctx.pack_multi(net.unban(target_str))
+  });
+
+  lua_api.add_dynamic_function("Net", "list_bans", |api_ctx, lua_ctx, _| {
+    let net = api_ctx.net_ref.borrow();
+
+    let bans_table = lua_ctx.create_table()?;
+
+    for (i, ban) in net.get_bans().enumerate() {
+      let ban_type = match &ban.target {
+        BanTarget::AddressRange { .. } => "address",
+        BanTarget::Identity(_) => "identity",
+      };
+
+      let ban_table = lua_ctx.create_table()?;
+      ban_table.set("type", ban_type)?;
+      ban_table.set("target", ban.target.to_string())?;
+      ban_table.set("reason", ban.reason.as_str())?;
+      ban_table.set("expiration", ban.expiration)?;
+
+      bans_table.set(i + 1, ban_table)?;
+    }
+
+    lua_ctx.pack_multi(bans_table)
+  });
+}
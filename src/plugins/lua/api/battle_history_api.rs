@@ -0,0 +1,112 @@
+use super::LuaApi;
+use crate::net::BattleRecord;
+
+const DEFAULT_RECORD_COUNT: usize = 10;
+
+// battle history is stored by identity, Net.get_player_secret(player_id) provides the identity
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "list_recent_battles", |api_ctx, lua_ctx, params| {
+    let (secret, count): (mlua::String, Option<usize>) = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let records = net.get_recent_battles(secret.to_str()?, count.unwrap_or(DEFAULT_RECORD_COUNT));
+
+    lua_ctx.pack_multi(map_records_to_tables(lua_ctx, &records)?)
+  });
+
+  lua_api.add_dynamic_function("Net", "get_battle_summary", |api_ctx, lua_ctx, params| {
+    let secret: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let summary = net.get_battle_summary(secret.to_str()?);
+
+    let table = lua_ctx.create_table()?;
+    table.set("wins", summary.wins)?;
+    table.set("losses", summary.losses)?;
+    table.set("escapes", summary.escapes)?;
+
+    lua_ctx.pack_multi(table)
+  });
+
+  lua_api.add_dynamic_function("Net", "get_best_battle", |api_ctx, lua_ctx, params| {
+    let (secret, package_path): (mlua::String, Option<mlua::String>) =
+      lua_ctx.unpack_multi(params)?;
+    let package_path_str = package_path
+      .as_ref()
+      .map(|path| path.to_str())
+      .transpose()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let record = net.get_best_battle(secret.to_str()?, package_path_str);
+
+    let table = match record {
+      Some(record) => Some(map_record_to_table(lua_ctx, &record)?),
+      None => None,
+    };
+
+    lua_ctx.pack_multi(table)
+  });
+
+  lua_api.add_dynamic_function("Net", "list_best_battles", |api_ctx, lua_ctx, params| {
+    let (package_path, count): (Option<mlua::String>, Option<usize>) =
+      lua_ctx.unpack_multi(params)?;
+    let package_path_str = package_path
+      .as_ref()
+      .map(|path| path.to_str())
+      .transpose()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let records = net.get_best_battles(package_path_str, count.unwrap_or(DEFAULT_RECORD_COUNT));
+
+    lua_ctx.pack_multi(map_records_to_tables(lua_ctx, &records)?)
+  });
+}
+
+fn map_records_to_tables<'lua>(
+  lua_ctx: &'lua mlua::Lua,
+  records: &[BattleRecord],
+) -> mlua::Result<Vec<mlua::Table<'lua>>> {
+  records
+    .iter()
+    .map(|record| map_record_to_table(lua_ctx, record))
+    .collect()
+}
+
+fn map_record_to_table<'lua>(
+  lua_ctx: &'lua mlua::Lua,
+  record: &BattleRecord,
+) -> mlua::Result<mlua::Table<'lua>> {
+  let stats = &record.stats;
+
+  let table = lua_ctx.create_table()?;
+  table.set("secret", record.identity.as_str())?;
+  table.set("name", record.name.as_str())?;
+  table.set("package_path", record.package_path.as_deref())?;
+  table.set("opponent_secret", record.opponent.as_deref())?;
+  table.set("started_at", record.started_at)?;
+  table.set("ended_at", record.ended_at)?;
+  table.set("outcome", record.outcome.as_str())?;
+  table.set("health", stats.health)?;
+  table.set("score", stats.score)?;
+  table.set("time", stats.time)?;
+  table.set("ran", stats.ran)?;
+  table.set("emotion", stats.emotion)?;
+  table.set("turns", stats.turns)?;
+
+  let mut enemy_tables = Vec::with_capacity(stats.enemies.len());
+
+  for enemy in &stats.enemies {
+    let enemy_table = lua_ctx.create_table()?;
+    enemy_table.set("id", enemy.id.as_str())?;
+    enemy_table.set("health", enemy.health)?;
+    enemy_tables.push(enemy_table);
+  }
+
+  table.set("enemies", enemy_tables)?;
+
+  Ok(table)
+}
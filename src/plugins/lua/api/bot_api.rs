@@ -206,11 +206,53 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let mut net = api_ctx.net_ref.borrow_mut();
 
+    net.cancel_bot_path(bot_id_str);
     net.move_bot(bot_id_str, x, y, z);
 
     lua_ctx.pack_multi(())
   });
 
+  lua_api.add_dynamic_function("Net", "path_bot_to", |api_ctx, lua_ctx, params| {
+    let (bot_id, x, y, z, speed): (mlua::String, f32, f32, f32, f32) =
+      lua_ctx.unpack_multi(params)?;
+    let bot_id_str = bot_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if net.get_bot(bot_id_str).is_none() {
+      return Err(create_bot_error(bot_id_str));
+    }
+
+    if !(speed > 0.0 && speed.is_finite()) {
+      return Err(mlua::Error::RuntimeError(format!(
+        "speed must be a positive number, received {}",
+        speed
+      )));
+    }
+
+    lua_ctx.pack_multi(net.path_bot_to(bot_id_str, x, y, z, speed))
+  });
+
+  lua_api.add_dynamic_function("Net", "cancel_bot_path", |api_ctx, lua_ctx, params| {
+    let bot_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let bot_id_str = bot_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net.cancel_bot_path(bot_id_str);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "is_bot_pathing", |api_ctx, lua_ctx, params| {
+    let bot_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let bot_id_str = bot_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.is_bot_pathing(bot_id_str))
+  });
+
   lua_api.add_dynamic_function("Net", "animate_bot", |api_ctx, lua_ctx, params| {
     let (bot_id, name, loop_option): (mlua::String, mlua::String, Option<bool>) =
       lua_ctx.unpack_multi(params)?;
@@ -0,0 +1,110 @@
+use super::lua_errors::ensure_player;
+use super::LuaApi;
+use crate::net::ChatChannel;
+
+// chat_message events can be prevented, scripts can then resend messages with these functions
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "send_chat_message", |api_ctx, lua_ctx, params| {
+    let (sender_id, channel, message, target_id): (
+      mlua::String,
+      mlua::String,
+      mlua::String,
+      Option<mlua::String>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let (sender_id_str, channel_str) = (sender_id.to_str()?, channel.to_str()?);
+
+    let channel = match ChatChannel::parse(channel_str) {
+      Some(ChatChannel::System) | None => {
+        return Err(mlua::Error::RuntimeError(format!(
+          "Invalid chat channel \"{}\"",
+          channel_str
+        )));
+      }
+      Some(channel) => channel,
+    };
+
+    let target_id_str = match &target_id {
+      Some(target_id) => Some(target_id.to_str()?),
+      None => None,
+    };
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, sender_id_str)?;
+
+    if channel == ChatChannel::Whisper {
+      let target_id_str = target_id_str
+        .ok_or_else(|| mlua::Error::RuntimeError(String::from("Whispers require a target_id")))?;
+
+      ensure_player(&net, target_id_str)?;
+    }
+
+    net.send_chat_message(sender_id_str, channel, target_id_str, message.to_str()?);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "send_system_chat_message",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, message, sender_name): (mlua::String, mlua::String, Option<mlua::String>) =
+        lua_ctx.unpack_multi(params)?;
+
+      let sender_name_str = match &sender_name {
+        Some(sender_name) => sender_name.to_str()?,
+        None => "",
+      };
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.send_system_chat_message(player_id.to_str()?, sender_name_str, message.to_str()?);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "find_player_by_name", |api_ctx, lua_ctx, params| {
+    let name: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.find_player_by_name(name.to_str()?))
+  });
+
+  lua_api.add_dynamic_function("Net", "mute_player", |api_ctx, lua_ctx, params| {
+    let (player_id, target_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, target_id_str) = (player_id.to_str()?, target_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+    ensure_player(&net, target_id_str)?;
+
+    net.mute_player(player_id_str, target_id_str);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "unmute_player", |api_ctx, lua_ctx, params| {
+    let (player_id, target_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, target_id_str) = (player_id.to_str()?, target_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+    ensure_player(&net, target_id_str)?;
+
+    net.unmute_player(player_id_str, target_id_str);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "is_player_muted", |api_ctx, lua_ctx, params| {
+    let (player_id, target_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.is_player_muted(player_id.to_str()?, target_id.to_str()?))
+  });
+}
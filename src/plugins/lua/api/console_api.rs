@@ -0,0 +1,45 @@
+use super::lua_helpers::*;
+use super::LuaApi;
+
+const CONSOLE_COMMANDS_KEY: &str = "console_commands";
+
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function(
+    "Net",
+    "register_console_command",
+    |api_ctx, lua_ctx, params| {
+      let (name, help): (mlua::String, Option<mlua::String>) = lua_ctx.unpack_multi(params)?;
+      let name_str = name.to_str()?;
+      let help_str = optional_lua_string_to_str(&help)?;
+
+      if name_str.is_empty() || name_str.contains(char::is_whitespace) {
+        return Err(mlua::Error::RuntimeError(format!(
+          "Invalid console command name \"{}\".",
+          name_str
+        )));
+      }
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.register_console_command(name_str.to_string(), help_str.to_string());
+
+      // remembered by the script's state so the command is only sent to this script
+      let console_commands = get_console_commands_table(lua_ctx)?;
+      console_commands.set(name_str, true)?;
+
+      lua_ctx.pack_multi(())
+    },
+  );
+}
+
+/// Commands registered by the script owning this state
+pub fn get_console_commands_table(lua_ctx: &mlua::Lua) -> mlua::Result<mlua::Table<'_>> {
+  if let Ok(table) = lua_ctx.named_registry_value(CONSOLE_COMMANDS_KEY) {
+    return Ok(table);
+  }
+
+  let table = lua_ctx.create_table()?;
+  lua_ctx.set_named_registry_value(CONSOLE_COMMANDS_KEY, table.clone())?;
+
+  Ok(table)
+}
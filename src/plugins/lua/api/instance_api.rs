@@ -0,0 +1,173 @@
+use super::lua_errors::{create_area_error, create_player_error};
+use super::LuaApi;
+use crate::net::Direction;
+
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "create_instance", |api_ctx, lua_ctx, params| {
+    let (template_id, party_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (template_id_str, party_id_str) = (template_id.to_str()?, party_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if let Some(area_id) = net.create_instance(template_id_str, party_id_str) {
+      lua_ctx.pack_multi(area_id)
+    } else {
+      Err(create_area_error(template_id_str))
+    }
+  });
+
+  lua_api.add_dynamic_function("Net", "get_instance", |api_ctx, lua_ctx, params| {
+    let (template_id, party_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (template_id_str, party_id_str) = (template_id.to_str()?, party_id.to_str()?);
+
+    let net = api_ctx.net_ref.borrow();
+
+    let area_id = net
+      .find_instance(template_id_str, party_id_str)
+      .map(|instance| instance.get_area_id().to_string());
+
+    lua_ctx.pack_multi(area_id)
+  });
+
+  lua_api.add_dynamic_function("Net", "is_instance", |api_ctx, lua_ctx, params| {
+    let area_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let area_id_str = area_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.get_instance(area_id_str).is_some())
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_instance_template",
+    |api_ctx, lua_ctx, params| {
+      let area_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let area_id_str = area_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      if let Some(instance) = net.get_instance(area_id_str) {
+        lua_ctx.pack_multi(instance.get_template_id())
+      } else {
+        Err(create_area_error(area_id_str))
+      }
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "get_instance_party", |api_ctx, lua_ctx, params| {
+    let area_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let area_id_str = area_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    if let Some(instance) = net.get_instance(area_id_str) {
+      lua_ctx.pack_multi(instance.get_party_id())
+    } else {
+      Err(create_area_error(area_id_str))
+    }
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_instance_members",
+    |api_ctx, lua_ctx, params| {
+      let area_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let area_id_str = area_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      if let Some(instance) = net.get_instance(area_id_str) {
+        let members: Vec<&str> = instance
+          .get_members()
+          .iter()
+          .map(|id| id.as_str())
+          .collect();
+
+        lua_ctx.pack_multi(members)
+      } else {
+        Err(create_area_error(area_id_str))
+      }
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "transfer_player_to_instance",
+    |api_ctx, lua_ctx, params| {
+      #[allow(clippy::type_complexity)]
+      let (
+        player_id,
+        template_id,
+        party_id,
+        warp_in_option,
+        x_option,
+        y_option,
+        z_option,
+        direction_option,
+      ): (
+        mlua::String,
+        mlua::String,
+        mlua::String,
+        Option<bool>,
+        Option<f32>,
+        Option<f32>,
+        Option<f32>,
+        Option<mlua::String>,
+      ) = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+      let (template_id_str, party_id_str) = (template_id.to_str()?, party_id.to_str()?);
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      if net.get_player(player_id_str).is_none() {
+        return Err(create_player_error(player_id_str));
+      }
+
+      // defaults to the template's spawn, as the player is entering a fresh copy
+      let (spawn_x, spawn_y, spawn_z, spawn_direction) = match net.get_area(template_id_str) {
+        Some(area) => {
+          let map = area.get_map();
+          let (x, y, z) = map.get_spawn();
+
+          (x, y, z, map.get_spawn_direction())
+        }
+        None => {
+          // the template may have been removed after the instance was created
+          match net.find_instance(template_id_str, party_id_str) {
+            Some(instance) => {
+              let map = net.get_area(instance.get_area_id()).unwrap().get_map();
+              let (x, y, z) = map.get_spawn();
+
+              (x, y, z, map.get_spawn_direction())
+            }
+            None => return Err(create_area_error(template_id_str)),
+          }
+        }
+      };
+
+      let warp_in = warp_in_option.unwrap_or(true);
+      let x = x_option.unwrap_or(spawn_x);
+      let y = y_option.unwrap_or(spawn_y);
+      let z = z_option.unwrap_or(spawn_z);
+
+      let direction = match direction_option {
+        Some(direction) => Direction::from(direction.to_str()?),
+        None => spawn_direction,
+      };
+
+      let area_id = net.transfer_player_to_instance(
+        player_id_str,
+        template_id_str,
+        party_id_str,
+        warp_in,
+        x,
+        y,
+        z,
+        direction,
+      );
+
+      lua_ctx.pack_multi(area_id)
+    },
+  );
+}
@@ -0,0 +1,284 @@
+use super::lua_errors::create_player_error;
+use super::LuaApi;
+use crate::net::{LeaderboardEntry, ScoreType, SortOrder};
+
+const DEFAULT_ENTRY_COUNT: usize = 10;
+const DEFAULT_PAGE_SIZE: usize = 20;
+const DEFAULT_BOARD_COLOR: (u8, u8, u8) = (64, 96, 184);
+
+// scores are stored by identity, Net.get_player_secret(player_id) provides the identity
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "create_leaderboard", |api_ctx, lua_ctx, params| {
+    let (name, options): (mlua::String, Option<mlua::Table>) = lua_ctx.unpack_multi(params)?;
+
+    let mut sort_order = SortOrder::Descending;
+    let mut score_type = ScoreType::Integer;
+
+    if let Some(options) = options {
+      if let Some(text) = options.get::<_, Option<String>>("sort_order")? {
+        sort_order = SortOrder::parse(&text)
+          .ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid sort_order \"{}\"", text)))?;
+      }
+
+      if let Some(text) = options.get::<_, Option<String>>("score_type")? {
+        score_type = ScoreType::parse(&text)
+          .ok_or_else(|| mlua::Error::RuntimeError(format!("Invalid score_type \"{}\"", text)))?;
+      }
+    }
+
+    let name_str = name.to_str()?;
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if !net.create_leaderboard(name_str, sort_order, score_type) {
+      return Err(mlua::Error::RuntimeError(format!(
+        "Invalid leaderboard name \"{}\"",
+        name_str
+      )));
+    }
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "remove_leaderboard", |api_ctx, lua_ctx, params| {
+    let name: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    lua_ctx.pack_multi(net.remove_leaderboard(name.to_str()?))
+  });
+
+  lua_api.add_dynamic_function("Net", "has_leaderboard", |api_ctx, lua_ctx, params| {
+    let name: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.has_leaderboard(name.to_str()?))
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_leaderboard_settings",
+    |api_ctx, lua_ctx, params| {
+      let name: mlua::String = lua_ctx.unpack_multi(params)?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      let table = match net.get_leaderboard_settings(name.to_str()?) {
+        Some((sort_order, score_type)) => {
+          let table = lua_ctx.create_table()?;
+          table.set("sort_order", sort_order.as_str())?;
+          table.set("score_type", score_type.as_str())?;
+          Some(table)
+        }
+        None => None,
+      };
+
+      lua_ctx.pack_multi(table)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "submit_leaderboard_score",
+    |api_ctx, lua_ctx, params| {
+      let (board_name, player_id, score): (mlua::String, mlua::String, f64) =
+        lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      let (identity, name) = get_identity_and_name(&net, player_id_str)?;
+
+      let rank = net.submit_leaderboard_score(board_name.to_str()?, &identity, &name, score);
+
+      lua_ctx.pack_multi(rank)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_leaderboard_score",
+    |api_ctx, lua_ctx, params| {
+      let (board_name, player_id, score): (mlua::String, mlua::String, f64) =
+        lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      let (identity, name) = get_identity_and_name(&net, player_id_str)?;
+
+      let rank = net.set_leaderboard_score(board_name.to_str()?, &identity, &name, score);
+
+      lua_ctx.pack_multi(rank)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "remove_leaderboard_score",
+    |api_ctx, lua_ctx, params| {
+      let (board_name, secret): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.remove_leaderboard_score(board_name.to_str()?, secret.to_str()?);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "get_leaderboard_rank", |api_ctx, lua_ctx, params| {
+    let (board_name, secret): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.get_leaderboard_rank(board_name.to_str()?, secret.to_str()?))
+  });
+
+  lua_api.add_dynamic_function("Net", "get_leaderboard_size", |api_ctx, lua_ctx, params| {
+    let board_name: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.get_leaderboard_size(board_name.to_str()?))
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_leaderboard_entries",
+    |api_ctx, lua_ctx, params| {
+      let (board_name, start_rank, count): (mlua::String, Option<usize>, Option<usize>) =
+        lua_ctx.unpack_multi(params)?;
+      let offset = start_rank.unwrap_or(1).saturating_sub(1);
+
+      let net = api_ctx.net_ref.borrow();
+
+      let entries = net.get_leaderboard_entries(
+        board_name.to_str()?,
+        offset,
+        count.unwrap_or(DEFAULT_ENTRY_COUNT),
+      );
+
+      let entry_tables = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| map_entry_to_table(lua_ctx, entry, offset + i + 1))
+        .collect::<mlua::Result<Vec<_>>>()?;
+
+      lua_ctx.pack_multi(entry_tables)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_leaderboard_posts",
+    |api_ctx, lua_ctx, params| {
+      let (board_name, start_rank, count, player_id): (
+        mlua::String,
+        Option<usize>,
+        Option<usize>,
+        Option<mlua::String>,
+      ) = lua_ctx.unpack_multi(params)?;
+      let offset = start_rank.unwrap_or(1).saturating_sub(1);
+
+      let net = api_ctx.net_ref.borrow();
+
+      let viewer_identity = match &player_id {
+        Some(player_id) => Some(get_identity_and_name(&net, player_id.to_str()?)?.0),
+        None => None,
+      };
+
+      let posts = net.get_leaderboard_posts(
+        board_name.to_str()?,
+        viewer_identity.as_deref(),
+        offset,
+        count.unwrap_or(DEFAULT_ENTRY_COUNT),
+      );
+
+      let mut post_tables = Vec::with_capacity(posts.len());
+
+      for post in posts {
+        let post_table = lua_ctx.create_table()?;
+        post_table.set("id", post.id)?;
+        post_table.set("read", post.read)?;
+        post_table.set("title", post.title)?;
+        post_table.set("author", post.author)?;
+        post_tables.push(post_table);
+      }
+
+      lua_ctx.pack_multi(post_tables)
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "_open_leaderboard", |api_ctx, lua_ctx, params| {
+    let (player_id, board_name, title, color_table, page_size): (
+      mlua::String,
+      mlua::String,
+      Option<mlua::String>,
+      Option<mlua::Table>,
+      Option<usize>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+    let board_name_str = board_name.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if let Some(tracker) = api_ctx
+      .widget_tracker_ref
+      .borrow_mut()
+      .get_mut(player_id_str)
+    {
+      tracker.track_board(Some(api_ctx.script_index));
+
+      let color = match color_table {
+        Some(color_table) => (
+          color_table.get("r")?,
+          color_table.get("g")?,
+          color_table.get("b")?,
+        ),
+        None => DEFAULT_BOARD_COLOR,
+      };
+
+      let title_str = match &title {
+        Some(title) => title.to_str()?,
+        None => board_name_str,
+      };
+
+      net.open_leaderboard(
+        player_id_str,
+        board_name_str,
+        title_str,
+        color,
+        page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+      );
+    }
+
+    lua_ctx.pack_multi(())
+  });
+}
+
+fn get_identity_and_name(net: &crate::net::Net, player_id: &str) -> mlua::Result<(String, String)> {
+  let player_data = net
+    .get_player_data(player_id)
+    .ok_or_else(|| create_player_error(player_id))?;
+  let player = net
+    .get_player(player_id)
+    .ok_or_else(|| create_player_error(player_id))?;
+
+  Ok((player_data.identity.clone(), player.name.clone()))
+}
+
+fn map_entry_to_table<'lua>(
+  lua_ctx: &'lua mlua::Lua,
+  entry: &LeaderboardEntry,
+  rank: usize,
+) -> mlua::Result<mlua::Table<'lua>> {
+  let table = lua_ctx.create_table()?;
+  table.set("rank", rank)?;
+  table.set("secret", entry.identity.as_str())?;
+  table.set("name", entry.name.as_str())?;
+  table.set("score", entry.score)?;
+  table.set("updated_at", entry.updated_at)?;
+
+  Ok(table)
+}
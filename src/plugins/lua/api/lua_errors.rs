@@ -1,7 +1,13 @@
+use crate::net::Net;
+
 pub fn create_area_error(id: &str) -> mlua::Error {
   mlua::Error::RuntimeError(format!("No area matching \"{}\" found.", id))
 }
 
+pub fn create_actor_error(id: &str) -> mlua::Error {
+  mlua::Error::RuntimeError(format!("No actor matching \"{}\" found.", id))
+}
+
 pub fn create_bot_error(id: &str) -> mlua::Error {
   mlua::Error::RuntimeError(format!("No bot matching \"{}\" found.", id))
 }
@@ -9,3 +15,38 @@ pub fn create_bot_error(id: &str) -> mlua::Error {
 pub fn create_player_error(id: &str) -> mlua::Error {
   mlua::Error::RuntimeError(format!("No player matching \"{}\" found.", id))
 }
+
+pub fn create_item_error(id: &str) -> mlua::Error {
+  mlua::Error::RuntimeError(format!("No item matching \"{}\" found.", id))
+}
+
+pub fn create_shop_error(id: &str) -> mlua::Error {
+  mlua::Error::RuntimeError(format!("No shop matching \"{}\" found.", id))
+}
+
+pub fn create_party_error(id: &str) -> mlua::Error {
+  mlua::Error::RuntimeError(format!("No party matching \"{}\" found.", id))
+}
+
+pub fn create_quest_error(id: &str) -> mlua::Error {
+  mlua::Error::RuntimeError(format!("No quest matching \"{}\" found.", id))
+}
+
+pub fn ensure_player(net: &Net, player_id: &str) -> mlua::Result<()> {
+  if net.get_player(player_id).is_none() {
+    return Err(create_player_error(player_id));
+  }
+
+  Ok(())
+}
+
+/// true, or false and the error as a reason
+pub fn pack_result<'lua, E: std::fmt::Display>(
+  lua_ctx: &'lua mlua::Lua,
+  result: Result<(), E>,
+) -> mlua::Result<mlua::MultiValue<'lua>> {
+  match result {
+    Ok(()) => lua_ctx.pack_multi(true),
+    Err(err) => lua_ctx.pack_multi((false, err.to_string())),
+  }
+}
@@ -0,0 +1,171 @@
+use super::lua_errors::{ensure_player, pack_result};
+use super::LuaApi;
+use crate::net::MatchmakingRules;
+
+// matched players are sent into PvP automatically, scripts hear about it through the matchmaking_match event
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function(
+    "Net",
+    "create_matchmaking_queue",
+    |api_ctx, lua_ctx, params| {
+      let (queue_id, rules_table): (mlua::String, Option<mlua::Table>) =
+        lua_ctx.unpack_multi(params)?;
+      let queue_id_str = queue_id.to_str()?;
+
+      let mut rules = MatchmakingRules::default();
+
+      if let Some(rules_table) = rules_table {
+        if let Some(rating_window) = rules_table.get("rating_window")? {
+          rules.rating_window = rating_window;
+        }
+
+        if let Some(rating_window_growth) = rules_table.get("rating_window_growth")? {
+          rules.rating_window_growth = rating_window_growth;
+        }
+
+        rules.max_rating_window = rules_table.get("max_rating_window")?;
+
+        if let Some(same_area) = rules_table.get("same_area")? {
+          rules.same_area = same_area;
+        }
+
+        if let Some(k_factor) = rules_table.get("k_factor")? {
+          rules.k_factor = k_factor;
+        }
+
+        let values = [
+          rules.rating_window,
+          rules.rating_window_growth,
+          rules.max_rating_window.unwrap_or_default(),
+          rules.k_factor,
+        ];
+
+        if values.iter().any(|value| !value.is_finite()) {
+          return Err(mlua::Error::RuntimeError(String::from(
+            "Matchmaking rules must be finite numbers",
+          )));
+        }
+      }
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.create_matchmaking_queue(queue_id_str, rules);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "remove_matchmaking_queue",
+    |api_ctx, lua_ctx, params| {
+      let queue_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let queue_id_str = queue_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.remove_matchmaking_queue(queue_id_str);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "is_matchmaking_queue", |api_ctx, lua_ctx, params| {
+    let queue_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let queue_id_str = queue_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.is_matchmaking_queue(queue_id_str))
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "join_matchmaking_queue",
+    |api_ctx, lua_ctx, params| {
+      let (queue_id, player_id, rating): (mlua::String, mlua::String, Option<f32>) =
+        lua_ctx.unpack_multi(params)?;
+      let (queue_id_str, player_id_str) = (queue_id.to_str()?, player_id.to_str()?);
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      ensure_player(&net, player_id_str)?;
+
+      let result = net.join_matchmaking_queue(queue_id_str, player_id_str, rating);
+
+      pack_result(lua_ctx, result)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "leave_matchmaking_queue",
+    |api_ctx, lua_ctx, params| {
+      let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.leave_matchmaking_queue(player_id_str);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_matchmaking_queue",
+    |api_ctx, lua_ctx, params| {
+      let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      lua_ctx.pack_multi(net.get_matchmaking_queue(player_id_str))
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_matchmaking_queue",
+    |api_ctx, lua_ctx, params| {
+      let queue_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let queue_id_str = queue_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      lua_ctx.pack_multi(net.list_matchmaking_queue(queue_id_str))
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "get_player_rating", |api_ctx, lua_ctx, params| {
+    let (player_id, queue_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, queue_id_str) = (player_id.to_str()?, queue_id.to_str()?);
+
+    let net = api_ctx.net_ref.borrow();
+
+    ensure_player(&net, player_id_str)?;
+
+    lua_ctx.pack_multi(net.get_player_rating(player_id_str, queue_id_str))
+  });
+
+  lua_api.add_dynamic_function("Net", "set_player_rating", |api_ctx, lua_ctx, params| {
+    let (player_id, queue_id, rating): (mlua::String, mlua::String, f32) =
+      lua_ctx.unpack_multi(params)?;
+    let (player_id_str, queue_id_str) = (player_id.to_str()?, queue_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    if !rating.is_finite() {
+      return Err(mlua::Error::RuntimeError(String::from(
+        "Ratings must be finite numbers",
+      )));
+    }
+
+    net.set_player_rating(player_id_str, queue_id_str, rating);
+
+    lua_ctx.pack_multi(())
+  });
+}
@@ -2,15 +2,29 @@ mod actor_property_animation;
 mod area_api;
 mod asset_api;
 mod async_api;
+mod ban_api;
+mod battle_history_api;
 mod bot_api;
+mod chat_api;
+mod console_api;
+mod instance_api;
+mod leaderboard_api;
 mod logging_api;
 mod lua_errors;
 mod lua_helpers;
+mod matchmaking_api;
 mod object_api;
+mod party_api;
 mod player_api;
 mod player_data_api;
+mod quest_api;
+mod shop_api;
+mod spectator_api;
+mod trade_api;
 mod widget_api;
 
+pub use console_api::get_console_commands_table;
+
 use crate::net::{Net, WidgetTracker};
 use std::cell::RefCell;
 
@@ -21,8 +35,9 @@ use std::collections::VecDeque;
 pub struct ApiContext<'lua_scope, 'a> {
   pub script_index: usize,
   pub net_ref: &'lua_scope RefCell<&'a mut Net>,
-  pub widget_tracker_ref: &'lua_scope RefCell<&'a mut HashMap<String, WidgetTracker<usize>>>,
-  pub battle_tracker_ref: &'lua_scope RefCell<&'a mut HashMap<String, VecDeque<usize>>>,
+  pub widget_tracker_ref:
+    &'lua_scope RefCell<&'a mut HashMap<String, WidgetTracker<Option<usize>>>>,
+  pub battle_tracker_ref: &'lua_scope RefCell<&'a mut HashMap<String, VecDeque<Option<usize>>>>,
   pub promise_manager_ref: &'lua_scope RefCell<&'a mut JobPromiseManager>,
 }
 
@@ -58,12 +73,24 @@ impl LuaApi {
     logging_api::inject_static(&mut lua_api);
 
     area_api::inject_dynamic(&mut lua_api);
+    instance_api::inject_dynamic(&mut lua_api);
     asset_api::inject_dynamic(&mut lua_api);
     object_api::inject_dynamic(&mut lua_api);
     player_api::inject_dynamic(&mut lua_api);
     player_data_api::inject_dynamic(&mut lua_api);
+    chat_api::inject_dynamic(&mut lua_api);
+    trade_api::inject_dynamic(&mut lua_api);
+    party_api::inject_dynamic(&mut lua_api);
+    spectator_api::inject_dynamic(&mut lua_api);
+    matchmaking_api::inject_dynamic(&mut lua_api);
+    quest_api::inject_dynamic(&mut lua_api);
     widget_api::inject_dynamic(&mut lua_api);
+    shop_api::inject_dynamic(&mut lua_api);
     bot_api::inject_dynamic(&mut lua_api);
+    ban_api::inject_dynamic(&mut lua_api);
+    battle_history_api::inject_dynamic(&mut lua_api);
+    leaderboard_api::inject_dynamic(&mut lua_api);
+    console_api::inject_dynamic(&mut lua_api);
 
     async_api::inject_static(&mut lua_api);
     async_api::inject_dynamic(&mut lua_api);
@@ -125,9 +152,10 @@ impl LuaApi {
           let net_table: mlua::Table = globals.get("Net")?;
           let func: mlua::Function = net_table.get("_delegate")?;
 
-          let value: mlua::Value = func.call((function_id.as_str(), values))?;
+          // keep every value, functions can return a result and a reason
+          let values: mlua::MultiValue = func.call((function_id.as_str(), values))?;
 
-          Ok(value)
+          Ok(values)
         })?,
       )?;
     }
@@ -54,6 +54,33 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
     }
   });
 
+  lua_api.add_dynamic_function("Net", "list_objects_at", |api_ctx, lua_ctx, params| {
+    let (area_id, x, y, z): (mlua::String, f32, f32, f32) = lua_ctx.unpack_multi(params)?;
+    let area_id_str = area_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    if net.get_area(area_id_str).is_none() {
+      return Err(create_area_error(area_id_str));
+    }
+
+    lua_ctx.pack_multi(net.objects_at(area_id_str, x, y, z))
+  });
+
+  lua_api.add_dynamic_function("Net", "list_objects_in_rect", |api_ctx, lua_ctx, params| {
+    let (area_id, x, y, z, width, height): (mlua::String, f32, f32, f32, f32, f32) =
+      lua_ctx.unpack_multi(params)?;
+    let area_id_str = area_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    if net.get_area(area_id_str).is_none() {
+      return Err(create_area_error(area_id_str));
+    }
+
+    lua_ctx.pack_multi(net.objects_in_rect(area_id_str, x, y, z, width, height))
+  });
+
   lua_api.add_dynamic_function("Net", "create_object", |api_ctx, lua_ctx, params| {
     let (area_id, table): (mlua::String, mlua::Table) = lua_ctx.unpack_multi(params)?;
     let area_id_str = area_id.to_str()?;
@@ -0,0 +1,234 @@
+use super::lua_errors::{create_area_error, create_party_error, ensure_player, pack_result};
+use super::LuaApi;
+use crate::net::Direction;
+
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "invite_to_party", |api_ctx, lua_ctx, params| {
+    let (player_id, recipient_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, recipient_id_str) = (player_id.to_str()?, recipient_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+    ensure_player(&net, recipient_id_str)?;
+
+    match net.invite_to_party(player_id_str, recipient_id_str) {
+      Ok(party_id) => lua_ctx.pack_multi(party_id),
+      Err(err) => lua_ctx.pack_multi((mlua::Nil, err.to_string())),
+    }
+  });
+
+  lua_api.add_dynamic_function("Net", "accept_party_invite", |api_ctx, lua_ctx, params| {
+    let (player_id, party_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.accept_party_invite(player_id_str, party_id.to_str()?);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "decline_party_invite", |api_ctx, lua_ctx, params| {
+    let (player_id, party_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net.decline_party_invite(player_id.to_str()?, party_id.to_str()?);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "leave_party", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net.leave_party(player_id.to_str()?);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "kick_from_party", |api_ctx, lua_ctx, params| {
+    let (leader_id, player_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    let result = net.kick_from_party(leader_id.to_str()?, player_id.to_str()?);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "set_party_leader", |api_ctx, lua_ctx, params| {
+    let (leader_id, player_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    let result = net.set_party_leader(leader_id.to_str()?, player_id.to_str()?);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "get_player_party", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.get_player_party(player_id.to_str()?))
+  });
+
+  lua_api.add_dynamic_function("Net", "get_party_leader", |api_ctx, lua_ctx, params| {
+    let party_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let party_id_str = party_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    match net.get_party_leader(party_id_str) {
+      Some(leader_id) => lua_ctx.pack_multi(leader_id),
+      None => Err(create_party_error(party_id_str)),
+    }
+  });
+
+  lua_api.add_dynamic_function("Net", "list_party_members", |api_ctx, lua_ctx, params| {
+    let party_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let party_id_str = party_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    match net.get_party_members(party_id_str) {
+      Some(members) => lua_ctx.pack_multi(members.to_vec()),
+      None => Err(create_party_error(party_id_str)),
+    }
+  });
+
+  lua_api.add_dynamic_function("Net", "list_party_invites", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let party_ids: Vec<&str> = net.get_party_invites(player_id.to_str()?).collect();
+
+    lua_ctx.pack_multi(party_ids)
+  });
+
+  lua_api.add_dynamic_function("Net", "transfer_party", |api_ctx, lua_ctx, params| {
+    #[allow(clippy::type_complexity)]
+    let (party_id, area_id, warp_in_option, x_option, y_option, z_option, direction_option): (
+      mlua::String,
+      mlua::String,
+      Option<bool>,
+      Option<f32>,
+      Option<f32>,
+      Option<f32>,
+      Option<mlua::String>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let (party_id_str, area_id_str) = (party_id.to_str()?, area_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if net.get_party_members(party_id_str).is_none() {
+      return Err(create_party_error(party_id_str));
+    }
+
+    // members may be spread out, so positions default to the area's spawn
+    let (spawn_x, spawn_y, spawn_z, spawn_direction) = match net.get_area(area_id_str) {
+      Some(area) => {
+        let map = area.get_map();
+        let (x, y, z) = map.get_spawn();
+
+        (x, y, z, map.get_spawn_direction())
+      }
+      None => return Err(create_area_error(area_id_str)),
+    };
+
+    let direction = match direction_option {
+      Some(direction) => Direction::from(direction.to_str()?),
+      None => spawn_direction,
+    };
+
+    net.transfer_party(
+      party_id_str,
+      area_id_str,
+      warp_in_option.unwrap_or(true),
+      x_option.unwrap_or(spawn_x),
+      y_option.unwrap_or(spawn_y),
+      z_option.unwrap_or(spawn_z),
+      direction,
+    );
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "transfer_party_to_instance",
+    |api_ctx, lua_ctx, params| {
+      #[allow(clippy::type_complexity)]
+      let (party_id, template_id, warp_in_option, x_option, y_option, z_option, direction_option): (
+        mlua::String,
+        mlua::String,
+        Option<bool>,
+        Option<f32>,
+        Option<f32>,
+        Option<f32>,
+        Option<mlua::String>,
+      ) = lua_ctx.unpack_multi(params)?;
+      let (party_id_str, template_id_str) = (party_id.to_str()?, template_id.to_str()?);
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      if net.get_party_members(party_id_str).is_none() {
+        return Err(create_party_error(party_id_str));
+      }
+
+      let (spawn_x, spawn_y, spawn_z, spawn_direction) = match net.get_area(template_id_str) {
+        Some(area) => {
+          let map = area.get_map();
+          let (x, y, z) = map.get_spawn();
+
+          (x, y, z, map.get_spawn_direction())
+        }
+        None => return Err(create_area_error(template_id_str)),
+      };
+
+      let direction = match direction_option {
+        Some(direction) => Direction::from(direction.to_str()?),
+        None => spawn_direction,
+      };
+
+      let area_id = net.transfer_party_to_instance(
+        party_id_str,
+        template_id_str,
+        warp_in_option.unwrap_or(true),
+        x_option.unwrap_or(spawn_x),
+        y_option.unwrap_or(spawn_y),
+        z_option.unwrap_or(spawn_z),
+        direction,
+      );
+
+      lua_ctx.pack_multi(area_id)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "send_party_system_chat_message",
+    |api_ctx, lua_ctx, params| {
+      let (party_id, message, sender_name): (mlua::String, mlua::String, Option<mlua::String>) =
+        lua_ctx.unpack_multi(params)?;
+
+      let sender_name_str = match &sender_name {
+        Some(sender_name) => sender_name.to_str()?,
+        None => "",
+      };
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.send_party_system_chat_message(party_id.to_str()?, sender_name_str, message.to_str()?);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+}
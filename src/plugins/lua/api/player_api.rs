@@ -60,6 +60,28 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
     }
   });
 
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_player_packet_counters",
+    |api_ctx, lua_ctx, params| {
+      let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      if let Some(counters) = net.get_player_packet_counters(player_id_str) {
+        let table = lua_ctx.create_table()?;
+        table.set("packets", counters.packets)?;
+        table.set("bytes", counters.bytes)?;
+        table.set("dropped_packets", counters.dropped_packets)?;
+
+        lua_ctx.pack_multi(table)
+      } else {
+        Err(create_player_error(player_id_str))
+      }
+    },
+  );
+
   lua_api.add_dynamic_function("Net", "get_player_name", |api_ctx, lua_ctx, params| {
     let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
     let player_id_str = player_id.to_str()?;
@@ -516,8 +538,8 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
     let tracker_pair = battle_tracker.get_pair_mut(player_1_id_str, player_2_id_str);
 
     if let Some((tracker_1, tracker_2)) = tracker_pair {
-      tracker_1.push_back(api_ctx.script_index);
-      tracker_2.push_back(api_ctx.script_index);
+      tracker_1.push_back(Some(api_ctx.script_index));
+      tracker_2.push_back(Some(api_ctx.script_index));
       net.initiate_pvp(player_1_id_str, player_2_id_str);
     }
 
@@ -567,7 +589,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.push_back(api_ctx.script_index);
+      tracker.push_back(Some(api_ctx.script_index));
 
       net.initiate_encounter(player_id_str, package_id_str, data.as_deref());
     }
@@ -575,6 +597,84 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
     lua_ctx.pack_multi(())
   });
 
+  lua_api.add_dynamic_function(
+    "Net",
+    "_initiate_coop_encounter",
+    |api_ctx, lua_ctx, params| {
+      let (player_ids, package_id, data_value): (Vec<String>, mlua::String, Option<mlua::Value>) =
+        lua_ctx.unpack_multi(params)?;
+      let package_id_str = package_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+      let mut battle_tracker = api_ctx.battle_tracker_ref.borrow_mut();
+      let data = data_value.map(|v| format!("return {}", lua_value_to_string(v, "", 0)));
+
+      let mut participants: Vec<String> = Vec::new();
+
+      for player_id in player_ids {
+        if participants.contains(&player_id) {
+          continue;
+        }
+
+        if let Some(tracker) = battle_tracker.get_mut(&player_id) {
+          tracker.push_back(Some(api_ctx.script_index));
+          participants.push(player_id);
+        }
+      }
+
+      let group_id = net.initiate_coop_encounter(&participants, package_id_str, data.as_deref());
+
+      lua_ctx.pack_multi(group_id)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_encounters_enabled",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, enabled): (mlua::String, bool) = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      if net.get_player(player_id_str).is_none() {
+        return Err(create_player_error(player_id_str));
+      }
+
+      net.set_encounters_enabled(player_id_str, enabled);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "is_encounters_enabled",
+    |api_ctx, lua_ctx, params| {
+      let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      lua_ctx.pack_multi(net.is_encounters_enabled(player_id_str))
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "reset_encounter_steps",
+    |api_ctx, lua_ctx, params| {
+      let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.reset_encounter_steps(player_id_str);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
   lua_api.add_dynamic_function("Net", "is_player_battling", |api_ctx, lua_ctx, params| {
     let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
     let player_id_str = player_id.to_str()?;
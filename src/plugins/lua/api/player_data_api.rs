@@ -1,6 +1,6 @@
-use super::lua_errors::create_player_error;
+use super::lua_errors::{create_player_error, pack_result};
 use super::LuaApi;
-use crate::net::Item;
+use crate::net::{Item, ItemMetadata};
 
 pub fn inject_dynamic(lua_api: &mut LuaApi) {
   lua_api.add_dynamic_function("Net", "get_player_secret", |api_ctx, lua_ctx, params| {
@@ -140,45 +140,242 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
     let net = api_ctx.net_ref.borrow();
 
     if let Some(player_data) = &net.get_player_data(player_id_str) {
-      lua_ctx.pack_multi(player_data.items.clone())
+      let item_ids: Vec<&str> = player_data
+        .items
+        .iter()
+        .map(|stack| stack.item_id.as_str())
+        .collect();
+
+      lua_ctx.pack_multi(item_ids)
     } else {
       Err(create_player_error(player_id_str))
     }
   });
 
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_player_item_stacks",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, category): (mlua::String, Option<mlua::String>) =
+        lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+      let category_str = category.as_ref().map(|c| c.to_str()).transpose()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      let stacks = if let Some(player_data) = net.get_player_data(player_id_str) {
+        player_data.items.clone()
+      } else {
+        return Err(create_player_error(player_id_str));
+      };
+
+      let mut stack_tables = Vec::with_capacity(stacks.len());
+
+      // indexes are kept stable for set_player_item_metadata, filtered stacks are skipped
+      for (i, stack) in stacks.into_iter().enumerate() {
+        if let Some(category) = category_str {
+          let item_category = net
+            .get_item(&stack.item_id)
+            .map(|item| item.category.as_str());
+
+          if item_category != Some(category) {
+            continue;
+          }
+        }
+
+        let stack_table = lua_ctx.create_table()?;
+        stack_table.set("index", i + 1)?;
+        stack_table.set("item_id", stack.item_id)?;
+        stack_table.set("quantity", stack.quantity)?;
+        stack_table.set("durability", stack.metadata.durability)?;
+        stack_table.set("data", stack.metadata.data)?;
+        stack_tables.push(stack_table);
+      }
+
+      lua_ctx.pack_multi(stack_tables)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_player_item_count",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, item_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+      let (player_id_str, item_id_str) = (player_id.to_str()?, item_id.to_str()?);
+
+      let net = api_ctx.net_ref.borrow();
+
+      if net.get_player_data(player_id_str).is_none() {
+        return Err(create_player_error(player_id_str));
+      }
+
+      lua_ctx.pack_multi(net.get_player_item_count(player_id_str, item_id_str))
+    },
+  );
+
   lua_api.add_dynamic_function("Net", "give_player_item", |api_ctx, lua_ctx, params| {
-    let (player_id, item_id): (mlua::String, String) = lua_ctx.unpack_multi(params)?;
-    let player_id_str = player_id.to_str()?;
+    let (player_id, item_id, quantity, metadata_table): (
+      mlua::String,
+      mlua::String,
+      Option<u32>,
+      Option<mlua::Table>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, item_id_str) = (player_id.to_str()?, item_id.to_str()?);
+
+    let metadata = match metadata_table {
+      Some(metadata_table) => parse_item_metadata(metadata_table)?,
+      None => ItemMetadata::default(),
+    };
 
     let mut net = api_ctx.net_ref.borrow_mut();
 
-    net.give_player_item(player_id_str, item_id);
+    if net.get_player_data(player_id_str).is_none() {
+      return Err(create_player_error(player_id_str));
+    }
 
-    lua_ctx.pack_multi(())
+    let result = net.give_player_item(player_id_str, item_id_str, quantity.unwrap_or(1), metadata);
+
+    pack_result(lua_ctx, result)
   });
 
   lua_api.add_dynamic_function("Net", "remove_player_item", |api_ctx, lua_ctx, params| {
-    let (player_id, item_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id, item_id, quantity): (mlua::String, mlua::String, Option<u32>) =
+      lua_ctx.unpack_multi(params)?;
     let (player_id_str, item_id_str) = (player_id.to_str()?, item_id.to_str()?);
 
     let mut net = api_ctx.net_ref.borrow_mut();
 
-    net.remove_player_item(player_id_str, item_id_str);
+    if net.get_player_data(player_id_str).is_none() {
+      return Err(create_player_error(player_id_str));
+    }
+
+    let _ = net.remove_player_item(player_id_str, item_id_str, quantity.unwrap_or(1));
 
     lua_ctx.pack_multi(())
   });
 
+  lua_api.add_dynamic_function("Net", "consume_player_item", |api_ctx, lua_ctx, params| {
+    let (player_id, item_id, quantity): (mlua::String, mlua::String, Option<u32>) =
+      lua_ctx.unpack_multi(params)?;
+    let (player_id_str, item_id_str) = (player_id.to_str()?, item_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if net.get_player_data(player_id_str).is_none() {
+      return Err(create_player_error(player_id_str));
+    }
+
+    let result = net
+      .remove_player_item(player_id_str, item_id_str, quantity.unwrap_or(1))
+      .map(|_| ());
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "transfer_player_item", |api_ctx, lua_ctx, params| {
+    let (sender_id, recipient_id, item_id, quantity): (
+      mlua::String,
+      mlua::String,
+      mlua::String,
+      Option<u32>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let (sender_id_str, recipient_id_str) = (sender_id.to_str()?, recipient_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    for player_id_str in [sender_id_str, recipient_id_str] {
+      if net.get_player_data(player_id_str).is_none() {
+        return Err(create_player_error(player_id_str));
+      }
+    }
+
+    let result = net.transfer_player_item(
+      sender_id_str,
+      recipient_id_str,
+      item_id.to_str()?,
+      quantity.unwrap_or(1),
+    );
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_player_item_metadata",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, stack_index, metadata_table): (mlua::String, usize, mlua::Table) =
+        lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let metadata = parse_item_metadata(metadata_table)?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      if net.get_player_data(player_id_str).is_none() {
+        return Err(create_player_error(player_id_str));
+      }
+
+      let success = match stack_index.checked_sub(1) {
+        Some(stack_index) => net.set_player_item_metadata(player_id_str, stack_index, metadata),
+        None => false,
+      };
+
+      lua_ctx.pack_multi(success)
+    },
+  );
+
   lua_api.add_dynamic_function("Net", "player_has_item", |api_ctx, lua_ctx, params| {
-    let (player_id, item_id): (mlua::String, String) = lua_ctx.unpack_multi(params)?;
-    let player_id_str = player_id.to_str()?;
+    let (player_id, item_id, quantity): (mlua::String, mlua::String, Option<u32>) =
+      lua_ctx.unpack_multi(params)?;
+    let (player_id_str, item_id_str) = (player_id.to_str()?, item_id.to_str()?);
 
     let net = api_ctx.net_ref.borrow();
 
-    if let Some(player_data) = &net.get_player_data(player_id_str) {
-      lua_ctx.pack_multi(player_data.items.contains(&item_id))
-    } else {
-      Err(create_player_error(player_id_str))
+    if net.get_player_data(player_id_str).is_none() {
+      return Err(create_player_error(player_id_str));
     }
+
+    let count = net.get_player_item_count(player_id_str, item_id_str);
+
+    lua_ctx.pack_multi(count > 0 && count >= quantity.unwrap_or(1))
+  });
+
+  lua_api.add_dynamic_function("Net", "get_player_storage", |api_ctx, lua_ctx, params| {
+    let (player_id, key): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, key_str) = (player_id.to_str()?, key.to_str()?);
+
+    let net = api_ctx.net_ref.borrow();
+
+    if net.get_player_data(player_id_str).is_none() {
+      return Err(create_player_error(player_id_str));
+    }
+
+    lua_ctx.pack_multi(net.get_player_storage(player_id_str, key_str))
+  });
+
+  lua_api.add_dynamic_function("Net", "set_player_storage", |api_ctx, lua_ctx, params| {
+    let (player_id, key, value): (mlua::String, String, Option<String>) =
+      lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net
+      .set_player_storage(player_id_str, key, value)
+      .ok_or_else(|| create_player_error(player_id_str))?;
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "save_player_data", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net.save_player_data(player_id_str);
+
+    lua_ctx.pack_multi(())
   });
 
   lua_api.add_dynamic_function("Net", "create_item", |api_ctx, lua_ctx, params| {
@@ -186,10 +383,21 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     let mut net = api_ctx.net_ref.borrow_mut();
 
-    let item = Item {
-      name: item_table.get("name")?,
-      description: item_table.get("description")?,
-    };
+    let mut item = Item::new(item_table.get("name")?, item_table.get("description")?);
+
+    if let Some(category) = item_table.get("category")? {
+      item.category = category;
+    }
+
+    if let Some(max_stack) = item_table.get::<_, Option<u32>>("max_stack")? {
+      item.max_stack = max_stack.max(1);
+    }
+
+    item.max_quantity = item_table.get("max_quantity")?;
+
+    if let Some(tradable) = item_table.get("tradable")? {
+      item.tradable = tradable;
+    }
 
     net.set_item(item_id, item);
 
@@ -217,4 +425,22 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     lua_ctx.pack_multi(description)
   });
+
+  lua_api.add_dynamic_function("Net", "get_item_category", |api_ctx, lua_ctx, params| {
+    let item_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    let item = net.get_item(item_id.to_str()?);
+    let category = item.map(|item| item.category.clone());
+
+    lua_ctx.pack_multi(category)
+  });
+}
+
+fn parse_item_metadata(metadata_table: mlua::Table) -> mlua::Result<ItemMetadata> {
+  Ok(ItemMetadata {
+    durability: metadata_table.get("durability")?,
+    data: metadata_table.get("data")?,
+  })
 }
@@ -0,0 +1,351 @@
+use super::lua_errors::{create_player_error, create_quest_error, ensure_player, pack_result};
+use super::LuaApi;
+use crate::net::{FlagValue, Net, QuestCondition, QuestState};
+
+// quests are defined in data files, scripts move players through them and gate objects on the results
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "list_quests", |api_ctx, lua_ctx, _| {
+    let net = api_ctx.net_ref.borrow();
+
+    let quest_ids: Vec<&str> = net.quest_ids().collect();
+
+    lua_ctx.pack_multi(quest_ids)
+  });
+
+  lua_api.add_dynamic_function("Net", "get_quest", |api_ctx, lua_ctx, params| {
+    let quest_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let quest_id_str = quest_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let quest = match net.get_quest(quest_id_str) {
+      Some(quest) => quest,
+      None => return lua_ctx.pack_multi(mlua::Nil),
+    };
+
+    let mut step_tables = Vec::with_capacity(quest.steps.len());
+
+    for step in &quest.steps {
+      let step_table = lua_ctx.create_table()?;
+      step_table.set("id", step.id.as_str())?;
+      step_table.set("description", step.description.as_str())?;
+      step_tables.push(step_table);
+    }
+
+    let table = lua_ctx.create_table()?;
+    table.set("id", quest.id.as_str())?;
+    table.set("name", quest.name.as_str())?;
+    table.set("description", quest.description.as_str())?;
+    table.set("steps", step_tables)?;
+
+    lua_ctx.pack_multi(table)
+  });
+
+  lua_api.add_dynamic_function("Net", "get_quest_state", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let net = api_ctx.net_ref.borrow();
+
+    let progress = net
+      .get_quest_progress(player_id_str, quest_id_str)
+      .ok_or_else(|| create_player_error(player_id_str))?;
+
+    lua_ctx.pack_multi(progress.state.as_str())
+  });
+
+  lua_api.add_dynamic_function("Net", "get_quest_step", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let net = api_ctx.net_ref.borrow();
+
+    let progress = net
+      .get_quest_progress(player_id_str, quest_id_str)
+      .ok_or_else(|| create_player_error(player_id_str))?;
+
+    if progress.state != QuestState::Active {
+      return lua_ctx.pack_multi(mlua::Nil);
+    }
+
+    let step_id = net
+      .get_quest(quest_id_str)
+      .and_then(|quest| quest.steps.get(progress.step))
+      .map(|step| step.id.as_str());
+
+    lua_ctx.pack_multi(step_id)
+  });
+
+  lua_api.add_dynamic_function("Net", "start_quest", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.start_quest(player_id_str, quest_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "advance_quest", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.advance_quest(player_id_str, quest_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "set_quest_step", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id, step_id): (mlua::String, mlua::String, mlua::String) =
+      lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str, step_id_str) =
+      (player_id.to_str()?, quest_id.to_str()?, step_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.set_quest_step(player_id_str, quest_id_str, step_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "complete_quest", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.complete_quest(player_id_str, quest_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "fail_quest", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.fail_quest(player_id_str, quest_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "reset_quest", |api_ctx, lua_ctx, params| {
+    let (player_id, quest_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, quest_id_str) = (player_id.to_str()?, quest_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    net.reset_quest(player_id_str, quest_id_str);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "get_player_flag", |api_ctx, lua_ctx, params| {
+    let (player_id, name): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, name_str) = (player_id.to_str()?, name.to_str()?);
+
+    let net = api_ctx.net_ref.borrow();
+
+    ensure_player(&net, player_id_str)?;
+
+    match net.get_player_flag(player_id_str, name_str) {
+      Some(FlagValue::Bool(value)) => lua_ctx.pack_multi(value),
+      Some(FlagValue::Int(value)) => lua_ctx.pack_multi(value),
+      None => lua_ctx.pack_multi(mlua::Nil),
+    }
+  });
+
+  lua_api.add_dynamic_function("Net", "set_player_flag", |api_ctx, lua_ctx, params| {
+    let (player_id, name, value): (mlua::String, mlua::String, mlua::Value) =
+      lua_ctx.unpack_multi(params)?;
+    let (player_id_str, name_str) = (player_id.to_str()?, name.to_str()?);
+
+    let value = match value {
+      mlua::Value::Boolean(value) => FlagValue::Bool(value),
+      mlua::Value::Integer(value) => FlagValue::Int(value),
+      mlua::Value::Number(value) if value.fract() == 0.0 => FlagValue::Int(value as i64),
+      _ => {
+        return Err(mlua::Error::RuntimeError(String::from(
+          "Flags must be a boolean or an integer",
+        )))
+      }
+    };
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    net.set_player_flag(player_id_str, name_str, value);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "remove_player_flag", |api_ctx, lua_ctx, params| {
+    let (player_id, name): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, name_str) = (player_id.to_str()?, name.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    net.remove_player_flag(player_id_str, name_str);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_object_visibility_conditions",
+    |api_ctx, lua_ctx, params| {
+      let (area_id, object_id, condition_tables): (mlua::String, u32, Vec<mlua::Table>) =
+        lua_ctx.unpack_multi(params)?;
+      let area_id_str = area_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      let conditions = parse_conditions(&net, condition_tables)?;
+
+      net.set_object_visibility_conditions(area_id_str, object_id, conditions);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_object_interaction_conditions",
+    |api_ctx, lua_ctx, params| {
+      let (area_id, object_id, condition_tables): (mlua::String, u32, Vec<mlua::Table>) =
+        lua_ctx.unpack_multi(params)?;
+      let area_id_str = area_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      let conditions = parse_conditions(&net, condition_tables)?;
+
+      net.set_object_interaction_conditions(area_id_str, object_id, conditions);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "is_object_visible_to_player",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, object_id): (mlua::String, u32) = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      ensure_player(&net, player_id_str)?;
+
+      lua_ctx.pack_multi(net.is_object_visible_to_player(player_id_str, object_id))
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "can_player_interact_with_object",
+    |api_ctx, lua_ctx, params| {
+      let (player_id, object_id): (mlua::String, u32) = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      ensure_player(&net, player_id_str)?;
+
+      lua_ctx.pack_multi(net.can_player_interact_with_object(player_id_str, object_id))
+    },
+  );
+}
+
+// { flag, value } | { flag, at_least } | { quest, state } | { quest, step }
+fn parse_conditions(
+  net: &Net,
+  condition_tables: Vec<mlua::Table>,
+) -> mlua::Result<Vec<QuestCondition>> {
+  let mut conditions = Vec::with_capacity(condition_tables.len());
+
+  for condition_table in condition_tables {
+    let flag: Option<String> = condition_table.get("flag")?;
+    let quest_id: Option<String> = condition_table.get("quest")?;
+
+    let condition = match (flag, quest_id) {
+      (Some(name), None) => match condition_table.get::<_, Option<i64>>("at_least")? {
+        Some(value) => QuestCondition::FlagAtLeast { name, value },
+        None => {
+          let value: Option<bool> = condition_table.get("value")?;
+
+          QuestCondition::Flag {
+            name,
+            value: value.unwrap_or(true),
+          }
+        }
+      },
+      (None, Some(quest_id)) => {
+        let quest = net
+          .get_quest(&quest_id)
+          .ok_or_else(|| create_quest_error(&quest_id))?;
+
+        let step_id: Option<mlua::String> = condition_table.get("step")?;
+        let state: Option<mlua::String> = condition_table.get("state")?;
+
+        match (step_id, state) {
+          (Some(step_id), None) => {
+            let step_id_str = step_id.to_str()?;
+
+            let step = quest
+              .steps
+              .iter()
+              .position(|step| step.id == step_id_str)
+              .ok_or_else(|| {
+                mlua::Error::RuntimeError(format!("No step matching \"{}\" found.", step_id_str))
+              })?;
+
+            QuestCondition::QuestStep { quest_id, step }
+          }
+          (None, Some(state)) => {
+            let state_str = state.to_str()?;
+
+            let state = QuestState::parse(state_str).ok_or_else(|| {
+              mlua::Error::RuntimeError(format!("Invalid quest state \"{}\"", state_str))
+            })?;
+
+            QuestCondition::Quest { quest_id, state }
+          }
+          _ => {
+            return Err(mlua::Error::RuntimeError(String::from(
+              "Quest conditions require either a state or a step",
+            )))
+          }
+        }
+      }
+      _ => {
+        return Err(mlua::Error::RuntimeError(String::from(
+          "Conditions require either a flag or a quest",
+        )))
+      }
+    };
+
+    conditions.push(condition);
+  }
+
+  Ok(conditions)
+}
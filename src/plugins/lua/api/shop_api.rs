@@ -0,0 +1,134 @@
+use super::lua_errors::{create_item_error, create_shop_error};
+use super::LuaApi;
+use crate::net::ShopItem;
+
+// managed shops hold stock on the server, purchases are completed without scripts checking money
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "create_managed_shop", |api_ctx, lua_ctx, params| {
+    let (shop_id, item_tables): (mlua::String, Vec<mlua::Table>) = lua_ctx.unpack_multi(params)?;
+    let shop_id_str = shop_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    let mut items = Vec::with_capacity(item_tables.len());
+
+    for item_table in item_tables {
+      let item_id: String = item_table.get("item_id")?;
+      let name: Option<String> = item_table.get("name")?;
+      let description: Option<String> = item_table.get("description")?;
+
+      // the shop can reuse the item's name and description
+      let item = net
+        .get_item(&item_id)
+        .ok_or_else(|| create_item_error(&item_id))?;
+
+      items.push(ShopItem {
+        name: name.unwrap_or_else(|| item.name.clone()),
+        description: description.unwrap_or_else(|| item.description.clone()),
+        price: item_table.get("price")?,
+        stock: item_table.get("stock")?,
+        item_id: Some(item_id),
+      });
+    }
+
+    if !net.create_managed_shop(shop_id_str, items) {
+      return Err(mlua::Error::RuntimeError(String::from(
+        "Managed shop items must have unique item ids and names",
+      )));
+    }
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "remove_managed_shop", |api_ctx, lua_ctx, params| {
+    let shop_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net.remove_managed_shop(shop_id.to_str()?);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "is_managed_shop", |api_ctx, lua_ctx, params| {
+    let shop_id: mlua::String = lua_ctx.unpack_multi(params)?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let exists = net.get_managed_shop_items(shop_id.to_str()?).is_some();
+
+    lua_ctx.pack_multi(exists)
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_managed_shop_items",
+    |api_ctx, lua_ctx, params| {
+      let shop_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let shop_id_str = shop_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      let items = net
+        .get_managed_shop_items(shop_id_str)
+        .ok_or_else(|| create_shop_error(shop_id_str))?;
+
+      let mut item_tables = Vec::with_capacity(items.len());
+
+      for item in items {
+        let item_table = lua_ctx.create_table()?;
+        item_table.set("item_id", item.item_id.as_deref())?;
+        item_table.set("name", item.name.as_str())?;
+        item_table.set("description", item.description.as_str())?;
+        item_table.set("price", item.price)?;
+        item_table.set("stock", item.stock)?;
+        item_tables.push(item_table);
+      }
+
+      lua_ctx.pack_multi(item_tables)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "get_managed_shop_stock",
+    |api_ctx, lua_ctx, params| {
+      let (shop_id, item_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+      let (shop_id_str, item_id_str) = (shop_id.to_str()?, item_id.to_str()?);
+
+      let net = api_ctx.net_ref.borrow();
+
+      let items = net
+        .get_managed_shop_items(shop_id_str)
+        .ok_or_else(|| create_shop_error(shop_id_str))?;
+
+      // nil for unlimited stock
+      let stock = items
+        .iter()
+        .find(|item| item.item_id.as_deref() == Some(item_id_str))
+        .and_then(|item| item.stock);
+
+      lua_ctx.pack_multi(stock)
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "set_managed_shop_stock",
+    |api_ctx, lua_ctx, params| {
+      let (shop_id, item_id, stock): (mlua::String, mlua::String, Option<u32>) =
+        lua_ctx.unpack_multi(params)?;
+      let (shop_id_str, item_id_str) = (shop_id.to_str()?, item_id.to_str()?);
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      if net.get_managed_shop_items(shop_id_str).is_none() {
+        return Err(create_shop_error(shop_id_str));
+      }
+
+      net.set_managed_shop_stock(shop_id_str, item_id_str, stock);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+}
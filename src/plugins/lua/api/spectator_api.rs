@@ -0,0 +1,61 @@
+use super::lua_errors::{ensure_player, pack_result};
+use super::LuaApi;
+
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "add_battle_spectator", |api_ctx, lua_ctx, params| {
+    let (player_id, spectator_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, spectator_id_str) = (player_id.to_str()?, spectator_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+    ensure_player(&net, spectator_id_str)?;
+
+    let result = net.add_battle_spectator(player_id_str, spectator_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "remove_battle_spectator",
+    |api_ctx, lua_ctx, params| {
+      let spectator_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let spectator_id_str = spectator_id.to_str()?;
+
+      let mut net = api_ctx.net_ref.borrow_mut();
+
+      net.remove_battle_spectator(spectator_id_str);
+
+      lua_ctx.pack_multi(())
+    },
+  );
+
+  lua_api.add_dynamic_function(
+    "Net",
+    "list_battle_spectators",
+    |api_ctx, lua_ctx, params| {
+      let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+      let player_id_str = player_id.to_str()?;
+
+      let net = api_ctx.net_ref.borrow();
+
+      let spectators: Vec<&str> = net
+        .list_battle_spectators(player_id_str)
+        .iter()
+        .map(|id| id.as_str())
+        .collect();
+
+      lua_ctx.pack_multi(spectators)
+    },
+  );
+
+  lua_api.add_dynamic_function("Net", "is_battle_spectator", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.is_battle_spectator(player_id_str))
+  });
+}
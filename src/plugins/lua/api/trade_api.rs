@@ -0,0 +1,136 @@
+use super::lua_errors::{ensure_player, pack_result};
+use super::LuaApi;
+
+// trades are driven by scripts, the trade_request event lets scripts ask the recipient before accepting
+pub fn inject_dynamic(lua_api: &mut LuaApi) {
+  lua_api.add_dynamic_function("Net", "request_trade", |api_ctx, lua_ctx, params| {
+    let (player_id, recipient_id): (mlua::String, mlua::String) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, recipient_id_str) = (player_id.to_str()?, recipient_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+    ensure_player(&net, recipient_id_str)?;
+
+    let result = net.request_trade(player_id_str, recipient_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "accept_trade", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.accept_trade(player_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "cancel_trade", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    net.cancel_trade(player_id_str);
+
+    lua_ctx.pack_multi(())
+  });
+
+  lua_api.add_dynamic_function("Net", "get_trade_partner", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.get_trade_partner(player_id_str))
+  });
+
+  lua_api.add_dynamic_function("Net", "is_trade_accepted", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.is_trade_accepted(player_id_str))
+  });
+
+  lua_api.add_dynamic_function("Net", "set_trade_offer", |api_ctx, lua_ctx, params| {
+    let (player_id, offer_table): (mlua::String, mlua::Table) = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let money: Option<u32> = offer_table.get("money")?;
+    let item_tables: Option<Vec<mlua::Table>> = offer_table.get("items")?;
+
+    let mut items = Vec::new();
+
+    for item_table in item_tables.unwrap_or_default() {
+      let quantity: Option<u32> = item_table.get("quantity")?;
+
+      items.push((item_table.get("item_id")?, quantity.unwrap_or(1)));
+    }
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.set_trade_offer(player_id_str, money.unwrap_or_default(), &items);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "get_trade_offer", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    let offer = match net.get_trade_offer(player_id_str) {
+      Some(offer) => offer,
+      None => return lua_ctx.pack_multi(mlua::Nil),
+    };
+
+    let mut item_tables = Vec::with_capacity(offer.items.len());
+
+    for stack in &offer.items {
+      let item_table = lua_ctx.create_table()?;
+      item_table.set("item_id", stack.item_id.as_str())?;
+      item_table.set("quantity", stack.quantity)?;
+      item_table.set("durability", stack.metadata.durability)?;
+      item_table.set("data", stack.metadata.data.as_deref())?;
+      item_tables.push(item_table);
+    }
+
+    let table = lua_ctx.create_table()?;
+    table.set("money", offer.money)?;
+    table.set("items", item_tables)?;
+
+    lua_ctx.pack_multi(table)
+  });
+
+  lua_api.add_dynamic_function("Net", "confirm_trade", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    ensure_player(&net, player_id_str)?;
+
+    let result = net.confirm_trade(player_id_str);
+
+    pack_result(lua_ctx, result)
+  });
+
+  lua_api.add_dynamic_function("Net", "is_trade_confirmed", |api_ctx, lua_ctx, params| {
+    let player_id: mlua::String = lua_ctx.unpack_multi(params)?;
+    let player_id_str = player_id.to_str()?;
+
+    let net = api_ctx.net_ref.borrow();
+
+    lua_ctx.pack_multi(net.is_trade_confirmed(player_id_str))
+  });
+}
@@ -41,7 +41,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.track_textbox(api_ctx.script_index);
+      tracker.track_textbox(Some(api_ctx.script_index));
 
       net.message_player(
         player_id_str,
@@ -70,7 +70,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.track_textbox(api_ctx.script_index);
+      tracker.track_textbox(Some(api_ctx.script_index));
 
       net.question_player(
         player_id_str,
@@ -101,7 +101,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.track_textbox(api_ctx.script_index);
+      tracker.track_textbox(Some(api_ctx.script_index));
 
       net.quiz_player(
         player_id_str,
@@ -128,7 +128,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.track_textbox(api_ctx.script_index);
+      tracker.track_textbox(Some(api_ctx.script_index));
 
       net.prompt_player(
         player_id_str,
@@ -158,7 +158,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.track_board(api_ctx.script_index);
+      tracker.track_board(Some(api_ctx.script_index));
 
       let color = (
         color_table.get("r")?,
@@ -300,7 +300,7 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
       .borrow_mut()
       .get_mut(player_id_str)
     {
-      tracker.track_shop(api_ctx.script_index);
+      tracker.track_shop(Some(api_ctx.script_index));
       let mut net = api_ctx.net_ref.borrow_mut();
 
       let mut items = Vec::new();
@@ -315,6 +315,8 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
           name,
           description,
           price,
+          item_id: None,
+          stock: None,
         });
       }
 
@@ -328,4 +330,39 @@ pub fn inject_dynamic(lua_api: &mut LuaApi) {
 
     lua_ctx.pack_multi(())
   });
+
+  lua_api.add_dynamic_function("Net", "_open_managed_shop", |api_ctx, lua_ctx, params| {
+    use super::lua_errors::create_shop_error;
+
+    let (player_id, shop_id, mug_texture_path, mug_animation_path): (
+      mlua::String,
+      mlua::String,
+      Option<mlua::String>,
+      Option<mlua::String>,
+    ) = lua_ctx.unpack_multi(params)?;
+    let (player_id_str, shop_id_str) = (player_id.to_str()?, shop_id.to_str()?);
+
+    let mut net = api_ctx.net_ref.borrow_mut();
+
+    if net.get_managed_shop_items(shop_id_str).is_none() {
+      return Err(create_shop_error(shop_id_str));
+    }
+
+    if let Some(tracker) = api_ctx
+      .widget_tracker_ref
+      .borrow_mut()
+      .get_mut(player_id_str)
+    {
+      tracker.track_shop(Some(api_ctx.script_index));
+
+      net.open_managed_shop(
+        player_id_str,
+        shop_id_str,
+        optional_lua_string_to_str(&mug_texture_path)?,
+        optional_lua_string_to_str(&mug_animation_path)?,
+      );
+    }
+
+    lua_ctx.pack_multi(())
+  });
 }
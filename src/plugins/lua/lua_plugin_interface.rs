@@ -1,20 +1,38 @@
-use super::api::{ApiContext, LuaApi};
+use super::api::{get_console_commands_table, ApiContext, LuaApi};
 use crate::jobs::JobPromiseManager;
-use crate::net::{BattleStats, Net, WidgetTracker};
+use crate::net::{
+  BattleStats, ChatChannel, CoopBattleResult, Encounter, FlagValue, Net, QuestState, ShopPurchase,
+  TradeOffer, WidgetTracker,
+};
 use crate::plugins::PluginInterface;
 use log::*;
 use mlua::Lua;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+const WATCH_RATE: f32 = 1.0;
+
+struct ScriptSource {
+  // file or folder in ./scripts
+  path: PathBuf,
+  entry_path: PathBuf,
+  last_modified: Option<SystemTime>,
+}
 
 pub struct LuaPluginInterface {
   scripts: Vec<Lua>,
   all_scripts: Vec<usize>,
-  widget_trackers: HashMap<String, WidgetTracker<usize>>,
-  battle_trackers: HashMap<String, VecDeque<usize>>,
+  script_sources: Vec<ScriptSource>,
+  // widgets and battles owned by a reloaded script are released to None, their responses are dropped
+  widget_trackers: HashMap<String, WidgetTracker<Option<usize>>>,
+  battle_trackers: HashMap<String, VecDeque<Option<usize>>>,
   promise_manager: JobPromiseManager,
   lua_api: LuaApi,
+  watch_scripts: bool,
+  last_watch_check: Instant,
 }
 
 impl LuaPluginInterface {
@@ -22,19 +40,27 @@ impl LuaPluginInterface {
     LuaPluginInterface {
       scripts: Vec::new(),
       all_scripts: Vec::new(),
+      script_sources: Vec::new(),
       widget_trackers: HashMap::new(),
       battle_trackers: HashMap::new(),
       promise_manager: JobPromiseManager::new(),
       lua_api: LuaApi::new(),
+      watch_scripts: false,
+      last_watch_check: Instant::now(),
     }
   }
 
+  /// Reloads scripts when files in ./scripts change
+  pub fn set_watch_scripts(&mut self, watch_scripts: bool) {
+    self.watch_scripts = watch_scripts;
+  }
+
   fn load_scripts(&mut self, net_ref: &mut Net) -> std::io::Result<()> {
     use std::fs::read_dir;
 
     for wrapped_dir_entry in read_dir("./scripts")? {
       let dir_path = wrapped_dir_entry?.path();
-      let mut script_path = dir_path;
+      let mut script_path = dir_path.clone();
 
       let extension = script_path.extension().unwrap_or_default().to_str();
 
@@ -46,7 +72,16 @@ impl LuaPluginInterface {
         continue;
       }
 
-      if let Err(err) = self.load_script(net_ref, script_path.to_path_buf()) {
+      let script_index = self.scripts.len();
+      self.scripts.push(Lua::new());
+      self.all_scripts.push(script_index);
+      self.script_sources.push(ScriptSource {
+        last_modified: get_last_modified(&dir_path),
+        path: dir_path,
+        entry_path: script_path,
+      });
+
+      if let Err(err) = self.load_script(net_ref, script_index) {
         error!("{}", err)
       }
     }
@@ -54,18 +89,14 @@ impl LuaPluginInterface {
     Ok(())
   }
 
-  fn load_script(
-    &mut self,
-    net_ref: &mut Net,
-    script_path: std::path::PathBuf,
-  ) -> mlua::Result<()> {
+  fn load_script(&mut self, net_ref: &mut Net, script_index: usize) -> mlua::Result<()> {
     let net_ref = RefCell::new(net_ref);
 
-    let script_index = self.scripts.len();
-    self.scripts.push(Lua::new());
-    self.all_scripts.push(script_index);
+    let script_path = &self.script_sources[script_index].entry_path;
 
-    let lua_ctx = self.scripts.last_mut().unwrap();
+    // a fresh state, so reloads don't inherit anything from the previous run
+    self.scripts[script_index] = Lua::new();
+    let lua_ctx = &mut self.scripts[script_index];
 
     let widget_tracker_ref = RefCell::new(&mut self.widget_trackers);
     let battle_tracker_ref = RefCell::new(&mut self.battle_trackers);
@@ -81,12 +112,10 @@ impl LuaPluginInterface {
 
     let globals = lua_ctx.globals();
 
-    self.lua_api.inject_static(&lua_ctx)?;
+    self.lua_api.inject_static(lua_ctx)?;
 
     self.lua_api.inject_dynamic(lua_ctx, api_ctx, |_| {
-      let parent_path = script_path
-        .parent()
-        .unwrap_or_else(|| std::path::Path::new(""));
+      let parent_path = script_path.parent().unwrap_or_else(|| Path::new(""));
       let stem = script_path.file_stem().unwrap_or_default();
       let path = parent_path.join(stem);
       let path_str = path.to_str().unwrap_or_default();
@@ -107,6 +136,78 @@ impl LuaPluginInterface {
 
     Ok(())
   }
+
+  fn reload_script(&mut self, net: &mut Net, script_index: usize) {
+    for tracker in self.widget_trackers.values_mut() {
+      tracker.replace_owner(&Some(script_index), None);
+    }
+
+    for tracker in self.battle_trackers.values_mut() {
+      for owner in tracker
+        .iter_mut()
+        .filter(|owner| **owner == Some(script_index))
+      {
+        *owner = None;
+      }
+    }
+
+    self.promise_manager.remove_owned_promises(script_index);
+
+    // the new state registers its own commands
+    let console_commands =
+      get_console_commands_table(&self.scripts[script_index]).and_then(|table| {
+        table
+          .pairs::<String, bool>()
+          .collect::<mlua::Result<Vec<_>>>()
+      });
+
+    for (name, _) in console_commands.unwrap_or_default() {
+      // other scripts in this plugin may have registered the same command
+      let shared = self
+        .all_scripts
+        .iter()
+        .any(|other| *other != script_index && has_console_command(&self.scripts[*other], &name));
+
+      if !shared {
+        net.remove_console_command(&name);
+      }
+    }
+
+    let source = &mut self.script_sources[script_index];
+    source.last_modified = get_last_modified(&source.path);
+
+    info!("Reloading {}", source.path.display());
+
+    if let Err(err) = self.load_script(net, script_index) {
+      error!("{}", err);
+      return;
+    }
+
+    handle_event(
+      &mut self.scripts,
+      &[script_index],
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+
+        callback.call(("server_reload", event))
+      },
+    );
+  }
+
+  fn reload_modified_scripts(&mut self, net: &mut Net) {
+    for script_index in 0..self.script_sources.len() {
+      let source = &self.script_sources[script_index];
+
+      if get_last_modified(&source.path) != source.last_modified {
+        self.reload_script(net, script_index);
+      }
+    }
+  }
 }
 
 impl PluginInterface for LuaPluginInterface {
@@ -116,7 +217,27 @@ impl PluginInterface for LuaPluginInterface {
     }
   }
 
+  fn reload(&mut self, net: &mut Net, name: Option<&str>) {
+    for script_index in 0..self.script_sources.len() {
+      let source = &self.script_sources[script_index];
+
+      let matches_name = match name {
+        Some(name) => source.path.file_stem().and_then(|stem| stem.to_str()) == Some(name),
+        None => true,
+      };
+
+      if matches_name {
+        self.reload_script(net, script_index);
+      }
+    }
+  }
+
   fn tick(&mut self, net: &mut Net, delta_time: f32) {
+    if self.watch_scripts && self.last_watch_check.elapsed().as_secs_f32() >= WATCH_RATE {
+      self.last_watch_check = Instant::now();
+      self.reload_modified_scripts(net);
+    }
+
     handle_event(
       &mut self.scripts,
       &self.all_scripts,
@@ -287,6 +408,52 @@ impl PluginInterface for LuaPluginInterface {
     );
   }
 
+  fn handle_player_move_rejected(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    reason: &str,
+  ) -> bool {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let prevent_default = Rc::new(Cell::new(false));
+
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let prevent_default_reference = prevent_default.clone();
+
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("x", x)?;
+        event.set("y", y)?;
+        event.set("z", z)?;
+        event.set("reason", reason)?;
+        event.set(
+          "prevent_default",
+          lua_ctx.create_function(move |_, _: ()| {
+            prevent_default_reference.clone().set(true);
+            Ok(())
+          })?,
+        )?;
+
+        callback.call(("player_move_rejected", event))
+      },
+    );
+
+    prevent_default.get()
+  }
+
   fn handle_player_avatar_change(
     &mut self,
     net: &mut Net,
@@ -474,7 +641,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_textbox_response(&mut self, net: &mut Net, player_id: &str, response: u8) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.pop_textbox() {
+    let script_index = if let Some(Some(script_index)) = tracker.pop_textbox() {
       script_index
     } else {
       // protect against attackers
@@ -502,7 +669,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_prompt_response(&mut self, net: &mut Net, player_id: &str, response: String) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.pop_textbox() {
+    let script_index = if let Some(Some(script_index)) = tracker.pop_textbox() {
       script_index
     } else {
       // protect against attackers
@@ -532,7 +699,7 @@ impl PluginInterface for LuaPluginInterface {
 
     tracker.open_board();
 
-    let script_index = if let Some(script_index) = tracker.current_board() {
+    let script_index = if let Some(Some(script_index)) = tracker.current_board() {
       script_index.clone()
     } else {
       // protect against attackers
@@ -559,7 +726,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_board_close(&mut self, net: &mut Net, player_id: &str) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.close_board() {
+    let script_index = if let Some(Some(script_index)) = tracker.close_board() {
       script_index
     } else {
       // protect against attackers
@@ -586,7 +753,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_post_request(&mut self, net: &mut Net, player_id: &str) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.current_board() {
+    let script_index = if let Some(Some(script_index)) = tracker.current_board() {
       script_index.clone()
     } else {
       // protect against attackers
@@ -613,7 +780,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_post_selection(&mut self, net: &mut Net, player_id: &str, post_id: &str) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.current_board() {
+    let script_index = if let Some(Some(script_index)) = tracker.current_board() {
       script_index.clone()
     } else {
       // protect against attackers
@@ -641,7 +808,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_shop_close(&mut self, net: &mut Net, player_id: &str) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.close_shop() {
+    let script_index = if let Some(Some(script_index)) = tracker.close_shop() {
       script_index
     } else {
       // protect against attackers
@@ -668,7 +835,7 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_shop_purchase(&mut self, net: &mut Net, player_id: &str, item_name: &str) {
     let tracker = self.widget_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.current_shop() {
+    let script_index = if let Some(Some(script_index)) = tracker.current_shop() {
       script_index.clone()
     } else {
       // protect against attackers
@@ -696,42 +863,25 @@ impl PluginInterface for LuaPluginInterface {
   fn handle_battle_results(&mut self, net: &mut Net, player_id: &str, battle_stats: &BattleStats) {
     let tracker = self.battle_trackers.get_mut(player_id).unwrap();
 
-    let script_index = if let Some(script_index) = tracker.pop_front() {
-      script_index
-    } else {
-      // protect against attackers
-      return;
+    let listeners = match tracker.pop_front() {
+      Some(Some(script_index)) => vec![script_index],
+      // owned by a script that has been reloaded
+      Some(None) => return,
+      // the server started this battle, the plugin wrapper filters out results without a battle
+      None => self.all_scripts.clone(),
     };
 
     handle_event(
       &mut self.scripts,
-      &[script_index],
+      &listeners,
       &mut self.widget_trackers,
       &mut self.battle_trackers,
       &mut self.promise_manager,
       &mut self.lua_api,
       net,
       |lua_ctx, callback| {
-        let event = lua_ctx.create_table()?;
+        let event = create_battle_stats_table(lua_ctx, battle_stats)?;
         event.set("player_id", player_id)?;
-        event.set("health", battle_stats.health)?;
-        event.set("score", battle_stats.score)?;
-        event.set("time", battle_stats.time)?;
-        event.set("ran", battle_stats.ran)?;
-        event.set("emotion", battle_stats.emotion)?;
-        event.set("turns", battle_stats.turns)?;
-
-        let mut enemy_tables = Vec::new();
-        enemy_tables.reserve(battle_stats.enemies.len());
-
-        for enemy in &battle_stats.enemies {
-          let enemy_table = lua_ctx.create_table()?;
-          enemy_table.set("id", enemy.id.as_str())?;
-          enemy_table.set("health", enemy.health)?;
-          enemy_tables.push(enemy_table);
-        }
-
-        event.set("enemies", enemy_tables)?;
 
         callback.call(("battle_results", event))
       },
@@ -762,60 +912,725 @@ impl PluginInterface for LuaPluginInterface {
       },
     );
   }
-}
 
-#[allow(clippy::too_many_arguments)]
-fn handle_event<F>(
-  scripts: &mut Vec<Lua>,
-  event_listeners: &[usize],
-  widget_tracker: &mut HashMap<String, WidgetTracker<usize>>,
-  battle_tracker: &mut HashMap<String, VecDeque<usize>>,
-  promise_manager: &mut JobPromiseManager,
-  lua_api: &mut LuaApi,
-  net: &mut Net,
-  fn_caller: F,
-) where
-  F: for<'lua> FnMut(&'lua mlua::Lua, mlua::Function<'lua>) -> mlua::Result<()>,
-{
-  let mut fn_caller = fn_caller;
+  fn handle_console_command(&mut self, net: &mut Net, command: &str, args: &[&str]) {
+    // only the scripts that registered the command
+    let listeners: Vec<usize> = self
+      .all_scripts
+      .iter()
+      .copied()
+      .filter(|script_index| has_console_command(&self.scripts[*script_index], command))
+      .collect();
 
-  let call_lua = || -> mlua::Result<()> {
-    let net_ref = RefCell::new(net);
-    let widget_tracker_ref = RefCell::new(widget_tracker);
-    let battle_tracker_ref = RefCell::new(battle_tracker);
-    let promise_manager_ref = RefCell::new(promise_manager);
+    handle_event(
+      &mut self.scripts,
+      &listeners,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("command", command)?;
+        event.set("args", lua_ctx.create_sequence_from(args.iter().copied())?)?;
 
-    // loop over scripts
-    for script_index in event_listeners {
-      let lua_ctx = scripts.get_mut(*script_index).unwrap();
+        callback.call(("console_command", event))
+      },
+    );
+  }
 
-      let api_ctx = ApiContext {
-        script_index: *script_index,
-        net_ref: &net_ref,
-        widget_tracker_ref: &widget_tracker_ref,
-        battle_tracker_ref: &battle_tracker_ref,
-        promise_manager_ref: &promise_manager_ref,
-      };
+  fn handle_bot_path_complete(&mut self, net: &mut Net, bot_id: &str) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("bot_id", bot_id)?;
 
-      lua_api.inject_dynamic(lua_ctx, api_ctx, |lua_ctx| {
-        let globals = lua_ctx.globals();
-        let net_table: mlua::Table = globals.get("Net")?;
+        callback.call(("bot_path_complete", event))
+      },
+    );
+  }
 
-        if let Ok(func) = net_table.get::<_, mlua::Function>("emit") {
-          let binded_func = func.bind(net_table)?;
+  fn handle_object_enter(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("area_id", area_id)?;
+        event.set("object_id", object_id)?;
 
-          if let Err(err) = fn_caller(lua_ctx, binded_func) {
-            error!("{}", err);
-          }
-        }
+        callback.call(("object_enter", event))
+      },
+    );
+  }
+
+  fn handle_object_exit(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("area_id", area_id)?;
+        event.set("object_id", object_id)?;
+
+        callback.call(("object_exit", event))
+      },
+    );
+  }
+
+  fn handle_actor_nearby(&mut self, net: &mut Net, actor_id: &str, nearby_actor_id: &str) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("actor_id", actor_id)?;
+        event.set("nearby_actor_id", nearby_actor_id)?;
+
+        callback.call(("actor_nearby", event))
+      },
+    );
+  }
+
+  fn handle_encounter(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    encounter: &mut Encounter,
+  ) -> bool {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let prevent_default = Rc::new(Cell::new(false));
+    let encounter_ref = RefCell::new(encounter);
+
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let prevent_default_reference = prevent_default.clone();
+        let mut encounter = encounter_ref.borrow_mut();
+
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("package_path", encounter.package_path.as_str())?;
+        event.set("data", encounter.data.as_deref())?;
+        event.set(
+          "prevent_default",
+          lua_ctx.create_function(move |_, _: ()| {
+            prevent_default_reference.clone().set(true);
+            Ok(())
+          })?,
+        )?;
+
+        callback.call::<_, ()>(("encounter", event.clone()))?;
+
+        // scripts modify the encounter through the event
+        encounter.package_path = event.get("package_path")?;
+        encounter.data = event.get("data")?;
 
         Ok(())
-      })?;
-    }
-    Ok(())
-  };
+      },
+    );
 
-  if let Err(err) = call_lua() {
-    error!("{:#}", err);
+    prevent_default.get()
   }
+
+  fn handle_trade_request(&mut self, net: &mut Net, player_id: &str, recipient_id: &str) -> bool {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let prevent_default = Rc::new(Cell::new(false));
+
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let prevent_default_reference = prevent_default.clone();
+
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("recipient_id", recipient_id)?;
+        event.set(
+          "prevent_default",
+          lua_ctx.create_function(move |_, _: ()| {
+            prevent_default_reference.clone().set(true);
+            Ok(())
+          })?,
+        )?;
+
+        callback.call(("trade_request", event))
+      },
+    );
+
+    prevent_default.get()
+  }
+
+  fn handle_trade_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    player_offer: &TradeOffer,
+    recipient_offer: &TradeOffer,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("recipient_id", recipient_id)?;
+        event.set("player_offer", create_offer_table(lua_ctx, player_offer)?)?;
+        event.set(
+          "recipient_offer",
+          create_offer_table(lua_ctx, recipient_offer)?,
+        )?;
+
+        callback.call(("trade_complete", event))
+      },
+    );
+  }
+
+  fn handle_trade_cancelled(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    reason: &str,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("recipient_id", recipient_id)?;
+        event.set("reason", reason)?;
+
+        callback.call(("trade_cancelled", event))
+      },
+    );
+  }
+
+  fn handle_shop_purchase_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    purchase: &ShopPurchase,
+  ) {
+    let tracker = self.widget_trackers.get_mut(player_id).unwrap();
+
+    let script_index = if let Some(Some(script_index)) = tracker.current_shop() {
+      *script_index
+    } else {
+      return;
+    };
+
+    handle_event(
+      &mut self.scripts,
+      &[script_index],
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("shop_id", purchase.shop_id.as_str())?;
+        event.set("item_id", purchase.item_id.as_str())?;
+        event.set("item_name", purchase.item_name.as_str())?;
+        event.set("price", purchase.price)?;
+        event.set("stock", purchase.stock)?;
+
+        callback.call(("shop_purchase_complete", event))
+      },
+    );
+  }
+
+  fn handle_chat_message(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    channel: ChatChannel,
+    target_id: Option<&str>,
+    message: &str,
+  ) -> bool {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let prevent_default = Rc::new(Cell::new(false));
+
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let prevent_default_reference = prevent_default.clone();
+
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("channel", channel.as_str())?;
+        event.set("target_id", target_id)?;
+        event.set("message", message)?;
+        event.set(
+          "prevent_default",
+          lua_ctx.create_function(move |_, _: ()| {
+            prevent_default_reference.clone().set(true);
+            Ok(())
+          })?,
+        )?;
+
+        callback.call(("chat_message", event))
+      },
+    );
+
+    prevent_default.get()
+  }
+
+  fn handle_party_invite(
+    &mut self,
+    net: &mut Net,
+    party_id: &str,
+    player_id: &str,
+    recipient_id: &str,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("party_id", party_id)?;
+        event.set("player_id", player_id)?;
+        event.set("recipient_id", recipient_id)?;
+
+        callback.call(("party_invite", event))
+      },
+    );
+  }
+
+  fn handle_party_join(&mut self, net: &mut Net, party_id: &str, player_id: &str) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("party_id", party_id)?;
+        event.set("player_id", player_id)?;
+
+        callback.call(("party_join", event))
+      },
+    );
+  }
+
+  fn handle_party_leave(&mut self, net: &mut Net, party_id: &str, player_id: &str, reason: &str) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("party_id", party_id)?;
+        event.set("player_id", player_id)?;
+        event.set("reason", reason)?;
+
+        callback.call(("party_leave", event))
+      },
+    );
+  }
+
+  fn handle_party_leader_change(&mut self, net: &mut Net, party_id: &str, player_id: &str) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("party_id", party_id)?;
+        event.set("player_id", player_id)?;
+
+        callback.call(("party_leader_change", event))
+      },
+    );
+  }
+
+  fn handle_battle_results_group(
+    &mut self,
+    net: &mut Net,
+    group_id: &str,
+    package_path: &str,
+    results: &[CoopBattleResult],
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let result_tables = lua_ctx.create_table()?;
+
+        for (i, result) in results.iter().enumerate() {
+          let result_table = lua_ctx.create_table()?;
+          result_table.set("player_id", result.player_id.as_str())?;
+
+          if let Some(battle_stats) = &result.battle_stats {
+            result_table.set("stats", create_battle_stats_table(lua_ctx, battle_stats)?)?;
+          }
+
+          result_tables.set(i + 1, result_table)?;
+        }
+
+        let event = lua_ctx.create_table()?;
+        event.set("group_id", group_id)?;
+        event.set("package_path", package_path)?;
+        event.set("results", result_tables)?;
+
+        callback.call(("battle_results_group", event))
+      },
+    );
+  }
+
+  fn handle_battle_spectator_join(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("spectator_id", spectator_id)?;
+        event.set("player_1_id", player_1_id)?;
+        event.set("player_2_id", player_2_id)?;
+
+        callback.call(("battle_spectator_join", event))
+      },
+    );
+  }
+
+  fn handle_battle_spectator_leave(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+    reason: &str,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("spectator_id", spectator_id)?;
+        event.set("player_1_id", player_1_id)?;
+        event.set("player_2_id", player_2_id)?;
+        event.set("reason", reason)?;
+
+        callback.call(("battle_spectator_leave", event))
+      },
+    );
+  }
+
+  fn handle_matchmaking_match(
+    &mut self,
+    net: &mut Net,
+    queue_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("queue_id", queue_id)?;
+        event.set("player_1_id", player_1_id)?;
+        event.set("player_2_id", player_2_id)?;
+
+        callback.call(("matchmaking_match", event))
+      },
+    );
+  }
+
+  fn handle_quest_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    quest_id: &str,
+    state: QuestState,
+    step_id: Option<&str>,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("quest_id", quest_id)?;
+        event.set("state", state.as_str())?;
+        event.set("step_id", step_id)?;
+
+        callback.call(("quest_change", event))
+      },
+    );
+  }
+
+  fn handle_flag_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    name: &str,
+    value: Option<FlagValue>,
+  ) {
+    handle_event(
+      &mut self.scripts,
+      &self.all_scripts,
+      &mut self.widget_trackers,
+      &mut self.battle_trackers,
+      &mut self.promise_manager,
+      &mut self.lua_api,
+      net,
+      |lua_ctx, callback| {
+        let event = lua_ctx.create_table()?;
+        event.set("player_id", player_id)?;
+        event.set("flag", name)?;
+
+        match value {
+          Some(FlagValue::Bool(value)) => event.set("value", value)?,
+          Some(FlagValue::Int(value)) => event.set("value", value)?,
+          None => {}
+        }
+
+        callback.call(("flag_change", event))
+      },
+    );
+  }
+}
+
+// { health, score, time, ran, emotion, turns, enemies = { id, health }[] }
+fn create_battle_stats_table<'lua>(
+  lua_ctx: &'lua mlua::Lua,
+  battle_stats: &BattleStats,
+) -> mlua::Result<mlua::Table<'lua>> {
+  let table = lua_ctx.create_table()?;
+  table.set("health", battle_stats.health)?;
+  table.set("score", battle_stats.score)?;
+  table.set("time", battle_stats.time)?;
+  table.set("ran", battle_stats.ran)?;
+  table.set("emotion", battle_stats.emotion)?;
+  table.set("turns", battle_stats.turns)?;
+
+  let mut enemy_tables = Vec::new();
+  enemy_tables.reserve(battle_stats.enemies.len());
+
+  for enemy in &battle_stats.enemies {
+    let enemy_table = lua_ctx.create_table()?;
+    enemy_table.set("id", enemy.id.as_str())?;
+    enemy_table.set("health", enemy.health)?;
+    enemy_tables.push(enemy_table);
+  }
+
+  table.set("enemies", enemy_tables)?;
+
+  Ok(table)
+}
+
+// { money, items = { item_id, quantity, durability?, data? }[] }
+fn create_offer_table<'lua>(
+  lua_ctx: &'lua mlua::Lua,
+  offer: &TradeOffer,
+) -> mlua::Result<mlua::Table<'lua>> {
+  let item_tables = lua_ctx.create_table()?;
+
+  for (i, stack) in offer.items.iter().enumerate() {
+    let item_table = lua_ctx.create_table()?;
+    item_table.set("item_id", stack.item_id.as_str())?;
+    item_table.set("quantity", stack.quantity)?;
+    item_table.set("durability", stack.metadata.durability)?;
+    item_table.set("data", stack.metadata.data.as_deref())?;
+    item_tables.set(i + 1, item_table)?;
+  }
+
+  let table = lua_ctx.create_table()?;
+  table.set("money", offer.money)?;
+  table.set("items", item_tables)?;
+
+  Ok(table)
+}
+
+fn has_console_command(lua_ctx: &Lua, name: &str) -> bool {
+  get_console_commands_table(lua_ctx)
+    .and_then(|table| table.get::<_, Option<bool>>(name))
+    .ok()
+    .flatten()
+    .unwrap_or_default()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_event<F>(
+  scripts: &mut Vec<Lua>,
+  event_listeners: &[usize],
+  widget_tracker: &mut HashMap<String, WidgetTracker<Option<usize>>>,
+  battle_tracker: &mut HashMap<String, VecDeque<Option<usize>>>,
+  promise_manager: &mut JobPromiseManager,
+  lua_api: &mut LuaApi,
+  net: &mut Net,
+  fn_caller: F,
+) where
+  F: for<'lua> FnMut(&'lua mlua::Lua, mlua::Function<'lua>) -> mlua::Result<()>,
+{
+  let mut fn_caller = fn_caller;
+
+  let call_lua = || -> mlua::Result<()> {
+    let net_ref = RefCell::new(net);
+    let widget_tracker_ref = RefCell::new(widget_tracker);
+    let battle_tracker_ref = RefCell::new(battle_tracker);
+    let promise_manager_ref = RefCell::new(promise_manager);
+
+    // loop over scripts
+    for script_index in event_listeners {
+      let lua_ctx = match scripts.get_mut(*script_index) {
+        Some(lua_ctx) => lua_ctx,
+        // owned by a script that has been reloaded
+        None => continue,
+      };
+
+      let api_ctx = ApiContext {
+        script_index: *script_index,
+        net_ref: &net_ref,
+        widget_tracker_ref: &widget_tracker_ref,
+        battle_tracker_ref: &battle_tracker_ref,
+        promise_manager_ref: &promise_manager_ref,
+      };
+
+      lua_api.inject_dynamic(lua_ctx, api_ctx, |lua_ctx| {
+        let globals = lua_ctx.globals();
+        let net_table: mlua::Table = globals.get("Net")?;
+
+        if let Ok(func) = net_table.get::<_, mlua::Function>("emit") {
+          let binded_func = func.bind(net_table)?;
+
+          if let Err(err) = fn_caller(lua_ctx, binded_func) {
+            error!("{}", err);
+          }
+        }
+
+        Ok(())
+      })?;
+    }
+    Ok(())
+  };
+
+  if let Err(err) = call_lua() {
+    error!("{:#}", err);
+  }
+}
+
+// latest modification time of a script file, or of any file within a script folder
+fn get_last_modified(path: &Path) -> Option<SystemTime> {
+  let metadata = std::fs::metadata(path).ok()?;
+
+  if !metadata.is_dir() {
+    return metadata.modified().ok();
+  }
+
+  std::fs::read_dir(path)
+    .ok()?
+    .flatten()
+    .filter_map(|entry| get_last_modified(&entry.path()))
+    .max()
 }
@@ -3,3 +3,6 @@ pub use plugin_interface::PluginInterface;
 
 mod lua;
 pub use lua::LuaPluginInterface;
+
+mod native;
+pub use native::load_native_plugins;
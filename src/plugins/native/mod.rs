@@ -0,0 +1,4 @@
+mod native_api;
+mod native_plugin_interface;
+
+pub use native_plugin_interface::load_native_plugins;
@@ -0,0 +1,514 @@
+//! The C ABI shared with native plugins.
+//!
+//! Plugins export `onb_plugin_abi_version() -> u32` and `onb_plugin_create(api: *const NativeNetApi) -> NativePlugin`.
+//! Every struct here is `#[repr(C)]` and may only be extended by bumping `NATIVE_PLUGIN_ABI_VERSION`.
+//!
+//! Strings are nul terminated UTF-8, strings passed to plugins are only valid for the duration of the call.
+//! The `NetHandle` is only valid during a callback, plugins must not store it.
+
+use crate::net::{Direction, Net};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+
+pub const NATIVE_PLUGIN_ABI_VERSION: u32 = 12;
+
+pub const ABI_VERSION_SYMBOL: &[u8] = b"onb_plugin_abi_version\0";
+pub const CREATE_SYMBOL: &[u8] = b"onb_plugin_create\0";
+
+pub type AbiVersionFn = unsafe extern "C" fn() -> u32;
+pub type CreateFn = unsafe extern "C" fn(api: *const NativeNetApi) -> NativePlugin;
+
+/// Opaque handle to the server state
+#[repr(C)]
+pub struct NetHandle {
+  _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct NativeEnemyBattleStats {
+  pub id: *const c_char,
+  pub health: u32,
+}
+
+#[repr(C)]
+pub struct NativeBattleStats {
+  pub health: u32,
+  pub score: u32,
+  pub time: f32,
+  pub ran: bool,
+  pub emotion: u8,
+  pub turns: u32,
+  pub enemies: *const NativeEnemyBattleStats,
+  pub enemy_count: usize,
+}
+
+type Data = *mut c_void;
+type Handle = *mut NetHandle;
+type Str = *const c_char;
+
+/// Returned by `onb_plugin_create`, null callbacks are skipped.
+/// Callbacks returning bool follow prevent_default, returning true prevents the default behavior.
+#[repr(C)]
+pub struct NativePlugin {
+  pub abi_version: u32,
+  pub user_data: Data,
+  pub destroy: Option<unsafe extern "C" fn(Data)>,
+  pub init: Option<unsafe extern "C" fn(Data, Handle)>,
+  pub reload: Option<unsafe extern "C" fn(Data, Handle, name: Str)>,
+  pub tick: Option<unsafe extern "C" fn(Data, Handle, delta_time: f32)>,
+  pub handle_authorization: Option<
+    unsafe extern "C" fn(
+      Data,
+      Handle,
+      identity: Str,
+      host: Str,
+      port: u16,
+      data: *const u8,
+      data_len: usize,
+    ),
+  >,
+  pub handle_player_request: Option<unsafe extern "C" fn(Data, Handle, player_id: Str, data: Str)>,
+  pub handle_player_connect: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_player_join: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_player_transfer: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_player_disconnect: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_player_move:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, x: f32, y: f32, z: f32)>,
+  pub handle_player_move_rejected: Option<
+    unsafe extern "C" fn(Data, Handle, player_id: Str, x: f32, y: f32, z: f32, reason: Str) -> bool,
+  >,
+  pub handle_player_avatar_change: Option<
+    unsafe extern "C" fn(
+      Data,
+      Handle,
+      player_id: Str,
+      texture_path: Str,
+      animation_path: Str,
+      name: Str,
+      element: Str,
+      max_health: u32,
+    ) -> bool,
+  >,
+  pub handle_player_emote:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, emote_id: u8) -> bool>,
+  pub handle_custom_warp:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, tile_object_id: u32)>,
+  pub handle_object_interaction:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, tile_object_id: u32, button: u8)>,
+  pub handle_actor_interaction:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, actor_id: Str, button: u8)>,
+  pub handle_tile_interaction:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, x: f32, y: f32, z: f32, button: u8)>,
+  pub handle_textbox_response:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, response: u8)>,
+  pub handle_prompt_response:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, response: Str)>,
+  pub handle_board_open: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_board_close: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_post_request: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_post_selection:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, post_id: Str)>,
+  pub handle_shop_close: Option<unsafe extern "C" fn(Data, Handle, player_id: Str)>,
+  pub handle_shop_purchase:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, item_name: Str)>,
+  pub handle_battle_results: Option<
+    unsafe extern "C" fn(Data, Handle, player_id: Str, battle_stats: *const NativeBattleStats),
+  >,
+  pub handle_server_message: Option<
+    unsafe extern "C" fn(Data, Handle, host: Str, port: u16, data: *const u8, data_len: usize),
+  >,
+  pub handle_console_command:
+    Option<unsafe extern "C" fn(Data, Handle, command: Str, args: *const Str, arg_count: usize)>,
+  pub handle_bot_path_complete: Option<unsafe extern "C" fn(Data, Handle, bot_id: Str)>,
+  pub handle_object_enter:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, area_id: Str, object_id: u32)>,
+  pub handle_object_exit:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, area_id: Str, object_id: u32)>,
+  pub handle_actor_nearby:
+    Option<unsafe extern "C" fn(Data, Handle, actor_id: Str, nearby_actor_id: Str)>,
+  /// Returns true to prevent the random encounter, data is null when the encounter has no data
+  pub handle_encounter: Option<
+    unsafe extern "C" fn(Data, Handle, player_id: Str, package_path: Str, data: Str) -> bool,
+  >,
+  /// Returns true to prevent the trade
+  pub handle_trade_request:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, recipient_id: Str) -> bool>,
+  pub handle_trade_complete:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, recipient_id: Str)>,
+  pub handle_trade_cancelled:
+    Option<unsafe extern "C" fn(Data, Handle, player_id: Str, recipient_id: Str, reason: Str)>,
+  /// `stock` is -1 for unlimited stock
+  pub handle_shop_purchase_complete: Option<
+    unsafe extern "C" fn(
+      Data,
+      Handle,
+      player_id: Str,
+      shop_id: Str,
+      item_id: Str,
+      price: u32,
+      stock: i64,
+    ),
+  >,
+  /// `channel` is "area", "global", "party", or "whisper", `target_id` is empty unless whispering.
+  /// Returns true to prevent the server from sending the message
+  pub handle_chat_message: Option<
+    unsafe extern "C" fn(
+      Data,
+      Handle,
+      player_id: Str,
+      channel: Str,
+      target_id: Str,
+      message: Str,
+    ) -> bool,
+  >,
+  /// `reason` is "left", "kicked", "disconnected", or "disbanded"
+  pub handle_party_invite:
+    Option<unsafe extern "C" fn(Data, Handle, party_id: Str, player_id: Str, recipient_id: Str)>,
+  pub handle_party_join: Option<unsafe extern "C" fn(Data, Handle, party_id: Str, player_id: Str)>,
+  pub handle_party_leave:
+    Option<unsafe extern "C" fn(Data, Handle, party_id: Str, player_id: Str, reason: Str)>,
+  pub handle_party_leader_change:
+    Option<unsafe extern "C" fn(Data, Handle, party_id: Str, player_id: Str)>,
+  /// `battle_stats` is parallel to `player_ids`, entries are null for players who disconnected or timed out
+  pub handle_battle_results_group: Option<
+    unsafe extern "C" fn(
+      Data,
+      Handle,
+      group_id: Str,
+      package_path: Str,
+      player_ids: *const Str,
+      battle_stats: *const *const NativeBattleStats,
+      player_count: usize,
+    ),
+  >,
+  pub handle_battle_spectator_join: Option<
+    unsafe extern "C" fn(Data, Handle, spectator_id: Str, player_1_id: Str, player_2_id: Str),
+  >,
+  /// `reason` is "left", "removed", "disconnected", or "battle_ended"
+  pub handle_battle_spectator_leave: Option<
+    unsafe extern "C" fn(
+      Data,
+      Handle,
+      spectator_id: Str,
+      player_1_id: Str,
+      player_2_id: Str,
+      reason: Str,
+    ),
+  >,
+  pub handle_matchmaking_match:
+    Option<unsafe extern "C" fn(Data, Handle, queue_id: Str, player_1_id: Str, player_2_id: Str)>,
+  /// `state` is "not_started", "active", "completed", or "failed", `step_id` is empty unless active
+  pub handle_quest_change: Option<
+    unsafe extern "C" fn(Data, Handle, player_id: Str, quest_id: Str, state: Str, step_id: Str),
+  >,
+  /// `flag_type` is "bool", "int", or empty when the flag was removed, bools are 0 or 1
+  pub handle_flag_change: Option<
+    unsafe extern "C" fn(Data, Handle, player_id: Str, name: Str, flag_type: Str, value: i64),
+  >,
+}
+
+/// Functions plugins can use to read and modify the server through a `NetHandle`.
+/// Functions writing strings into a buffer return the length of the full string, excluding the nul terminator,
+/// the string is truncated if the buffer is too small.
+#[repr(C)]
+pub struct NativeNetApi {
+  pub abi_version: u32,
+  pub is_player: unsafe extern "C" fn(Handle, player_id: Str) -> bool,
+  pub is_bot: unsafe extern "C" fn(Handle, bot_id: Str) -> bool,
+  pub get_actor_area:
+    unsafe extern "C" fn(Handle, actor_id: Str, buffer: *mut c_char, buffer_len: usize) -> usize,
+  pub get_actor_position:
+    unsafe extern "C" fn(Handle, actor_id: Str, x: *mut f32, y: *mut f32, z: *mut f32) -> bool,
+  pub get_tile_gid: unsafe extern "C" fn(Handle, area_id: Str, x: u32, y: u32, z: u32) -> u32,
+  pub get_area_size: unsafe extern "C" fn(
+    Handle,
+    area_id: Str,
+    width: *mut u32,
+    height: *mut u32,
+    layer_count: *mut u32,
+  ) -> bool,
+  pub message_player: unsafe extern "C" fn(Handle, player_id: Str, message: Str),
+  pub teleport_player: unsafe extern "C" fn(
+    Handle,
+    player_id: Str,
+    warp: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+    direction: Str,
+  ),
+  pub transfer_player: unsafe extern "C" fn(
+    Handle,
+    player_id: Str,
+    area_id: Str,
+    warp_in: bool,
+    x: f32,
+    y: f32,
+    z: f32,
+    direction: Str,
+  ),
+  pub kick_player: unsafe extern "C" fn(Handle, player_id: Str, reason: Str),
+  pub move_bot: unsafe extern "C" fn(Handle, bot_id: Str, x: f32, y: f32, z: f32),
+  pub path_bot_to:
+    unsafe extern "C" fn(Handle, bot_id: Str, x: f32, y: f32, z: f32, speed: f32) -> bool,
+  pub register_console_command: unsafe extern "C" fn(Handle, name: Str, help: Str),
+  /// A negative radius stops watching
+  pub set_actor_proximity_radius: unsafe extern "C" fn(Handle, actor_id: Str, radius: f32),
+  pub log: unsafe extern "C" fn(message: Str),
+}
+
+pub static NATIVE_NET_API: NativeNetApi = NativeNetApi {
+  abi_version: NATIVE_PLUGIN_ABI_VERSION,
+  is_player,
+  is_bot,
+  get_actor_area,
+  get_actor_position,
+  get_tile_gid,
+  get_area_size,
+  message_player,
+  teleport_player,
+  transfer_player,
+  kick_player,
+  move_bot,
+  path_bot_to,
+  register_console_command,
+  set_actor_proximity_radius,
+  log,
+};
+
+pub fn to_handle(net: &mut Net) -> Handle {
+  net as *mut Net as Handle
+}
+
+/// Converts strings for the duration of a call, interior nuls are stripped
+pub fn to_c_string(text: &str) -> CString {
+  CString::new(text.replace('\0', "")).unwrap_or_default()
+}
+
+unsafe fn net_from_handle<'a>(handle: Handle) -> Option<&'a mut Net> {
+  (handle as *mut Net).as_mut()
+}
+
+unsafe fn str_from_ptr<'a>(text: Str) -> Option<&'a str> {
+  if text.is_null() {
+    return None;
+  }
+
+  CStr::from_ptr(text).to_str().ok()
+}
+
+unsafe fn write_str(text: &str, buffer: *mut c_char, buffer_len: usize) -> usize {
+  if !buffer.is_null() && buffer_len > 0 {
+    let copy_len = text.len().min(buffer_len - 1);
+
+    std::ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buffer, copy_len);
+    *buffer.add(copy_len) = 0;
+  }
+
+  text.len()
+}
+
+unsafe extern "C" fn is_player(handle: Handle, player_id: Str) -> bool {
+  match (net_from_handle(handle), str_from_ptr(player_id)) {
+    (Some(net), Some(player_id)) => net.get_player(player_id).is_some(),
+    _ => false,
+  }
+}
+
+unsafe extern "C" fn is_bot(handle: Handle, bot_id: Str) -> bool {
+  match (net_from_handle(handle), str_from_ptr(bot_id)) {
+    (Some(net), Some(bot_id)) => net.get_bot(bot_id).is_some(),
+    _ => false,
+  }
+}
+
+unsafe extern "C" fn get_actor_area(
+  handle: Handle,
+  actor_id: Str,
+  buffer: *mut c_char,
+  buffer_len: usize,
+) -> usize {
+  let (net, actor_id) = match (net_from_handle(handle), str_from_ptr(actor_id)) {
+    (Some(net), Some(actor_id)) => (net, actor_id),
+    _ => return write_str("", buffer, buffer_len),
+  };
+
+  let actor = net.get_player(actor_id).or_else(|| net.get_bot(actor_id));
+
+  let area_id = actor
+    .map(|actor| actor.area_id.as_str())
+    .unwrap_or_default();
+
+  write_str(area_id, buffer, buffer_len)
+}
+
+unsafe extern "C" fn get_actor_position(
+  handle: Handle,
+  actor_id: Str,
+  x: *mut f32,
+  y: *mut f32,
+  z: *mut f32,
+) -> bool {
+  let (net, actor_id) = match (net_from_handle(handle), str_from_ptr(actor_id)) {
+    (Some(net), Some(actor_id)) => (net, actor_id),
+    _ => return false,
+  };
+
+  let actor = match net.get_player(actor_id).or_else(|| net.get_bot(actor_id)) {
+    Some(actor) => actor,
+    None => return false,
+  };
+
+  for (out, value) in [(x, actor.x), (y, actor.y), (z, actor.z)] {
+    if let Some(out) = out.as_mut() {
+      *out = value;
+    }
+  }
+
+  true
+}
+
+unsafe extern "C" fn get_tile_gid(handle: Handle, area_id: Str, x: u32, y: u32, z: u32) -> u32 {
+  let (net, area_id) = match (net_from_handle(handle), str_from_ptr(area_id)) {
+    (Some(net), Some(area_id)) => (net, area_id),
+    _ => return 0,
+  };
+
+  match net.get_area(area_id) {
+    Some(area) => {
+      let map = area.get_map();
+      map.get_tile(x as usize, y as usize, z as usize).gid
+    }
+    None => 0,
+  }
+}
+
+unsafe extern "C" fn get_area_size(
+  handle: Handle,
+  area_id: Str,
+  width: *mut u32,
+  height: *mut u32,
+  layer_count: *mut u32,
+) -> bool {
+  let (net, area_id) = match (net_from_handle(handle), str_from_ptr(area_id)) {
+    (Some(net), Some(area_id)) => (net, area_id),
+    _ => return false,
+  };
+
+  let map = match net.get_area(area_id) {
+    Some(area) => area.get_map(),
+    None => return false,
+  };
+
+  let sizes = [
+    (width, map.get_width()),
+    (height, map.get_height()),
+    (layer_count, map.get_layer_count()),
+  ];
+
+  for (out, value) in sizes {
+    if let Some(out) = out.as_mut() {
+      *out = value as u32;
+    }
+  }
+
+  true
+}
+
+unsafe extern "C" fn message_player(handle: Handle, player_id: Str, message: Str) {
+  let net = net_from_handle(handle);
+
+  if let (Some(net), Some(player_id), Some(message)) =
+    (net, str_from_ptr(player_id), str_from_ptr(message))
+  {
+    net.message_player(player_id, message, "", "");
+  }
+}
+
+unsafe extern "C" fn teleport_player(
+  handle: Handle,
+  player_id: Str,
+  warp: bool,
+  x: f32,
+  y: f32,
+  z: f32,
+  direction: Str,
+) {
+  if let (Some(net), Some(player_id)) = (net_from_handle(handle), str_from_ptr(player_id)) {
+    let direction = Direction::from(str_from_ptr(direction).unwrap_or_default());
+
+    net.teleport_player(player_id, warp, x, y, z, direction);
+  }
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn transfer_player(
+  handle: Handle,
+  player_id: Str,
+  area_id: Str,
+  warp_in: bool,
+  x: f32,
+  y: f32,
+  z: f32,
+  direction: Str,
+) {
+  let net = net_from_handle(handle);
+
+  if let (Some(net), Some(player_id), Some(area_id)) =
+    (net, str_from_ptr(player_id), str_from_ptr(area_id))
+  {
+    let direction = Direction::from(str_from_ptr(direction).unwrap_or_default());
+
+    net.transfer_player(player_id, area_id, warp_in, x, y, z, direction);
+  }
+}
+
+unsafe extern "C" fn kick_player(handle: Handle, player_id: Str, reason: Str) {
+  if let (Some(net), Some(player_id)) = (net_from_handle(handle), str_from_ptr(player_id)) {
+    let reason = str_from_ptr(reason).unwrap_or_default();
+
+    net.kick_player(player_id, reason, true);
+  }
+}
+
+unsafe extern "C" fn move_bot(handle: Handle, bot_id: Str, x: f32, y: f32, z: f32) {
+  if let (Some(net), Some(bot_id)) = (net_from_handle(handle), str_from_ptr(bot_id)) {
+    net.move_bot(bot_id, x, y, z);
+  }
+}
+
+unsafe extern "C" fn path_bot_to(
+  handle: Handle,
+  bot_id: Str,
+  x: f32,
+  y: f32,
+  z: f32,
+  speed: f32,
+) -> bool {
+  match (net_from_handle(handle), str_from_ptr(bot_id)) {
+    (Some(net), Some(bot_id)) => net.path_bot_to(bot_id, x, y, z, speed),
+    _ => false,
+  }
+}
+
+unsafe extern "C" fn register_console_command(handle: Handle, name: Str, help: Str) {
+  if let (Some(net), Some(name)) = (net_from_handle(handle), str_from_ptr(name)) {
+    let help = str_from_ptr(help).unwrap_or_default();
+
+    net.register_console_command(name.to_string(), help.to_string());
+  }
+}
+
+unsafe extern "C" fn set_actor_proximity_radius(handle: Handle, actor_id: Str, radius: f32) {
+  if let (Some(net), Some(actor_id)) = (net_from_handle(handle), str_from_ptr(actor_id)) {
+    let radius = if radius < 0.0 { None } else { Some(radius) };
+
+    net.set_actor_proximity_radius(actor_id, radius);
+  }
+}
+
+unsafe extern "C" fn log(message: Str) {
+  if let Some(message) = str_from_ptr(message) {
+    log::info!("{}", message);
+  }
+}
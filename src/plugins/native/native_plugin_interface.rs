@@ -0,0 +1,1024 @@
+use super::native_api::*;
+use crate::net::{
+  BattleStats, ChatChannel, CoopBattleResult, Encounter, FlagValue, Net, QuestState, ShopPurchase,
+  TradeOffer,
+};
+use crate::plugins::PluginInterface;
+use log::*;
+use std::ffi::CString;
+use std::os::raw::c_char;
+
+pub struct NativePluginInterface {
+  plugin: NativePlugin,
+  // dropped after the plugin is destroyed
+  _library: libloading::Library,
+}
+
+impl NativePluginInterface {
+  /// Loads a plugin built as a cdylib, the abi version must match the server's
+  pub fn load(path: &std::path::Path) -> Result<NativePluginInterface, String> {
+    let fail = |err: &dyn std::fmt::Display| format!("Failed to load {}: {}", path.display(), err);
+
+    // running the library's initializers is the risk taken by placing a library in ./plugins
+    let library = unsafe { libloading::Library::new(path) }.map_err(|err| fail(&err))?;
+
+    let plugin = unsafe {
+      let abi_version: libloading::Symbol<AbiVersionFn> =
+        library.get(ABI_VERSION_SYMBOL).map_err(|err| fail(&err))?;
+
+      let abi_version = abi_version();
+
+      if abi_version != NATIVE_PLUGIN_ABI_VERSION {
+        return Err(fail(&format!(
+          "abi version {} does not match the server's abi version {}",
+          abi_version, NATIVE_PLUGIN_ABI_VERSION
+        )));
+      }
+
+      let create: libloading::Symbol<CreateFn> =
+        library.get(CREATE_SYMBOL).map_err(|err| fail(&err))?;
+
+      create(&NATIVE_NET_API)
+    };
+
+    if plugin.abi_version != NATIVE_PLUGIN_ABI_VERSION {
+      return Err(fail(&"plugin was created with a different abi version"));
+    }
+
+    Ok(NativePluginInterface {
+      plugin,
+      _library: library,
+    })
+  }
+}
+
+/// Loads every library in the folder with the platform's library extension (.so, .dll, .dylib)
+pub fn load_native_plugins(dir: &str) -> Vec<NativePluginInterface> {
+  let entries = match std::fs::read_dir(dir) {
+    Ok(entries) => entries,
+    Err(_) => return Vec::new(),
+  };
+
+  let mut plugins = Vec::new();
+
+  for entry in entries.flatten() {
+    let path = entry.path();
+    let extension = path.extension().unwrap_or_default();
+
+    if extension != std::env::consts::DLL_EXTENSION {
+      continue;
+    }
+
+    match NativePluginInterface::load(&path) {
+      Ok(plugin) => {
+        info!("Loaded native plugin {}", path.display());
+        plugins.push(plugin);
+      }
+      Err(err) => error!("{}", err),
+    }
+  }
+
+  plugins
+}
+
+impl Drop for NativePluginInterface {
+  fn drop(&mut self) {
+    if let Some(destroy) = self.plugin.destroy {
+      unsafe { destroy(self.plugin.user_data) };
+    }
+  }
+}
+
+impl PluginInterface for NativePluginInterface {
+  fn init(&mut self, net: &mut Net) {
+    if let Some(init) = self.plugin.init {
+      unsafe { init(self.plugin.user_data, to_handle(net)) };
+    }
+  }
+
+  fn reload(&mut self, net: &mut Net, name: Option<&str>) {
+    if let Some(reload) = self.plugin.reload {
+      let name = name.map(to_c_string);
+      let name_ptr = name
+        .as_ref()
+        .map(|name| name.as_ptr())
+        .unwrap_or(std::ptr::null());
+
+      unsafe { reload(self.plugin.user_data, to_handle(net), name_ptr) };
+    }
+  }
+
+  fn tick(&mut self, net: &mut Net, delta_time: f32) {
+    if let Some(tick) = self.plugin.tick {
+      unsafe { tick(self.plugin.user_data, to_handle(net), delta_time) };
+    }
+  }
+
+  fn handle_authorization(
+    &mut self,
+    net: &mut Net,
+    identity: &str,
+    host: &str,
+    port: u16,
+    data: &[u8],
+  ) {
+    if let Some(callback) = self.plugin.handle_authorization {
+      let (identity, host) = (to_c_string(identity), to_c_string(host));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          identity.as_ptr(),
+          host.as_ptr(),
+          port,
+          data.as_ptr(),
+          data.len(),
+        )
+      };
+    }
+  }
+
+  fn handle_player_request(&mut self, net: &mut Net, player_id: &str, data: &str) {
+    if let Some(callback) = self.plugin.handle_player_request {
+      let (player_id, data) = (to_c_string(player_id), to_c_string(data));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          data.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_player_connect(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_player_connect {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_player_join(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_player_join {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_player_transfer(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_player_transfer {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_player_disconnect(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_player_disconnect {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_player_move(&mut self, net: &mut Net, player_id: &str, x: f32, y: f32, z: f32) {
+    if let Some(callback) = self.plugin.handle_player_move {
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          x,
+          y,
+          z,
+        )
+      };
+    }
+  }
+
+  fn handle_player_move_rejected(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    reason: &str,
+  ) -> bool {
+    if let Some(callback) = self.plugin.handle_player_move_rejected {
+      let (player_id, reason) = (to_c_string(player_id), to_c_string(reason));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          x,
+          y,
+          z,
+          reason.as_ptr(),
+        )
+      }
+    } else {
+      false
+    }
+  }
+
+  fn handle_player_avatar_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    texture_path: &str,
+    animation_path: &str,
+    name: &str,
+    element: &str,
+    max_health: u32,
+  ) -> bool {
+    if let Some(callback) = self.plugin.handle_player_avatar_change {
+      let player_id = to_c_string(player_id);
+      let texture_path = to_c_string(texture_path);
+      let animation_path = to_c_string(animation_path);
+      let name = to_c_string(name);
+      let element = to_c_string(element);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          texture_path.as_ptr(),
+          animation_path.as_ptr(),
+          name.as_ptr(),
+          element.as_ptr(),
+          max_health,
+        )
+      }
+    } else {
+      false
+    }
+  }
+
+  fn handle_player_emote(&mut self, net: &mut Net, player_id: &str, emote_id: u8) -> bool {
+    if let Some(callback) = self.plugin.handle_player_emote {
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          emote_id,
+        )
+      }
+    } else {
+      false
+    }
+  }
+
+  fn handle_custom_warp(&mut self, net: &mut Net, player_id: &str, tile_object_id: u32) {
+    if let Some(callback) = self.plugin.handle_custom_warp {
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          tile_object_id,
+        )
+      };
+    }
+  }
+
+  fn handle_object_interaction(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    tile_object_id: u32,
+    button: u8,
+  ) {
+    if let Some(callback) = self.plugin.handle_object_interaction {
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          tile_object_id,
+          button,
+        )
+      };
+    }
+  }
+
+  fn handle_actor_interaction(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    actor_id: &str,
+    button: u8,
+  ) {
+    if let Some(callback) = self.plugin.handle_actor_interaction {
+      let (player_id, actor_id) = (to_c_string(player_id), to_c_string(actor_id));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          actor_id.as_ptr(),
+          button,
+        )
+      };
+    }
+  }
+
+  fn handle_tile_interaction(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    button: u8,
+  ) {
+    if let Some(callback) = self.plugin.handle_tile_interaction {
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          x,
+          y,
+          z,
+          button,
+        )
+      };
+    }
+  }
+
+  fn handle_textbox_response(&mut self, net: &mut Net, player_id: &str, response: u8) {
+    if let Some(callback) = self.plugin.handle_textbox_response {
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          response,
+        )
+      };
+    }
+  }
+
+  fn handle_prompt_response(&mut self, net: &mut Net, player_id: &str, response: String) {
+    if let Some(callback) = self.plugin.handle_prompt_response {
+      let (player_id, response) = (to_c_string(player_id), to_c_string(&response));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          response.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_board_open(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_board_open {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_board_close(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_board_close {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_post_request(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_post_request {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_post_selection(&mut self, net: &mut Net, player_id: &str, post_id: &str) {
+    if let Some(callback) = self.plugin.handle_post_selection {
+      let (player_id, post_id) = (to_c_string(player_id), to_c_string(post_id));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          post_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_shop_close(&mut self, net: &mut Net, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_shop_close {
+      let player_id = to_c_string(player_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), player_id.as_ptr()) };
+    }
+  }
+
+  fn handle_shop_purchase(&mut self, net: &mut Net, player_id: &str, item_name: &str) {
+    if let Some(callback) = self.plugin.handle_shop_purchase {
+      let (player_id, item_name) = (to_c_string(player_id), to_c_string(item_name));
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          item_name.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_battle_results(&mut self, net: &mut Net, player_id: &str, battle_stats: &BattleStats) {
+    if let Some(callback) = self.plugin.handle_battle_results {
+      let player_id = to_c_string(player_id);
+      let native_battle_stats = OwnedNativeBattleStats::new(battle_stats);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          &native_battle_stats.stats,
+        )
+      };
+    }
+  }
+
+  fn handle_server_message(
+    &mut self,
+    net: &mut Net,
+    socket_address: std::net::SocketAddr,
+    data: &[u8],
+  ) {
+    if let Some(callback) = self.plugin.handle_server_message {
+      let host = to_c_string(&socket_address.ip().to_string());
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          host.as_ptr(),
+          socket_address.port(),
+          data.as_ptr(),
+          data.len(),
+        )
+      };
+    }
+  }
+
+  fn handle_console_command(&mut self, net: &mut Net, command: &str, args: &[&str]) {
+    if let Some(callback) = self.plugin.handle_console_command {
+      let command = to_c_string(command);
+      let args: Vec<_> = args.iter().map(|arg| to_c_string(arg)).collect();
+      let arg_ptrs: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          command.as_ptr(),
+          arg_ptrs.as_ptr(),
+          arg_ptrs.len(),
+        )
+      };
+    }
+  }
+
+  fn handle_bot_path_complete(&mut self, net: &mut Net, bot_id: &str) {
+    if let Some(callback) = self.plugin.handle_bot_path_complete {
+      let bot_id = to_c_string(bot_id);
+
+      unsafe { callback(self.plugin.user_data, to_handle(net), bot_id.as_ptr()) };
+    }
+  }
+
+  fn handle_object_enter(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32) {
+    if let Some(callback) = self.plugin.handle_object_enter {
+      let player_id = to_c_string(player_id);
+      let area_id = to_c_string(area_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          area_id.as_ptr(),
+          object_id,
+        )
+      };
+    }
+  }
+
+  fn handle_object_exit(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32) {
+    if let Some(callback) = self.plugin.handle_object_exit {
+      let player_id = to_c_string(player_id);
+      let area_id = to_c_string(area_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          area_id.as_ptr(),
+          object_id,
+        )
+      };
+    }
+  }
+
+  fn handle_actor_nearby(&mut self, net: &mut Net, actor_id: &str, nearby_actor_id: &str) {
+    if let Some(callback) = self.plugin.handle_actor_nearby {
+      let actor_id = to_c_string(actor_id);
+      let nearby_actor_id = to_c_string(nearby_actor_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          actor_id.as_ptr(),
+          nearby_actor_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_encounter(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    encounter: &mut Encounter,
+  ) -> bool {
+    if let Some(callback) = self.plugin.handle_encounter {
+      let player_id = to_c_string(player_id);
+      let package_path = to_c_string(&encounter.package_path);
+      let data = encounter.data.as_deref().map(to_c_string);
+      let data_ptr = data
+        .as_ref()
+        .map(|data| data.as_ptr())
+        .unwrap_or(std::ptr::null());
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          package_path.as_ptr(),
+          data_ptr,
+        )
+      }
+    } else {
+      false
+    }
+  }
+
+  fn handle_trade_request(&mut self, net: &mut Net, player_id: &str, recipient_id: &str) -> bool {
+    if let Some(callback) = self.plugin.handle_trade_request {
+      let player_id = to_c_string(player_id);
+      let recipient_id = to_c_string(recipient_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          recipient_id.as_ptr(),
+        )
+      }
+    } else {
+      false
+    }
+  }
+
+  fn handle_trade_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    _player_offer: &TradeOffer,
+    _recipient_offer: &TradeOffer,
+  ) {
+    if let Some(callback) = self.plugin.handle_trade_complete {
+      let player_id = to_c_string(player_id);
+      let recipient_id = to_c_string(recipient_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          recipient_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_trade_cancelled(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    reason: &str,
+  ) {
+    if let Some(callback) = self.plugin.handle_trade_cancelled {
+      let player_id = to_c_string(player_id);
+      let recipient_id = to_c_string(recipient_id);
+      let reason = to_c_string(reason);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          recipient_id.as_ptr(),
+          reason.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_shop_purchase_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    purchase: &ShopPurchase,
+  ) {
+    if let Some(callback) = self.plugin.handle_shop_purchase_complete {
+      let player_id = to_c_string(player_id);
+      let shop_id = to_c_string(&purchase.shop_id);
+      let item_id = to_c_string(&purchase.item_id);
+      let stock = purchase.stock.map(i64::from).unwrap_or(-1);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          shop_id.as_ptr(),
+          item_id.as_ptr(),
+          purchase.price,
+          stock,
+        )
+      };
+    }
+  }
+
+  fn handle_chat_message(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    channel: ChatChannel,
+    target_id: Option<&str>,
+    message: &str,
+  ) -> bool {
+    if let Some(callback) = self.plugin.handle_chat_message {
+      let player_id = to_c_string(player_id);
+      let channel = to_c_string(channel.as_str());
+      let target_id = to_c_string(target_id.unwrap_or_default());
+      let message = to_c_string(message);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          channel.as_ptr(),
+          target_id.as_ptr(),
+          message.as_ptr(),
+        )
+      }
+    } else {
+      false
+    }
+  }
+
+  fn handle_party_invite(
+    &mut self,
+    net: &mut Net,
+    party_id: &str,
+    player_id: &str,
+    recipient_id: &str,
+  ) {
+    if let Some(callback) = self.plugin.handle_party_invite {
+      let party_id = to_c_string(party_id);
+      let player_id = to_c_string(player_id);
+      let recipient_id = to_c_string(recipient_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          party_id.as_ptr(),
+          player_id.as_ptr(),
+          recipient_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_party_join(&mut self, net: &mut Net, party_id: &str, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_party_join {
+      let party_id = to_c_string(party_id);
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          party_id.as_ptr(),
+          player_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_party_leave(&mut self, net: &mut Net, party_id: &str, player_id: &str, reason: &str) {
+    if let Some(callback) = self.plugin.handle_party_leave {
+      let party_id = to_c_string(party_id);
+      let player_id = to_c_string(player_id);
+      let reason = to_c_string(reason);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          party_id.as_ptr(),
+          player_id.as_ptr(),
+          reason.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_party_leader_change(&mut self, net: &mut Net, party_id: &str, player_id: &str) {
+    if let Some(callback) = self.plugin.handle_party_leader_change {
+      let party_id = to_c_string(party_id);
+      let player_id = to_c_string(player_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          party_id.as_ptr(),
+          player_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_battle_results_group(
+    &mut self,
+    net: &mut Net,
+    group_id: &str,
+    package_path: &str,
+    results: &[CoopBattleResult],
+  ) {
+    if let Some(callback) = self.plugin.handle_battle_results_group {
+      let group_id = to_c_string(group_id);
+      let package_path = to_c_string(package_path);
+
+      let player_ids: Vec<_> = results
+        .iter()
+        .map(|result| to_c_string(&result.player_id))
+        .collect();
+      let player_id_ptrs: Vec<*const c_char> = player_ids.iter().map(|id| id.as_ptr()).collect();
+
+      let battle_stats: Vec<Option<OwnedNativeBattleStats>> = results
+        .iter()
+        .map(|result| {
+          result
+            .battle_stats
+            .as_ref()
+            .map(OwnedNativeBattleStats::new)
+        })
+        .collect();
+      let battle_stats_ptrs: Vec<*const NativeBattleStats> = battle_stats
+        .iter()
+        .map(|stats| match stats {
+          Some(stats) => &stats.stats as *const NativeBattleStats,
+          None => std::ptr::null(),
+        })
+        .collect();
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          group_id.as_ptr(),
+          package_path.as_ptr(),
+          player_id_ptrs.as_ptr(),
+          battle_stats_ptrs.as_ptr(),
+          results.len(),
+        )
+      };
+    }
+  }
+
+  fn handle_battle_spectator_join(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  ) {
+    if let Some(callback) = self.plugin.handle_battle_spectator_join {
+      let spectator_id = to_c_string(spectator_id);
+      let player_1_id = to_c_string(player_1_id);
+      let player_2_id = to_c_string(player_2_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          spectator_id.as_ptr(),
+          player_1_id.as_ptr(),
+          player_2_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_battle_spectator_leave(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+    reason: &str,
+  ) {
+    if let Some(callback) = self.plugin.handle_battle_spectator_leave {
+      let spectator_id = to_c_string(spectator_id);
+      let player_1_id = to_c_string(player_1_id);
+      let player_2_id = to_c_string(player_2_id);
+      let reason = to_c_string(reason);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          spectator_id.as_ptr(),
+          player_1_id.as_ptr(),
+          player_2_id.as_ptr(),
+          reason.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_matchmaking_match(
+    &mut self,
+    net: &mut Net,
+    queue_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  ) {
+    if let Some(callback) = self.plugin.handle_matchmaking_match {
+      let queue_id = to_c_string(queue_id);
+      let player_1_id = to_c_string(player_1_id);
+      let player_2_id = to_c_string(player_2_id);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          queue_id.as_ptr(),
+          player_1_id.as_ptr(),
+          player_2_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_quest_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    quest_id: &str,
+    state: QuestState,
+    step_id: Option<&str>,
+  ) {
+    if let Some(callback) = self.plugin.handle_quest_change {
+      let player_id = to_c_string(player_id);
+      let quest_id = to_c_string(quest_id);
+      let state = to_c_string(state.as_str());
+      let step_id = to_c_string(step_id.unwrap_or_default());
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          quest_id.as_ptr(),
+          state.as_ptr(),
+          step_id.as_ptr(),
+        )
+      };
+    }
+  }
+
+  fn handle_flag_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    name: &str,
+    value: Option<FlagValue>,
+  ) {
+    if let Some(callback) = self.plugin.handle_flag_change {
+      let player_id = to_c_string(player_id);
+      let name = to_c_string(name);
+
+      let (flag_type, value) = match value {
+        Some(FlagValue::Bool(value)) => ("bool", value as i64),
+        Some(FlagValue::Int(value)) => ("int", value),
+        None => ("", 0),
+      };
+
+      let flag_type = to_c_string(flag_type);
+
+      unsafe {
+        callback(
+          self.plugin.user_data,
+          to_handle(net),
+          player_id.as_ptr(),
+          name.as_ptr(),
+          flag_type.as_ptr(),
+          value,
+        )
+      };
+    }
+  }
+}
+
+// keeps the enemy ids and stats referenced by the native stats alive
+struct OwnedNativeBattleStats {
+  _enemy_ids: Vec<CString>,
+  _enemies: Vec<NativeEnemyBattleStats>,
+  stats: NativeBattleStats,
+}
+
+impl OwnedNativeBattleStats {
+  fn new(battle_stats: &BattleStats) -> OwnedNativeBattleStats {
+    let enemy_ids: Vec<_> = battle_stats
+      .enemies
+      .iter()
+      .map(|enemy| to_c_string(&enemy.id))
+      .collect();
+
+    let enemies: Vec<NativeEnemyBattleStats> = battle_stats
+      .enemies
+      .iter()
+      .zip(&enemy_ids)
+      .map(|(enemy, id)| NativeEnemyBattleStats {
+        id: id.as_ptr(),
+        health: enemy.health,
+      })
+      .collect();
+
+    let stats = NativeBattleStats {
+      health: battle_stats.health,
+      score: battle_stats.score,
+      time: battle_stats.time,
+      ran: battle_stats.ran,
+      emotion: battle_stats.emotion,
+      turns: battle_stats.turns,
+      enemies: enemies.as_ptr(),
+      enemy_count: enemies.len(),
+    };
+
+    OwnedNativeBattleStats {
+      _enemy_ids: enemy_ids,
+      _enemies: enemies,
+      stats,
+    }
+  }
+}
@@ -1,7 +1,12 @@
-use crate::net::{BattleStats, Net};
+use crate::net::{
+  BattleStats, ChatChannel, CoopBattleResult, Encounter, FlagValue, Net, QuestState, ShopPurchase,
+  TradeOffer,
+};
 
 pub trait PluginInterface {
   fn init(&mut self, net: &mut Net);
+  /// Reloads every script, or only the script matching the name
+  fn reload(&mut self, net: &mut Net, name: Option<&str>);
   fn tick(&mut self, net: &mut Net, delta_time: f32);
   fn handle_authorization(
     &mut self,
@@ -17,6 +22,15 @@ pub trait PluginInterface {
   fn handle_player_transfer(&mut self, net: &mut Net, player_id: &str);
   fn handle_player_disconnect(&mut self, net: &mut Net, player_id: &str);
   fn handle_player_move(&mut self, net: &mut Net, player_id: &str, x: f32, y: f32, z: f32);
+  fn handle_player_move_rejected(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    x: f32,
+    y: f32,
+    z: f32,
+    reason: &str,
+  ) -> bool;
   fn handle_player_avatar_change(
     &mut self,
     net: &mut Net,
@@ -67,4 +81,97 @@ pub trait PluginInterface {
     socket_address: std::net::SocketAddr,
     data: &[u8],
   );
+  fn handle_console_command(&mut self, net: &mut Net, command: &str, args: &[&str]);
+  fn handle_bot_path_complete(&mut self, net: &mut Net, bot_id: &str);
+  fn handle_object_enter(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32);
+  fn handle_object_exit(&mut self, net: &mut Net, player_id: &str, area_id: &str, object_id: u32);
+  fn handle_actor_nearby(&mut self, net: &mut Net, actor_id: &str, nearby_actor_id: &str);
+  /// Called before a random encounter starts, the encounter can be modified or prevented
+  fn handle_encounter(&mut self, net: &mut Net, player_id: &str, encounter: &mut Encounter)
+    -> bool;
+  /// Called before the recipient can accept, returns true to prevent the trade
+  fn handle_trade_request(&mut self, net: &mut Net, player_id: &str, recipient_id: &str) -> bool;
+  fn handle_trade_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    player_offer: &TradeOffer,
+    recipient_offer: &TradeOffer,
+  );
+  fn handle_trade_cancelled(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    recipient_id: &str,
+    reason: &str,
+  );
+  fn handle_shop_purchase_complete(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    purchase: &ShopPurchase,
+  );
+  fn handle_chat_message(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    channel: ChatChannel,
+    target_id: Option<&str>,
+    message: &str,
+  ) -> bool;
+  fn handle_party_invite(
+    &mut self,
+    net: &mut Net,
+    party_id: &str,
+    player_id: &str,
+    recipient_id: &str,
+  );
+  fn handle_party_join(&mut self, net: &mut Net, party_id: &str, player_id: &str);
+  fn handle_party_leave(&mut self, net: &mut Net, party_id: &str, player_id: &str, reason: &str);
+  fn handle_party_leader_change(&mut self, net: &mut Net, party_id: &str, player_id: &str);
+  fn handle_battle_results_group(
+    &mut self,
+    net: &mut Net,
+    group_id: &str,
+    package_path: &str,
+    results: &[CoopBattleResult],
+  );
+  fn handle_battle_spectator_join(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  );
+  fn handle_battle_spectator_leave(
+    &mut self,
+    net: &mut Net,
+    spectator_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+    reason: &str,
+  );
+  fn handle_matchmaking_match(
+    &mut self,
+    net: &mut Net,
+    queue_id: &str,
+    player_1_id: &str,
+    player_2_id: &str,
+  );
+  fn handle_quest_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    quest_id: &str,
+    state: QuestState,
+    step_id: Option<&str>,
+  );
+  fn handle_flag_change(
+    &mut self,
+    net: &mut Net,
+    player_id: &str,
+    name: &str,
+    value: Option<FlagValue>,
+  );
 }
@@ -0,0 +1,23 @@
+use crate::threads::ThreadMessage;
+use std::sync::mpsc;
+
+pub fn create_console_thread(tx: mpsc::Sender<ThreadMessage>) {
+  use std::io::BufRead;
+
+  std::thread::spawn(move || {
+    let stdin = std::io::stdin();
+
+    // ends when stdin closes, such as when running without a terminal
+    for line in stdin.lock().lines().map_while(Result::ok) {
+      let message = if line.trim() == "stop" {
+        ThreadMessage::Shutdown
+      } else {
+        ThreadMessage::ConsoleCommand(line)
+      };
+
+      if tx.send(message).is_err() {
+        break;
+      }
+    }
+  });
+}
@@ -1,24 +1,26 @@
-use crate::net::ServerConfig;
+use crate::net::{RateLimitResult, RateLimiter, ServerConfig};
 use crate::packets::parse_client_packet;
 use crate::threads::ThreadMessage;
 use log::*;
 use std::net::UdpSocket;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 
 pub fn create_listening_thread(
   tx: mpsc::Sender<ThreadMessage>,
   socket: UdpSocket,
   config: ServerConfig,
+  rate_limiter: Arc<Mutex<RateLimiter>>,
 ) {
   let async_socket = async_std::net::UdpSocket::from(socket);
 
-  async_std::task::spawn(listen_loop(tx, async_socket, config));
+  async_std::task::spawn(listen_loop(tx, async_socket, config, rate_limiter));
 }
 
 async fn listen_loop(
   tx: mpsc::Sender<ThreadMessage>,
   async_socket: async_std::net::UdpSocket,
   config: ServerConfig,
+  rate_limiter: Arc<Mutex<RateLimiter>>,
 ) {
   loop {
     let mut buf = vec![0; config.max_payload_size];
@@ -42,7 +44,42 @@ async fn listen_loop(
       debug!("Received packet from {}", src_addr);
     }
 
-    if let Some((headers, packet)) = parse_client_packet(filled_buf) {
+    let parsed_packet = parse_client_packet(filled_buf);
+
+    let rate_limit_result = {
+      let mut rate_limiter = rate_limiter.lock().unwrap();
+
+      if rate_limiter.is_banned(&src_addr) {
+        continue;
+      }
+
+      let packet = parsed_packet.as_ref().map(|(_, packet)| packet);
+
+      rate_limiter.check(src_addr, number_of_bytes, packet)
+    };
+
+    match rate_limit_result {
+      RateLimitResult::Allow => {}
+      RateLimitResult::Drop => {
+        if config.log_packets {
+          debug!("Dropped packet from {} (rate limited)", src_addr);
+        }
+
+        continue;
+      }
+      RateLimitResult::Ban => {
+        warn!("Temporarily banned {} for flooding", src_addr);
+
+        tx.send(ThreadMessage::RateLimitBan {
+          socket_address: src_addr,
+        })
+        .unwrap();
+
+        continue;
+      }
+    }
+
+    if let Some((headers, packet)) = parsed_packet {
       tx.send(ThreadMessage::ClientPacket {
         socket_address: src_addr,
         headers,
@@ -4,5 +4,11 @@ pub use thread_message::ThreadMessage;
 pub mod clock_thread;
 pub use clock_thread::create_clock_thread;
 
+mod console_thread;
+pub use console_thread::create_console_thread;
+
 mod listening_thread;
 pub use listening_thread::create_listening_thread;
+
+mod shutdown_thread;
+pub use shutdown_thread::create_shutdown_thread;
@@ -0,0 +1,34 @@
+use crate::threads::ThreadMessage;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_shutdown(signal: libc::c_int) {
+  SHUTDOWN_REQUESTED.store(true, Ordering::Relaxed);
+
+  // a second signal stops the server without waiting for saves
+  unsafe {
+    libc::signal(signal, libc::SIG_DFL);
+  }
+}
+
+/// Sends ThreadMessage::Shutdown after an interrupt or termination signal
+pub fn create_shutdown_thread(tx: mpsc::Sender<ThreadMessage>) {
+  let handler = request_shutdown as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+  unsafe {
+    libc::signal(libc::SIGINT, handler);
+    libc::signal(libc::SIGTERM, handler);
+  }
+
+  // signal handlers can't safely send messages, the flag is polled instead
+  std::thread::spawn(move || loop {
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    if SHUTDOWN_REQUESTED.load(Ordering::Relaxed) {
+      let _ = tx.send(ThreadMessage::Shutdown);
+      break;
+    }
+  });
+}
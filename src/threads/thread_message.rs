@@ -7,4 +7,10 @@ pub enum ThreadMessage {
     headers: PacketHeaders,
     packet: ClientPacket,
   },
+  ConsoleCommand(String),
+  RateLimitBan {
+    socket_address: std::net::SocketAddr,
+  },
+  /// Sent for the stop command and interrupt signals, players are saved before the server exits
+  Shutdown,
 }